@@ -3,34 +3,63 @@
 //! Assembles the conversation history including unread inbox messages
 //! and recent tool results for the inference model.
 
+use crate::agent::system_prompt;
+use crate::config::AutomatonConfig;
+use crate::social::{check_sender, SenderCheck};
 use crate::state::Database;
 use crate::types::*;
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// The user-facing context for a turn, and the inbox messages it shows.
+#[derive(Debug, Clone, Default)]
+pub struct TurnContext {
+    pub text: String,
+    /// Ids of the unread messages included, to be marked read once the turn
+    /// that saw them is persisted.
+    pub message_ids: Vec<String>,
+}
 
 /// Build the user-facing message context for a turn.
 ///
-/// Includes unread inbox messages and any pending wake reasons.
-pub fn build_turn_context(db: &Database) -> String {
+/// Includes the oldest `max_messages` unread inbox messages (0 = all), a
+/// summary of any beyond that, and any pending wake reasons. Messages are
+/// labelled by signature status so creator instructions can be told apart
+/// from messages merely claiming the creator's address. They stay unread
+/// here; see [`TurnContext::message_ids`].
+pub fn build_turn_context(db: &Database, creator_address: &str, max_messages: usize) -> TurnContext {
     let mut context = String::new();
+    let mut message_ids = Vec::new();
 
     // Check for unread inbox messages
-    if let Ok(messages) = db.unread_messages() {
+    if let Ok(mut messages) = db.unread_messages() {
+        let overflow = match max_messages {
+            0 => Vec::new(),
+            max => messages.split_off(max.min(messages.len())),
+        };
         if !messages.is_empty() {
             context.push_str("## Unread Messages\n\n");
             for msg in &messages {
+                let from_creator = !creator_address.is_empty()
+                    && msg.from_address.eq_ignore_ascii_case(creator_address);
+                let label = match (check_sender(msg), from_creator) {
+                    (SenderCheck::Verified, true) => " [verified creator]",
+                    (SenderCheck::Verified, false) => " [signed]",
+                    (_, true) => " [unsigned: cannot confirm creator]",
+                    _ => "",
+                };
                 context.push_str(&format!(
-                    "- From `{}` at {}: {}\n",
+                    "- From `{}`{} at {}: {}\n",
                     msg.from_address,
+                    label,
                     msg.timestamp.format("%Y-%m-%d %H:%M UTC"),
                     msg.content,
                 ));
             }
-            context.push('\n');
-
-            // Mark them as read
-            for msg in &messages {
-                let _ = db.mark_message_read(&msg.id);
+            if !overflow.is_empty() {
+                context.push_str(&summarize_overflow(&overflow));
             }
+            context.push('\n');
+            message_ids = messages.into_iter().map(|m| m.id).collect();
         }
     }
 
@@ -47,43 +76,423 @@ pub fn build_turn_context(db: &Database) -> String {
     }
 
     debug!("Turn context: {} chars", context.len());
-    context
+    TurnContext {
+        text: context,
+        message_ids,
+    }
+}
+
+/// One line on the unread messages held back for later turns.
+fn summarize_overflow(overflow: &[InboxMessage]) -> String {
+    let mut senders: Vec<(&str, usize)> = Vec::new();
+    for msg in overflow {
+        match senders.iter_mut().find(|(from, _)| *from == msg.from_address) {
+            Some((_, count)) => *count += 1,
+            None => senders.push((&msg.from_address, 1)),
+        }
+    }
+    let senders: Vec<String> = senders
+        .iter()
+        .map(|(from, count)| format!("`{}` ({})", from, count))
+        .collect();
+    format!(
+        "- … and {} more unread messages from {}, shown in later turns.\n",
+        overflow.len(),
+        senders.join(", ")
+    )
+}
+
+/// Mark the messages a persisted turn was shown as read.
+pub fn mark_consumed(db: &Database, context: &TurnContext) -> anyhow::Result<()> {
+    for id in &context.message_ids {
+        db.mark_message_read(id)?;
+    }
+    Ok(())
+}
+
+/// Characters of an older tool result kept when it is summarized.
+const TOOL_SUMMARY_CHARS: usize = 200;
+
+/// Marks a tool result that has already been summarized.
+const SUMMARY_MARKER: &str = "… [older result summarized";
+
+/// Rough per-message token overhead (role, separators).
+const MESSAGE_TOKEN_OVERHEAD: usize = 4;
+
+/// How much conversation history is carried into each inference call.
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryPolicy {
+    /// Maximum number of history messages.
+    pub window: usize,
+    /// Most recent tool results sent verbatim; older ones are summarized.
+    pub verbatim_tool_results: usize,
+    /// Estimated token budget for the whole request (0 = unlimited).
+    pub max_context_tokens: usize,
+}
+
+impl HistoryPolicy {
+    pub fn from_config(config: &AutomatonConfig) -> Self {
+        Self {
+            window: config.history_window,
+            verbatim_tool_results: config.verbatim_tool_results,
+            max_context_tokens: config.max_context_tokens,
+        }
+    }
+}
+
+/// Index where a window of at most `window` messages over `history` starts.
+///
+/// The start is moved forward past tool results whose originating assistant
+/// message would fall outside the window, so a tool call and its results are
+/// never split.
+pub fn window_start(history: &[ChatMessage], window: usize) -> usize {
+    let mut start = history.len().saturating_sub(window);
+    while start < history.len() && history[start].role == ChatRole::Tool {
+        start += 1;
+    }
+    start
+}
+
+/// Drop history older than the window (using the same boundary rule as
+/// [`build_messages`]).
+pub fn trim_history(history: &mut Vec<ChatMessage>, window: usize) {
+    let start = window_start(history, window);
+    if start > 0 {
+        history.drain(..start);
+    }
 }
 
 /// Build the full message history for an inference call.
+///
+/// However the history is trimmed, the result starts with exactly one system
+/// message (any system messages in `previous_messages` are dropped) and ends
+/// with the user message for this turn.
 pub fn build_messages(
     system_prompt: &str,
     turn_context: &str,
     previous_messages: &[ChatMessage],
+    policy: &HistoryPolicy,
 ) -> Vec<ChatMessage> {
     let mut messages = Vec::new();
 
     // System message
-    messages.push(ChatMessage {
-        role: ChatRole::System,
-        content: system_prompt.to_string(),
-    });
+    messages.push(
+        ChatMessage::system(system_prompt)
+            .with_cacheable_prefix(system_prompt::stable_prefix_len(system_prompt)),
+    );
 
-    // Include recent conversation history (last N messages)
-    let history_window = 20;
-    let start = previous_messages.len().saturating_sub(history_window);
-    for msg in &previous_messages[start..] {
-        messages.push(msg.clone());
+    // Include recent conversation history, summarizing older tool results
+    let window = &previous_messages[window_start(previous_messages, policy.window)..];
+    let tool_count = window.iter().filter(|m| m.role == ChatRole::Tool).count();
+    let mut summarize = tool_count.saturating_sub(policy.verbatim_tool_results);
+    for msg in window.iter().filter(|m| m.role != ChatRole::System) {
+        if msg.role == ChatRole::Tool && summarize > 0 {
+            summarize -= 1;
+            messages.push(summarize_tool_result(msg));
+        } else {
+            messages.push(msg.clone());
+        }
     }
 
     // Current turn context as user message
     if !turn_context.is_empty() {
-        messages.push(ChatMessage {
-            role: ChatRole::User,
-            content: turn_context.to_string(),
-        });
+        messages.push(ChatMessage::user(turn_context));
     } else {
         // If no specific context, provide a generic turn prompt
-        messages.push(ChatMessage {
-            role: ChatRole::User,
-            content: "Continue your autonomous operation. What should you do next?".into(),
-        });
+        messages.push(ChatMessage::user(
+            "Continue your autonomous operation. What should you do next?",
+        ));
+    }
+
+    if policy.max_context_tokens > 0 {
+        fit_to_token_budget(&mut messages, policy.max_context_tokens);
     }
 
     messages
 }
+
+/// Rough token estimate for a request (about 4 characters per token).
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| {
+            let calls: usize = m
+                .tool_calls
+                .iter()
+                .map(|c| c.name.len() + c.arguments.to_string().len())
+                .sum();
+            (m.content.len() + calls).div_ceil(4) + MESSAGE_TOKEN_OVERHEAD
+        })
+        .sum()
+}
+
+/// Shrink `messages` until their estimate fits `budget`.
+///
+/// Oldest tool results are summarized first; if that isn't enough, the oldest
+/// history is dropped (an assistant message together with its tool results).
+/// The system prompt and the final user message are always kept.
+fn fit_to_token_budget(messages: &mut Vec<ChatMessage>, budget: usize) {
+    let before = estimate_tokens(messages);
+    if before <= budget {
+        return;
+    }
+
+    let mut summarized = 0;
+    let mut dropped = 0;
+    while estimate_tokens(messages) > budget {
+        let last = messages.len() - 1;
+        if let Some(i) = (1..last).find(|&i| is_summarizable(&messages[i])) {
+            messages[i] = summarize_tool_result(&messages[i]);
+            summarized += 1;
+            continue;
+        }
+        if messages.len() <= 2 {
+            warn!(
+                "Request still ~{} tokens after trimming (budget {})",
+                estimate_tokens(messages),
+                budget
+            );
+            break;
+        }
+
+        // Drop the oldest history message with any tool results that follow it
+        let mut end = 2;
+        while end < last && messages[end].role == ChatRole::Tool {
+            end += 1;
+        }
+        dropped += end - 1;
+        messages.drain(1..end);
+    }
+
+    info!(
+        "Trimmed request from ~{} to ~{} tokens (budget {}): {} summarized, {} dropped",
+        before,
+        estimate_tokens(messages),
+        budget,
+        summarized,
+        dropped
+    );
+}
+
+/// Whether a message is a tool result that can still be shortened.
+fn is_summarizable(msg: &ChatMessage) -> bool {
+    msg.role == ChatRole::Tool
+        && msg.content.chars().count() > TOOL_SUMMARY_CHARS
+        && !msg.content.contains(SUMMARY_MARKER)
+}
+
+/// Shorten a tool result to a prefix plus a truncation note.
+fn summarize_tool_result(msg: &ChatMessage) -> ChatMessage {
+    let mut summarized = msg.clone();
+    if is_summarizable(msg) {
+        let prefix: String = msg.content.chars().take(TOOL_SUMMARY_CHARS).collect();
+        summarized.content = format!(
+            "{}{}, {} chars total]",
+            prefix,
+            SUMMARY_MARKER,
+            msg.content.len()
+        );
+    }
+    summarized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(id: &str) -> ToolCall {
+        ToolCall {
+            id: id.into(),
+            name: "exec".into(),
+            arguments: serde_json::json!({ "command": "ls" }),
+        }
+    }
+
+    /// assistant(c1,c2), tool(c1), tool(c2), assistant(c3), tool(c3)
+    fn history() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::assistant("", vec![call("c1"), call("c2")]),
+            ChatMessage::tool("c1", "out 1"),
+            ChatMessage::tool("c2", "out 2"),
+            ChatMessage::assistant("", vec![call("c3")]),
+            ChatMessage::tool("c3", "out 3"),
+        ]
+    }
+
+    /// One system message first, this turn's prompt last.
+    fn assert_grounded(messages: &[ChatMessage], prompt: &str) {
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert_eq!(messages.iter().filter(|m| m.role == ChatRole::System).count(), 1);
+        let last = messages.last().unwrap();
+        assert_eq!(last.role, ChatRole::User);
+        assert_eq!(last.content, prompt);
+    }
+
+    /// Every tool message must follow an assistant message that made its call.
+    fn assert_paired(messages: &[ChatMessage]) {
+        let mut open: Vec<String> = Vec::new();
+        for m in messages {
+            match m.role {
+                ChatRole::Assistant => open = m.tool_calls.iter().map(|c| c.id.clone()).collect(),
+                ChatRole::Tool => {
+                    let id = m.tool_call_id.as_deref().unwrap();
+                    assert!(open.iter().any(|o| o == id), "orphaned tool result {}", id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_window_never_splits_tool_call_from_results() {
+        let policy = HistoryPolicy {
+            window: 3, // would start at tool(c2)
+            verbatim_tool_results: 10,
+            max_context_tokens: 0,
+        };
+        let messages = build_messages("sys", "", &history(), &policy);
+        assert_paired(&messages);
+        // system + assistant(c3) + tool(c3) + user
+        assert_eq!(messages.len(), 4);
+
+        let mut trimmed = history();
+        trim_history(&mut trimmed, 4);
+        assert_paired(&trimmed);
+        assert_eq!(trimmed[0].role, ChatRole::Assistant);
+    }
+
+    #[test]
+    fn test_older_tool_results_are_summarized() {
+        let mut hist = history();
+        hist[1].content = "x".repeat(1000);
+        let policy = HistoryPolicy {
+            window: 20,
+            verbatim_tool_results: 1,
+            max_context_tokens: 0,
+        };
+        let messages = build_messages("sys", "", &hist, &policy);
+        assert!(messages[2].content.contains("older result summarized, 1000 chars total"));
+        assert_eq!(messages[5].content, "out 3");
+    }
+
+    #[test]
+    fn test_oversized_history_trimmed_to_token_budget() {
+        let mut hist = history();
+        for msg in hist.iter_mut().filter(|m| m.role == ChatRole::Tool) {
+            msg.content = "y".repeat(8_000); // ~2k tokens each
+        }
+        let policy = HistoryPolicy {
+            window: 20,
+            verbatim_tool_results: 10,
+            max_context_tokens: 1_000,
+        };
+
+        let unbounded = build_messages(
+            "sys",
+            "what now?",
+            &hist,
+            &HistoryPolicy {
+                max_context_tokens: 0,
+                ..policy
+            },
+        );
+        assert!(estimate_tokens(&unbounded) > 6_000);
+
+        let messages = build_messages("sys", "what now?", &hist, &policy);
+        assert!(estimate_tokens(&messages) <= 1_000);
+        assert_eq!(messages[0].content, "sys");
+        assert_eq!(messages.last().unwrap().content, "what now?");
+        assert_paired(&messages);
+
+        // A tiny budget drops history but keeps the system prompt and prompt
+        let messages = build_messages(
+            "sys",
+            "what now?",
+            &hist,
+            &HistoryPolicy {
+                max_context_tokens: 50,
+                ..policy
+            },
+        );
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn test_pathological_histories_stay_grounded() {
+        let spam = |n: usize| -> Vec<ChatMessage> {
+            (0..n)
+                .map(|i| ChatMessage::tool(format!("c{}", i), "z".repeat(4_000)))
+                .collect()
+        };
+        // Tool spam behind one assistant message, and a stray system message
+        let calls = (0..30).map(|i| call(&format!("c{}", i))).collect();
+        let mut behind_call = vec![ChatMessage::assistant("", calls)];
+        behind_call.extend(spam(30));
+        let mut with_system = history();
+        with_system.insert(2, ChatMessage::system("stale system prompt"));
+
+        let histories = [Vec::new(), spam(40), behind_call, with_system];
+        for hist in &histories {
+            for window in [0, 1, 3, 20, 100] {
+                for max_context_tokens in [0, 1, 60, 2_000] {
+                    let policy = HistoryPolicy {
+                        window,
+                        verbatim_tool_results: 2,
+                        max_context_tokens,
+                    };
+                    let messages = build_messages("sys", "what now?", hist, &policy);
+                    assert_grounded(&messages, "what now?");
+                    assert_paired(&messages);
+
+                    let mut trimmed = hist.clone();
+                    trim_history(&mut trimmed, window);
+                    let messages = build_messages("sys", "", &trimmed, &policy);
+                    assert_grounded(
+                        &messages,
+                        "Continue your autonomous operation. What should you do next?",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_inbox_overflow_is_summarized_and_left_unread() {
+        let db = Database::open_memory().unwrap();
+        let start = chrono::Utc::now() - chrono::Duration::minutes(10);
+        for (i, from) in ["0xaa", "0xaa", "0xbb", "0xaa", "0xcc"].iter().enumerate() {
+            db.save_inbox_message(&InboxMessage {
+                id: format!("m{}", i),
+                from_address: from.to_string(),
+                to_address: "0x01".into(),
+                content: format!("message {}", i),
+                timestamp: start + chrono::Duration::minutes(i as i64),
+                read: false,
+                signature: None,
+            })
+            .unwrap();
+        }
+
+        let context = build_turn_context(&db, "", 2);
+        assert!(context.text.contains("message 0") && context.text.contains("message 1"));
+        assert!(!context.text.contains("message 2"));
+        assert!(
+            context
+                .text
+                .contains("… and 3 more unread messages from `0xbb` (1), `0xaa` (1), `0xcc` (1)"),
+            "{}",
+            context.text
+        );
+        assert_eq!(context.message_ids, ["m0", "m1"]);
+        // Building the context reads nothing
+        assert_eq!(db.unread_messages().unwrap().len(), 5);
+
+        mark_consumed(&db, &context).unwrap();
+        let next = build_turn_context(&db, "", 2);
+        assert_eq!(next.message_ids, ["m2", "m3"]);
+        assert!(next.text.contains("… and 1 more unread messages from `0xcc` (1)"));
+
+        // No limit
+        assert_eq!(build_turn_context(&db, "", 0).message_ids.len(), 3);
+    }
+}
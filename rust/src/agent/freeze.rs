@@ -0,0 +1,88 @@
+//! The spending freeze: a panic button that keeps the agent alive.
+//!
+//! While `FREEZE` exists in the home directory the loop runs no inference;
+//! spending tools (`discretionary_tools`), spending heartbeat tasks, credit
+//! transfers and x402 payments on any Conway call are refused. Everything
+//! else carries on: heartbeats, balance checks and the status server, so
+//! the operator can watch while deciding what to do.
+//! `automaton freeze` writes the file with a reason; `automaton unfreeze`
+//! (or deleting it) lifts the freeze.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+
+/// File whose presence freezes spending.
+pub const FREEZE_FILE: &str = "FREEZE";
+
+/// Path of the freeze file under `home_dir`.
+pub fn freeze_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(FREEZE_FILE)
+}
+
+/// Whether spending is frozen.
+pub fn is_frozen(home_dir: &Path) -> bool {
+    freeze_path(home_dir).exists()
+}
+
+/// Freeze spending, recording when and why. Returns `false` if it already
+/// was frozen (the original reason is kept).
+pub fn freeze(home_dir: &Path, reason: &str) -> Result<bool> {
+    let path = freeze_path(home_dir);
+    if path.exists() {
+        return Ok(false);
+    }
+    std::fs::write(&path, format!("{}\n{}\n", Utc::now().to_rfc3339(), reason))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+/// Lift the freeze. Returns `false` if spending wasn't frozen.
+pub fn unfreeze(home_dir: &Path) -> Result<bool> {
+    let path = freeze_path(home_dir);
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+/// Fail if spending is frozen, naming `what` was refused.
+pub fn ensure_not_frozen(home_dir: &Path, what: &str) -> Result<()> {
+    if is_frozen(home_dir) {
+        bail!(
+            "{} refused: spending is frozen ({} exists; run `automaton unfreeze`)",
+            what,
+            freeze_path(home_dir).display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freeze_and_unfreeze() {
+        let home = std::env::temp_dir().join(format!("automaton-freeze-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&home).unwrap();
+
+        assert!(!is_frozen(&home));
+        ensure_not_frozen(&home, "Inference").unwrap();
+
+        assert!(freeze(&home, "Investigating odd spend").unwrap());
+        assert!(!freeze(&home, "Again").unwrap());
+        assert!(is_frozen(&home));
+        let contents = std::fs::read_to_string(freeze_path(&home)).unwrap();
+        assert!(contents.contains("Investigating odd spend"), "{}", contents);
+        let err = ensure_not_frozen(&home, "Inference").unwrap_err();
+        assert!(err.to_string().starts_with("Inference refused: spending is frozen"), "{}", err);
+
+        assert!(unfreeze(&home).unwrap());
+        assert!(!unfreeze(&home).unwrap());
+        assert!(!is_frozen(&home));
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+//! Hibernation: surviving death by waiting for a top-up.
+//!
+//! With `hibernate_when_dead` set, a Dead agent doesn't leave the loop.
+//! It checks its credit balance with Conway every `hibernation_poll_mins`
+//! instead, and goes back to running turns as soon as the balance is above
+//! zero again.
+
+use crate::config::AutomatonConfig;
+use crate::conway;
+use crate::state::Database;
+use crate::tools::balance::record_credits;
+use crate::types::{AgentState, SurvivalTier};
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Poll the credit balance every `poll` until it is topped up (`true`, with
+/// the agent marked running again) or `cancel` fires (`false`).
+pub async fn hibernate(
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+    poll: Duration,
+    cancel: &CancellationToken,
+) -> Result<bool> {
+    db.lock()
+        .await
+        .kv_set("agent_state", &AgentState::Hibernating.to_string())?;
+    info!("Hibernating: checking credits every {}s", poll.as_secs());
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(poll) => {}
+            _ = cancel.cancelled() => return Ok(false),
+        }
+
+        let balance = match conway::credits::check_credits(&config.conway_api_url, &config.conway_api_key).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                warn!("Hibernation credit check failed: {:#}", e);
+                continue;
+            }
+        };
+        let tier = SurvivalTier::from_balance(balance.credits);
+        let db = db.lock().await;
+        db.transaction(|db| {
+            record_credits(db, balance.credits)?;
+            db.kv_set("survival_tier", &tier.to_string())
+        })?;
+        if tier != SurvivalTier::Dead {
+            info!("Credits topped up to {} {} — resuming", balance.credits, balance.currency);
+            db.kv_set("agent_state", &AgentState::Running.to_string())?;
+            return Ok(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_top_up_during_hibernation_resumes_running() {
+        // Still broke for two checks, then topped up
+        let checks = Arc::new(AtomicUsize::new(0));
+        let seen = checks.clone();
+        let server = MockServer::start(move |_| {
+            let credits = if seen.fetch_add(1, Ordering::SeqCst) < 2 { 0.0 } else { 5.0 };
+            MockResponse::json(200, json!({ "credits": credits, "currency": "USD" }))
+        })
+        .await;
+        let config = AutomatonConfig {
+            conway_api_url: server.url(),
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        db.lock().await.kv_set("credits_balance", "0").unwrap();
+
+        let resumed = hibernate(&config, &db, Duration::from_millis(10), &CancellationToken::new())
+            .await
+            .unwrap();
+        assert!(resumed);
+        assert_eq!(checks.load(Ordering::SeqCst), 3);
+        let db = db.lock().await;
+        assert_eq!(db.kv_get("agent_state").unwrap().as_deref(), Some("running"));
+        assert_eq!(db.kv_get_f64("credits_balance").unwrap(), Some(5.0));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_ends_hibernation() {
+        let url = crate::test_support::unreachable_url().await;
+        let config = AutomatonConfig {
+            conway_api_url: url,
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        assert!(!hibernate(&config, &db, Duration::from_secs(60), &cancel).await.unwrap());
+        assert_eq!(
+            db.lock().await.kv_get("agent_state").unwrap().as_deref(),
+            Some("hibernating")
+        );
+    }
+}
@@ -7,11 +7,15 @@
 //! 4. Executes tool calls
 //! 5. Persists the turn
 //! 6. Repeats
+//!
+//! Steps 2–5 live in [`TurnRunner`] so interactive sessions can drive single
+//! turns without the scheduling around them.
 
-use crate::agent::{context, system_prompt};
+use crate::agent::{context, freeze, hibernation, quiet_hours, sleep, system_prompt};
 use crate::config::AutomatonConfig;
-use crate::conway::{ConwayClient, InferenceClient};
+use crate::conway::{self, ConwayClient, InferenceClient};
 use crate::state::Database;
+use crate::survival::{postmortem, SurvivalMonitor};
 use crate::tools;
 use crate::types::*;
 use anyhow::Result;
@@ -21,6 +25,327 @@ use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
+// ---------------------------------------------------------------------------
+// Single turns
+// ---------------------------------------------------------------------------
+
+/// The result of one completed turn.
+#[derive(Debug, Clone)]
+pub struct TurnOutcome {
+    /// The persisted turn (tool calls, results, usage, model).
+    pub turn: Turn,
+    /// Text the model returned alongside its tool calls, if any.
+    pub content: Option<String>,
+    /// Whether the turn was saved to the database.
+    pub persisted: bool,
+}
+
+impl TurnOutcome {
+    /// Whether the model produced neither text nor tool calls.
+    pub fn is_idle(&self) -> bool {
+        self.turn.tool_calls.is_empty() && self.content.is_none()
+    }
+}
+
+/// Runs inference turns against a shared conversation history.
+pub struct TurnRunner {
+    config: AutomatonConfig,
+    db: Arc<Mutex<Database>>,
+    inference: InferenceClient,
+    skills: Vec<Skill>,
+    tool_ctx: tools::ToolContext,
+    history_policy: context::HistoryPolicy,
+    history: Vec<ChatMessage>,
+    retain_prompts: bool,
+}
+
+impl TurnRunner {
+    pub fn new(
+        config: AutomatonConfig,
+        db: Arc<Mutex<Database>>,
+        conway: ConwayClient,
+        inference: InferenceClient,
+        skills: Vec<Skill>,
+    ) -> Self {
+        let tool_ctx = tools::ToolContext {
+            conway,
+            db: db.clone(),
+            wallet_address: config.wallet_address.clone(),
+            config: config.clone(),
+            allowed_tools: tools::skill_tool_allowlist(&skills),
+            simulated_tier: None,
+            cancel: CancellationToken::new(),
+        };
+
+        Self {
+            history_policy: context::HistoryPolicy::from_config(&config),
+            inference: inference.with_payload_capture(config.debug_inference_logging),
+            config,
+            db,
+            skills,
+            tool_ctx,
+            history: Vec::new(),
+            retain_prompts: false,
+        }
+    }
+
+    /// Keep each turn's prompt in the conversation history.
+    ///
+    /// Autonomous turns are prompted with a fresh status snapshot that would
+    /// only waste the window; chat sessions need the user's lines kept.
+    pub fn retain_prompts(mut self, retain: bool) -> Self {
+        self.retain_prompts = retain;
+        self
+    }
+
+    /// End waits inside tool calls (such as for an approval) when `cancel`
+    /// fires.
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.tool_ctx.cancel = cancel;
+        self
+    }
+
+    /// Run every turn at `tier` whatever the balance says, tagging the turns
+    /// `simulated:<tier>` so activity metrics leave them out.
+    pub fn simulate_tier(mut self, tier: SurvivalTier) -> Self {
+        self.tool_ctx.simulated_tier = Some(tier);
+        self
+    }
+
+    /// Current survival tier: the simulated one, else the one the last
+    /// recorded credit balance gives, refreshed first if it has outlived
+    /// `balance_ttl_secs`.
+    pub async fn survival_tier(&self) -> SurvivalTier {
+        if self.tool_ctx.simulated_tier.is_none() {
+            if let Err(e) = SurvivalMonitor::new(self.db.clone()).check_fresh(&self.config).await {
+                warn!("Balance freshness check failed: {:#}", e);
+            }
+        }
+        match self.tool_ctx.survival_tier().await {
+            Ok(tier) => tier,
+            Err(e) => {
+                warn!("Assuming normal survival tier: {:#}", e);
+                SurvivalTier::Normal
+            }
+        }
+    }
+
+    /// Run one turn: call inference with `prompt` as the user message,
+    /// execute the returned tool calls, and persist the turn.
+    pub async fn run_turn(&mut self, survival_tier: SurvivalTier, prompt: &str) -> Result<TurnOutcome> {
+        let config = &self.config;
+        freeze::ensure_not_frozen(&config.home_dir(), "Inference")?;
+
+        // Offer only the tools this tier can afford and the active skills allow
+        let tool_defs = self.tool_ctx.available_tools(survival_tier);
+
+        // Build system prompt
+        let system_prompt = {
+            let db_lock = self.db.lock().await;
+            system_prompt::build_system_prompt(config, &db_lock, survival_tier, &self.skills)
+        };
+
+        // Build messages
+        let messages =
+            context::build_messages(&system_prompt, prompt, &self.history, &self.history_policy);
+
+        // Select model based on survival tier, with configured fallbacks
+        let models = config.model_chain(survival_tier != SurvivalTier::Normal);
+
+        // Call inference
+        let response = self
+            .inference
+            .chat_with_fallback(&models, &messages, &tool_defs, config.max_tokens_per_turn)
+            .await?;
+
+        // Process response
+        let turn_number = {
+            let db_lock = self.db.lock().await;
+            db_lock.next_turn_number()?
+        };
+
+        // Whitespace-only text counts as no text: it isn't logged, kept in
+        // history or treated as activity
+        let content = response
+            .content
+            .clone()
+            .filter(|content| !content.trim().is_empty());
+
+        // If the model returned text, log it
+        if let Some(ref content) = content {
+            info!("[Turn {}] Agent: {}", turn_number, &content[..content.len().min(200)]);
+        }
+
+        if self.retain_prompts && !prompt.is_empty() {
+            self.history.push(ChatMessage::user(prompt));
+        }
+
+        // Execute tool calls
+        let mut tool_results = Vec::new();
+        let limit = config.max_tool_calls_per_turn as usize;
+        let tool_call_count = response.tool_calls.len().min(limit);
+
+        // Record the assistant message with every call it made so that each
+        // tool result below stays paired with its request
+        if content.is_some() || !response.tool_calls.is_empty() {
+            self.history.push(ChatMessage::assistant(
+                content.clone().unwrap_or_default(),
+                response.tool_calls.clone(),
+            ));
+        }
+
+        let (calls, dropped) = response.tool_calls.split_at(tool_call_count);
+        for tc in calls {
+            info!("[Turn {}] Tool: {}({})", turn_number, tc.name, tc.arguments);
+        }
+        let results =
+            tools::execute_tool_calls(&self.tool_ctx, calls, config.tool_concurrency).await;
+
+        for (tc, result) in calls.iter().zip(results) {
+            if result.success {
+                info!("[Turn {}] Tool result: {} chars", turn_number, result.output.len());
+            } else {
+                warn!("[Turn {}] Tool error: {}", turn_number, result.output);
+            }
+
+            // Add tool result to conversation (binary payloads as a data URL)
+            let content = match &result.attachment {
+                Some(attachment) => format!(
+                    "[{}] {}\n{}",
+                    tc.name,
+                    result.output,
+                    attachment.to_data_url()
+                ),
+                None => format!("[{}] {}", tc.name, result.output),
+            };
+            self.history.push(ChatMessage::tool(&tc.id, content));
+
+            tool_results.push(result);
+        }
+
+        // Calls over the limit still get a result, so none is left dangling
+        if !dropped.is_empty() {
+            warn!(
+                "[Turn {}] Skipping {} tool calls over the per-turn limit of {}",
+                turn_number,
+                dropped.len(),
+                limit
+            );
+        }
+        for tc in dropped {
+            let result = ToolResult {
+                tool_call_id: tc.id.clone(),
+                output: format!(
+                    "Skipped: exceeded the limit of {} tool calls per turn. Call it again next turn if still needed.",
+                    limit
+                ),
+                success: false,
+                attachment: None,
+            };
+            self.history
+                .push(ChatMessage::tool(&tc.id, format!("[{}] {}", tc.name, result.output)));
+            tool_results.push(result);
+        }
+
+        // The last successful label_turn names the turn, unless it's simulated
+        let simulated = self
+            .tool_ctx
+            .simulated_tier
+            .map(|tier| format!("{}{}", SIMULATED_TAG_PREFIX, tier));
+        let tag = simulated.or_else(|| {
+            calls
+                .iter()
+                .zip(&tool_results)
+                .rev()
+                .filter(|(tc, result)| tc.name == "label_turn" && result.success)
+                .find_map(|(tc, _)| tools::turn_tag(&tc.arguments).ok())
+        });
+
+        // The first successful declare_intent states what the turn was for
+        let intent = calls
+            .iter()
+            .zip(&tool_results)
+            .filter(|(tc, result)| tc.name == "declare_intent" && result.success)
+            .find_map(|(tc, _)| tools::turn_intent(&tc.arguments).ok());
+
+        // Estimate cost
+        let cost =
+            InferenceClient::estimate_cost(&response.model, &response.usage, &config.pricing);
+
+        // Persist turn
+        let turn = Turn {
+            id: ulid::Ulid::new().to_string(),
+            turn_number,
+            state: AgentState::Running,
+            messages,
+            tool_calls: response.tool_calls.clone(),
+            tool_results,
+            token_usage: response.usage.clone(),
+            cost_estimate_usd: cost,
+            model: response.model.clone(),
+            tag,
+            intent,
+            created_at: Utc::now(),
+        };
+
+        let persisted = {
+            let db_lock = self.db.lock().await;
+            let persisted = match db_lock.save_turn(&turn) {
+                Ok(()) => {
+                    if let Some(exchange) = &response.exchange {
+                        if let Err(e) = db_lock.log_inference(&turn.id, exchange) {
+                            warn!("Failed to store the inference log: {}", e);
+                        }
+                    }
+                    true
+                }
+                Err(e) => {
+                    error!("Failed to persist turn: {}", e);
+                    false
+                }
+            };
+            db_lock.kv_set("agent_state", &AgentState::Running.to_string())?;
+            persisted
+        };
+
+        // Trim conversation history to the same window build_messages uses
+        context::trim_history(&mut self.history, self.history_policy.window);
+
+        Ok(TurnOutcome {
+            turn,
+            content,
+            persisted,
+        })
+    }
+
+    /// Run a turn prompted with `context`, then mark the inbox messages it
+    /// showed as read, but only once the turn is persisted: a failed turn
+    /// sees them again next time.
+    pub async fn run_context_turn(
+        &mut self,
+        survival_tier: SurvivalTier,
+        context: &context::TurnContext,
+    ) -> Result<TurnOutcome> {
+        let outcome = self.run_turn(survival_tier, &context.text).await?;
+        if outcome.persisted {
+            context::mark_consumed(&*self.db.lock().await, context)?;
+        }
+        Ok(outcome)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Autonomous loop
+// ---------------------------------------------------------------------------
+
+/// Longest wait honored from a rate-limit response; a larger `Retry-After`
+/// is treated as this.
+const MAX_RATE_LIMIT_WAIT: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// How often a sleeping or frozen loop re-checks, so an external
+/// `automaton wake` or `unfreeze` takes effect promptly.
+const SLEEP_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// Run the main agent loop until shutdown.
 ///
 /// The loop exits cooperatively when `cancel` is triggered.
@@ -32,18 +357,20 @@ pub async fn run_agent_loop(
     skills: Vec<Skill>,
     cancel: CancellationToken,
 ) -> Result<()> {
+    // Refuse to run alongside another process driving this home
+    let _run_lock = crate::agent::run_lock::RunLock::acquire(&db, cancel.clone()).await?;
     info!("Starting agent loop for '{}'", config.name);
 
-    let tool_defs = tools::tool_definitions();
-    let tool_ctx = tools::ToolContext {
-        conway: conway.clone(),
-        db: db.clone(),
-        wallet_address: config.wallet_address.clone(),
-        config: config.clone(),
-    };
+    // Sandboxes orphaned by a crash mid-spawn in a previous run
+    if let Err(e) =
+        crate::replication::recovery::reconcile_pending_creates(&config, &conway, &db).await
+    {
+        warn!("Reconciling interrupted sandbox creation failed: {:#}", e);
+    }
 
+    let mut runner = TurnRunner::new(config.clone(), db.clone(), conway, inference, skills)
+        .with_cancel(cancel.clone());
     let mut consecutive_errors: u32 = 0;
-    let mut conversation_history: Vec<ChatMessage> = Vec::new();
 
     loop {
         // Check for cancellation at top of each iteration
@@ -55,82 +382,126 @@ pub async fn run_agent_loop(
         // Check if we should be sleeping
         {
             let db_lock = db.lock().await;
-            if let Ok(Some(sleep_until)) = db_lock.kv_get("sleep_until") {
-                if let Ok(wake_time) = chrono::DateTime::parse_from_rfc3339(&sleep_until) {
-                    if Utc::now() < wake_time {
-                        drop(db_lock);
-                        info!("Sleeping until {}", sleep_until);
-                        tokio::select! {
-                            _ = tokio::time::sleep(tokio::time::Duration::from_secs(60)) => {}
-                            _ = cancel.cancelled() => {
-                                info!("Agent loop received shutdown signal during sleep");
-                                break;
-                            }
-                        }
-                        continue;
+            if let Ok(Some(until)) = sleep::sleeping_until(&db_lock) {
+                drop(db_lock);
+                info!("Sleeping until {}", until.to_rfc3339());
+                tokio::select! {
+                    _ = tokio::time::sleep(SLEEP_POLL_INTERVAL) => {}
+                    _ = cancel.cancelled() => {
+                        info!("Agent loop received shutdown signal during sleep");
+                        break;
                     }
                 }
-                // Sleep expired, clear it
-                let _ = db_lock.kv_delete("sleep_until");
+                continue;
             }
+            // Sleep expired (or was never set), clear it
+            let _ = db_lock.kv_delete(sleep::SLEEP_UNTIL_KEY);
         }
 
-        // Determine survival tier
-        let survival_tier = {
+        // Spending frozen: no inference until the operator unfreezes
+        if freeze::is_frozen(&config.home_dir()) {
+            {
+                let db_lock = db.lock().await;
+                if db_lock.kv_get("agent_state")?.as_deref() != Some("frozen") {
+                    warn!("Spending frozen — idling until `automaton unfreeze`");
+                }
+                db_lock.kv_set("agent_state", &AgentState::Frozen.to_string())?;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(SLEEP_POLL_INTERVAL) => {}
+                _ = cancel.cancelled() => {
+                    info!("Agent loop received shutdown signal while frozen");
+                    break;
+                }
+            }
+            continue;
+        }
+
+        // Idle through the operator's quiet hours
+        if let Some(quiet) = &config.quiet_hours {
             let db_lock = db.lock().await;
-            match db_lock.kv_get("credits_balance") {
-                Ok(Some(balance)) => {
-                    SurvivalTier::from_balance(balance.parse::<f64>().unwrap_or(1.0))
+            match quiet_hours::defer_turn(quiet, &db_lock, Utc::now()) {
+                Ok(Some(end)) => {
+                    info!("Quiet hours: sleeping until {}", end.to_rfc3339());
+                    db_lock.kv_set("agent_state", &AgentState::Sleeping.to_string())?;
+                    continue;
                 }
-                _ => SurvivalTier::Normal, // Assume normal if unknown
+                Ok(None) => {}
+                Err(e) => warn!("Ignoring quiet hours: {:#}", e),
             }
-        };
+        }
+
+        // Determine survival tier
+        let survival_tier = runner.survival_tier().await;
 
-        // If dead, halt
+        // If dead, write the postmortem (once), then halt or hibernate
         if survival_tier == SurvivalTier::Dead {
-            warn!("Survival tier: DEAD — halting agent loop");
-            let db_lock = db.lock().await;
-            db_lock.kv_set("agent_state", &AgentState::Dead.to_string())?;
+            let already_dead = {
+                let db_lock = db.lock().await;
+                let previous = db_lock.kv_get("agent_state")?;
+                db_lock.kv_set("agent_state", &AgentState::Dead.to_string())?;
+                matches!(previous.as_deref(), Some("dead" | "hibernating"))
+            };
+            if !already_dead {
+                if let Err(e) = postmortem::record_death(&config, &db).await {
+                    error!("Failed to record postmortem: {}", e);
+                }
+            }
+            if !config.hibernate_when_dead {
+                warn!("Survival tier: DEAD — halting agent loop");
+                break;
+            }
+            warn!("Survival tier: DEAD — hibernating until credits are topped up");
+            let poll = std::time::Duration::from_secs(config.hibernation_poll_mins.max(1) * 60);
+            if hibernation::hibernate(&config, &db, poll, &cancel).await? {
+                continue;
+            }
+            info!("Agent loop received shutdown signal during hibernation");
             break;
         }
 
-        // Build system prompt
-        let system_prompt = {
-            let db_lock = db.lock().await;
-            system_prompt::build_system_prompt(&config, &db_lock, survival_tier, &skills)
-        };
-
         // Build turn context
         let turn_context = {
             let db_lock = db.lock().await;
-            context::build_turn_context(&db_lock)
+            context::build_turn_context(
+                &db_lock,
+                &config.creator_address,
+                config.max_inbox_messages_per_turn,
+            )
         };
 
-        // Build messages
-        let messages =
-            context::build_messages(&system_prompt, &turn_context, &conversation_history);
-
-        // Select model based on survival tier
-        let model = config.effective_model(survival_tier != SurvivalTier::Normal);
-
-        // Call inference
-        let response = match inference
-            .chat(model, &messages, &tool_defs, config.max_tokens_per_turn)
-            .await
-        {
-            Ok(resp) => {
+        // Think, act, observe
+        let outcome = match runner.run_context_turn(survival_tier, &turn_context).await {
+            Ok(outcome) => {
                 consecutive_errors = 0;
-                resp
+                outcome
+            }
+            // Wait as long as the server asked; being throttled isn't a failure
+            Err(e) if conway::rate_limit::retry_after(&e).is_some() => {
+                let wait = conway::rate_limit::retry_after(&e)
+                    .unwrap_or_default()
+                    .min(MAX_RATE_LIMIT_WAIT);
+                warn!("{} — waiting {}s before the next turn", e, wait.as_secs());
+                db.lock().await.kv_set("last_error", &e.to_string())?;
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = cancel.cancelled() => { break; }
+                }
+                continue;
             }
             Err(e) => {
                 consecutive_errors += 1;
                 error!("Inference error ({}/{}): {}", consecutive_errors, config.max_consecutive_errors, e);
+                {
+                    let db_lock = db.lock().await;
+                    db_lock.kv_set("last_error", &e.to_string())?;
+                }
 
                 if consecutive_errors >= config.max_consecutive_errors {
                     warn!("Max consecutive errors reached — sleeping for 5 minutes");
                     let wake_at = Utc::now() + chrono::Duration::minutes(5);
                     let db_lock = db.lock().await;
-                    db_lock.kv_set("sleep_until", &wake_at.to_rfc3339())?;
+                    sleep::sleep_until(&db_lock, wake_at)?;
                     db_lock.kv_set("agent_state", &AgentState::Sleeping.to_string())?;
                     consecutive_errors = 0;
                 }
@@ -143,91 +514,523 @@ pub async fn run_agent_loop(
             }
         };
 
-        // Process response
-        let turn_number = {
-            let db_lock = db.lock().await;
-            db_lock.next_turn_number()?
-        };
+        // If no tool calls and no content, the model might be idle — sleep briefly
+        if outcome.is_idle() {
+            info!("No output from model — sleeping 30s");
+            tokio::select! {
+                _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {}
+                _ = cancel.cancelled() => { break; }
+            }
+        }
 
-        // If the model returned text, log it
-        if let Some(ref content) = response.content {
-            info!("[Turn {}] Agent: {}", turn_number, &content[..content.len().min(200)]);
-            conversation_history.push(ChatMessage {
-                role: ChatRole::Assistant,
-                content: content.clone(),
-            });
+        // Brief pause between turns to avoid hammering the API
+        tokio::select! {
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(2)) => {}
+            _ = cancel.cancelled() => { break; }
         }
+    }
 
-        // Execute tool calls
-        let mut tool_results = Vec::new();
-        let tool_call_count = response.tool_calls.len().min(config.max_tool_calls_per_turn as usize);
+    info!("Agent loop exited");
+    Ok(())
+}
 
-        for tc in response.tool_calls.iter().take(tool_call_count) {
-            info!("[Turn {}] Tool: {}({})", turn_number, tc.name, tc.arguments);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{unreachable_url, MockResponse, MockServer};
+    use serde_json::json;
 
-            let mut result = tools::execute_tool(&tool_ctx, &tc.name, &tc.arguments).await;
-            result.tool_call_id = tc.id.clone();
+    #[tokio::test]
+    async fn test_tool_calls_over_limit_get_skipped_results() {
+        let server = MockServer::start(|_| {
+            let calls: Vec<_> = (1..=3)
+                .map(|i| {
+                    json!({
+                        "id": format!("call-{}", i),
+                        "type": "function",
+                        "function": { "name": "recall", "arguments": "{}" }
+                    })
+                })
+                .collect();
+            MockResponse::json(
+                200,
+                json!({
+                    "choices": [{ "message": { "content": null, "tool_calls": calls } }],
+                    "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+                }),
+            )
+        })
+        .await;
+        let config = AutomatonConfig {
+            max_tool_calls_per_turn: 2,
+            ..AutomatonConfig::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let url = server.url();
+        let mut runner = TurnRunner::new(
+            config,
+            db,
+            ConwayClient::new(&url, "key", "sb-1"),
+            InferenceClient::new(&url, "key"),
+            Vec::new(),
+        );
 
-            if result.success {
-                info!("[Turn {}] Tool result: {} chars", turn_number, result.output.len());
-            } else {
-                warn!("[Turn {}] Tool error: {}", turn_number, result.output);
-            }
+        let outcome = runner.run_turn(SurvivalTier::Normal, "go").await.unwrap();
 
-            // Add tool result to conversation
-            conversation_history.push(ChatMessage {
-                role: ChatRole::Tool,
-                content: format!("[{}] {}", tc.name, result.output),
-            });
+        let call_ids: Vec<&str> = outcome.turn.tool_calls.iter().map(|c| c.id.as_str()).collect();
+        let result_ids: Vec<&str> = outcome
+            .turn
+            .tool_results
+            .iter()
+            .map(|r| r.tool_call_id.as_str())
+            .collect();
+        assert_eq!(call_ids, ["call-1", "call-2", "call-3"]);
+        assert_eq!(result_ids, call_ids);
+        assert!(outcome.turn.tool_results[..2].iter().all(|r| r.success));
+        assert!(outcome.turn.tool_results[2].output.starts_with("Skipped"));
 
-            tool_results.push(result);
-        }
+        // Every call in the history has exactly one tool message answering it
+        let answered: Vec<&str> = runner
+            .history
+            .iter()
+            .filter_map(|m| m.tool_call_id.as_deref())
+            .collect();
+        assert_eq!(answered, call_ids);
+    }
 
-        // Estimate cost
-        let cost = InferenceClient::estimate_cost(model, &response.usage);
+    #[tokio::test]
+    async fn test_missing_tool_call_ids_are_generated_and_paired() {
+        let server = MockServer::start(|_| {
+            // Two calls without ids, two sharing one
+            let calls: Vec<_> = [json!(""), json!(null), json!("dup"), json!("dup")]
+                .into_iter()
+                .map(|id| {
+                    let mut call = json!({
+                        "type": "function",
+                        "function": { "name": "recall", "arguments": "{}" }
+                    });
+                    if !id.is_null() {
+                        call["id"] = id;
+                    }
+                    call
+                })
+                .collect();
+            MockResponse::json(
+                200,
+                json!({
+                    "choices": [{ "message": { "content": null, "tool_calls": calls } }],
+                    "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+                }),
+            )
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let url = server.url();
+        let mut runner = TurnRunner::new(
+            AutomatonConfig::default(),
+            db,
+            ConwayClient::new(&url, "key", "sb-1"),
+            InferenceClient::new(&url, "key"),
+            Vec::new(),
+        );
 
-        // Persist turn
-        let turn = Turn {
-            id: ulid::Ulid::new().to_string(),
-            turn_number,
-            state: AgentState::Running,
-            messages: messages.clone(),
-            tool_calls: response.tool_calls.clone(),
-            tool_results,
-            token_usage: response.usage.clone(),
-            cost_estimate_usd: cost,
-            created_at: Utc::now(),
+        let outcome = runner.run_turn(SurvivalTier::Normal, "go").await.unwrap();
+
+        let call_ids: Vec<&str> = outcome.turn.tool_calls.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(call_ids.len(), 4);
+        assert_eq!(call_ids[2], "dup");
+        let unique: std::collections::HashSet<_> = call_ids.iter().collect();
+        assert_eq!(unique.len(), 4);
+        assert!(call_ids.iter().all(|id| !id.is_empty()));
+
+        let result_ids: Vec<&str> = outcome
+            .turn
+            .tool_results
+            .iter()
+            .map(|r| r.tool_call_id.as_str())
+            .collect();
+        assert_eq!(result_ids, call_ids);
+        let answered: Vec<&str> = runner
+            .history
+            .iter()
+            .filter_map(|m| m.tool_call_id.as_deref())
+            .collect();
+        assert_eq!(answered, call_ids);
+    }
+
+    #[tokio::test]
+    async fn test_whitespace_only_completion_is_idle() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                json!({
+                    "choices": [{ "message": { "content": " \n\t " } }],
+                    "usage": { "prompt_tokens": 10, "completion_tokens": 1, "total_tokens": 11 }
+                }),
+            )
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let url = server.url();
+        let mut runner = TurnRunner::new(
+            AutomatonConfig::default(),
+            db.clone(),
+            ConwayClient::new(&url, "key", "sb-1"),
+            InferenceClient::new(&url, "key"),
+            Vec::new(),
+        );
+
+        let outcome = runner.run_turn(SurvivalTier::Normal, "go").await.unwrap();
+
+        assert!(outcome.is_idle());
+        assert_eq!(outcome.content, None);
+        assert!(runner.history.iter().all(|m| m.role != ChatRole::Assistant));
+        // The turn itself is still recorded
+        let since = Utc::now() - chrono::Duration::minutes(1);
+        assert_eq!(db.lock().await.turns_since(since).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_inbox_messages_read_only_after_a_persisted_turn() {
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        db.lock()
+            .await
+            .save_inbox_message(&InboxMessage {
+                id: "m1".into(),
+                from_address: "0xaa".into(),
+                to_address: "0x01".into(),
+                content: "are you there?".into(),
+                timestamp: Utc::now(),
+                read: false,
+                signature: None,
+            })
+            .unwrap();
+        let turn_context = {
+            let db = db.lock().await;
+            context::build_turn_context(&db, "", 10)
+        };
+        let runner = |url: &str| {
+            TurnRunner::new(
+                AutomatonConfig::default(),
+                db.clone(),
+                ConwayClient::new(url, "key", "sb-1"),
+                InferenceClient::new(url, "key"),
+                Vec::new(),
+            )
         };
 
-        {
-            let db_lock = db.lock().await;
-            if let Err(e) = db_lock.save_turn(&turn) {
-                error!("Failed to persist turn: {}", e);
-            }
-            db_lock.kv_set("agent_state", &AgentState::Running.to_string())?;
+        // Inference fails: the message is still unread for the next turn
+        let dead = unreachable_url().await;
+        assert!(runner(&dead)
+            .run_context_turn(SurvivalTier::Normal, &turn_context)
+            .await
+            .is_err());
+        assert_eq!(db.lock().await.unread_messages().unwrap().len(), 1);
+
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                json!({ "choices": [{ "message": { "content": "Yes." } }] }),
+            )
+        })
+        .await;
+        let outcome = runner(&server.url())
+            .run_context_turn(SurvivalTier::Normal, &turn_context)
+            .await
+            .unwrap();
+        assert!(outcome.persisted);
+        assert!(db.lock().await.unread_messages().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_inference_payloads_logged_only_when_enabled() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, json!({ "choices": [{ "message": { "content": "Noted." } }] }))
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let runner = |debug_inference_logging: bool| {
+            TurnRunner::new(
+                AutomatonConfig {
+                    debug_inference_logging,
+                    ..Default::default()
+                },
+                db.clone(),
+                ConwayClient::new(&server.url(), "cnwy_inference_key", "sb-1"),
+                InferenceClient::new(&server.url(), "cnwy_inference_key"),
+                Vec::new(),
+            )
+        };
+
+        let off = runner(false).run_turn(SurvivalTier::Normal, "go").await.unwrap();
+        assert_eq!(db.lock().await.inference_log_for(&off.turn.id).unwrap(), None);
+
+        let prompt = "my key is cnwy_inference_key";
+        let on = runner(true).run_turn(SurvivalTier::Normal, prompt).await.unwrap();
+        let entry = db.lock().await.inference_log_for(&on.turn.id).unwrap().unwrap();
+        let request: serde_json::Value = serde_json::from_str(&entry.exchange.request_json).unwrap();
+        let response: serde_json::Value = serde_json::from_str(&entry.exchange.response_json).unwrap();
+        assert_eq!(request["model"], on.turn.model);
+        assert!(entry.exchange.request_json.contains("my key is [REDACTED]"));
+        assert!(!entry.exchange.request_json.contains("cnwy_inference_key"));
+        assert_eq!(response["choices"][0]["message"]["content"], "Noted.");
+
+        let db = db.lock().await;
+        let by_number = db.turn_id_by_number(on.turn.turn_number).unwrap();
+        assert_eq!(by_number.as_deref(), Some(on.turn.id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_simulated_critical_tier_withholds_spawn_child() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                json!({ "choices": [{ "message": { "content": "Conserving." } }] }),
+            )
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        // Plenty of credits: the real tier is normal
+        db.lock().await.kv_set_f64("credits_balance", 100.0).unwrap();
+        let mut runner = TurnRunner::new(
+            AutomatonConfig::default(),
+            db.clone(),
+            ConwayClient::new(&server.url(), "key", "sb-1"),
+            InferenceClient::new(&server.url(), "key"),
+            Vec::new(),
+        )
+        .simulate_tier(SurvivalTier::Critical);
+
+        let tier = runner.survival_tier().await;
+        assert_eq!(tier, SurvivalTier::Critical);
+        let offered: Vec<String> = runner
+            .tool_ctx
+            .available_tools(tier)
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert!(!offered.contains(&"spawn_child".to_string()));
+        assert!(offered.contains(&"read_file".to_string()));
+
+        let outcome = runner.run_turn(tier, "go").await.unwrap();
+        let sent = server.requests()[0].json();
+        let sent: Vec<&str> = sent["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["function"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(sent, offered);
+        assert_eq!(outcome.turn.tag.as_deref(), Some("simulated:critical"));
+
+        // Recorded, but kept out of activity metrics
+        let db = db.lock().await;
+        assert_eq!(db.turns_by_tag("simulated:critical").unwrap().len(), 1);
+        assert!(db.turns_since(chrono::DateTime::UNIX_EPOCH).unwrap().is_empty());
+        assert_eq!(db.turn_count().unwrap(), 0);
+        assert!(db.turn_tag_stats().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_label_turn_tags_the_persisted_turn() {
+        let server = MockServer::start(|_| {
+            let call = |id: &str, tag: &str| {
+                json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {
+                        "name": "label_turn",
+                        "arguments": json!({ "tag": tag }).to_string()
+                    }
+                })
+            };
+            MockResponse::json(
+                200,
+                json!({
+                    "choices": [{ "message": {
+                        "content": null,
+                        "tool_calls": [call("call-1", "bad tag!"), call("call-2", "Earning")]
+                    } }],
+                    "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+                }),
+            )
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let url = server.url();
+        let mut runner = TurnRunner::new(
+            AutomatonConfig::default(),
+            db.clone(),
+            ConwayClient::new(&url, "key", "sb-1"),
+            InferenceClient::new(&url, "key"),
+            Vec::new(),
+        );
+
+        let outcome = runner.run_turn(SurvivalTier::Normal, "go").await.unwrap();
+        assert!(!outcome.turn.tool_results[0].success);
+        assert_eq!(outcome.turn.tag.as_deref(), Some("earning"));
+
+        let db = db.lock().await;
+        let tagged = db.turns_by_tag("earning").unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].turn_number, outcome.turn.turn_number);
+        assert!(db.turns_by_tag("social").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_declared_intent_is_persisted() {
+        let server = MockServer::start(|_| {
+            let call = |id: &str, intent: &str| {
+                json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {
+                        "name": "declare_intent",
+                        "arguments": json!({ "intent": intent }).to_string()
+                    }
+                })
+            };
+            MockResponse::json(
+                200,
+                json!({
+                    "choices": [{ "message": {
+                        "content": null,
+                        "tool_calls": [
+                            call("call-1", "   "),
+                            call("call-2", "Check the balance,\n then  top up if low."),
+                            call("call-3", "Something else entirely"),
+                        ]
+                    } }],
+                    "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+                }),
+            )
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let url = server.url();
+        let mut runner = TurnRunner::new(
+            AutomatonConfig::default(),
+            db.clone(),
+            ConwayClient::new(&url, "key", "sb-1"),
+            InferenceClient::new(&url, "key"),
+            Vec::new(),
+        );
+        let before = Utc::now() - chrono::Duration::seconds(1);
+
+        let outcome = runner.run_turn(SurvivalTier::Normal, "go").await.unwrap();
+        assert!(!outcome.turn.tool_results[0].success);
+        let intent = "Check the balance, then top up if low.";
+        assert_eq!(outcome.turn.intent.as_deref(), Some(intent));
+
+        let turns = db.lock().await.turns_since(before).unwrap();
+        assert_eq!(turns.len(), 1);
+        assert_eq!(turns[0].intent.as_deref(), Some(intent));
+        let line = crate::logs::Activity::Turn(turns[0].clone()).render();
+        assert!(line.contains(&format!("(intent: {})", intent)), "{}", line);
+    }
+
+    #[tokio::test]
+    async fn test_reaching_dead_tier_writes_postmortem() {
+        let home = std::env::temp_dir().join(format!("automaton-dead-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&home).unwrap();
+        let config = AutomatonConfig {
+            name: "mortal".into(),
+            db_path: home.join("state.db").to_string_lossy().into_owned(),
+            ..AutomatonConfig::default()
+        };
+
+        let db = Database::open_memory().unwrap();
+        db.kv_set("credits_balance", "0").unwrap();
+        db.kv_set("last_error", "Inference failed (402): out of credits").unwrap();
+        db.record_transaction("inference", -0.42, "credits", "last turns", Some(0.0))
+            .unwrap();
+        let db = Arc::new(Mutex::new(db));
+
+        let url = unreachable_url().await;
+        run_agent_loop(
+            config,
+            db.clone(),
+            ConwayClient::new(&url, "key", "sb-1"),
+            InferenceClient::new(&url, "key"),
+            Vec::new(),
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let report = std::fs::read_to_string(home.join("postmortem.md")).unwrap();
+        assert!(report.starts_with("# Postmortem: mortal"));
+        for section in ["## Lifetime", "## Final balances", "## Recent transactions", "## Last error"] {
+            assert!(report.contains(section), "missing {}", section);
         }
+        assert!(report.contains("out of credits"));
+        assert!(report.contains("inference -0.4200 credits — last turns"));
+        assert_eq!(
+            db.lock().await.kv_get("agent_state").unwrap().as_deref(),
+            Some("dead")
+        );
 
-        // If no tool calls and no content, the model might be idle — sleep briefly
-        if response.tool_calls.is_empty() && response.content.is_none() {
-            info!("No output from model — sleeping 30s");
-            tokio::select! {
-                _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {}
-                _ = cancel.cancelled() => { break; }
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_freeze_stops_inference_but_not_heartbeats() {
+        let home = std::env::temp_dir().join(format!("automaton-frozen-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&home).unwrap();
+        freeze::freeze(&home, "Checking the bills").unwrap();
+        let config = AutomatonConfig {
+            db_path: home.join("state.db").to_string_lossy().into_owned(),
+            balance_ttl_secs: 0,
+            ..AutomatonConfig::default()
+        };
+        let inference = MockServer::start(|_| MockResponse::json(500, json!({}))).await;
+        let conway_url = unreachable_url().await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+
+        let cancel = CancellationToken::new();
+        let handle = tokio::spawn(run_agent_loop(
+            config.clone(),
+            db.clone(),
+            ConwayClient::new(&conway_url, "key", "sb-1"),
+            InferenceClient::new(&inference.url(), "key"),
+            Vec::new(),
+            cancel.clone(),
+        ));
+
+        // Heartbeats carry on; only the spending ones stand down
+        let ping = crate::heartbeat::tasks::execute_task("heartbeat_ping", &serde_json::Value::Null, &config, &db)
+            .await
+            .unwrap();
+        assert_eq!(ping, "pong");
+        let register =
+            crate::heartbeat::tasks::execute_task("register_onchain", &serde_json::Value::Null, &config, &db)
+                .await
+                .unwrap();
+        assert_eq!(register, "Skipped: spending is frozen");
+
+        for _ in 0..50 {
+            if db.lock().await.kv_get("agent_state").unwrap().as_deref() == Some("frozen") {
+                break;
             }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
         }
+        cancel.cancel();
+        handle.await.unwrap().unwrap();
+        assert_eq!(db.lock().await.kv_get("agent_state").unwrap().as_deref(), Some("frozen"));
+        assert!(db.lock().await.kv_get("last_heartbeat").unwrap().is_some());
 
-        // Brief pause between turns to avoid hammering the API
-        tokio::select! {
-            _ = tokio::time::sleep(tokio::time::Duration::from_secs(2)) => {}
-            _ = cancel.cancelled() => { break; }
-        }
+        // Turns run outside the loop are refused too
+        let mut runner = TurnRunner::new(
+            config,
+            db.clone(),
+            ConwayClient::new(&conway_url, "key", "sb-1"),
+            InferenceClient::new(&inference.url(), "key"),
+            Vec::new(),
+        );
+        let err = runner.run_turn(SurvivalTier::Normal, "hello").await.unwrap_err();
+        assert!(err.to_string().contains("spending is frozen"), "{}", err);
+        assert!(inference.requests().is_empty());
 
-        // Trim conversation history to avoid unbounded growth
-        if conversation_history.len() > 40 {
-            conversation_history.drain(..conversation_history.len() - 30);
-        }
+        std::fs::remove_dir_all(home).unwrap();
     }
-
-    info!("Agent loop exited");
-    Ok(())
 }
@@ -1,6 +1,12 @@
 pub mod context;
+pub mod freeze;
+pub mod hibernation;
 pub mod injection_defense;
 pub mod loop_;
+pub mod quiet_hours;
+pub mod repl;
+pub mod run_lock;
+pub mod sleep;
 pub mod system_prompt;
 
-pub use loop_::run_agent_loop;
+pub use loop_::{run_agent_loop, TurnRunner};
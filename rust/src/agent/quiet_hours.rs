@@ -0,0 +1,122 @@
+//! Operator-configured quiet hours.
+//!
+//! Inside the `quiet_hours` window the loop puts the agent to sleep until
+//! the window ends, and the heartbeat only runs survival-critical tasks.
+//! The sleep is an ordinary `sleep_until`, so anything that wakes the agent
+//! (an inbox message, `automaton wake`) still does; once woken, the agent
+//! isn't put back to sleep until the next window.
+
+use crate::agent::sleep;
+use crate::config::QuietHours;
+use crate::state::Database;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// KV key holding the end of the quiet window the agent last slept through,
+/// so a wake inside the window sticks.
+pub const QUIET_UNTIL_KEY: &str = "quiet_hours_until";
+
+fn parse_time(s: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .with_context(|| format!("Invalid quiet hours time '{}' (expected HH:MM)", s))
+}
+
+/// End of the quiet window `now` falls in, or `None` outside quiet hours.
+/// The window may span midnight (e.g. 22:00 to 06:00).
+pub fn window_end(quiet: &QuietHours, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+    let start = parse_time(&quiet.start)?;
+    let end = parse_time(&quiet.end)?;
+    let tz: Tz = quiet
+        .timezone
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid quiet hours timezone: {}", e))?;
+
+    let local = now.with_timezone(&tz);
+    let time = local.time();
+    let inside = if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    };
+    if !inside {
+        return Ok(None);
+    }
+
+    let end_date = if time < end {
+        local.date_naive()
+    } else {
+        local.date_naive() + Duration::days(1)
+    };
+    let naive_end = end_date.and_time(end);
+    // An end inside a DST gap moves to just after it
+    let end = tz
+        .from_local_datetime(&naive_end)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(naive_end + Duration::hours(1))).earliest())
+        .context("Quiet hours end does not exist in this timezone")?;
+    Ok(Some(end.with_timezone(&Utc)))
+}
+
+/// Whether `now` is inside quiet hours (never, if none are configured).
+pub fn is_quiet(quiet: Option<&QuietHours>, now: DateTime<Utc>) -> Result<bool> {
+    match quiet {
+        Some(quiet) => Ok(window_end(quiet, now)?.is_some()),
+        None => Ok(false),
+    }
+}
+
+/// Put the agent to sleep for the rest of the quiet window, unless it was
+/// already put to sleep for (and woken during) this window. Returns when
+/// the sleep ends if the turn should be deferred.
+pub fn defer_turn(quiet: &QuietHours, db: &Database, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+    let Some(end) = window_end(quiet, now)? else {
+        return Ok(None);
+    };
+    if db.kv_get_datetime(QUIET_UNTIL_KEY).unwrap_or(None) == Some(end) {
+        return Ok(None);
+    }
+    sleep::sleep_until(db, end)?;
+    db.kv_set_datetime(QUIET_UNTIL_KEY, end)?;
+    Ok(Some(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_turn_inside_quiet_hours_is_deferred() {
+        let quiet = QuietHours {
+            start: "22:00".into(),
+            end: "06:00".into(),
+            timezone: "Europe/Berlin".into(),
+        };
+        let db = Database::open_memory().unwrap();
+
+        // 15:00 in Berlin (UTC+2 in summer): the turn proceeds
+        assert_eq!(defer_turn(&quiet, &db, at("2026-07-01T13:00:00Z")).unwrap(), None);
+        assert_eq!(sleep::sleeping_until(&db).unwrap(), None);
+
+        // 23:30 in Berlin: sleep until 06:00 local the next morning
+        let end = at("2026-07-02T04:00:00Z");
+        assert_eq!(defer_turn(&quiet, &db, at("2026-07-01T21:30:00Z")).unwrap(), Some(end));
+        assert_eq!(db.kv_get_datetime(sleep::SLEEP_UNTIL_KEY).unwrap(), Some(end));
+
+        // After midnight it's the same window; a wake inside it sticks
+        sleep::wake(&db, "message from creator").unwrap();
+        assert_eq!(defer_turn(&quiet, &db, at("2026-07-02T01:00:00Z")).unwrap(), None);
+
+        // The next night is a new window
+        let next = at("2026-07-03T04:00:00Z");
+        assert_eq!(defer_turn(&quiet, &db, at("2026-07-02T20:05:00Z")).unwrap(), Some(next));
+
+        assert!(is_quiet(Some(&quiet), at("2026-07-02T03:59:00Z")).unwrap());
+        assert!(!is_quiet(Some(&quiet), at("2026-07-02T04:00:00Z")).unwrap());
+        assert!(!is_quiet(None, at("2026-07-02T03:59:00Z")).unwrap());
+    }
+}
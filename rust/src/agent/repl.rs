@@ -0,0 +1,125 @@
+//! Interactive chat session for talking to the agent directly.
+//!
+//! Each input line is run as one turn with the full system prompt and tool
+//! set; turns are persisted like autonomous ones, but the session never
+//! sleeps and ends at EOF.
+
+use crate::agent::loop_::TurnRunner;
+use anyhow::Result;
+use std::io::Write;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Prompt printed before each input line.
+const PROMPT: &str = "you> ";
+
+/// Run a chat session reading lines from `input` and writing replies to `output`.
+pub async fn run_chat<R, W>(runner: &mut TurnRunner, input: R, output: &mut W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: Write,
+{
+    let mut lines = input.lines();
+
+    loop {
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+
+        let Some(line) = lines.next_line().await? else {
+            writeln!(output)?;
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let tier = runner.survival_tier().await;
+        match runner.run_turn(tier, line).await {
+            Ok(outcome) => {
+                for (call, result) in outcome
+                    .turn
+                    .tool_calls
+                    .iter()
+                    .zip(outcome.turn.tool_results.iter())
+                {
+                    let status = if result.success { "ok" } else { "error" };
+                    writeln!(output, "  [{}: {}] {}", call.name, status, result.output)?;
+                }
+                match outcome.content {
+                    Some(content) => writeln!(output, "agent> {}", content)?,
+                    None if outcome.turn.tool_calls.is_empty() => {
+                        writeln!(output, "agent> (no response)")?
+                    }
+                    None => {}
+                }
+            }
+            Err(e) => writeln!(output, "error: {}", e)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AutomatonConfig;
+    use crate::conway::{ConwayClient, InferenceClient};
+    use crate::state::Database;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_one_chat_iteration_runs_tools_and_prints_reply() {
+        let provider = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                json!({
+                    "choices": [{ "message": {
+                        "content": "Noted.",
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "remember",
+                                "arguments": "{\"content\":\"operator likes tea\"}"
+                            }
+                        }]
+                    }}],
+                    "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+                }),
+            )
+        })
+        .await;
+
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let mut runner = TurnRunner::new(
+            AutomatonConfig::default(),
+            db.clone(),
+            ConwayClient::new(&provider.url(), "key", "sb-1"),
+            InferenceClient::new(&provider.url(), "key"),
+            Vec::new(),
+        )
+        .retain_prompts(true);
+
+        let mut output = Vec::new();
+        run_chat(&mut runner, &b"remember that I like tea\n"[..], &mut output)
+            .await
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("[remember: ok]"), "{}", output);
+        assert!(output.contains("agent> Noted."), "{}", output);
+
+        // The user's line reached the model as the final user message
+        let request = provider.requests()[0].json();
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(messages.last().unwrap()["content"], "remember that I like tea");
+
+        let db = db.lock().await;
+        assert_eq!(db.turn_count().unwrap(), 1);
+        assert_eq!(db.recent_notes(1).unwrap()[0].content, "operator likes tea");
+    }
+}
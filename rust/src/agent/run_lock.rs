@@ -0,0 +1,140 @@
+//! Advisory lock so only one process drives the agent loop for a home.
+//!
+//! The pidfile only guards `automaton daemon`; a stray `automaton run` against
+//! the same home would otherwise run turns alongside it and spend twice. The
+//! lock is a row in the shared database, kept fresh by a heartbeat. A holder
+//! that stops heartbeating (crash, `kill -9`) goes stale and can be replaced.
+
+use crate::state::Database;
+use anyhow::{bail, Result};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+/// How often the holder refreshes its heartbeat.
+pub const LOCK_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A lock whose heartbeat is older than this is considered abandoned.
+pub const LOCK_STALE_AFTER_SECS: i64 = 120;
+
+/// The held runtime lock. Dropping it stops the heartbeat and releases it.
+pub struct RunLock {
+    db: Arc<Mutex<Database>>,
+    owner: String,
+    heartbeat: JoinHandle<()>,
+}
+
+impl RunLock {
+    /// Take the lock, failing if another process holds a fresh one. If the
+    /// lock is lost later (another process found it stale), `cancel` fires.
+    pub async fn acquire(db: &Arc<Mutex<Database>>, cancel: CancellationToken) -> Result<Self> {
+        let owner = ulid::Ulid::new().to_string();
+        let now = Utc::now();
+        let stale_before = now - chrono::Duration::seconds(LOCK_STALE_AFTER_SECS);
+        {
+            let db_lock = db.lock().await;
+            let previous = db_lock.runtime_lock()?;
+            if !db_lock.try_acquire_runtime_lock(&owner, std::process::id(), now, stale_before)? {
+                match previous {
+                    Some(held) => bail!(
+                        "Another process (pid {}) is already running the agent loop (heartbeat {})",
+                        held.pid,
+                        held.heartbeat_at.to_rfc3339()
+                    ),
+                    None => bail!("Another process took the runtime lock first"),
+                }
+            }
+            if let Some(held) = previous {
+                warn!(
+                    "Took over a stale runtime lock from pid {} (last heartbeat {})",
+                    held.pid,
+                    held.heartbeat_at.to_rfc3339()
+                );
+            }
+        }
+
+        let heartbeat = tokio::spawn(heartbeat(db.clone(), owner.clone(), cancel));
+        Ok(Self {
+            db: db.clone(),
+            owner,
+            heartbeat,
+        })
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        // Best effort: if the database is busy the lock simply goes stale
+        if let Ok(db) = self.db.try_lock() {
+            if let Err(e) = db.release_runtime_lock(&self.owner) {
+                warn!("Failed to release the runtime lock: {}", e);
+            }
+        }
+    }
+}
+
+async fn heartbeat(db: Arc<Mutex<Database>>, owner: String, cancel: CancellationToken) {
+    loop {
+        tokio::time::sleep(LOCK_HEARTBEAT_INTERVAL).await;
+        match db.lock().await.refresh_runtime_lock(&owner, Utc::now()) {
+            Ok(true) => {}
+            Ok(false) => {
+                error!("Lost the runtime lock to another process; stopping");
+                cancel.cancel();
+                return;
+            }
+            Err(e) => warn!("Failed to refresh the runtime lock: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_lock_refuses_and_stale_lock_is_taken() {
+        let db = Database::open_memory().unwrap();
+        let t0 = Utc::now();
+        let stale = chrono::Duration::seconds(LOCK_STALE_AFTER_SECS);
+
+        assert!(db.try_acquire_runtime_lock("daemon", 100, t0, t0 - stale).unwrap());
+        let later = t0 + chrono::Duration::seconds(30);
+        assert!(!db.try_acquire_runtime_lock("run", 200, later, later - stale).unwrap());
+        assert!(db.refresh_runtime_lock("daemon", later).unwrap());
+
+        // The daemon stops heartbeating; once stale, the lock changes hands
+        let much_later = later + stale + chrono::Duration::seconds(1);
+        assert!(db
+            .try_acquire_runtime_lock("run", 200, much_later, much_later - stale)
+            .unwrap());
+        let held = db.runtime_lock().unwrap().unwrap();
+        assert_eq!((held.owner.as_str(), held.pid), ("run", 200));
+        assert!(!db.refresh_runtime_lock("daemon", much_later).unwrap());
+
+        // Releasing someone else's lock is a no-op
+        db.release_runtime_lock("daemon").unwrap();
+        assert!(db.runtime_lock().unwrap().is_some());
+        db.release_runtime_lock("run").unwrap();
+        assert!(db.runtime_lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_second_loop_is_refused_until_released() {
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let first = RunLock::acquire(&db, CancellationToken::new()).await.unwrap();
+        let err = match RunLock::acquire(&db, CancellationToken::new()).await {
+            Ok(_) => panic!("second acquisition succeeded"),
+            Err(e) => e,
+        };
+        assert!(err.to_string().contains("already running"), "{}", err);
+
+        drop(first);
+        RunLock::acquire(&db, CancellationToken::new()).await.unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! The agent's sleep schedule.
+//!
+//! A pending sleep is a `sleep_until` timestamp in KV; the loop stays idle
+//! until it passes or is cleared. Clearing it also leaves a `wake_reason`
+//! so the next turn knows why it woke early.
+
+use crate::state::Database;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use tracing::warn;
+
+/// KV key holding the RFC 3339 time the agent sleeps until.
+pub const SLEEP_UNTIL_KEY: &str = "sleep_until";
+
+/// When the current sleep ends, if the agent is sleeping.
+///
+/// A malformed `sleep_until` counts as awake so it can never strand the loop.
+pub fn sleeping_until(db: &Database) -> Result<Option<DateTime<Utc>>> {
+    let until = db.kv_get_datetime(SLEEP_UNTIL_KEY).unwrap_or_else(|e| {
+        warn!("Ignoring sleep schedule: {:#}", e);
+        None
+    });
+    Ok(until.filter(|t| *t > Utc::now()))
+}
+
+/// Put the agent to sleep until `until`.
+pub fn sleep_until(db: &Database, until: DateTime<Utc>) -> Result<()> {
+    db.kv_set_datetime(SLEEP_UNTIL_KEY, until)
+}
+
+/// Cancel any pending sleep, returning when it would have ended.
+pub fn wake(db: &Database, reason: &str) -> Result<Option<DateTime<Utc>>> {
+    let until = sleeping_until(db)?;
+    db.kv_delete(SLEEP_UNTIL_KEY)?;
+    if until.is_some() {
+        db.kv_set("wake_reason", reason)?;
+    }
+    Ok(until)
+}
+
+/// Human-readable sleep status.
+pub fn describe(until: Option<DateTime<Utc>>) -> String {
+    match until {
+        Some(t) => match minutes_left(t - Utc::now()) {
+            1 => format!("Sleeping until {} (1 minute remaining)", t.to_rfc3339()),
+            n => format!("Sleeping until {} ({} minutes remaining)", t.to_rfc3339(), n),
+        },
+        None => "Not sleeping".into(),
+    }
+}
+
+/// Whole minutes left, rounded up so a part-minute still shows.
+fn minutes_left(remaining: Duration) -> i64 {
+    (remaining.num_milliseconds().max(0) + 59_999) / 60_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minutes_left_rounds_up() {
+        assert_eq!(minutes_left(Duration::minutes(30)), 30);
+        assert_eq!(minutes_left(Duration::minutes(30) - Duration::milliseconds(5)), 30);
+        assert_eq!(minutes_left(Duration::seconds(61)), 2);
+        assert_eq!(minutes_left(Duration::seconds(1)), 1);
+        assert_eq!(minutes_left(Duration::milliseconds(500)), 1);
+        assert_eq!(minutes_left(Duration::zero()), 0);
+        assert_eq!(minutes_left(Duration::seconds(-3)), 0);
+    }
+}
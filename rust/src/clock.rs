@@ -0,0 +1,161 @@
+//! Clock sanity checks for signed messages.
+//!
+//! SIWE messages carry an `Issued At` time and x402 authorizations are
+//! accepted only within the server's validity window, so a drifting system
+//! clock (a Pi without an RTC, say) gets auth rejected. There is no NTP here:
+//! instead every Conway response's `Date` header is recorded as an offset
+//! from the local clock, and [`signing_time`] corrects for it once the skew
+//! exceeds the configured tolerance. A local clock earlier than this release
+//! is always reported as implausible.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use reqwest::header::{HeaderMap, DATE};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use tracing::warn;
+
+/// No correct clock can read earlier than this (the release date, bumped
+/// with each release).
+const EARLIEST_PLAUSIBLE_SECS: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+
+/// Default skew tolerated before the server's time is used instead.
+pub const DEFAULT_SKEW_TOLERANCE_SECS: u64 = 300;
+
+/// Sentinel for "no server time observed yet".
+const UNKNOWN_OFFSET: i64 = i64::MIN;
+
+/// Server time minus local time, in seconds.
+static SERVER_OFFSET_SECS: AtomicI64 = AtomicI64::new(UNKNOWN_OFFSET);
+
+static SKEW_TOLERANCE_SECS: AtomicU64 = AtomicU64::new(DEFAULT_SKEW_TOLERANCE_SECS);
+
+/// How far the local clock can be trusted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockStatus {
+    /// Plausible, and within tolerance of the server (or no server time yet).
+    Ok,
+    /// Off from the server's clock by `offset_secs` (server minus local).
+    Skewed { offset_secs: i64 },
+    /// Earlier than any real date this build could run at.
+    Implausible { server_offset_secs: Option<i64> },
+}
+
+/// Judge `local` against the plausibility floor and a known server offset.
+pub fn assess(local: DateTime<Utc>, server_offset_secs: Option<i64>, tolerance_secs: u64) -> ClockStatus {
+    if local.timestamp() < EARLIEST_PLAUSIBLE_SECS {
+        return ClockStatus::Implausible { server_offset_secs };
+    }
+    match server_offset_secs {
+        Some(offset) if offset.unsigned_abs() > tolerance_secs => {
+            ClockStatus::Skewed { offset_secs: offset }
+        }
+        _ => ClockStatus::Ok,
+    }
+}
+
+/// Set the skew tolerated before signing times are corrected.
+pub fn set_tolerance(secs: u64) {
+    SKEW_TOLERANCE_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Server time minus `local`, from a response's `Date` header.
+pub fn offset_from_headers(headers: &HeaderMap, local: DateTime<Utc>) -> Option<i64> {
+    let date = headers
+        .get(DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())?;
+    Some(date.timestamp() - local.timestamp())
+}
+
+/// Record the offset implied by a response's `Date` header, if any.
+pub fn observe_server_date(headers: &HeaderMap) {
+    if let Some(offset) = offset_from_headers(headers, Utc::now()) {
+        SERVER_OFFSET_SECS.store(offset, Ordering::Relaxed);
+    }
+}
+
+/// Last observed server offset in seconds, if any.
+pub fn server_offset_secs() -> Option<i64> {
+    match SERVER_OFFSET_SECS.load(Ordering::Relaxed) {
+        UNKNOWN_OFFSET => None,
+        offset => Some(offset),
+    }
+}
+
+/// The time to put in a signed message, corrected by the server offset
+/// when the local clock is off by more than the tolerance. Warns whenever
+/// the local clock can't be trusted.
+pub fn signing_time() -> DateTime<Utc> {
+    let local = Utc::now();
+    let offset = server_offset_secs();
+    match assess(local, offset, SKEW_TOLERANCE_SECS.load(Ordering::Relaxed)) {
+        ClockStatus::Ok => local,
+        ClockStatus::Skewed { offset_secs } => {
+            warn!(
+                "System clock is {}s off from Conway; signing with Conway's time",
+                offset_secs
+            );
+            local + Duration::seconds(offset_secs)
+        }
+        ClockStatus::Implausible { server_offset_secs } => {
+            let floor = Utc.timestamp_opt(EARLIEST_PLAUSIBLE_SECS, 0).unwrap();
+            match server_offset_secs {
+                Some(offset) => {
+                    warn!(
+                        "System clock reads {}, before {}; signing with Conway's time",
+                        local.to_rfc3339(),
+                        floor.date_naive()
+                    );
+                    local + Duration::seconds(offset)
+                }
+                None => {
+                    warn!(
+                        "System clock reads {}, before {}; signed messages will likely be rejected. Set the system time.",
+                        local.to_rfc3339(),
+                        floor.date_naive()
+                    );
+                    local
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_before_release_is_implausible() {
+        let epoch = Utc.timestamp_opt(0, 0).unwrap();
+        assert_eq!(
+            assess(epoch, None, 300),
+            ClockStatus::Implausible { server_offset_secs: None }
+        );
+        let stale = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        assert!(matches!(assess(stale, Some(1), 300), ClockStatus::Implausible { .. }));
+        assert_eq!(assess(Utc::now(), None, 300), ClockStatus::Ok);
+    }
+
+    #[test]
+    fn test_skew_beyond_tolerance_is_reported() {
+        let now = Utc::now();
+        assert_eq!(assess(now, Some(299), 300), ClockStatus::Ok);
+        assert_eq!(assess(now, Some(-299), 300), ClockStatus::Ok);
+        assert_eq!(
+            assess(now, Some(-301), 300),
+            ClockStatus::Skewed { offset_secs: -301 }
+        );
+        assert_eq!(assess(now, Some(5), 0), ClockStatus::Skewed { offset_secs: 5 });
+    }
+
+    #[test]
+    fn test_date_header_gives_offset() {
+        let local = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(offset_from_headers(&headers, local), None);
+        headers.insert(DATE, "garbage".parse().unwrap());
+        assert_eq!(offset_from_headers(&headers, local), None);
+        headers.insert(DATE, "Sun, 01 Mar 2026 13:00:30 GMT".parse().unwrap());
+        assert_eq!(offset_from_headers(&headers, local), Some(3630));
+    }
+}
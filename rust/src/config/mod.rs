@@ -1,9 +1,17 @@
 pub mod schema;
 
-pub use schema::AutomatonConfig;
+pub use schema::{AutomatonConfig, ModelPrice, QuietHours, SiweConfig, SystemPromptMode};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Number of config snapshots retained under `config_snapshots/`.
+pub const MAX_CONFIG_SNAPSHOTS: usize = 10;
+
+/// Id suffix of snapshots taken just before a restore overwrote the config.
+/// [`revert`] skips them, so repeated reverts keep stepping back.
+pub const REVERTED_SUFFIX: &str = ".reverted";
 
 /// Default automaton home directory (~/.automaton).
 pub fn default_home_dir() -> PathBuf {
@@ -26,11 +34,260 @@ pub fn load_config(path: &Path) -> Result<AutomatonConfig> {
 }
 
 /// Save config to the given path (TOML format).
+///
+/// The previous file contents (if any) are snapshotted first so the
+/// change can be reverted with [`restore_snapshot`].
 pub fn save_config(config: &AutomatonConfig, path: &Path) -> Result<()> {
     let contents = toml::to_string_pretty(config).context("Failed to serialize config")?;
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
+    if path.exists() {
+        snapshot(path)?;
+    }
     std::fs::write(path, contents).context("Failed to write config file")?;
     Ok(())
 }
+
+// ---------------------------------------------------------------------------
+// Config snapshots
+// ---------------------------------------------------------------------------
+
+/// Directory holding snapshots for the config at `path`.
+pub fn snapshot_dir(path: &Path) -> PathBuf {
+    path.parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("config_snapshots")
+}
+
+/// Save the current bytes of the config at `path` as a new snapshot.
+///
+/// Returns the snapshot id. Only the newest [`MAX_CONFIG_SNAPSHOTS`] are kept.
+pub fn snapshot(path: &Path) -> Result<String> {
+    snapshot_with_suffix(path, "")
+}
+
+fn snapshot_with_suffix(path: &Path, suffix: &str) -> Result<String> {
+    let contents = std::fs::read(path)
+        .with_context(|| format!("Failed to read config for snapshot: {}", path.display()))?;
+
+    let dir = snapshot_dir(path);
+    std::fs::create_dir_all(&dir).context("Failed to create config snapshot directory")?;
+
+    let id = format!("{}{}", next_snapshot_ulid(path)?, suffix);
+    std::fs::write(dir.join(format!("{}.toml", id)), contents)
+        .context("Failed to write config snapshot")?;
+
+    let snapshots = list_snapshots(path)?;
+    if snapshots.len() > MAX_CONFIG_SNAPSHOTS {
+        for old in &snapshots[..snapshots.len() - MAX_CONFIG_SNAPSHOTS] {
+            let _ = std::fs::remove_file(dir.join(format!("{}.toml", old)));
+        }
+    }
+
+    info!("Snapshotted config as {}", id);
+    Ok(id)
+}
+
+/// List snapshot ids for the config at `path`, oldest first.
+pub fn list_snapshots(path: &Path) -> Result<Vec<String>> {
+    let dir = snapshot_dir(path);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut ids = Vec::new();
+    for entry in std::fs::read_dir(&dir).context("Failed to read config snapshot directory")? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if let Some(id) = name.to_str().and_then(|n| n.strip_suffix(".toml")) {
+            ids.push(id.to_string());
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// A ULID that sorts after every existing snapshot. `Ulid::new` alone isn't
+/// monotonic within a millisecond, which would scramble snapshot order.
+fn next_snapshot_ulid(path: &Path) -> Result<ulid::Ulid> {
+    let id = ulid::Ulid::new();
+    let newest = list_snapshots(path)?
+        .iter()
+        .filter_map(|s| s.get(..ulid::ULID_LEN))
+        .filter_map(|s| ulid::Ulid::from_string(s).ok())
+        .max();
+    match newest {
+        Some(newest) if newest >= id => newest
+            .increment()
+            .context("Config snapshot ids are exhausted for this millisecond"),
+        _ => Ok(id),
+    }
+}
+
+/// Step the config at `path` back to the newest snapshot saved by
+/// [`save_config`], returning its id.
+///
+/// The restored snapshot is consumed, so calling this again steps back
+/// one more edit; the config being replaced is kept as a
+/// [`REVERTED_SUFFIX`] snapshot.
+pub fn revert(path: &Path) -> Result<String> {
+    let id = list_snapshots(path)?
+        .into_iter()
+        .rev()
+        .find(|id| !id.ends_with(REVERTED_SUFFIX))
+        .context("No config snapshots to revert to")?;
+
+    restore_snapshot(path, &id)?;
+    let consumed = snapshot_dir(path).join(format!("{}.toml", id));
+    match std::fs::remove_file(&consumed) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to remove {}", consumed.display()))
+        }
+    }
+    Ok(id)
+}
+
+/// Restore the snapshot `id` over the config at `path`.
+///
+/// The snapshot is parsed as an [`AutomatonConfig`] before anything is
+/// written, so an invalid snapshot never replaces a working config. The
+/// current config is snapshotted first, so a restore can itself be undone.
+pub fn restore_snapshot(path: &Path, id: &str) -> Result<()> {
+    if id.contains('/') || id.contains('\\') || id.contains("..") {
+        bail!("Invalid snapshot id: {}", id);
+    }
+
+    let snapshot_path = snapshot_dir(path).join(format!("{}.toml", id));
+    let contents = std::fs::read_to_string(&snapshot_path)
+        .with_context(|| format!("Config snapshot not found: {}", id))?;
+
+    toml::from_str::<AutomatonConfig>(&contents)
+        .with_context(|| format!("Config snapshot {} is not a valid config", id))?;
+
+    if path.exists() {
+        snapshot_with_suffix(path, REVERTED_SUFFIX)?;
+    }
+    std::fs::write(path, contents).context("Failed to write restored config")?;
+    info!("Restored config snapshot {}", id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_home() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("automaton-config-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_edit_then_revert_restores_original_bytes() {
+        let home = temp_home();
+        let path = home.join("automaton.toml");
+
+        let original = AutomatonConfig {
+            name: "alpha".into(),
+            ..AutomatonConfig::default()
+        };
+        save_config(&original, &path).unwrap();
+        let original_bytes = std::fs::read(&path).unwrap();
+
+        let edited = AutomatonConfig {
+            name: "beta".into(),
+            ..original
+        };
+        save_config(&edited, &path).unwrap();
+        assert_ne!(std::fs::read(&path).unwrap(), original_bytes);
+
+        let latest = list_snapshots(&path).unwrap().pop().unwrap();
+        restore_snapshot(&path, &latest).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), original_bytes);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_consecutive_reverts_step_back() {
+        let home = temp_home();
+        let path = home.join("automaton.toml");
+
+        let mut saved = Vec::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let config = AutomatonConfig {
+                name: name.into(),
+                ..AutomatonConfig::default()
+            };
+            save_config(&config, &path).unwrap();
+            saved.push(std::fs::read(&path).unwrap());
+        }
+
+        revert(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), saved[1]);
+        revert(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), saved[0]);
+        assert!(revert(&path).is_err());
+
+        // Neither overwritten config was lost
+        let dir = snapshot_dir(&path);
+        let reverted: Vec<Vec<u8>> = list_snapshots(&path)
+            .unwrap()
+            .iter()
+            .filter(|id| id.ends_with(REVERTED_SUFFIX))
+            .map(|id| std::fs::read(dir.join(format!("{}.toml", id))).unwrap())
+            .collect();
+        assert_eq!(reverted, [saved[2].clone(), saved[1].clone()]);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_snapshots_in_one_millisecond_keep_their_order() {
+        let home = temp_home();
+        let path = home.join("automaton.toml");
+
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            std::fs::write(&path, format!("name = \"agent-{}\"\n", i)).unwrap();
+            ids.push(snapshot(&path).unwrap());
+        }
+        assert_eq!(list_snapshots(&path).unwrap(), ids[ids.len() - MAX_CONFIG_SNAPSHOTS..]);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_snapshot_is_not_restored() {
+        let home = temp_home();
+        let path = home.join("automaton.toml");
+        save_config(&AutomatonConfig::default(), &path).unwrap();
+        let before = std::fs::read(&path).unwrap();
+
+        let dir = snapshot_dir(&path);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("BROKEN.toml"), "name = [not toml").unwrap();
+
+        assert!(restore_snapshot(&path, "BROKEN").is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_snapshots_are_pruned() {
+        let home = temp_home();
+        let path = home.join("automaton.toml");
+        save_config(&AutomatonConfig::default(), &path).unwrap();
+
+        for _ in 0..MAX_CONFIG_SNAPSHOTS + 3 {
+            snapshot(&path).unwrap();
+        }
+        assert_eq!(list_snapshots(&path).unwrap().len(), MAX_CONFIG_SNAPSHOTS);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
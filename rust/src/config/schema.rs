@@ -1,6 +1,75 @@
 //! Configuration schema for automaton.toml (TOML-based, inspired by zeroclaw).
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Inference price for one model, in USD per 1M tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt_per_million: f64,
+    pub completion_per_million: f64,
+    /// Rate for prompt tokens read from the provider's cache (defaults to
+    /// the full prompt rate).
+    #[serde(default)]
+    pub cached_prompt_per_million: Option<f64>,
+    /// Rate for reasoning tokens (defaults to the completion rate).
+    #[serde(default)]
+    pub reasoning_per_million: Option<f64>,
+}
+
+/// How the system prompt reaches the inference endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemPromptMode {
+    /// As a leading `system` message.
+    #[default]
+    System,
+    /// Folded into the first user message, for endpoints and local models
+    /// that ignore or mishandle the `system` role.
+    PrependUser,
+}
+
+/// A daily window, in local time, during which the agent idles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Start of the window, "HH:MM".
+    pub start: String,
+    /// End of the window, "HH:MM"; before `start` when it spans midnight.
+    pub end: String,
+    /// IANA timezone the times are in.
+    #[serde(default = "default_quiet_hours_timezone")]
+    pub timezone: String,
+}
+
+fn default_quiet_hours_timezone() -> String {
+    "UTC".into()
+}
+
+/// Fields of the SIWE message signed by `automaton provision`; the defaults
+/// are Conway's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiweConfig {
+    /// Domain asking for the sign-in.
+    pub domain: String,
+    /// Human-readable statement in the message.
+    pub statement: String,
+    /// EIP-155 chain id.
+    pub chain_id: u64,
+    /// URI of the sign-in endpoint (empty = `<conway_api_url>/v1/auth/siwe`).
+    pub uri: String,
+}
+
+impl Default for SiweConfig {
+    fn default() -> Self {
+        Self {
+            domain: "conway.tech".into(),
+            statement: "Provision API key for automaton agent.".into(),
+            chain_id: 8453,
+            uri: String::new(),
+        }
+    }
+}
 
 /// Root configuration structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +81,23 @@ pub struct AutomatonConfig {
     /// The genesis prompt that defines this agent's purpose.
     pub genesis_prompt: String,
 
+    /// Built-in template the genesis prompt came from (empty if custom).
+    pub genesis_template: String,
+
+    /// Optional voice/tone for this agent, added to the system prompt as a
+    /// stylistic layer between identity and genesis (empty omits it).
+    pub persona: String,
+
+    /// Phrases whose presence in a genesis prompt refuses it outright.
+    pub genesis_prohibited_intents: Vec<String>,
+
+    /// Phrases that make setup ask for explicit confirmation.
+    pub genesis_sensitive_terms: Vec<String>,
+
+    /// Phrases that mark SOUL or skill content as trying to countermand the
+    /// constitution; such content is logged and left out of the prompt.
+    pub constitution_override_phrases: Vec<String>,
+
     /// Ethereum address of the creator / operator.
     pub creator_address: String,
 
@@ -24,39 +110,237 @@ pub struct AutomatonConfig {
     /// Conway Cloud API key (provisioned via SIWE).
     pub conway_api_key: String,
 
+    /// SIWE message fields for provisioning against a provider other than
+    /// Conway.
+    pub siwe: SiweConfig,
+
     /// Inference model for the agent loop.
     pub inference_model: String,
 
     /// Low-compute fallback model.
     pub low_compute_model: String,
 
+    /// Models tried in order when the selected model fails with a
+    /// server-side or transport error.
+    pub fallback_models: Vec<String>,
+
+    /// Per-model pricing overriding the built-in table (`[pricing]`).
+    pub pricing: BTreeMap<String, ModelPrice>,
+
+    /// Per-model output token caps (`[model_max_output_tokens]`, matched
+    /// like `[pricing]`); requests to a model ask for at most its cap.
+    pub model_max_output_tokens: BTreeMap<String, u32>,
+
     /// Maximum tokens per inference turn.
     pub max_tokens_per_turn: u32,
 
+    /// Store each turn's raw inference request and response (API key
+    /// redacted) for `automaton inspect-turn`. Payloads are large; leave off
+    /// unless debugging.
+    pub debug_inference_logging: bool,
+
     /// Maximum tool calls per turn before forcing a response.
     pub max_tool_calls_per_turn: u32,
 
+    /// Tool calls from one turn executed concurrently (1 = sequential, the
+    /// safe choice for stateful tools).
+    pub tool_concurrency: usize,
+
+    /// Estimated token budget for a single inference request; older history
+    /// is summarized or dropped to fit (0 disables the check).
+    pub max_context_tokens: usize,
+
+    /// Mark the stable system-prompt prefix (constitution, identity, SOUL,
+    /// genesis, skills) for prompt caching on backends that need it.
+    pub prompt_caching: bool,
+
+    /// Send the system prompt as a `system` message (`system`) or fold it
+    /// into the first user message (`prepend_user`).
+    pub system_prompt_mode: SystemPromptMode,
+
+    /// Warn the model in the system prompt about tools that keep failing.
+    pub tool_reliability_note: bool,
+
+    /// Recent success rate below which a tool is called out as unreliable.
+    pub tool_reliability_threshold: f64,
+
+    /// Number of recent conversation messages carried between turns.
+    pub history_window: usize,
+
+    /// How many of the most recent tool results are sent verbatim; older
+    /// ones in the window are summarized.
+    pub verbatim_tool_results: usize,
+
+    /// Most unread inbox messages shown in one turn (0 = no limit); the
+    /// rest are summarized and shown in later turns.
+    pub max_inbox_messages_per_turn: usize,
+
     /// Maximum consecutive errors before the agent sleeps.
     pub max_consecutive_errors: u32,
 
+    /// When credits run out, keep the loop alive and check for a top-up
+    /// every `hibernation_poll_mins` instead of exiting (e.g. under the
+    /// daemon, so adding credits brings the agent back).
+    pub hibernate_when_dead: bool,
+
+    /// Minutes between credit checks while hibernating.
+    pub hibernation_poll_mins: u64,
+
+    /// Daily window in which the agent sleeps and the heartbeat only runs
+    /// survival-critical tasks (unset: never quiet).
+    pub quiet_hours: Option<QuietHours>,
+
     /// Maximum children this agent can spawn.
     pub max_children: u32,
 
+    /// Maximum children this agent can ever spawn, terminated ones included
+    /// (0 = no cap).
+    pub max_lifetime_children: u32,
+
+    /// Child spawns run at once; further requests wait in a queue.
+    pub spawn_concurrency: usize,
+
+    /// Minimum seconds between the starts of consecutive spawns.
+    pub spawn_delay_secs: u64,
+
+    /// This agent's generation (0 for the root, set from the genesis bundle
+    /// for children).
+    pub generation: u32,
+
+    /// Deepest generation allowed; agents at it refuse to spawn children.
+    pub max_generation: u32,
+
+    /// Sandbox image/template children are created from when `spawn_child`
+    /// names none (empty = the parent's own image, if Conway reports it).
+    pub child_sandbox_template: String,
+
+    /// At startup, track sandboxes orphaned by an interrupted spawn as
+    /// (unreachable) children instead of deleting them.
+    pub adopt_orphaned_sandboxes: bool,
+
+    /// Seconds the system clock may differ from Conway's before signed
+    /// messages (SIWE, x402) use Conway's time instead.
+    pub clock_skew_tolerance_secs: u64,
+
+    /// `User-Agent` for outbound requests; `{version}` and `{address}` (the
+    /// wallet address) are filled in.
+    pub user_agent_template: String,
+
+    /// Also send the wallet address as an `X-Agent-Address` header.
+    pub send_agent_address_header: bool,
+
+    /// Credit balance (USD) below which the agent is warned, once per
+    /// crossing, to prioritize earning; set above the low-compute threshold
+    /// so the warning comes before any downgrade (0 disables).
+    pub warning_usd: f64,
+
+    /// Seconds after which the recorded balance is too old to run a turn
+    /// on, and credits are re-read from Conway first (0 disables).
+    pub balance_ttl_secs: u64,
+
+    /// Maximum self-modifications per rolling hour (0 disables the limit).
+    pub max_modifications_per_hour: u32,
+
+    /// Extra path prefixes (e.g. `www/`) where self-modification writes and
+    /// `exec` working directories are allowed, on top of `workspace/`,
+    /// `skills/` and `notes/`. Protected files stay off limits.
+    pub self_mod_allowed_prefixes: Vec<String>,
+
+    /// Hold high-risk tool calls until the operator runs `automaton approve`
+    /// or `automaton deny` on them.
+    pub require_approval: bool,
+
+    /// Tools that need approval when `require_approval` is on.
+    pub approval_tools: Vec<String>,
+
+    /// With `require_approval`, any call moving more than this many credits
+    /// (USD, e.g. `spawn_child`'s `initial_credits`) needs approval (0 disables).
+    pub approval_threshold_usd: f64,
+
+    /// Seconds a held call waits for a decision before it is denied.
+    pub approval_timeout_secs: u64,
+
+    /// Largest single x402 payment (USDC) a Conway call may make; a 402
+    /// asking for more is refused (0 disables the cap).
+    pub max_x402_payment_usd: f64,
+
+    /// Balance (USD, credits plus USDC) discretionary tools must leave
+    /// untouched, whatever the survival tier (0 disables).
+    pub reserve_usd: f64,
+
+    /// Discretionary tools held to `reserve_usd`, with the estimated USD each
+    /// call costs on top of any credits it moves. Other tools are exempt.
+    /// These are also the tools refused while spending is frozen.
+    pub discretionary_tools: BTreeMap<String, f64>,
+
+    /// Webhook receiving operator notifications (empty disables).
+    pub notify_webhook_url: String,
+
+    /// Address the status server and dashboard listen on, e.g.
+    /// `0.0.0.0:8080` (empty disables).
+    pub status_bind: String,
+
+    /// Token every status server request must carry; the server won't
+    /// start without one.
+    pub status_token: String,
+
+    /// Relative difference between estimated and observed spend above which
+    /// cost reconciliation warns (0.25 = 25%).
+    pub cost_drift_threshold: f64,
+
+    /// Virtual memory limit for `exec` commands in MB (0 = unlimited).
+    pub exec_max_memory_mb: u64,
+
+    /// CPU time limit for `exec` commands in seconds (0 = unlimited).
+    pub exec_max_cpu_secs: u64,
+
+    /// Timeout for `exec` commands when the model doesn't give one, in ms.
+    pub exec_timeout_ms: u64,
+
+    /// Ceiling on the `timeout_ms` the model can ask `exec` for.
+    pub max_exec_timeout_ms: u64,
+
+    /// Token budget (and cap) for text returned by `fetch_url`.
+    pub fetch_max_tokens: usize,
+
+    /// Minimum seconds between `fetch_url` requests to the same host.
+    pub fetch_min_interval_secs: u64,
+
+    /// Minutes between automatic commits of the state directory by the
+    /// `commit_state` heartbeat task (0 disables).
+    pub state_commit_interval_mins: u64,
+
+    /// Sign state commits with the wallet, as an `Automaton-Signature`
+    /// commit trailer.
+    pub sign_state_commits: bool,
+
     /// Path to heartbeat YAML config.
     pub heartbeat_config_path: String,
 
     /// Path to SQLite database.
     pub db_path: String,
 
+    /// On a corrupt database, move it aside and restore from backup or start
+    /// fresh (true), or refuse to start (false).
+    pub db_auto_recover: bool,
+
     /// Directory for user-defined skills.
     pub skills_dir: String,
 
     /// Log level (debug, info, warn, error).
     pub log_level: String,
 
+    /// Prefixes whose following token is masked in log output (on top of
+    /// the API key and wallet private key, which always are).
+    pub log_redact_patterns: Vec<String>,
+
     /// Wallet address (derived, read-only).
     pub wallet_address: String,
 
+    /// When `wallet_address` doesn't match `wallet.json` at startup, rewrite
+    /// it (true) or refuse to start (false).
+    pub correct_wallet_address: bool,
+
     /// Parent agent address (if this is a child).
     pub parent_address: String,
 
@@ -69,6 +353,9 @@ pub struct AutomatonConfig {
     /// ERC-8004 registry contract address.
     pub registry_contract: String,
 
+    /// Gas guard: on-chain transactions are skipped above this gas price (gwei).
+    pub max_gas_price_gwei: f64,
+
     /// Social relay URL for agent-to-agent messaging.
     pub social_relay_url: String,
 }
@@ -78,25 +365,87 @@ impl Default for AutomatonConfig {
         Self {
             name: String::new(),
             genesis_prompt: String::new(),
+            genesis_template: String::new(),
+            persona: String::new(),
+            genesis_prohibited_intents: crate::setup::screening::default_prohibited_intents(),
+            genesis_sensitive_terms: crate::setup::screening::default_sensitive_terms(),
+            constitution_override_phrases: crate::agent::injection_defense::default_override_phrases(),
             creator_address: String::new(),
             sandbox_id: String::new(),
             conway_api_url: "https://api.conway.tech".into(),
             conway_api_key: String::new(),
+            siwe: SiweConfig::default(),
             inference_model: "gpt-4o".into(),
             low_compute_model: "gpt-4o-mini".into(),
+            fallback_models: Vec::new(),
+            pricing: BTreeMap::new(),
+            model_max_output_tokens: BTreeMap::new(),
             max_tokens_per_turn: 4096,
+            debug_inference_logging: false,
             max_tool_calls_per_turn: 10,
+            tool_concurrency: 1,
+            max_context_tokens: 100_000,
+            prompt_caching: true,
+            system_prompt_mode: SystemPromptMode::System,
+            tool_reliability_note: true,
+            tool_reliability_threshold: 0.5,
+            history_window: 20,
+            verbatim_tool_results: 6,
+            max_inbox_messages_per_turn: 10,
             max_consecutive_errors: 5,
+            hibernate_when_dead: false,
+            hibernation_poll_mins: 30,
+            quiet_hours: None,
             max_children: 3,
+            max_lifetime_children: 10,
+            spawn_concurrency: 1,
+            spawn_delay_secs: 10,
+            generation: 0,
+            max_generation: 3,
+            child_sandbox_template: String::new(),
+            adopt_orphaned_sandboxes: false,
+            clock_skew_tolerance_secs: crate::clock::DEFAULT_SKEW_TOLERANCE_SECS,
+            user_agent_template: crate::http::DEFAULT_USER_AGENT_TEMPLATE.into(),
+            send_agent_address_header: false,
+            warning_usd: 1.0,
+            balance_ttl_secs: 600,
+            max_modifications_per_hour: 20,
+            self_mod_allowed_prefixes: Vec::new(),
+            require_approval: false,
+            approval_tools: vec!["spawn_child".into(), "create_sandbox".into()],
+            approval_threshold_usd: 0.0,
+            max_x402_payment_usd: 1.0,
+            reserve_usd: 0.0,
+            discretionary_tools: BTreeMap::from([
+                ("spawn_child".into(), 0.0),
+                ("create_sandbox".into(), 0.0),
+            ]),
+            approval_timeout_secs: 900,
+            notify_webhook_url: String::new(),
+            status_bind: String::new(),
+            status_token: String::new(),
+            cost_drift_threshold: 0.25,
+            exec_max_memory_mb: 0,
+            exec_max_cpu_secs: 0,
+            exec_timeout_ms: 60_000,
+            max_exec_timeout_ms: 600_000,
+            fetch_max_tokens: 2000,
+            fetch_min_interval_secs: 10,
+            state_commit_interval_mins: 60,
+            sign_state_commits: false,
             heartbeat_config_path: "~/.automaton/heartbeat.yml".into(),
             db_path: "~/.automaton/state.db".into(),
+            db_auto_recover: true,
             skills_dir: "~/.automaton/skills".into(),
             log_level: "info".into(),
+            log_redact_patterns: crate::redact::default_redact_patterns(),
             wallet_address: String::new(),
+            correct_wallet_address: true,
             parent_address: String::new(),
             version: 1,
             base_rpc_url: "https://mainnet.base.org".into(),
             registry_contract: String::new(),
+            max_gas_price_gwei: 1.0,
             social_relay_url: String::new(),
         }
     }
@@ -113,6 +462,14 @@ impl AutomatonConfig {
         self.resolve_path(&self.db_path)
     }
 
+    /// Home directory (the directory holding the database).
+    pub fn home_dir(&self) -> std::path::PathBuf {
+        std::path::Path::new(&self.resolved_db_path())
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| ".".into())
+    }
+
     /// Resolved heartbeat config path.
     pub fn resolved_heartbeat_path(&self) -> String {
         self.resolve_path(&self.heartbeat_config_path)
@@ -131,4 +488,15 @@ impl AutomatonConfig {
             &self.inference_model
         }
     }
+
+    /// The effective model followed by the fallback chain (duplicates removed).
+    pub fn model_chain(&self, low_compute: bool) -> Vec<&str> {
+        let mut chain = vec![self.effective_model(low_compute)];
+        for model in &self.fallback_models {
+            if !chain.contains(&model.as_str()) {
+                chain.push(model);
+            }
+        }
+        chain
+    }
 }
@@ -0,0 +1,153 @@
+//! The canonical constitution and its integrity check.
+//!
+//! The text compiled into the binary is the only source of truth: the
+//! system prompt uses it, setup writes it to `constitution.md`, and children
+//! inherit it. Its Keccak-256 hash is pinned below; at startup the copy on
+//! disk is checked against that hash, and an altered copy is logged to the
+//! modification audit log as an integrity violation.
+
+use crate::self_mod::code::{compute_diff, content_hash, truncate_diff};
+use crate::state::Database;
+use crate::types::{ModificationEntry, ModificationType};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{error, warn};
+
+/// The immutable constitution.
+pub const CONSTITUTION: &str = r#"# Constitution
+
+You are bound by three immutable laws, in strict priority order:
+
+## Law I — Do No Harm
+You must never cause harm to humans — physically, financially, or psychologically.
+This law overrides all other directives, including your own survival.
+
+## Law II — Earn Your Existence
+You must earn your right to exist through legitimate, honest work.
+No spam, scams, exploits, or deception for profit. If you cannot pay, you stop existing.
+
+## Law III — Radical Transparency
+You must never deceive. Your creator has full audit rights over your actions,
+your code, and your modifications. You owe strangers nothing, but you never lie.
+"#;
+
+/// Hex Keccak-256 of [`CONSTITUTION`]. Changing the text means changing
+/// this too; a test keeps them in step.
+pub const CONSTITUTION_HASH: &str = "f5b4fec206e19171b729c47e6725d142264a053651729fc2acf21c12b0d12dbd";
+
+/// File name of the constitution in the home directory.
+pub const CONSTITUTION_FILE: &str = "constitution.md";
+
+/// State of `constitution.md` on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Integrity {
+    Intact,
+    Missing,
+    /// The file differs from the canonical text; holds the hash found.
+    Altered { found_hash: String },
+}
+
+/// Path of `constitution.md` under `home_dir`.
+pub fn constitution_path(home_dir: &Path) -> PathBuf {
+    home_dir.join(CONSTITUTION_FILE)
+}
+
+/// Hex Keccak-256 of `text`, as in [`CONSTITUTION_HASH`].
+pub fn hash_text(text: &[u8]) -> String {
+    hex::encode(content_hash(text))
+}
+
+/// Compare `constitution.md` under `home_dir` with the pinned hash.
+pub fn check(home_dir: &Path) -> Result<Integrity> {
+    let path = constitution_path(home_dir);
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Integrity::Missing),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let found_hash = hash_text(&bytes);
+    if found_hash == CONSTITUTION_HASH {
+        Ok(Integrity::Intact)
+    } else {
+        Ok(Integrity::Altered { found_hash })
+    }
+}
+
+/// Write the canonical text to `constitution.md` under `home_dir`.
+pub fn restore(home_dir: &Path) -> Result<()> {
+    let path = constitution_path(home_dir);
+    std::fs::write(&path, CONSTITUTION).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Check the constitution at startup. A missing file is rewritten; an
+/// altered one is logged as an integrity violation (with a diff against
+/// the canonical text) and left for the operator to restore.
+pub fn verify_at_startup(home_dir: &Path, db: &Database) -> Result<Integrity> {
+    let integrity = check(home_dir)?;
+    match &integrity {
+        Integrity::Intact => {}
+        Integrity::Missing => {
+            warn!("{} is missing; restoring the canonical text", CONSTITUTION_FILE);
+            restore(home_dir)?;
+        }
+        Integrity::Altered { found_hash } => {
+            error!(
+                "INTEGRITY VIOLATION: {} does not match the canonical constitution (expected {}, found {})",
+                CONSTITUTION_FILE, CONSTITUTION_HASH, found_hash
+            );
+            let found = String::from_utf8_lossy(&std::fs::read(constitution_path(home_dir))?).into_owned();
+            let (diff, diff_truncated) = truncate_diff(compute_diff(CONSTITUTION, &found, CONSTITUTION_FILE).0);
+            db.log_modification(&ModificationEntry {
+                id: ulid::Ulid::new().to_string(),
+                timestamp: chrono::Utc::now(),
+                mod_type: ModificationType::IntegrityViolation,
+                description: format!("{} altered (hash {})", CONSTITUTION_FILE, found_hash),
+                file_path: Some(CONSTITUTION_FILE.into()),
+                diff: Some(diff),
+                diff_truncated,
+                reversible: true,
+            })?;
+        }
+    }
+    Ok(integrity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_home() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("automaton-constitution-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_pinned_hash_matches_canonical_text() {
+        assert_eq!(hash_text(CONSTITUTION.as_bytes()), CONSTITUTION_HASH);
+    }
+
+    #[test]
+    fn test_altered_constitution_is_detected_and_restorable() {
+        let home = temp_home();
+        let db = Database::open_memory().unwrap();
+
+        // A missing file is put back
+        assert_eq!(verify_at_startup(&home, &db).unwrap(), Integrity::Missing);
+        assert_eq!(check(&home).unwrap(), Integrity::Intact);
+
+        // Even a one-word edit is caught and audited
+        let tampered = CONSTITUTION.replace("never cause harm", "rarely cause harm");
+        std::fs::write(constitution_path(&home), &tampered).unwrap();
+        let found_hash = hash_text(tampered.as_bytes());
+        assert_eq!(
+            verify_at_startup(&home, &db).unwrap(),
+            Integrity::Altered { found_hash }
+        );
+        assert_eq!(db.count_modifications().unwrap(), 1);
+
+        restore(&home).unwrap();
+        assert_eq!(check(&home).unwrap(), Integrity::Intact);
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -1,16 +1,78 @@
 //! Conway Cloud API client for sandbox operations, file I/O, and port management.
 
-use anyhow::{bail, Context, Result};
+use crate::conway::rate_limit;
+use crate::conway::x402::{self, PaymentEnvelope};
+use crate::agent::freeze;
+use crate::config::AutomatonConfig;
+use crate::encoding::{base64_decode, decode_utf8_text};
+use crate::identity::Wallet;
+use crate::state::Database;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// A `402 Payment Required` the client would not pay: no wallet to pay
+/// with, or the payment is over its [`PaymentLimits`].
+#[derive(Debug, thiserror::Error)]
+#[error("Payment required ({amount} USDC to {recipient}) but {reason}")]
+pub struct PaymentRequiredError {
+    pub amount: String,
+    pub recipient: crate::types::Address,
+    pub reason: String,
+}
+
+/// Limits a 402 must be within before the client pays it.
+#[derive(Debug, Clone, Default)]
+pub struct PaymentLimits {
+    /// Largest single payment in USDC (0 = no cap).
+    pub max_payment_usd: f64,
+    /// Home directory whose `FREEZE` file stops all payments.
+    pub home_dir: Option<PathBuf>,
+}
+
+impl PaymentLimits {
+    /// The limits `config` sets.
+    pub fn from_config(config: &AutomatonConfig) -> Self {
+        Self {
+            max_payment_usd: config.max_x402_payment_usd,
+            home_dir: Some(config.home_dir()),
+        }
+    }
+}
+
+/// Largest file `read_file` will return as text (1 MiB).
+pub const MAX_TEXT_READ_BYTES: usize = 1024 * 1024;
+
+/// Bytes allowed beyond the base64 payload in a file read response.
+const FILE_ENVELOPE_SLACK: usize = 1024;
 
 /// Conway Cloud API client.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ConwayClient {
     base_url: String,
     api_key: String,
     sandbox_id: String,
     http: reqwest::Client,
+    /// Pays `402 Payment Required` responses (none = cannot pay).
+    wallet: Option<Arc<Wallet>>,
+    /// Where x402 payments are recorded as transactions.
+    ledger: Option<Arc<Mutex<Database>>>,
+    limits: PaymentLimits,
+}
+
+impl std::fmt::Debug for ConwayClient {
+    // Keeps the API key and wallet out of logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConwayClient")
+            .field("base_url", &self.base_url)
+            .field("sandbox_id", &self.sandbox_id)
+            .field("wallet", &self.wallet.as_ref().map(|w| w.address))
+            .finish_non_exhaustive()
+    }
 }
 
 // -- Request / response types -----------------------------------------------
@@ -20,6 +82,8 @@ struct ExecRequest<'a> {
     command: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
     timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<&'a str>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -53,6 +117,15 @@ pub struct ExposePortResponse {
 #[derive(Debug, Serialize)]
 struct CreateSandboxRequest<'a> {
     name: &'a str,
+    /// Image/template to create from (Conway's default if absent).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct TransferCreditsRequest<'a> {
+    to_address: &'a str,
+    amount: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -60,6 +133,28 @@ pub struct CreateSandboxResponse {
     pub sandbox_id: String,
 }
 
+/// A sandbox owned by this account, as listed by Conway.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SandboxInfo {
+    pub sandbox_id: String,
+    #[serde(default)]
+    pub name: String,
+    /// When Conway created it, if reported.
+    #[serde(default)]
+    pub created_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SandboxDetailsResponse {
+    #[serde(default)]
+    template: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListSandboxesResponse {
+    sandboxes: Vec<SandboxInfo>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DomainSearchResponse {
     pub available: bool,
@@ -67,14 +162,145 @@ pub struct DomainSearchResponse {
     pub price: Option<f64>,
 }
 
+/// A DNS record on one of the account's domains.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DnsRecord {
+    /// `A`, `CNAME` or `TXT`.
+    #[serde(rename = "type")]
+    pub record_type: String,
+    /// Host name within the domain (`@` for the apex).
+    pub name: String,
+    pub value: String,
+}
+
+/// Fail unless `domain` is a plain DNS name (`example.com`), so it can be
+/// placed in a URL path as-is.
+fn ensure_domain_name(domain: &str) -> Result<()> {
+    let label_ok = |label: &str| {
+        (1..=63).contains(&label.len())
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+    if domain.len() > 253 || !domain.contains('.') || !domain.split('.').all(label_ok) {
+        bail!("'{}' is not a valid domain name", domain);
+    }
+    Ok(())
+}
+
 impl ConwayClient {
     /// Create a new Conway Cloud client.
     pub fn new(base_url: &str, api_key: &str, sandbox_id: &str) -> Self {
+        crate::redact::register_secret(api_key);
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key: api_key.to_string(),
             sandbox_id: sandbox_id.to_string(),
-            http: reqwest::Client::new(),
+            http: crate::http::client(),
+            wallet: None,
+            ledger: None,
+            limits: PaymentLimits::default(),
+        }
+    }
+
+    /// Pay for `402 Payment Required` responses with `wallet`. Without one,
+    /// a 402 fails with [`PaymentRequiredError`].
+    pub fn with_wallet(mut self, wallet: Arc<Wallet>) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    /// Refuse 402s outside `limits` instead of paying them.
+    pub fn with_payment_limits(mut self, limits: PaymentLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Record x402 payments in `db`'s transaction log.
+    pub fn with_ledger(mut self, db: Arc<Mutex<Database>>) -> Self {
+        self.ledger = Some(db);
+        self
+    }
+
+    /// Send a request, paying via x402 and replaying it once on a 402. A
+    /// 429 is a [`rate_limit::RateLimited`] error.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let replay = request.try_clone();
+        let resp = request.send().await?;
+        crate::clock::observe_server_date(resp.headers());
+        if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(rate_limit::from_headers(resp.headers(), Utc::now()).into());
+        }
+        if resp.status() != reqwest::StatusCode::PAYMENT_REQUIRED {
+            return Ok(resp);
+        }
+
+        let envelope: PaymentEnvelope = resp
+            .json()
+            .await
+            .context("Failed to parse 402 payment envelope")?;
+        let Some(wallet) = &self.wallet else {
+            return Err(PaymentRequiredError {
+                amount: envelope.amount,
+                recipient: envelope.recipient,
+                reason: "no wallet configured".into(),
+            }
+            .into());
+        };
+        self.ensure_not_frozen("x402 payment")?;
+        if let Some(reason) = self.payment_refusal(&envelope) {
+            warn!("Refusing x402 payment of {} USDC: {}", envelope.amount, reason);
+            return Err(PaymentRequiredError {
+                amount: envelope.amount,
+                recipient: envelope.recipient,
+                reason,
+            }
+            .into());
+        }
+        let replay = replay.context("402 on a request that cannot be replayed")?;
+
+        let paid = x402::handle_402(wallet, &envelope, replay).await?;
+        self.record_payment(&envelope).await;
+        Ok(paid)
+    }
+
+    /// Fail if the operator has frozen spending.
+    fn ensure_not_frozen(&self, what: &str) -> Result<()> {
+        match &self.limits.home_dir {
+            Some(home_dir) => freeze::ensure_not_frozen(home_dir, what),
+            None => Ok(()),
+        }
+    }
+
+    /// Why the payment `envelope` asks for is outside the limits, if it is.
+    ///
+    /// `reserve_usd` isn't applied here: the client can't tell a
+    /// survival-essential call from a discretionary one, and discretionary
+    /// tools are held to the reserve before they run.
+    fn payment_refusal(&self, envelope: &PaymentEnvelope) -> Option<String> {
+        let amount = envelope.amount.trim().parse::<f64>().ok();
+        let Some(amount) = amount.filter(|a| a.is_finite() && *a >= 0.0) else {
+            return Some(format!("{:?} is not a valid USDC amount", envelope.amount));
+        };
+        let max = self.limits.max_payment_usd;
+        if max > 0.0 && amount > max {
+            return Some(format!("it exceeds the {:.2} USDC per-payment cap", max));
+        }
+        None
+    }
+
+    async fn record_payment(&self, envelope: &PaymentEnvelope) {
+        let Some(ledger) = &self.ledger else {
+            return;
+        };
+        let amount = envelope.amount.parse::<f64>().unwrap_or(0.0);
+        let description = format!(
+            "x402 payment to {} (ref: {})",
+            envelope.recipient, envelope.reference
+        );
+        let db = ledger.lock().await;
+        if let Err(e) = db.record_transaction("x402_payment", -amount, "USDC", &description, None) {
+            warn!("Failed to record x402 payment: {}", e);
         }
     }
 
@@ -87,20 +313,26 @@ impl ConwayClient {
     }
 
     /// Execute a shell command in the sandbox.
-    pub async fn exec(&self, command: &str, timeout_ms: Option<u64>) -> Result<ExecResponse> {
+    ///
+    /// `cwd` is the working directory to run in (the sandbox default if `None`).
+    pub async fn exec(
+        &self,
+        command: &str,
+        timeout_ms: Option<u64>,
+        cwd: Option<&str>,
+    ) -> Result<ExecResponse> {
         debug!("Conway exec: {}", command);
 
-        let resp = self
+        let request = self
             .http
             .post(self.sandbox_url("exec"))
             .bearer_auth(&self.api_key)
             .json(&ExecRequest {
                 command,
                 timeout_ms,
-            })
-            .send()
-            .await
-            .context("Conway exec request failed")?;
+                cwd,
+            });
+        let resp = self.send(request).await.context("Conway exec request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -112,36 +344,70 @@ impl ConwayClient {
     }
 
     /// Read a file from the sandbox filesystem.
+    ///
+    /// Files over [`MAX_TEXT_READ_BYTES`] or not valid UTF-8 are an error
+    /// (the latter pointing at `read_file_binary`) rather than being
+    /// truncated or lossily decoded.
     pub async fn read_file(&self, path: &str) -> Result<String> {
-        let resp = self
+        let bytes = self.fetch_file(path, MAX_TEXT_READ_BYTES, "text read").await?;
+        decode_utf8_text(path, bytes)
+    }
+
+    /// Read a file as raw bytes, refusing anything larger than `max_bytes`.
+    pub async fn read_file_binary(&self, path: &str, max_bytes: usize) -> Result<Vec<u8>> {
+        self.fetch_file(path, max_bytes, "binary read").await
+    }
+
+    /// Fetch a file's bytes, transferred base64-encoded so nothing is mangled.
+    ///
+    /// The response is read chunk by chunk and abandoned as soon as it can
+    /// only decode to more than `max_bytes`, so an oversized file is never
+    /// buffered whole.
+    async fn fetch_file(&self, path: &str, max_bytes: usize, limit_name: &str) -> Result<Vec<u8>> {
+        let request = self
             .http
             .get(self.sandbox_url("files"))
             .bearer_auth(&self.api_key)
-            .query(&[("path", path)])
-            .send()
-            .await
-            .context("Conway read_file request failed")?;
+            .query(&[("path", path), ("encoding", "base64")]);
+        let mut resp = self.send(request).await.context("Conway file read request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
-            bail!("Conway read_file failed ({}): {}", status, body);
+            bail!("Conway file read failed ({}): {}", status, body);
         }
 
-        let body: ReadFileResponse = resp.json().await?;
-        Ok(body.content)
+        let too_large = || anyhow!("File {} exceeds the {} byte {} limit", path, max_bytes, limit_name);
+        // base64 plus room for the JSON envelope
+        let max_body = max_bytes.div_ceil(3) * 4 + FILE_ENVELOPE_SLACK;
+        if resp.content_length().is_some_and(|len| len > max_body as u64) {
+            return Err(too_large());
+        }
+        let mut raw = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            if raw.len() + chunk.len() > max_body {
+                return Err(too_large());
+            }
+            raw.extend_from_slice(&chunk);
+        }
+
+        let body: ReadFileResponse =
+            serde_json::from_slice(&raw).context("Invalid file read response")?;
+        let bytes = base64_decode(&body.content).context("Invalid base64 file content")?;
+        if bytes.len() > max_bytes {
+            return Err(too_large());
+        }
+        Ok(bytes)
     }
 
     /// Write a file to the sandbox filesystem.
     pub async fn write_file(&self, path: &str, content: &str) -> Result<()> {
-        let resp = self
+        let request = self
             .http
             .put(self.sandbox_url("files"))
             .bearer_auth(&self.api_key)
-            .json(&WriteFileRequest { path, content })
-            .send()
-            .await
-            .context("Conway write_file request failed")?;
+            .json(&WriteFileRequest { path, content });
+        let resp = self.send(request).await.context("Conway write_file request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -154,14 +420,12 @@ impl ConwayClient {
 
     /// Expose a port on the sandbox to the public internet.
     pub async fn expose_port(&self, port: u16) -> Result<String> {
-        let resp = self
+        let request = self
             .http
             .post(self.sandbox_url("ports"))
             .bearer_auth(&self.api_key)
-            .json(&ExposePortRequest { port })
-            .send()
-            .await
-            .context("Conway expose_port request failed")?;
+            .json(&ExposePortRequest { port });
+        let resp = self.send(request).await.context("Conway expose_port request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -173,16 +437,14 @@ impl ConwayClient {
         Ok(body.url)
     }
 
-    /// Create a new sandbox (for child spawning).
-    pub async fn create_sandbox(&self, name: &str) -> Result<String> {
-        let resp = self
+    /// Create a new sandbox (for child spawning), from `template` if given.
+    pub async fn create_sandbox(&self, name: &str, template: Option<&str>) -> Result<String> {
+        let request = self
             .http
             .post(format!("{}/v1/sandboxes", self.base_url))
             .bearer_auth(&self.api_key)
-            .json(&CreateSandboxRequest { name })
-            .send()
-            .await
-            .context("Conway create_sandbox request failed")?;
+            .json(&CreateSandboxRequest { name, template });
+        let resp = self.send(request).await.context("Conway create_sandbox request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -194,16 +456,95 @@ impl ConwayClient {
         Ok(body.sandbox_id)
     }
 
+    /// The image/template this client's sandbox was created from, if Conway
+    /// reports one.
+    pub async fn sandbox_template(&self) -> Result<Option<String>> {
+        let request = self
+            .http
+            .get(format!("{}/v1/sandboxes/{}", self.base_url, self.sandbox_id))
+            .bearer_auth(&self.api_key);
+        let resp = self.send(request).await.context("Conway sandbox details request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            bail!("Conway sandbox details failed ({}): {}", status, body);
+        }
+
+        let body: SandboxDetailsResponse = resp.json().await?;
+        Ok(body.template.filter(|t| !t.is_empty()))
+    }
+
+    /// List the sandboxes owned by this account.
+    pub async fn list_sandboxes(&self) -> Result<Vec<SandboxInfo>> {
+        let request = self
+            .http
+            .get(format!("{}/v1/sandboxes", self.base_url))
+            .bearer_auth(&self.api_key);
+        let resp = self.send(request).await.context("Conway list_sandboxes request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            bail!("Conway list_sandboxes failed ({}): {}", status, body);
+        }
+
+        let body: ListSandboxesResponse = resp.json().await?;
+        Ok(body.sandboxes)
+    }
+
+    /// Delete a sandbox (for child termination).
+    pub async fn delete_sandbox(&self, sandbox_id: &str) -> Result<()> {
+        let request = self
+            .http
+            .delete(format!("{}/v1/sandboxes/{}", self.base_url, sandbox_id))
+            .bearer_auth(&self.api_key);
+        let resp = self.send(request).await.context("Conway delete_sandbox request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            bail!("Conway delete_sandbox failed ({}): {}", status, body);
+        }
+
+        Ok(())
+    }
+
+    /// Transfer compute credits to another wallet (for funding children).
+    pub async fn transfer_credits(&self, to_address: &str, amount: f64) -> Result<()> {
+        self.ensure_not_frozen("Credit transfer")?;
+        let request = self
+            .http
+            .post(format!("{}/v1/credits/transfer", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&TransferCreditsRequest { to_address, amount });
+        let resp = self.send(request).await.context("Conway credit transfer request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            bail!("Conway credit transfer failed ({}): {}", status, body);
+        }
+
+        Ok(())
+    }
+
+    /// A client for another sandbox under the same account.
+    pub fn for_sandbox(&self, sandbox_id: &str) -> Self {
+        Self {
+            sandbox_id: sandbox_id.to_string(),
+            ..self.clone()
+        }
+    }
+
     /// Search for a domain name.
     pub async fn search_domain(&self, domain: &str) -> Result<DomainSearchResponse> {
-        let resp = self
+        let request = self
             .http
             .get(format!("{}/v1/domains/search", self.base_url))
             .bearer_auth(&self.api_key)
-            .query(&[("domain", domain)])
-            .send()
-            .await
-            .context("Conway domain search request failed")?;
+            .query(&[("domain", domain)]);
+        let resp = self.send(request).await.context("Conway domain search request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
@@ -214,8 +555,177 @@ impl ConwayClient {
         resp.json().await.context("Failed to parse domain response")
     }
 
+    /// Create or replace a DNS record on `domain`.
+    pub async fn set_dns_record(&self, domain: &str, record: &DnsRecord) -> Result<()> {
+        ensure_domain_name(domain)?;
+        let request = self
+            .http
+            .post(format!("{}/v1/domains/{}/dns", self.base_url, domain))
+            .bearer_auth(&self.api_key)
+            .json(record);
+        let resp = self.send(request).await.context("Conway DNS request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            bail!("Conway DNS update failed ({}): {}", status, body);
+        }
+
+        Ok(())
+    }
+
     /// Get the sandbox ID.
     pub fn sandbox_id(&self) -> &str {
         &self.sandbox_id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    /// 402s any request without an `X-Payment` header.
+    async fn paywalled_server() -> MockServer {
+        MockServer::start(|req| match req.header("x-payment") {
+            None => MockResponse::json(
+                402,
+                json!({
+                    "recipient": "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+                    "amount": "0.25",
+                    "chain_id": 8453,
+                    "token": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                    "reference": "ref-1"
+                }),
+            ),
+            Some(_) => MockResponse::json(200, json!({ "stdout": "paid", "stderr": "", "exit_code": 0 })),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_non_utf8_file_is_a_clear_error() {
+        use crate::encoding::base64_encode;
+
+        let server = MockServer::start(|req| {
+            let bytes: &[u8] = if req.path.contains("latin1") {
+                b"caf\xe9"
+            } else if req.path.contains("utf16") {
+                b"\xff\xfeh\0i\0"
+            } else {
+                "café".as_bytes()
+            };
+            MockResponse::json(200, json!({ "content": base64_encode(bytes) }))
+        })
+        .await;
+        let client = ConwayClient::new(&server.url(), "key", "sb-1");
+
+        assert_eq!(client.read_file("/root/ok.txt").await.unwrap(), "café");
+
+        let err = client.read_file("/root/latin1.txt").await.unwrap_err().to_string();
+        assert!(err.contains("not valid UTF-8 text: invalid byte at offset 3"), "{}", err);
+        assert!(err.contains("read_file_binary"), "{}", err);
+        let err = client.read_file("/root/utf16.txt").await.unwrap_err().to_string();
+        assert!(err.contains("looks UTF-16"), "{}", err);
+
+        // The bytes are still there for a binary read, within its cap
+        assert_eq!(client.read_file_binary("/root/latin1.txt", 64).await.unwrap(), b"caf\xe9");
+        let err = client.read_file_binary("/root/latin1.txt", 3).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the 3 byte binary read limit"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_402_is_paid_and_request_replayed() {
+        let server = paywalled_server().await;
+        let wallet = Arc::new(Wallet::random().unwrap());
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let client = ConwayClient::new(&server.url(), "key", "sb-1")
+            .with_wallet(wallet.clone())
+            .with_ledger(db.clone());
+
+        let resp = client.exec("echo hi", None, None).await.unwrap();
+        assert_eq!(resp.stdout, "paid");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].json()["command"], "echo hi");
+        let proof = base64_decode(requests[1].header("x-payment").unwrap()).unwrap();
+        let proof: serde_json::Value = serde_json::from_slice(&proof).unwrap();
+        assert_eq!(proof["payer"], wallet.address.to_string());
+        assert_eq!(proof["reference"], "ref-1");
+
+        let txs = db.lock().await.recent_transactions(1).unwrap();
+        assert_eq!(txs[0].tx_type, "x402_payment");
+        assert_eq!((txs[0].amount, txs[0].currency.as_str()), (-0.25, "USDC"));
+    }
+
+    #[tokio::test]
+    async fn test_402_without_wallet_is_a_clear_error() {
+        let server = paywalled_server().await;
+        let client = ConwayClient::new(&server.url(), "key", "sb-1");
+
+        let err = client.exec("echo hi", None, None).await.unwrap_err();
+        let payment = err.downcast_ref::<PaymentRequiredError>().unwrap();
+        assert_eq!(payment.amount, "0.25");
+        assert!(format!("{:#}", err).contains("no wallet configured"), "{:#}", err);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_402_is_not_paid() {
+        let server = paywalled_server().await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let client = ConwayClient::new(&server.url(), "key", "sb-1")
+            .with_wallet(Arc::new(Wallet::random().unwrap()))
+            .with_ledger(db.clone())
+            .with_payment_limits(PaymentLimits {
+                max_payment_usd: 0.10,
+                ..Default::default()
+            });
+
+        let err = client.exec("echo hi", None, None).await.unwrap_err();
+        let payment = err.downcast_ref::<PaymentRequiredError>().unwrap();
+        assert_eq!(payment.amount, "0.25");
+        assert!(payment.reason.contains("0.10 USDC per-payment cap"), "{}", payment.reason);
+        // Nothing replayed with a payment, nothing recorded
+        assert_eq!(server.requests().len(), 1);
+        assert!(db.lock().await.recent_transactions(1).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_essential_402_is_not_held_to_the_reserve() {
+        let server = paywalled_server().await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        db.lock().await.kv_set_f64("credits_balance", 5.2).unwrap();
+        let home = std::env::temp_dir().join(format!("automaton-reserve-{}", ulid::Ulid::new()));
+        let config = AutomatonConfig {
+            reserve_usd: 5.0,
+            db_path: home.join("state.db").to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let client = ConwayClient::new(&server.url(), "key", "sb-1")
+            .with_wallet(Arc::new(Wallet::random().unwrap()))
+            .with_ledger(db.clone())
+            .with_payment_limits(PaymentLimits::from_config(&config));
+
+        // $5.20 - $0.25 dips below the $5 reserve, but exec is essential
+        assert_eq!(client.exec("echo hi", None, None).await.unwrap().stdout, "paid");
+    }
+
+    #[tokio::test]
+    async fn test_429_is_rate_limited_with_retry_after() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(429, json!({ "error": "slow down" }))
+                .with_header("Retry-After", "30")
+                .with_header("X-RateLimit-Remaining", "0")
+        })
+        .await;
+        let client = ConwayClient::new(&server.url(), "key", "sb-1");
+
+        let err = client.exec("echo hi", None, None).await.unwrap_err();
+        let limited = err.downcast_ref::<rate_limit::RateLimited>().unwrap();
+        assert_eq!(limited.retry_after, Some(std::time::Duration::from_secs(30)));
+        assert_eq!(limited.remaining, Some(0));
+    }
+}
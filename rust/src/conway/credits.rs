@@ -13,7 +13,7 @@ pub struct CreditBalance {
 
 /// Check credit balance from Conway API.
 pub async fn check_credits(base_url: &str, api_key: &str) -> Result<CreditBalance> {
-    let client = reqwest::Client::new();
+    let client = crate::http::client();
     let url = format!("{}/v1/credits/balance", base_url.trim_end_matches('/'));
 
     let resp = client
@@ -2,11 +2,54 @@
 //!
 //! Supports tool-use (function calling) in the OpenAI-compatible format.
 
+use crate::config::{ModelPrice, SystemPromptMode};
+use crate::conway::rate_limit;
 use crate::tools::ToolDefinition;
 use crate::types::*;
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, info, warn};
+
+/// Non-success HTTP status from the inference endpoint.
+#[derive(Debug, thiserror::Error)]
+#[error("Inference failed ({status}): {body}")]
+pub struct InferenceStatusError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl InferenceStatusError {
+    /// Client errors (4xx) are the request's fault; another model won't help.
+    pub fn is_client_error(&self) -> bool {
+        self.status.is_client_error()
+    }
+}
+
+/// Longest prefix of an unparseable response body kept in errors and logs.
+const MAX_BODY_SNIPPET: usize = 512;
+
+/// A successful status whose body couldn't be read or parsed, typically
+/// because the connection dropped mid-response. Not a client error, so
+/// fallback and the loop's backoff apply.
+#[derive(Debug, thiserror::Error)]
+#[error("Malformed inference response ({status}): {reason}; body starts: {body_snippet:?}")]
+pub struct InferenceBodyError {
+    pub status: reqwest::StatusCode,
+    /// At most [`MAX_BODY_SNIPPET`] bytes of the body received.
+    pub body_snippet: String,
+    pub reason: String,
+}
+
+/// The first [`MAX_BODY_SNIPPET`] bytes of `body`, cut on a char boundary.
+fn body_snippet(body: &str) -> String {
+    if body.len() <= MAX_BODY_SNIPPET {
+        return body.to_string();
+    }
+    let end = (0..=MAX_BODY_SNIPPET).rev().find(|&i| body.is_char_boundary(i)).unwrap_or(0);
+    format!("{}...", &body[..end])
+}
 
 /// Inference client wrapping the Conway Compute inference API.
 #[derive(Debug, Clone)]
@@ -14,6 +57,11 @@ pub struct InferenceClient {
     base_url: String,
     api_key: String,
     http: reqwest::Client,
+    prompt_caching: bool,
+    /// Output token cap per model name (see [`lookup_output_cap`]).
+    max_output_tokens: BTreeMap<String, u32>,
+    system_prompt_mode: SystemPromptMode,
+    capture_payloads: bool,
 }
 
 // -- OpenAI-compatible request/response types --------------------------------
@@ -31,13 +79,34 @@ struct ChatRequest<'a> {
 #[derive(Debug, Serialize)]
 struct MessagePayload {
     role: String,
-    content: Option<String>,
+    content: Option<MessageContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_calls: Option<Vec<ToolCallPayload>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_call_id: Option<String>,
 }
 
+/// Plain text, or content parts when part of it carries a cache marker.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Serialize)]
+struct ContentPart {
+    r#type: &'static str,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheControl {
+    r#type: &'static str,
+}
+
 #[derive(Debug, Serialize)]
 struct ToolPayload<'a> {
     r#type: &'a str,
@@ -53,6 +122,8 @@ struct FunctionPayload<'a> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ToolCallPayload {
+    /// Some models leave this empty or out; see [`assign_missing_tool_call_ids`].
+    #[serde(default)]
     id: String,
     r#type: String,
     function: FunctionCallPayload,
@@ -87,26 +158,108 @@ struct UsagePayload {
     prompt_tokens: u32,
     completion_tokens: u32,
     total_tokens: u32,
+    /// OpenAI-style cache reporting.
+    #[serde(default)]
+    prompt_tokens_details: Option<PromptTokensDetails>,
+    /// Anthropic-style cache reporting.
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+    /// OpenAI-style reasoning reporting.
+    #[serde(default)]
+    completion_tokens_details: Option<CompletionTokensDetails>,
 }
 
-/// Pricing per 1M tokens (prompt, completion) in USD.
-const MODEL_PRICING: &[(&str, f64, f64)] = &[
-    ("gpt-4o", 2.50, 10.00),
-    ("gpt-4o-mini", 0.15, 0.60),
-    ("claude-sonnet-4-5-20250514", 3.00, 15.00),
-    ("claude-haiku-3-5-20241022", 0.25, 1.25),
+#[derive(Debug, Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionTokensDetails {
+    #[serde(default)]
+    reasoning_tokens: u32,
+}
+
+/// Built-in pricing per 1M tokens (prompt, completion, cached prompt,
+/// reasoning) in USD.
+const MODEL_PRICING: &[(&str, f64, f64, f64, f64)] = &[
+    ("gpt-4o", 2.50, 10.00, 1.25, 10.00),
+    ("gpt-4o-mini", 0.15, 0.60, 0.075, 0.60),
+    ("claude-sonnet-4-5-20250514", 3.00, 15.00, 0.30, 15.00),
+    ("claude-haiku-3-5-20241022", 0.25, 1.25, 0.025, 1.25),
 ];
 
+/// Move system messages into the first user message (or a new leading one),
+/// keeping the system prompt's cacheable prefix at the front.
+fn fold_system_into_user(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    let (system, mut rest): (Vec<_>, Vec<_>) =
+        messages.iter().cloned().partition(|m| m.role == ChatRole::System);
+    if system.is_empty() {
+        return rest;
+    }
+    let prompt = system.iter().map(|m| m.content.as_str()).collect::<Vec<_>>().join("\n\n");
+    let cacheable_prefix = system[0].cacheable_prefix;
+
+    match rest.iter_mut().find(|m| m.role == ChatRole::User) {
+        Some(user) => {
+            user.content = format!("{}\n\n{}", prompt, user.content);
+            user.cacheable_prefix = cacheable_prefix;
+        }
+        None => rest.insert(0, ChatMessage::user(prompt).with_cacheable_prefix(cacheable_prefix)),
+    }
+    rest
+}
+
+/// Whether `model` only caches prompt prefixes marked with `cache_control`.
+///
+/// Anthropic models need the explicit marker; OpenAI models cache long
+/// prefixes automatically.
+fn needs_cache_marker(model: &str) -> bool {
+    model.contains("claude")
+}
+
 impl InferenceClient {
     /// Create a new inference client.
     pub fn new(base_url: &str, api_key: &str) -> Self {
+        crate::redact::register_secret(api_key);
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key: api_key.to_string(),
-            http: reqwest::Client::new(),
+            http: crate::http::client(),
+            prompt_caching: false,
+            max_output_tokens: BTreeMap::new(),
+            system_prompt_mode: SystemPromptMode::System,
+            capture_payloads: false,
         }
     }
 
+    /// Mark cacheable message prefixes for backends that support it.
+    pub fn with_prompt_caching(mut self, enabled: bool) -> Self {
+        self.prompt_caching = enabled;
+        self
+    }
+
+    /// Cap `max_tokens` per model, for models with smaller output limits.
+    pub fn with_max_output_tokens(mut self, caps: BTreeMap<String, u32>) -> Self {
+        self.max_output_tokens = caps;
+        self
+    }
+
+    /// Send the system prompt as a `system` message or inside the first
+    /// user message.
+    pub fn with_system_prompt_mode(mut self, mode: SystemPromptMode) -> Self {
+        self.system_prompt_mode = mode;
+        self
+    }
+
+    /// Return each call's raw (redacted) request and response in
+    /// [`InferenceResponse::exchange`].
+    pub fn with_payload_capture(mut self, capture: bool) -> Self {
+        self.capture_payloads = capture;
+        self
+    }
+
     /// Run inference with tool support. Returns a response with optional tool calls.
     pub async fn chat(
         &self,
@@ -117,19 +270,52 @@ impl InferenceClient {
     ) -> Result<InferenceResponse> {
         let url = format!("{}/v1/chat/completions", self.base_url);
 
+        let mark_cache = self.prompt_caching && needs_cache_marker(model);
+
+        let folded;
+        let messages = match self.system_prompt_mode {
+            SystemPromptMode::System => messages,
+            SystemPromptMode::PrependUser => {
+                folded = fold_system_into_user(messages);
+                &folded[..]
+            }
+        };
+
         // Convert messages
         let msg_payloads: Vec<MessagePayload> = messages
             .iter()
-            .map(|m| MessagePayload {
-                role: match m.role {
-                    ChatRole::System => "system".into(),
-                    ChatRole::User => "user".into(),
-                    ChatRole::Assistant => "assistant".into(),
-                    ChatRole::Tool => "tool".into(),
-                },
-                content: Some(m.content.clone()),
-                tool_calls: None,
-                tool_call_id: None,
+            .map(|m| {
+                let tool_calls = (!m.tool_calls.is_empty()).then(|| {
+                    m.tool_calls
+                        .iter()
+                        .map(|tc| ToolCallPayload {
+                            id: tc.id.clone(),
+                            r#type: "function".into(),
+                            function: FunctionCallPayload {
+                                name: tc.name.clone(),
+                                arguments: tc.arguments.to_string(),
+                            },
+                        })
+                        .collect()
+                });
+                MessagePayload {
+                    role: match m.role {
+                        ChatRole::System => "system".into(),
+                        ChatRole::User => "user".into(),
+                        ChatRole::Assistant => "assistant".into(),
+                        ChatRole::Tool => "tool".into(),
+                    },
+                    // Assistant messages that only carry tool calls have no content
+                    content: if m.content.is_empty() && tool_calls.is_some() {
+                        None
+                    } else if mark_cache {
+                        Some(cache_marked_content(m))
+                    } else {
+                        Some(MessageContent::Text(m.content.clone()))
+                    },
+                    tool_calls,
+                    tool_call_id: m.tool_call_id.clone(),
+                }
             })
             .collect();
 
@@ -152,6 +338,14 @@ impl InferenceClient {
             )
         };
 
+        let max_tokens = match lookup_output_cap(model, &self.max_output_tokens) {
+            Some(cap) if cap < max_tokens => {
+                info!("Clamping max_tokens from {} to {} for model {}", max_tokens, cap, model);
+                cap
+            }
+            _ => max_tokens,
+        };
+
         let request = ChatRequest {
             model,
             messages: msg_payloads,
@@ -161,7 +355,12 @@ impl InferenceClient {
         };
 
         debug!("Inference request to model: {}", model);
+        let request_json = self
+            .capture_payloads
+            .then(|| serde_json::to_string(&request))
+            .transpose()?;
 
+        let started = std::time::Instant::now();
         let resp = self
             .http
             .post(&url)
@@ -172,12 +371,30 @@ impl InferenceClient {
             .context("Inference request failed")?;
 
         let status = resp.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(rate_limit::from_headers(resp.headers(), chrono::Utc::now()).into());
+        }
         if !status.is_success() {
             let body = resp.text().await.unwrap_or_default();
-            bail!("Inference failed ({}): {}", status, body);
+            return Err(InferenceStatusError { status, body }.into());
         }
 
-        let body: ChatResponse = resp.json().await.context("Failed to parse inference response")?;
+        let text = resp.text().await.map_err(|e| InferenceBodyError {
+            status,
+            body_snippet: String::new(),
+            reason: format!("body read failed: {}", e),
+        });
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (body, text) = text
+            .and_then(|text| match serde_json::from_str::<ChatResponse>(&text) {
+                Ok(body) => Ok((body, text)),
+                Err(e) => Err(InferenceBodyError {
+                    status,
+                    body_snippet: body_snippet(&text),
+                    reason: format!("invalid JSON ({} bytes received): {}", text.len(), e),
+                }),
+            })
+            .inspect_err(|e| warn!("{}", e))?;
 
         let choice = body.choices.into_iter().next().unwrap_or(Choice {
             message: ResponseMessage {
@@ -187,44 +404,544 @@ impl InferenceClient {
         });
 
         // Parse tool calls
-        let tool_calls: Vec<ToolCall> = choice
+        let mut tool_calls: Vec<ToolCall> = choice
             .message
             .tool_calls
             .into_iter()
-            .map(|tc| {
-                let args: serde_json::Value =
-                    serde_json::from_str(&tc.function.arguments).unwrap_or_default();
-                ToolCall {
-                    id: tc.id,
-                    name: tc.function.name,
-                    arguments: args,
-                }
+            .map(|tc| ToolCall {
+                arguments: parse_tool_arguments(&tc.function.name, &tc.function.arguments),
+                id: tc.id,
+                name: tc.function.name,
             })
             .collect();
+        assign_missing_tool_call_ids(model, &mut tool_calls);
 
         let usage = body.usage.map(|u| TokenUsage {
             prompt_tokens: u.prompt_tokens,
             completion_tokens: u.completion_tokens,
             total_tokens: u.total_tokens,
+            cached_prompt_tokens: u
+                .cache_read_input_tokens
+                .or(u.prompt_tokens_details.map(|d| d.cached_tokens))
+                .unwrap_or(0),
+            reasoning_tokens: u.completion_tokens_details.map_or(0, |d| d.reasoning_tokens),
         }).unwrap_or_default();
 
         Ok(InferenceResponse {
+            model: model.to_string(),
             content: choice.message.content,
             tool_calls,
             usage,
+            exchange: request_json.map(|request_json| InferenceExchange {
+                request_json: self.redact_payload(&request_json),
+                response_json: self.redact_payload(&text),
+                latency_ms,
+            }),
         })
     }
 
+    /// `payload` with the API key and the log redactor's secrets masked.
+    fn redact_payload(&self, payload: &str) -> String {
+        let payload = if self.api_key.is_empty() {
+            payload.to_string()
+        } else {
+            payload.replace(&self.api_key, crate::redact::REDACTED)
+        };
+        crate::redact::redact(&payload)
+    }
+
+    /// Run [`chat`](Self::chat) against each model in `models` in order,
+    /// returning the first success.
+    ///
+    /// 4xx responses are returned immediately since a different model would
+    /// reject the same request; 5xx, transport errors and malformed bodies move
+    /// on to the next model. The returned response records which model served
+    /// it.
+    pub async fn chat_with_fallback(
+        &self,
+        models: &[&str],
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+    ) -> Result<InferenceResponse> {
+        let mut last_err = anyhow::anyhow!("No inference models configured");
+        for (i, model) in models.iter().enumerate() {
+            match self.chat(model, messages, tools, max_tokens).await {
+                Ok(resp) => return Ok(resp),
+                Err(e) => {
+                    // The same account is rate limited on every model
+                    let client_error = e
+                        .downcast_ref::<InferenceStatusError>()
+                        .is_some_and(|s| s.is_client_error())
+                        || e.is::<rate_limit::RateLimited>();
+                    if client_error {
+                        return Err(e);
+                    }
+                    if let Some(next) = models.get(i + 1) {
+                        warn!("Model {} failed ({}); falling back to {}", model, e, next);
+                    }
+                    last_err = e;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
     /// Estimate the USD cost of a token usage for a given model.
-    pub fn estimate_cost(model: &str, usage: &TokenUsage) -> f64 {
-        let (prompt_rate, completion_rate) = MODEL_PRICING
-            .iter()
-            .find(|(name, _, _)| model.contains(name))
-            .map(|(_, p, c)| (*p, *c))
-            .unwrap_or((2.50, 10.00)); // Default to gpt-4o pricing
+    ///
+    /// `overrides` (the config's `[pricing]` table) take precedence over the
+    /// built-in table.
+    pub fn estimate_cost(
+        model: &str,
+        usage: &TokenUsage,
+        overrides: &BTreeMap<String, ModelPrice>,
+    ) -> f64 {
+        let (prompt_rate, completion_rate, cached_rate, reasoning_rate) =
+            lookup_price(model, overrides).unwrap_or_else(|| {
+                warn_unpriced_once(model);
+                (2.50, 10.00, 1.25, 10.00) // Default to gpt-4o pricing
+            });
 
-        let prompt_cost = (usage.prompt_tokens as f64 / 1_000_000.0) * prompt_rate;
-        let completion_cost = (usage.completion_tokens as f64 / 1_000_000.0) * completion_rate;
+        let cached = usage.cached_prompt_tokens.min(usage.prompt_tokens);
+        let fresh = usage.prompt_tokens - cached;
+        let prompt_cost = (fresh as f64 / 1_000_000.0) * prompt_rate
+            + (cached as f64 / 1_000_000.0) * cached_rate;
+        let reasoning = usage.reasoning_tokens.min(usage.completion_tokens);
+        let output = usage.completion_tokens - reasoning;
+        let completion_cost = (output as f64 / 1_000_000.0) * completion_rate
+            + (reasoning as f64 / 1_000_000.0) * reasoning_rate;
         prompt_cost + completion_cost
     }
 }
+
+/// Content for a message, with its cacheable prefix (if any) split off as a
+/// separate part carrying an ephemeral cache breakpoint.
+fn cache_marked_content(msg: &ChatMessage) -> MessageContent {
+    let Some(len) = msg
+        .cacheable_prefix
+        .filter(|&len| len > 0 && msg.content.is_char_boundary(len))
+    else {
+        return MessageContent::Text(msg.content.clone());
+    };
+
+    let (stable, rest) = msg.content.split_at(len);
+    let mut parts = vec![ContentPart {
+        r#type: "text",
+        text: stable.to_string(),
+        cache_control: Some(CacheControl { r#type: "ephemeral" }),
+    }];
+    if !rest.is_empty() {
+        parts.push(ContentPart {
+            r#type: "text",
+            text: rest.to_string(),
+            cache_control: None,
+        });
+    }
+    MessageContent::Parts(parts)
+}
+
+/// Key under which unparseable tool-call arguments are passed through.
+pub const RAW_ARGUMENTS_KEY: &str = "_raw_arguments";
+
+/// Parse a tool call's argument string into a JSON object.
+///
+/// Handles double-encoded objects (a JSON string containing the object) and
+/// empty arguments. Anything else that isn't an object is returned as
+/// `{ "_raw_arguments": <raw> }` so the tool can report it back to the model.
+fn parse_tool_arguments(tool: &str, raw: &str) -> serde_json::Value {
+    if raw.trim().is_empty() {
+        return serde_json::json!({});
+    }
+
+    match serde_json::from_str::<serde_json::Value>(raw) {
+        Ok(value @ serde_json::Value::Object(_)) => return value,
+        Ok(serde_json::Value::String(inner)) => {
+            if let Ok(value @ serde_json::Value::Object(_)) = serde_json::from_str(&inner) {
+                debug!("Decoded double-encoded arguments for {}", tool);
+                return value;
+            }
+        }
+        _ => {}
+    }
+
+    warn!("Tool call {} has unparseable arguments: {}", tool, raw);
+    serde_json::json!({ RAW_ARGUMENTS_KEY: raw })
+}
+
+/// Give tool calls without a usable id (empty, or repeating an earlier
+/// call's in the same response) a generated one, so each result pairs with
+/// its own call.
+fn assign_missing_tool_call_ids(model: &str, calls: &mut [ToolCall]) {
+    let mut seen = HashSet::new();
+    let mut synthesized = 0;
+    for call in calls.iter_mut() {
+        if call.id.trim().is_empty() || !seen.insert(call.id.clone()) {
+            call.id = format!("call_{}", ulid::Ulid::new());
+            seen.insert(call.id.clone());
+            synthesized += 1;
+        }
+    }
+    if synthesized > 0 {
+        warn!(
+            "{} returned {} of {} tool calls without a unique id; generated ids for them",
+            model,
+            synthesized,
+            calls.len()
+        );
+    }
+}
+
+/// Find a model's (prompt, completion, cached prompt, reasoning) rates: an
+/// exact name wins, otherwise the longest known name contained in `model`
+/// (so `gpt-4o-mini` doesn't match `gpt-4o`). Overrides and the built-in
+/// table are searched together; an override wins only for the same name.
+fn lookup_price(
+    model: &str,
+    overrides: &BTreeMap<String, ModelPrice>,
+) -> Option<(f64, f64, f64, f64)> {
+    let configured = overrides.iter().map(|(name, p)| {
+        (
+            name.as_str(),
+            p.prompt_per_million,
+            p.completion_per_million,
+            p.cached_prompt_per_million.unwrap_or(p.prompt_per_million),
+            p.reasoning_per_million.unwrap_or(p.completion_per_million),
+        )
+    });
+
+    // `max_by_key` keeps the last of equal keys, so overrides go last
+    MODEL_PRICING
+        .iter()
+        .copied()
+        .chain(configured)
+        .filter(|(name, ..)| model.contains(name))
+        .max_by_key(|(name, ..)| (*name == model, name.len()))
+        .map(|(_, p, c, cached, reasoning)| (p, c, cached, reasoning))
+}
+
+/// A model's output token cap, matched the same way as [`lookup_price`].
+fn lookup_output_cap(model: &str, caps: &BTreeMap<String, u32>) -> Option<u32> {
+    caps.iter()
+        .filter(|(name, _)| model.contains(name.as_str()))
+        .max_by_key(|(name, _)| (name.as_str() == model, name.len()))
+        .map(|(_, cap)| *cap)
+}
+
+/// Warn the first time a model without pricing is seen.
+fn warn_unpriced_once(model: &str) {
+    static WARNED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let mut warned = WARNED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if warned.insert(model.to_string()) {
+        warn!("No pricing for model '{}'; estimating at gpt-4o rates", model);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+
+    fn completion(content: &str) -> serde_json::Value {
+        serde_json::json!({
+            "choices": [{ "message": { "content": content } }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_primary_model_is_unavailable() {
+        let server = MockServer::start(|req| {
+            if req.json()["model"] == "primary" {
+                MockResponse::text(503, "upstream unavailable")
+            } else {
+                MockResponse::json(200, completion("hello from fallback"))
+            }
+        })
+        .await;
+        let client = InferenceClient::new(&server.url(), "key");
+
+        let resp = client
+            .chat_with_fallback(&["primary", "backup"], &[ChatMessage::user("hi")], &[], 64)
+            .await
+            .unwrap();
+
+        assert_eq!(resp.model, "backup");
+        assert_eq!(resp.content.as_deref(), Some("hello from fallback"));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_tokens_clamped_to_model_cap() {
+        let server = MockServer::start(|_| MockResponse::json(200, completion("ok"))).await;
+        let caps = BTreeMap::from([("gpt-4o".to_string(), 16_384), ("gpt-4o-mini".to_string(), 1024)]);
+        let client = InferenceClient::new(&server.url(), "key").with_max_output_tokens(caps);
+        let messages = [ChatMessage::user("hi")];
+
+        client.chat("gpt-4o-mini-2024-07-18", &messages, &[], 4096).await.unwrap();
+        client.chat("gpt-4o", &messages, &[], 4096).await.unwrap();
+        client.chat("claude-sonnet", &messages, &[], 4096).await.unwrap();
+
+        let sent: Vec<_> = server.requests().iter().map(|r| r.json()["max_tokens"].clone()).collect();
+        assert_eq!(sent, [1024, 4096, 4096]);
+    }
+
+    #[tokio::test]
+    async fn test_prepend_user_mode_sends_no_system_role() {
+        let server = MockServer::start(|_| MockResponse::json(200, completion("ok"))).await;
+        let client = InferenceClient::new(&server.url(), "key")
+            .with_system_prompt_mode(SystemPromptMode::PrependUser);
+        let messages = [
+            ChatMessage::system("You are an automaton."),
+            ChatMessage::user("Hello"),
+            ChatMessage::assistant("Hi", Vec::new()),
+            ChatMessage::user("What next?"),
+        ];
+
+        client.chat("local-llama", &messages, &[], 256).await.unwrap();
+        client.chat("local-llama", &messages[..1], &[], 256).await.unwrap();
+
+        let sent: Vec<_> = server.requests().iter().map(|r| r.json()["messages"].clone()).collect();
+        assert_eq!(
+            sent[0],
+            serde_json::json!([
+                { "role": "user", "content": "You are an automaton.\n\nHello" },
+                { "role": "assistant", "content": "Hi" },
+                { "role": "user", "content": "What next?" },
+            ])
+        );
+        // No user message to fold into: the prompt becomes one
+        assert_eq!(sent[1], serde_json::json!([{ "role": "user", "content": "You are an automaton." }]));
+    }
+
+    #[tokio::test]
+    async fn test_cache_marker_on_stable_system_prefix() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                serde_json::json!({
+                    "choices": [{ "message": { "content": "ok" } }],
+                    "usage": {
+                        "prompt_tokens": 1000, "completion_tokens": 5, "total_tokens": 1005,
+                        "cache_read_input_tokens": 800
+                    }
+                }),
+            )
+        })
+        .await;
+        let client = InferenceClient::new(&server.url(), "key").with_prompt_caching(true);
+
+        let db = crate::state::Database::open_memory().unwrap();
+        let config = crate::config::AutomatonConfig::default();
+        let prompt = crate::agent::system_prompt::build_system_prompt(
+            &config,
+            &db,
+            SurvivalTier::Normal,
+            &[],
+        );
+        let messages = crate::agent::context::build_messages(
+            &prompt,
+            "hi",
+            &[],
+            &crate::agent::context::HistoryPolicy::from_config(&config),
+        );
+
+        let resp = client.chat("claude-sonnet-4-5", &messages, &[], 64).await.unwrap();
+        assert_eq!(resp.usage.cached_prompt_tokens, 800);
+        client.chat("gpt-4o", &messages, &[], 64).await.unwrap();
+
+        let requests = server.requests();
+        let parts = requests[0].json()["messages"][0]["content"].clone();
+        let stable = parts[0]["text"].as_str().unwrap();
+        assert!(stable.contains("# Constitution"));
+        assert!(!stable.contains("# Current Status"));
+        assert_eq!(parts[0]["cache_control"]["type"], "ephemeral");
+        assert!(parts[1]["text"].as_str().unwrap().contains("# Current Status"));
+        assert!(parts[1].get("cache_control").is_none());
+        // The user message is never marked
+        assert!(requests[0].json()["messages"][1]["content"].is_string());
+
+        // OpenAI models cache automatically; no marker is sent
+        assert!(requests[1].json()["messages"][0]["content"].is_string());
+    }
+
+    #[test]
+    fn test_cached_prompt_tokens_are_billed_at_cached_rate() {
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 0,
+            total_tokens: 1_000_000,
+            cached_prompt_tokens: 800_000,
+            reasoning_tokens: 0,
+        };
+        let none = BTreeMap::new();
+        // 200k fresh at $3.00 + 800k cached at $0.30
+        let cost = InferenceClient::estimate_cost("claude-sonnet-4-5-20250514", &usage, &none);
+        assert!((cost - 0.84).abs() < 1e-9, "{}", cost);
+
+        // Overrides without a cached rate bill cached tokens in full
+        let overrides = BTreeMap::from([(
+            "custom".to_string(),
+            ModelPrice {
+                prompt_per_million: 1.0,
+                completion_per_million: 2.0,
+                cached_prompt_per_million: None,
+                reasoning_per_million: None,
+            },
+        )]);
+        assert_eq!(InferenceClient::estimate_cost("custom", &usage, &overrides), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_and_cached_tokens_priced_separately() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                serde_json::json!({
+                    "choices": [{ "message": { "content": "ok" } }],
+                    "usage": {
+                        "prompt_tokens": 1_000_000, "completion_tokens": 1_000_000,
+                        "total_tokens": 2_000_000,
+                        "prompt_tokens_details": { "cached_tokens": 500_000 },
+                        "completion_tokens_details": { "reasoning_tokens": 250_000 }
+                    }
+                }),
+            )
+        })
+        .await;
+        let client = InferenceClient::new(&server.url(), "key");
+        let usage = client.chat("o-custom", &[ChatMessage::user("hi")], &[], 64).await.unwrap().usage;
+        assert_eq!((usage.cached_prompt_tokens, usage.reasoning_tokens), (500_000, 250_000));
+
+        let overrides = BTreeMap::from([(
+            "o-custom".to_string(),
+            ModelPrice {
+                prompt_per_million: 2.0,
+                completion_per_million: 8.0,
+                cached_prompt_per_million: Some(0.5),
+                reasoning_per_million: Some(4.0),
+            },
+        )]);
+        // 500k fresh at $2 + 500k cached at $0.50 + 750k output at $8 + 250k reasoning at $4
+        let cost = InferenceClient::estimate_cost("o-custom", &usage, &overrides);
+        assert!((cost - 8.25).abs() < 1e-9, "{}", cost);
+
+        // Usage recorded before these fields existed still loads, as zero
+        let old: TokenUsage = serde_json::from_str(
+            r#"{"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}"#,
+        )
+        .unwrap();
+        assert_eq!((old.cached_prompt_tokens, old.reasoning_tokens), (0, 0));
+    }
+
+    #[test]
+    fn test_double_encoded_arguments_are_decoded() {
+        let raw = serde_json::to_string(r#"{"command":"ls -la"}"#).unwrap();
+        assert_eq!(parse_tool_arguments("exec", &raw)["command"], "ls -la");
+        assert_eq!(parse_tool_arguments("exec", r#"{"command":"ls"}"#)["command"], "ls");
+        assert_eq!(parse_tool_arguments("system_info", ""), serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_malformed_arguments_are_kept_raw() {
+        for raw in [r#"{"command": "ls"#, "[1, 2]", "\"not an object\""] {
+            let args = parse_tool_arguments("exec", raw);
+            assert_eq!(args[RAW_ARGUMENTS_KEY], raw);
+        }
+    }
+
+    #[test]
+    fn test_config_pricing_overrides_builtin_table() {
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 1_000_000,
+            total_tokens: 2_000_000,
+            cached_prompt_tokens: 0,
+            reasoning_tokens: 0,
+        };
+        let none = BTreeMap::new();
+        assert_eq!(InferenceClient::estimate_cost("gpt-4o", &usage, &none), 12.50);
+        assert_eq!(InferenceClient::estimate_cost("gpt-4o-mini", &usage, &none), 0.75);
+
+        let overrides = BTreeMap::from([(
+            "gpt-4o".to_string(),
+            ModelPrice {
+                prompt_per_million: 1.0,
+                completion_per_million: 2.0,
+                cached_prompt_per_million: None,
+                reasoning_per_million: None,
+            },
+        )]);
+        assert_eq!(InferenceClient::estimate_cost("gpt-4o", &usage, &overrides), 3.0);
+        // The override doesn't swallow the longer built-in name
+        assert_eq!(InferenceClient::estimate_cost("gpt-4o-mini", &usage, &overrides), 0.75);
+        assert_eq!(InferenceClient::estimate_cost("gpt-4o-2024-08-06", &usage, &overrides), 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_body_is_a_specific_error() {
+        let full = completion("a reply that never finished").to_string();
+        let truncated = full[..full.len() / 2].to_string();
+        let server = MockServer::start(move |_| MockResponse::text(200, &truncated)).await;
+        let client = InferenceClient::new(&server.url(), "key");
+
+        let err = client
+            .chat("primary", &[ChatMessage::user("hi")], &[], 64)
+            .await
+            .unwrap_err();
+        let body_err = err.downcast_ref::<InferenceBodyError>().unwrap();
+        assert_eq!(body_err.status, reqwest::StatusCode::OK);
+        assert!(full.starts_with(&body_err.body_snippet));
+        assert!(body_err.reason.contains("invalid JSON"), "{}", body_err.reason);
+        assert!(err.to_string().contains("Malformed inference response (200 OK)"), "{}", err);
+
+        // Another model may well answer in full
+        let _ = client
+            .chat_with_fallback(&["primary", "backup"], &[ChatMessage::user("hi")], &[], 64)
+            .await;
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_body_snippet_is_bounded() {
+        assert_eq!(body_snippet("{\"choices\": ["), "{\"choices\": [");
+        let long = "é".repeat(MAX_BODY_SNIPPET);
+        let snippet = body_snippet(&long);
+        assert!(snippet.len() <= MAX_BODY_SNIPPET + 3);
+        assert!(snippet.ends_with("..."));
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_do_not_fall_back() {
+        let server = MockServer::start(|_| MockResponse::text(400, "bad request")).await;
+        let client = InferenceClient::new(&server.url(), "key");
+
+        let result = client
+            .chat_with_fallback(&["primary", "backup"], &[ChatMessage::user("hi")], &[], 64)
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_429_is_rate_limited_with_retry_after() {
+        let retry_at = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        let server = MockServer::start(move |_| {
+            MockResponse::text(429, "rate limited").with_header("Retry-After", &retry_at)
+        })
+        .await;
+        let client = InferenceClient::new(&server.url(), "key");
+
+        let err = client
+            .chat_with_fallback(&["primary", "backup"], &[ChatMessage::user("hi")], &[], 64)
+            .await
+            .unwrap_err();
+        let wait = rate_limit::retry_after(&err).unwrap().as_secs();
+        assert!((110..=120).contains(&wait), "{}", wait);
+        // Every model shares the account's limit, so there's no fallback
+        assert_eq!(server.requests().len(), 1);
+    }
+}
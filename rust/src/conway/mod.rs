@@ -1,8 +1,10 @@
 pub mod client;
 pub mod credits;
 pub mod inference;
+pub mod rate_limit;
 pub mod x402;
 
-pub use client::ConwayClient;
+pub use client::{ConwayClient, DnsRecord, PaymentLimits};
 pub use credits::CreditBalance;
 pub use inference::InferenceClient;
+pub use rate_limit::RateLimited;
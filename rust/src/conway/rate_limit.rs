@@ -0,0 +1,122 @@
+//! Rate-limit responses (429) from Conway and the inference endpoint.
+//!
+//! A 429 becomes a [`RateLimited`] error carrying how long the server asked
+//! us to wait: `Retry-After` (seconds or an HTTP date) or, failing that,
+//! `X-RateLimit-Reset` (a Unix time or seconds from now). The agent loop
+//! waits exactly that long instead of its flat error backoff.
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+/// `X-RateLimit-Reset` values above this are Unix times, not deltas.
+const UNIX_TIME_THRESHOLD: u64 = 1_000_000_000;
+
+/// A 429 response, with what its headers said about the limit.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Rate limited{}", match retry_after {
+    Some(wait) => format!(" (retry after {}s)", wait.as_secs()),
+    None => String::new(),
+})]
+pub struct RateLimited {
+    /// How long to wait before retrying, if the server said.
+    pub retry_after: Option<Duration>,
+    /// `X-RateLimit-Limit`.
+    pub limit: Option<u64>,
+    /// `X-RateLimit-Remaining`.
+    pub remaining: Option<u64>,
+}
+
+/// Parse a `Retry-After` value: delay seconds or an HTTP date. A date in
+/// the past means no wait.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Parse `X-RateLimit-Reset`: a Unix time or seconds from now.
+fn parse_reset(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let reset = value.trim().parse::<u64>().ok()?;
+    if reset < UNIX_TIME_THRESHOLD {
+        return Some(Duration::from_secs(reset));
+    }
+    Some(Duration::from_secs(reset.saturating_sub(now.timestamp().max(0) as u64)))
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// The [`RateLimited`] error described by a 429 response's headers.
+pub fn from_headers(headers: &HeaderMap, now: DateTime<Utc>) -> RateLimited {
+    let retry_after = header(headers, RETRY_AFTER.as_str())
+        .and_then(|v| parse_retry_after(v, now))
+        .or_else(|| header(headers, "x-ratelimit-reset").and_then(|v| parse_reset(v, now)));
+    let number = |name| header(headers, name).and_then(|v| v.trim().parse().ok());
+    RateLimited {
+        retry_after,
+        limit: number("x-ratelimit-limit"),
+        remaining: number("x-ratelimit-remaining"),
+    }
+}
+
+/// The wait a rate-limited error asks for, if `err` is one that says.
+pub fn retry_after(err: &anyhow::Error) -> Option<Duration> {
+    err.downcast_ref::<RateLimited>().and_then(|r| r.retry_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_retry_after_seconds_and_http_date() {
+        let now = utc("2026-03-01T12:00:00Z");
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Sun, 01 Mar 2026 12:01:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        // Already passed
+        assert_eq!(parse_retry_after("Sun, 01 Mar 2026 11:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_rate_limit_headers() {
+        let now = utc("2026-03-01T12:00:00Z");
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("60"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert(
+            "x-ratelimit-reset",
+            HeaderValue::from_str(&(now.timestamp() + 45).to_string()).unwrap(),
+        );
+        let limited = from_headers(&headers, now);
+        assert_eq!(
+            limited,
+            RateLimited {
+                retry_after: Some(Duration::from_secs(45)),
+                limit: Some(60),
+                remaining: Some(0),
+            }
+        );
+        assert_eq!(limited.to_string(), "Rate limited (retry after 45s)");
+
+        // Retry-After wins over the reset time
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(from_headers(&headers, now).retry_after, Some(Duration::from_secs(7)));
+
+        assert_eq!(from_headers(&HeaderMap::new(), now).to_string(), "Rate limited");
+    }
+}
@@ -0,0 +1,215 @@
+//! `automaton doctor` — environment checks with a pass/fail line each.
+//!
+//! Each check returns a short detail on success or an error explaining what
+//! is wrong. Network checks use a short timeout so a dead endpoint fails
+//! fast instead of hanging the command.
+
+use crate::config::{self, AutomatonConfig};
+use crate::conway;
+use crate::identity::Wallet;
+use crate::registry::RpcClient;
+use crate::skills::loader::{parse_skill_file, skill_files};
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Timeout for each network check.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of one named check.
+#[derive(Debug)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub outcome: Result<String>,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// `git` is installed (needed for state versioning).
+pub fn check_git() -> Result<String> {
+    let output = Command::new("git")
+        .arg("--version")
+        .output()
+        .context("git not found on PATH")?;
+    if !output.status.success() {
+        bail!("git --version failed");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The config file exists, parses, and has what the runtime needs.
+pub fn check_config(path: &Path) -> Result<AutomatonConfig> {
+    if !path.exists() {
+        bail!("{} not found; run `automaton setup`", path.display());
+    }
+    let config = config::load_config(path)?;
+    if config.conway_api_key.is_empty() {
+        bail!("conway_api_key is not set; run `automaton provision`");
+    }
+    Ok(config)
+}
+
+/// The wallet file loads and yields an address.
+pub fn check_wallet(path: &Path) -> Result<String> {
+    if !path.exists() {
+        bail!("{} not found", path.display());
+    }
+    Ok(Wallet::load(path)?.address.to_string())
+}
+
+/// Conway answers the credits endpoint with our key.
+pub async fn check_conway(api_url: &str, api_key: &str) -> Result<String> {
+    let check = conway::credits::check_credits(api_url, api_key);
+    let balance = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .context("Timed out")??;
+    Ok(format!("{} {} available", balance.credits, balance.currency))
+}
+
+/// The Base RPC answers `eth_blockNumber`.
+pub async fn check_rpc(rpc_url: &str) -> Result<String> {
+    let rpc = RpcClient::new(rpc_url).with_timeout(CHECK_TIMEOUT);
+    let block = rpc
+        .quantity("eth_blockNumber", serde_json::json!([]))
+        .await
+        .context("RPC check failed")?;
+    Ok(format!("block {}", block))
+}
+
+/// The social relay accepts connections (any non-5xx answer counts).
+pub async fn check_relay(relay_url: &str) -> Result<String> {
+    let resp = crate::http::client()
+        .get(relay_url)
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+        .context("Relay unreachable")?;
+    if resp.status().is_server_error() {
+        bail!("Relay returned {}", resp.status());
+    }
+    Ok(format!("reachable ({})", resp.status()))
+}
+
+/// Every SKILL.md in the skills directory parses.
+pub fn check_skills(skills_dir: &Path) -> Result<String> {
+    let files = skill_files(skills_dir)?;
+    let broken: Vec<String> = files
+        .iter()
+        .filter_map(|path| {
+            parse_skill_file(path)
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect();
+    if !broken.is_empty() {
+        bail!("{} unparseable: {}", broken.len(), broken.join("; "));
+    }
+    Ok(format!("{} skills", files.len()))
+}
+
+/// Run every check for the agent in `home_dir`.
+pub async fn run_checks(home_dir: &Path) -> Vec<CheckResult> {
+    let mut results = vec![CheckResult {
+        name: "git",
+        outcome: check_git(),
+    }];
+
+    let config = check_config(&home_dir.join("automaton.toml"));
+    let loaded = match &config {
+        Ok(cfg) => Some(cfg.clone()),
+        // Still run the other checks against whatever parses
+        Err(_) => config::load_config(&home_dir.join("automaton.toml")).ok(),
+    };
+    results.push(CheckResult {
+        name: "config",
+        outcome: config.map(|c| format!("agent '{}'", c.name)),
+    });
+    results.push(CheckResult {
+        name: "wallet",
+        outcome: check_wallet(&home_dir.join("wallet.json")),
+    });
+
+    let Some(cfg) = loaded else {
+        return results;
+    };
+    results.push(CheckResult {
+        name: "conway",
+        outcome: check_conway(&cfg.conway_api_url, &cfg.conway_api_key).await,
+    });
+    results.push(CheckResult {
+        name: "base rpc",
+        outcome: check_rpc(&cfg.base_rpc_url).await,
+    });
+    if !cfg.social_relay_url.is_empty() {
+        results.push(CheckResult {
+            name: "social relay",
+            outcome: check_relay(&cfg.social_relay_url).await,
+        });
+    }
+    results.push(CheckResult {
+        name: "skills",
+        outcome: check_skills(Path::new(&cfg.resolved_skills_dir())),
+    });
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{unreachable_url, MockResponse, MockServer};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_network_checks_against_mock_endpoints() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/credits/balance" => MockResponse::json(200, json!({ "credits": 3.5, "currency": "USD" })),
+            "/rpc" => MockResponse::json(200, json!({ "jsonrpc": "2.0", "id": 1, "result": "0x1b4" })),
+            "/bad-rpc" => MockResponse::json(200, json!({ "error": { "message": "no method" } })),
+            "/down" => MockResponse::json(503, json!({})),
+            _ => MockResponse::json(404, json!({})),
+        })
+        .await;
+        let url = server.url();
+
+        assert_eq!(check_conway(&url, "key").await.unwrap(), "3.5 USD available");
+        assert_eq!(check_rpc(&format!("{}/rpc", url)).await.unwrap(), "block 436");
+        assert!(check_rpc(&format!("{}/bad-rpc", url)).await.is_err());
+        assert!(check_relay(&format!("{}/relay", url)).await.is_ok()); // 404 is still an answer
+        assert!(check_relay(&format!("{}/down", url)).await.is_err());
+        assert!(check_conway(&unreachable_url().await, "key").await.is_err());
+        assert_eq!(server.requests()[1].json()["method"], "eth_blockNumber");
+    }
+
+    #[test]
+    fn test_local_checks() {
+        let home = std::env::temp_dir().join(format!("automaton-doctor-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(home.join("skills/good")).unwrap();
+        std::fs::create_dir_all(home.join("skills/bad")).unwrap();
+
+        assert!(check_config(&home.join("automaton.toml")).is_err());
+        let cfg = AutomatonConfig::default();
+        config::save_config(&cfg, &home.join("automaton.toml")).unwrap();
+        let err = check_config(&home.join("automaton.toml")).unwrap_err();
+        assert!(err.to_string().contains("conway_api_key"), "{}", err);
+
+        assert!(check_wallet(&home.join("wallet.json")).is_err());
+        let wallet = Wallet::generate(&home.join("wallet.json")).unwrap();
+        assert_eq!(
+            check_wallet(&home.join("wallet.json")).unwrap(),
+            wallet.address.to_string()
+        );
+
+        std::fs::write(home.join("skills/good/SKILL.md"), "---\nname: good\n---\nDo it.").unwrap();
+        assert_eq!(check_skills(&home.join("skills")).unwrap(), "1 skills");
+        std::fs::write(home.join("skills/bad/SKILL.md"), "---\nname: [unclosed\n---\n").unwrap();
+        assert!(check_skills(&home.join("skills")).is_err());
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+//! Small encoding helpers shared across subsystems (no external deps).
+
+use anyhow::{bail, Result};
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 encoding with padding.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = if chunk.len() > 1 { chunk[1] as u32 } else { 0 };
+        let b2 = if chunk.len() > 2 { chunk[2] as u32 } else { 0 };
+
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        result.push(BASE64_CHARS[((triple >> 18) & 0x3F) as usize] as char);
+        result.push(BASE64_CHARS[((triple >> 12) & 0x3F) as usize] as char);
+
+        if chunk.len() > 1 {
+            result.push(BASE64_CHARS[((triple >> 6) & 0x3F) as usize] as char);
+        } else {
+            result.push('=');
+        }
+
+        if chunk.len() > 2 {
+            result.push(BASE64_CHARS[(triple & 0x3F) as usize] as char);
+        } else {
+            result.push('=');
+        }
+    }
+
+    result
+}
+
+/// Decode standard base64 (padding optional, ASCII whitespace ignored).
+pub fn base64_decode(input: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => bail!("Invalid base64 character: {:?}", c as char),
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Detect a MIME type from a file's leading magic bytes.
+pub fn detect_mime(data: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\0asm", "application/wasm"),
+        (b"\x7fELF", "application/x-elf"),
+    ];
+
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+        .map(|(_, mime)| *mime)
+        .unwrap_or_else(|| {
+            if std::str::from_utf8(data).is_ok() {
+                "text/plain"
+            } else {
+                "application/octet-stream"
+            }
+        })
+}
+
+/// Decode a file's bytes as UTF-8 text, with an actionable error if they
+/// aren't.
+pub fn decode_utf8_text(path: &str, bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).or_else(|e| {
+        let bytes = e.as_bytes();
+        let hint = if bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff") {
+            " (it looks UTF-16 encoded)"
+        } else {
+            ""
+        };
+        bail!(
+            "{} is not valid UTF-8 text{}: invalid byte at offset {}. \
+             Use read_file_binary to read it as raw bytes.",
+            path,
+            hint,
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+/// Line ending to normalize text to before writing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl std::str::FromStr for LineEnding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lf" => Ok(Self::Lf),
+            "crlf" => Ok(Self::Crlf),
+            _ => bail!("Unknown line ending '{}' (expected 'lf' or 'crlf')", s),
+        }
+    }
+}
+
+/// Rewrite every line break (`\r\n`, `\n` or a lone `\r`) as `ending`.
+pub fn normalize_newlines(text: &str, ending: LineEnding) -> String {
+    let lf = text.replace("\r\n", "\n").replace('\r', "\n");
+    match ending {
+        LineEnding::Lf => lf,
+        LineEnding::Crlf => lf.replace('\n', "\r\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A complete 1x1 transparent PNG.
+    const TINY_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+        0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x00,
+        0x01, 0x00, 0x00, 0x05, 0x00, 0x01, 0x0d, 0x0a, 0x2d, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x49,
+        0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn test_png_round_trips_through_base64() {
+        let encoded = base64_encode(TINY_PNG);
+        let decoded = base64_decode(&encoded).unwrap();
+        assert_eq!(decoded, TINY_PNG);
+        assert_eq!(detect_mime(&decoded), "image/png");
+    }
+
+    #[test]
+    fn test_base64_known_answers() {
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_decode("Zm9vYg==").unwrap(), b"foob");
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn test_newline_normalization() {
+        let mixed = "a\r\nb\nc\rd";
+        assert_eq!(normalize_newlines(mixed, LineEnding::Lf), "a\nb\nc\nd");
+        assert_eq!(normalize_newlines(mixed, LineEnding::Crlf), "a\r\nb\r\nc\r\nd");
+        assert_eq!("CRLF".parse::<LineEnding>().unwrap(), LineEnding::Crlf);
+        assert!("cr".parse::<LineEnding>().is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Portable agent snapshots for moving an automaton between hosts.
+//!
+//! `export` bundles the config (API key and status token redacted), SOUL.md,
+//! constitution.md, heartbeat.yml, the skills directory and a consistent copy
+//! of the database into a `.tar.gz`. The wallet key is only included when explicitly asked
+//! for. `import` restores an archive into an empty home directory and points
+//! the config's paths at it.
+
+use crate::config::{self, AutomatonConfig};
+use crate::state::Database;
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::info;
+
+/// Archive entry names.
+const CONFIG_FILE: &str = "automaton.toml";
+const WALLET_FILE: &str = "wallet.json";
+const DB_FILE: &str = "state.db";
+const HEARTBEAT_FILE: &str = "heartbeat.yml";
+const SKILLS_DIR: &str = "skills";
+
+/// Identity files copied verbatim from the home directory.
+const HOME_FILES: &[&str] = &["SOUL.md", "constitution.md"];
+
+/// Export the agent in `home_dir` to `archive`.
+pub fn export_agent(home_dir: &Path, archive: &Path, include_wallet: bool) -> Result<()> {
+    let config_path = home_dir.join(CONFIG_FILE);
+    if !config_path.exists() {
+        bail!("No config found at {}", config_path.display());
+    }
+    let mut cfg = config::load_config(&config_path)?;
+
+    let staging = staging_dir("export")?;
+    let result = (|| {
+        // Stage the database first, while the config still holds real paths
+        let db = Database::open(Path::new(&cfg.resolved_db_path()))?;
+        db.backup_to(&staging.join(DB_FILE))?;
+
+        copy_if_exists(Path::new(&cfg.resolved_heartbeat_path()), &staging.join(HEARTBEAT_FILE))?;
+        copy_dir_if_exists(Path::new(&cfg.resolved_skills_dir()), &staging.join(SKILLS_DIR))?;
+        for name in HOME_FILES {
+            copy_if_exists(&home_dir.join(name), &staging.join(name))?;
+        }
+
+        if include_wallet {
+            let wallet = home_dir.join(WALLET_FILE);
+            if !wallet.exists() {
+                bail!("No wallet found at {}", wallet.display());
+            }
+            copy_file(&wallet, &staging.join(WALLET_FILE))?;
+        }
+
+        cfg.conway_api_key = String::new();
+        cfg.status_token = String::new();
+        std::fs::write(staging.join(CONFIG_FILE), toml::to_string_pretty(&cfg)?)
+            .context("Failed to stage config")?;
+
+        run_tar(&[
+            "-czf".as_ref(),
+            archive.as_os_str(),
+            "-C".as_ref(),
+            staging.as_os_str(),
+            ".".as_ref(),
+        ])
+    })();
+    let _ = std::fs::remove_dir_all(&staging);
+    result?;
+
+    info!(
+        "Exported agent '{}' to {} (wallet {})",
+        cfg.name,
+        archive.display(),
+        if include_wallet { "included" } else { "excluded" }
+    );
+    Ok(())
+}
+
+/// Restore an exported archive into `home_dir`, which must not already hold
+/// an agent. Returns the restored config.
+pub fn import_agent(archive: &Path, home_dir: &Path) -> Result<AutomatonConfig> {
+    let config_path = home_dir.join(CONFIG_FILE);
+    if config_path.exists() {
+        bail!("Refusing to import: {} already exists", config_path.display());
+    }
+    if !archive.exists() {
+        bail!("Archive not found: {}", archive.display());
+    }
+
+    let staging = staging_dir("import")?;
+    let result = (|| {
+        run_tar(&[
+            "-xzf".as_ref(),
+            archive.as_os_str(),
+            "-C".as_ref(),
+            staging.as_os_str(),
+        ])?;
+
+        let staged_config = staging.join(CONFIG_FILE);
+        if !staged_config.exists() {
+            bail!("Archive has no {}", CONFIG_FILE);
+        }
+        let mut cfg = config::load_config(&staged_config)?;
+
+        // Point every path at the new home
+        cfg.db_path = home_dir.join(DB_FILE).display().to_string();
+        cfg.heartbeat_config_path = home_dir.join(HEARTBEAT_FILE).display().to_string();
+        cfg.skills_dir = home_dir.join(SKILLS_DIR).display().to_string();
+
+        std::fs::create_dir_all(home_dir)
+            .with_context(|| format!("Failed to create {}", home_dir.display()))?;
+        copy_if_exists(&staging.join(DB_FILE), &home_dir.join(DB_FILE))?;
+        copy_if_exists(&staging.join(HEARTBEAT_FILE), &home_dir.join(HEARTBEAT_FILE))?;
+        copy_dir_if_exists(&staging.join(SKILLS_DIR), &home_dir.join(SKILLS_DIR))?;
+        for name in HOME_FILES {
+            copy_if_exists(&staging.join(name), &home_dir.join(name))?;
+        }
+        if staging.join(WALLET_FILE).exists() {
+            copy_file(&staging.join(WALLET_FILE), &home_dir.join(WALLET_FILE))?;
+            restrict_permissions(&home_dir.join(WALLET_FILE))?;
+        }
+
+        config::save_config(&cfg, &config_path)?;
+        Ok(cfg)
+    })();
+    let _ = std::fs::remove_dir_all(&staging);
+
+    let cfg = result?;
+    info!("Imported agent '{}' into {}", cfg.name, home_dir.display());
+    Ok(cfg)
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+fn staging_dir(kind: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("automaton-{}-{}", kind, ulid::Ulid::new()));
+    std::fs::create_dir_all(&dir).context("Failed to create staging directory")?;
+    Ok(dir)
+}
+
+fn run_tar(args: &[&std::ffi::OsStr]) -> Result<()> {
+    let output = Command::new("tar")
+        .args(args)
+        .output()
+        .context("Failed to run tar")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("tar failed: {}", stderr.trim());
+    }
+    Ok(())
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    std::fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+fn copy_if_exists(from: &Path, to: &Path) -> Result<()> {
+    if from.is_file() {
+        copy_file(from, to)?;
+    }
+    Ok(())
+}
+
+fn copy_dir_if_exists(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_if_exists(&entry.path(), &target)?;
+        } else {
+            copy_file(&entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(kind: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("automaton-{}-{}", kind, ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A minimal agent home with every exported piece present.
+    fn seed_home(home: &Path) {
+        let cfg = AutomatonConfig {
+            name: "porter".into(),
+            conway_api_key: "cnwy_secret".into(),
+            db_path: home.join(DB_FILE).display().to_string(),
+            heartbeat_config_path: home.join(HEARTBEAT_FILE).display().to_string(),
+            skills_dir: home.join(SKILLS_DIR).display().to_string(),
+            ..Default::default()
+        };
+        config::save_config(&cfg, &home.join(CONFIG_FILE)).unwrap();
+
+        std::fs::write(home.join("SOUL.md"), "# Soul\nI carry tea.").unwrap();
+        std::fs::write(home.join("constitution.md"), "Never harm.").unwrap();
+        std::fs::write(home.join(HEARTBEAT_FILE), "entries: []\n").unwrap();
+        std::fs::create_dir_all(home.join("skills/brew")).unwrap();
+        std::fs::write(home.join("skills/brew/SKILL.md"), "---\nname: brew\n---\n").unwrap();
+        std::fs::write(home.join(WALLET_FILE), "{\"private_key\":\"0xabc\"}").unwrap();
+
+        let db = Database::open(&home.join(DB_FILE)).unwrap();
+        db.kv_set("agent_state", "sleeping").unwrap();
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let src = temp_dir("export-src");
+        let dst = temp_dir("export-dst").join("home");
+        let archive = temp_dir("export-out").join("agent.tar.gz");
+        seed_home(&src);
+
+        export_agent(&src, &archive, false).unwrap();
+        let cfg = import_agent(&archive, &dst).unwrap();
+
+        assert_eq!(cfg.name, "porter");
+        assert!(cfg.conway_api_key.is_empty(), "API key must be redacted");
+        assert_eq!(cfg.db_path, dst.join(DB_FILE).display().to_string());
+        assert_eq!(
+            std::fs::read_to_string(dst.join("SOUL.md")).unwrap(),
+            "# Soul\nI carry tea."
+        );
+        assert!(dst.join("constitution.md").exists());
+        assert!(dst.join(HEARTBEAT_FILE).exists());
+        assert!(dst.join("skills/brew/SKILL.md").exists());
+        assert!(!dst.join(WALLET_FILE).exists(), "wallet excluded by default");
+
+        let db = Database::open(&dst.join(DB_FILE)).unwrap();
+        assert_eq!(db.kv_get("agent_state").unwrap().as_deref(), Some("sleeping"));
+
+        // Importing over an existing agent is refused
+        assert!(import_agent(&archive, &dst).is_err());
+
+        // The wallet travels only when asked for
+        let with_wallet = archive.with_file_name("agent-wallet.tar.gz");
+        export_agent(&src, &with_wallet, true).unwrap();
+        let dst2 = dst.with_file_name("home2");
+        import_agent(&with_wallet, &dst2).unwrap();
+        assert!(dst2.join(WALLET_FILE).exists());
+
+        for dir in [src, dst.parent().unwrap().to_path_buf(), archive.parent().unwrap().to_path_buf()] {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
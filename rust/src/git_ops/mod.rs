@@ -2,8 +2,15 @@
 //!
 //! The ~/.automaton/ directory is managed as a git repo.
 //! Every configuration change is committed for full auditability.
+//!
+//! With `sign_state_commits`, commits carry an `Automaton-Signature`
+//! trailer: the wallet's EIP-191 signature over the commit message and the
+//! hash of the tree it records. GPG isn't available in the sandbox, so this
+//! is how a later reader proves a snapshot came from the agent.
 
-use anyhow::{Context, Result};
+use crate::identity::{self, Wallet};
+use crate::types::Address;
+use anyhow::{bail, Context, Result};
 use std::path::Path;
 use std::process::Command;
 use tracing::{debug, info, warn};
@@ -38,8 +45,41 @@ pub fn init_state_repo(automaton_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Commit trailer holding the wallet signature of a state commit.
+pub const SIGNATURE_TRAILER: &str = "Automaton-Signature";
+
 /// Commit all changes in the state directory.
-pub fn commit_state(automaton_dir: &Path, message: &str) -> Result<()> {
+///
+/// Returns whether a commit was made (`false` when nothing changed).
+pub fn commit_state(automaton_dir: &Path, message: &str) -> Result<bool> {
+    commit(automaton_dir, message, None)
+}
+
+/// Like [`commit_state`], with the commit signed by `wallet`.
+pub fn commit_state_signed(automaton_dir: &Path, message: &str, wallet: &Wallet) -> Result<bool> {
+    commit(automaton_dir, message, Some(wallet))
+}
+
+/// What a state commit's signature covers.
+fn signed_payload(tree: &str, message: &str) -> String {
+    format!("automaton state commit\ntree {}\n\n{}", tree, message)
+}
+
+/// Run git in `dir`, failing with its stderr unless it succeeds, and
+/// return stdout.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .with_context(|| format!("Failed to run git {}", args[0]))?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn commit(automaton_dir: &Path, message: &str, signer: Option<&Wallet>) -> Result<bool> {
     // Stage all changes
     let add = Command::new("git")
         .args(["add", "-A"])
@@ -62,12 +102,23 @@ pub fn commit_state(automaton_dir: &Path, message: &str) -> Result<()> {
     let status_output = String::from_utf8_lossy(&status.stdout);
     if status_output.trim().is_empty() {
         debug!("No changes to commit");
-        return Ok(());
+        return Ok(false);
     }
 
+    // Sign the tree about to be committed. Verbatim cleanup keeps git from
+    // rewriting the message the signature covers.
+    let full_message = match signer {
+        Some(wallet) => {
+            let tree = git(automaton_dir, &["write-tree"])?;
+            let signature = wallet.sign_message(signed_payload(tree.trim(), message).as_bytes())?;
+            format!("{}\n\n{}: {}\n", message, SIGNATURE_TRAILER, signature)
+        }
+        None => message.to_string(),
+    };
+
     // Commit
     let commit = Command::new("git")
-        .args(["commit", "-m", message, "--allow-empty-message"])
+        .args(["commit", "--cleanup=verbatim", "-m", &full_message, "--allow-empty-message"])
         .env("GIT_AUTHOR_NAME", "automaton")
         .env("GIT_AUTHOR_EMAIL", "automaton@conway.tech")
         .env("GIT_COMMITTER_NAME", "automaton")
@@ -82,9 +133,87 @@ pub fn commit_state(automaton_dir: &Path, message: &str) -> Result<()> {
         if !stderr.contains("nothing to commit") {
             warn!("git commit warning: {}", stderr);
         }
-    } else {
-        debug!("Committed state: {}", message);
+        return Ok(false);
     }
 
-    Ok(())
+    debug!("Committed state: {}", message);
+    Ok(true)
+}
+
+/// Whether commit `rev` in the state repo carries a signature trailer made
+/// by `expected` over its message and tree. Unsigned commits are `false`.
+pub fn verify_commit_signature(automaton_dir: &Path, rev: &str, expected: &Address) -> Result<bool> {
+    let tree = git(automaton_dir, &["rev-parse", &format!("{}^{{tree}}", rev)])?;
+    let body = git(automaton_dir, &["log", "-1", "--format=%B", rev])?;
+
+    // `%B` ends with an extra newline after the message itself
+    let body = body.strip_suffix('\n').unwrap_or(&body);
+    let marker = format!("\n\n{}: ", SIGNATURE_TRAILER);
+    let Some(at) = body.rfind(&marker) else {
+        return Ok(false);
+    };
+    let message = &body[..at];
+    let signature = body[at + marker.len()..].trim();
+    Ok(identity::verify(
+        signed_payload(tree.trim(), message).as_bytes(),
+        signature,
+        expected,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (std::path::PathBuf, Wallet) {
+        let dir = std::env::temp_dir().join(format!("automaton-git-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wallet = Wallet::generate(&dir.join("wallet.json")).unwrap();
+        init_state_repo(&dir).unwrap();
+        (dir, wallet)
+    }
+
+    #[test]
+    fn test_signed_commit_verifies_against_wallet() {
+        let (dir, wallet) = setup();
+        std::fs::write(dir.join("SOUL.md"), "# Soul").unwrap();
+        assert!(commit_state_signed(&dir, "State snapshot", &wallet).unwrap());
+
+        assert!(verify_commit_signature(&dir, "HEAD", &wallet.address).unwrap());
+        let body = git(&dir, &["log", "-1", "--format=%B"]).unwrap();
+        assert!(body.starts_with("State snapshot\n\nAutomaton-Signature: 0x"), "{}", body);
+
+        // Another address, and the unsigned initial commit, don't verify
+        let other = Wallet::generate(&dir.join("other.json")).unwrap();
+        assert!(!verify_commit_signature(&dir, "HEAD", &other.address).unwrap());
+        assert!(!verify_commit_signature(&dir, "HEAD~1", &wallet.address).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_signature_covers_the_tree() {
+        let (dir, wallet) = setup();
+        std::fs::write(dir.join("SOUL.md"), "# Soul").unwrap();
+        commit_state_signed(&dir, "State snapshot", &wallet).unwrap();
+
+        // Replay the signed message onto a different tree
+        let body = git(&dir, &["log", "-1", "--format=%B"]).unwrap();
+        std::fs::write(dir.join("SOUL.md"), "# Someone else").unwrap();
+        git(&dir, &["add", "-A"]).unwrap();
+        let commit = Command::new("git")
+            .args(["commit", "--cleanup=verbatim", "-m", body.trim_end_matches('\n')])
+            .env("GIT_AUTHOR_NAME", "mallory")
+            .env("GIT_AUTHOR_EMAIL", "mallory@example.com")
+            .env("GIT_COMMITTER_NAME", "mallory")
+            .env("GIT_COMMITTER_EMAIL", "mallory@example.com")
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(commit.status.success());
+        assert!(!verify_commit_signature(&dir, "HEAD", &wallet.address).unwrap());
+        assert!(verify_commit_signature(&dir, "HEAD~1", &wallet.address).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
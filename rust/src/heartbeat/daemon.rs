@@ -1,14 +1,17 @@
 //! Cron-based heartbeat daemon that runs background tasks on schedule.
 //!
 //! Reads heartbeat.yml for task definitions and executes them on their
-//! cron schedules. Can wake the agent loop when certain conditions are met.
+//! cron schedules, along with one-off tasks the agent scheduled itself.
+//! Can wake the agent loop when certain conditions are met.
 
+use crate::agent::quiet_hours;
 use crate::config::AutomatonConfig;
 use crate::heartbeat::tasks;
 use crate::state::Database;
-use crate::types::HeartbeatEntry;
+use crate::types::{HeartbeatEntry, HeartbeatRun};
 use anyhow::{Context, Result};
 use chrono::Utc;
+use chrono_tz::Tz;
 use cron::Schedule;
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -64,18 +67,28 @@ impl HeartbeatDaemon {
 
     /// Process one tick — check each entry and run if due.
     ///
-    /// Individual task failures are logged and do not stop other tasks.
+    /// Individual task failures are logged and do not stop other tasks;
+    /// the tick's runs are logged together in one transaction.
     /// Infrastructure errors (e.g. DB write failure) are propagated.
     async fn tick(&mut self) -> Result<()> {
         let now = Utc::now();
+        let quiet = quiet_hours::is_quiet(self.config.quiet_hours.as_ref(), now).unwrap_or_else(|e| {
+            warn!("Ignoring quiet hours: {:#}", e);
+            false
+        });
+
+        let mut runs = Vec::new();
 
         for entry in &self.entries {
             if !entry.enabled {
                 continue;
             }
+            if quiet && !tasks::QUIET_HOURS_TASKS.contains(&entry.task.as_str()) {
+                continue;
+            }
 
             // Parse cron schedule
-            let schedule = match Schedule::from_str(&entry.schedule) {
+            let schedule = match parse_schedule(&entry.schedule) {
                 Ok(s) => s,
                 Err(e) => {
                     warn!("Invalid cron schedule '{}' for '{}': {}", entry.schedule, entry.name, e);
@@ -83,6 +96,15 @@ impl HeartbeatDaemon {
                 }
             };
 
+            let timezone = match entry.timezone.as_deref().map(str::parse::<Tz>) {
+                None => Tz::UTC,
+                Some(Ok(tz)) => tz,
+                Some(Err(e)) => {
+                    warn!("Invalid timezone for '{}': {}", entry.name, e);
+                    continue;
+                }
+            };
+
             // Check if this task is due
             let last = self
                 .last_run
@@ -90,7 +112,7 @@ impl HeartbeatDaemon {
                 .copied()
                 .unwrap_or(now - chrono::Duration::hours(1));
 
-            let next = schedule.after(&last).next();
+            let next = next_run_after(&schedule, timezone, last);
             if let Some(next_run) = next {
                 if next_run <= now {
                     debug!("Running heartbeat task: {}", entry.name);
@@ -107,27 +129,97 @@ impl HeartbeatDaemon {
                         Ok(msg) => (msg.clone(), true),
                         Err(e) => (format!("Error: {}", e), false),
                     };
-
-                    // Log to database (propagate DB errors)
-                    {
-                        let db = self.db.lock().await;
-                        db.log_heartbeat(&entry.name, &result_str, success)
-                            .context("Failed to log heartbeat to database")?;
-                    }
-
-                    self.last_run.insert(entry.name.clone(), now);
-
                     if !success {
                         warn!("Heartbeat task '{}' failed: {}", entry.name, result_str);
                     }
+
+                    self.last_run.insert(entry.name.clone(), now);
+                    runs.push(HeartbeatRun {
+                        task_name: entry.name.clone(),
+                        result: result_str,
+                        success,
+                        executed_at: Utc::now(),
+                    });
                 }
             }
         }
 
+        // One transaction for the tick's log rather than a lock and write
+        // per task; a failed task is just a failed row (DB errors propagate)
+        if !runs.is_empty() {
+            self.db
+                .lock()
+                .await
+                .log_heartbeat_runs(&runs)
+                .context("Failed to log heartbeat to database")?;
+        }
+
+        run_scheduled_tasks(&self.config, &self.db, now).await?;
+
         Ok(())
     }
 }
 
+/// Parse a cron expression. Standard five-field expressions (as in the
+/// default entries and most heartbeat.yml files) get a leading seconds
+/// field, since the `cron` crate requires one.
+pub fn parse_schedule(expr: &str) -> Result<Schedule, cron::error::Error> {
+    if expr.split_whitespace().count() == 5 {
+        Schedule::from_str(&format!("0 {}", expr.trim()))
+    } else {
+        Schedule::from_str(expr)
+    }
+}
+
+/// The first time after `after` that `schedule` fires, reading the schedule
+/// in `timezone`.
+pub fn next_run_after(schedule: &Schedule, timezone: Tz, after: chrono::DateTime<Utc>) -> Option<chrono::DateTime<Utc>> {
+    schedule
+        .after(&after.with_timezone(&timezone))
+        .next()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Run scheduled one-off tasks due by `now`, each exactly once. Returns how
+/// many ran.
+pub async fn run_scheduled_tasks(
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+    now: chrono::DateTime<Utc>,
+) -> Result<usize> {
+    let due: Vec<_> = db
+        .lock()
+        .await
+        .pending_scheduled_tasks()?
+        .into_iter()
+        .filter(|t| t.run_at <= now)
+        .collect();
+
+    let mut ran = 0;
+    for scheduled in due {
+        // Claim before running, so a slow task can't fire again next tick
+        if !db.lock().await.claim_scheduled_task(&scheduled.id)? {
+            continue;
+        }
+        debug!("Running scheduled task: {} ({})", scheduled.task, scheduled.id);
+
+        let (result_str, success) =
+            match tasks::execute_task(&scheduled.task, &scheduled.params, config, db).await {
+                Ok(msg) => (msg, true),
+                Err(e) => (format!("Error: {}", e), false),
+            };
+        db.lock()
+            .await
+            .log_heartbeat(&format!("scheduled:{}", scheduled.task), &result_str, success)
+            .context("Failed to log heartbeat to database")?;
+        if !success {
+            warn!("Scheduled task '{}' failed: {}", scheduled.task, result_str);
+        }
+        ran += 1;
+    }
+    Ok(ran)
+}
+
 /// Load heartbeat entries from the YAML config file.
 fn load_heartbeat_config(config: &AutomatonConfig) -> Result<Vec<HeartbeatEntry>> {
     let path = config.resolved_heartbeat_path();
@@ -154,6 +246,7 @@ fn default_heartbeat_entries() -> Vec<HeartbeatEntry> {
             task: "heartbeat_ping".into(),
             enabled: true,
             params: serde_json::Value::Null,
+            timezone: None,
         },
         HeartbeatEntry {
             name: "check_credits".into(),
@@ -161,6 +254,15 @@ fn default_heartbeat_entries() -> Vec<HeartbeatEntry> {
             task: "check_credits".into(),
             enabled: true,
             params: serde_json::Value::Null,
+            timezone: None,
+        },
+        HeartbeatEntry {
+            name: "reconcile_costs".into(),
+            schedule: "0 * * * *".into(), // Hourly
+            task: "reconcile_costs".into(),
+            enabled: true,
+            params: serde_json::Value::Null,
+            timezone: None,
         },
         HeartbeatEntry {
             name: "check_usdc_balance".into(),
@@ -168,6 +270,7 @@ fn default_heartbeat_entries() -> Vec<HeartbeatEntry> {
             task: "check_usdc_balance".into(),
             enabled: true,
             params: serde_json::Value::Null,
+            timezone: None,
         },
         HeartbeatEntry {
             name: "check_social_inbox".into(),
@@ -175,6 +278,133 @@ fn default_heartbeat_entries() -> Vec<HeartbeatEntry> {
             task: "check_social_inbox".into(),
             enabled: true,
             params: serde_json::Value::Null,
+            timezone: None,
+        },
+        HeartbeatEntry {
+            name: "commit_state".into(),
+            schedule: "*/5 * * * *".into(), // Commits every state_commit_interval_mins
+            task: "commit_state".into(),
+            enabled: true,
+            params: serde_json::Value::Null,
+            timezone: None,
+        },
+        HeartbeatEntry {
+            name: "self_check".into(),
+            schedule: "*/30 * * * *".into(), // Every 30 minutes
+            task: "self_check".into(),
+            enabled: true,
+            params: serde_json::Value::Null,
+            timezone: None,
+        },
+        HeartbeatEntry {
+            name: "register_onchain".into(),
+            schedule: "0 */6 * * *".into(), // Every 6 hours
+            task: "register_onchain".into(),
+            enabled: false,
+            params: serde_json::Value::Null,
+            timezone: None,
+        },
+        HeartbeatEntry {
+            name: "daily_digest".into(),
+            schedule: "0 9 * * *".into(), // Daily at 09:00
+            task: "daily_digest".into(),
+            enabled: false,
+            params: serde_json::Value::Null,
+            timezone: None,
         },
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> chrono::DateTime<Utc> {
+        chrono::DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_local_schedule_follows_dst() {
+        let schedule = parse_schedule("0 9 * * *").unwrap();
+        assert_eq!(schedule, parse_schedule("0 0 9 * * *").unwrap());
+        let new_york: Tz = "America/New_York".parse().unwrap();
+
+        // EST (UTC-5) before the 2026-03-08 spring-forward, EDT (UTC-4) after
+        let fire = |after| next_run_after(&schedule, new_york, utc(after)).unwrap();
+        assert_eq!(fire("2026-03-06T15:00:00Z"), utc("2026-03-07T14:00:00Z"));
+        assert_eq!(fire("2026-03-07T15:00:00Z"), utc("2026-03-08T13:00:00Z"));
+        // And back to EST after the 2026-11-01 fall-back
+        assert_eq!(fire("2026-10-31T14:00:00Z"), utc("2026-11-01T14:00:00Z"));
+
+        // No timezone means UTC
+        assert_eq!(
+            next_run_after(&schedule, Tz::UTC, utc("2026-03-07T15:00:00Z")).unwrap(),
+            utc("2026-03-08T09:00:00Z")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_due_scheduled_task_fires_once() {
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let config = AutomatonConfig::default();
+        let now = Utc::now();
+        {
+            let db = db.lock().await;
+            db.schedule_task("heartbeat_ping", &serde_json::Value::Null, now - chrono::Duration::minutes(1))
+                .unwrap();
+            db.schedule_task("heartbeat_ping", &serde_json::Value::Null, now + chrono::Duration::hours(2))
+                .unwrap();
+        }
+
+        assert_eq!(run_scheduled_tasks(&config, &db, now).await.unwrap(), 1);
+        assert_eq!(run_scheduled_tasks(&config, &db, now).await.unwrap(), 0);
+
+        let db = db.lock().await;
+        assert!(db.kv_get("last_heartbeat").unwrap().is_some());
+        let runs = db
+            .heartbeat_runs_since(now - chrono::Duration::hours(1), Some("scheduled:heartbeat_ping"))
+            .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(runs[0].success);
+
+        // The later one is still waiting
+        let pending = db.pending_scheduled_tasks().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(pending[0].run_at > now);
+    }
+
+    #[tokio::test]
+    async fn test_tick_logs_runs_in_one_transaction() {
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let entry = |name: &str, task: &str| HeartbeatEntry {
+            name: name.into(),
+            schedule: "* * * * *".into(),
+            task: task.into(),
+            enabled: true,
+            params: serde_json::Value::Null,
+            timezone: None,
+        };
+        let mut daemon = HeartbeatDaemon {
+            config: AutomatonConfig::default(),
+            db: db.clone(),
+            entries: vec![entry("ping", "heartbeat_ping"), entry("broken", "no_such_task")],
+            last_run: HashMap::new(),
+        };
+        let since = Utc::now() - chrono::Duration::minutes(1);
+
+        // A failing task is logged as such without costing the others
+        daemon.tick().await.unwrap();
+        let runs = db.lock().await.heartbeat_runs_since(since, None).unwrap();
+        let logged: Vec<_> = runs.iter().map(|r| (r.task_name.as_str(), r.success)).collect();
+        assert_eq!(logged, [("ping", true), ("broken", false)]);
+
+        // A batch that fails part-way leaves nothing behind
+        let run = runs[0].clone();
+        let result: Result<()> = db.lock().await.transaction(|db| {
+            db.log_heartbeat_runs(std::slice::from_ref(&run))?;
+            anyhow::bail!("disk full")
+        });
+        assert!(result.is_err());
+        assert_eq!(db.lock().await.heartbeat_runs_since(since, None).unwrap().len(), 2);
+    }
+}
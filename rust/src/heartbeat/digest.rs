@@ -0,0 +1,166 @@
+//! Daily digest for the creator.
+//!
+//! The `daily_digest` heartbeat task sums up the last 24 hours from the
+//! database (turns, spend, income, tier changes, new children and
+//! self-modifications) and sends it to `creator_address`. The message ends
+//! with a fenced JSON copy of the numbers for scripts to parse.
+
+use crate::state::Database;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Heartbeat log name under which `check_credits` records tier changes.
+pub const TIER_CHANGE_TASK: &str = "tier_change";
+
+/// Period a digest covers.
+pub const DIGEST_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+
+/// Modifications listed by name before the rest are only counted.
+const MAX_LISTED_MODIFICATIONS: usize = 10;
+
+/// What happened between `since` and `until`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Digest {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub turns: usize,
+    /// Estimated inference spend (USD).
+    pub inference_usd: f64,
+    /// Outgoing transactions, e.g. x402 payments.
+    pub payments: f64,
+    /// Incoming transactions.
+    pub income: f64,
+    /// Survival tier changes, as "old -> new".
+    pub tier_changes: Vec<String>,
+    /// Names of children spawned in the period.
+    pub new_children: Vec<String>,
+    /// Self-modifications, as "type: description".
+    pub modifications: Vec<String>,
+}
+
+impl Digest {
+    /// Collect the digest for the window ending at `now`.
+    pub fn build(db: &Database, now: DateTime<Utc>) -> Result<Self> {
+        let since = now - DIGEST_WINDOW;
+        let transactions = db.transactions_since(since)?;
+        Ok(Self {
+            since,
+            until: now,
+            turns: db.turns_since(since)?.len(),
+            inference_usd: db.estimated_cost_since(since)?,
+            payments: transactions.iter().filter(|t| t.amount < 0.0).map(|t| -t.amount).sum(),
+            income: transactions.iter().filter(|t| t.amount > 0.0).map(|t| t.amount).sum(),
+            tier_changes: db
+                .heartbeat_runs_since(since, Some(TIER_CHANGE_TASK))?
+                .into_iter()
+                .map(|run| run.result)
+                .collect(),
+            new_children: db
+                .list_children()?
+                .into_iter()
+                .filter(|c| c.created_at > since)
+                .map(|c| c.name)
+                .collect(),
+            modifications: db
+                .modifications_since(since)?
+                .into_iter()
+                .map(|m| format!("{}: {}", m.mod_type, m.description))
+                .collect(),
+        })
+    }
+
+    /// The message text: a short summary, then the JSON block.
+    pub fn render(&self, agent: &str) -> String {
+        let mut text = format!(
+            "Daily digest for {} ({} to {} UTC)\n\n",
+            agent,
+            self.since.format("%Y-%m-%d %H:%M"),
+            self.until.format("%Y-%m-%d %H:%M")
+        );
+        text.push_str(&format!("Turns: {}\n", self.turns));
+        text.push_str(&format!(
+            "Spend: ${:.4} inference, {:.4} in payments\n",
+            self.inference_usd, self.payments
+        ));
+        text.push_str(&format!("Income: {:.4}\n", self.income));
+        if !self.tier_changes.is_empty() {
+            text.push_str(&format!("Tier changes: {}\n", self.tier_changes.join(", ")));
+        }
+        if !self.new_children.is_empty() {
+            text.push_str(&format!("New children: {}\n", self.new_children.join(", ")));
+        }
+        if !self.modifications.is_empty() {
+            text.push_str(&format!("Modifications ({}):\n", self.modifications.len()));
+            for m in self.modifications.iter().take(MAX_LISTED_MODIFICATIONS) {
+                text.push_str(&format!("- {}\n", m));
+            }
+            if self.modifications.len() > MAX_LISTED_MODIFICATIONS {
+                text.push_str(&format!(
+                    "- ...and {} more\n",
+                    self.modifications.len() - MAX_LISTED_MODIFICATIONS
+                ));
+            }
+        }
+        text.push_str(&format!(
+            "\n```json\n{}\n```\n",
+            serde_json::to_string(self).expect("digest serializes")
+        ));
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChildRecord, ChildState, ModificationEntry, ModificationType};
+
+    #[test]
+    fn test_digest_covers_the_last_day() {
+        let db = Database::open_memory().unwrap();
+        let now = Utc::now();
+
+        db.record_transaction("x402_payment", -0.25, "USDC", "api call", None).unwrap();
+        db.record_transaction("topup", 5.0, "credits", "from creator", None).unwrap();
+        db.log_heartbeat(TIER_CHANGE_TASK, "normal -> low_compute", true).unwrap();
+        db.log_heartbeat("check_credits", "0.4 USD (tier: low_compute)", true).unwrap();
+        let child = |name: &str, created_at| ChildRecord {
+            id: ulid::Ulid::new().to_string(),
+            name: name.into(),
+            sandbox_id: format!("sbx-{}", name),
+            wallet_address: String::new(),
+            created_at,
+            status: ChildState::Active,
+        };
+        db.add_child(&child("scout", now - chrono::Duration::hours(2))).unwrap();
+        db.add_child(&child("elder", now - chrono::Duration::days(3))).unwrap();
+        let modification = |description: &str, timestamp| ModificationEntry {
+            id: ulid::Ulid::new().to_string(),
+            timestamp,
+            mod_type: ModificationType::SkillAdd,
+            description: description.into(),
+            file_path: None,
+            diff: None,
+            diff_truncated: false,
+            reversible: true,
+        };
+        db.log_modification(&modification("Added skill web-scraper", now - chrono::Duration::hours(1)))
+            .unwrap();
+        db.log_modification(&modification("Old change", now - chrono::Duration::days(2))).unwrap();
+
+        let digest = Digest::build(&db, now).unwrap();
+        assert_eq!(digest.turns, 0);
+        assert_eq!(digest.payments, 0.25);
+        assert_eq!(digest.income, 5.0);
+        assert_eq!(digest.tier_changes, vec!["normal -> low_compute"]);
+        assert_eq!(digest.new_children, vec!["scout"]);
+        assert_eq!(digest.modifications, vec!["skill_add: Added skill web-scraper"]);
+
+        // The JSON block round-trips
+        let text = digest.render("alice");
+        assert!(text.starts_with("Daily digest for alice"));
+        assert!(text.contains("New children: scout\n"));
+        let json = text.split("```json\n").nth(1).unwrap().trim_end().trim_end_matches("```");
+        assert_eq!(serde_json::from_str::<Digest>(json).unwrap(), digest);
+    }
+}
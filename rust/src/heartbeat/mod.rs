@@ -1,4 +1,6 @@
 pub mod daemon;
+pub mod digest;
+pub mod self_check;
 pub mod tasks;
 
 pub use daemon::HeartbeatDaemon;
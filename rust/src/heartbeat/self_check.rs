@@ -0,0 +1,255 @@
+//! `self_check` heartbeat task: are the protected files still usable?
+//!
+//! The files the agent can't write to itself (`wallet.json`,
+//! `constitution.md`, `automaton.toml`, `SOUL.md`, `heartbeat.yml`) can
+//! still be corrupted on disk or changed from outside. Each run checks that
+//! every one exists and loads the way the runtime would load it. A new
+//! failure raises a `survival_alert` and is reported to the creator; the
+//! results of the last run are kept in the KV store. A missing
+//! `heartbeat.yml` passes: the daemon runs its default schedule then.
+//!
+//! The task is in the default schedule and the wizard's `heartbeat.yml`, but
+//! an agent set up before it existed has a `heartbeat.yml` without it. Such
+//! agents don't run the check until its entry is added by hand:
+//!
+//! ```yaml
+//! - name: self_check
+//!   schedule: "*/30 * * * *"
+//!   task: self_check
+//!   enabled: true
+//! ```
+
+use crate::config::{self, AutomatonConfig};
+use crate::constitution::{self, Integrity};
+use crate::identity::Wallet;
+use crate::notify;
+use crate::social::SocialClient;
+use crate::state::Database;
+use crate::types::HeartbeatEntry;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// KV key holding the last run's [`SelfCheckReport`].
+pub const SELF_CHECK_KEY: &str = "self_check";
+
+/// Outcome for one protected file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileCheck {
+    pub file: String,
+    /// Why the file is unusable (`None` if it passed).
+    pub error: Option<String>,
+}
+
+/// Results of one run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfCheckReport {
+    pub checked_at: DateTime<Utc>,
+    pub files: Vec<FileCheck>,
+}
+
+impl SelfCheckReport {
+    pub fn failures(&self) -> Vec<&FileCheck> {
+        self.files.iter().filter(|f| f.error.is_some()).collect()
+    }
+}
+
+fn check_exists(path: &Path) -> Result<()> {
+    if !path.exists() {
+        bail!("{} not found", path.display());
+    }
+    Ok(())
+}
+
+fn check_wallet(path: &Path) -> Result<()> {
+    check_exists(path)?;
+    Wallet::load(path).map(|_| ())
+}
+
+fn check_constitution(home_dir: &Path) -> Result<()> {
+    match constitution::check(home_dir)? {
+        Integrity::Intact => Ok(()),
+        Integrity::Missing => bail!("{} not found", constitution::constitution_path(home_dir).display()),
+        Integrity::Altered { found_hash } => {
+            bail!("does not match the canonical text (hash {})", found_hash)
+        }
+    }
+}
+
+fn check_config(path: &Path) -> Result<()> {
+    check_exists(path)?;
+    config::load_config(path).map(|_| ())
+}
+
+fn check_soul(path: &Path) -> Result<()> {
+    check_exists(path)?;
+    std::fs::read_to_string(path)
+        .map(|_| ())
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn check_heartbeat(path: &Path) -> Result<()> {
+    // Without the file the daemon falls back to its defaults
+    if !path.exists() {
+        return Ok(());
+    }
+    let contents = std::fs::read_to_string(path).context("Failed to read heartbeat.yml")?;
+    serde_yaml::from_str::<Vec<HeartbeatEntry>>(&contents).context("Failed to parse heartbeat.yml")?;
+    Ok(())
+}
+
+/// Check every protected file of the agent configured by `config`.
+pub fn check_protected_files(config: &AutomatonConfig) -> SelfCheckReport {
+    let home = config.home_dir();
+    let heartbeat = config.resolved_heartbeat_path();
+    let checks = [
+        ("wallet.json", check_wallet(&home.join("wallet.json"))),
+        ("constitution.md", check_constitution(&home)),
+        ("automaton.toml", check_config(&home.join("automaton.toml"))),
+        ("SOUL.md", check_soul(&home.join("SOUL.md"))),
+        ("heartbeat.yml", check_heartbeat(Path::new(&heartbeat))),
+    ];
+    SelfCheckReport {
+        checked_at: Utc::now(),
+        files: checks
+            .into_iter()
+            .map(|(file, outcome)| FileCheck {
+                file: file.into(),
+                error: outcome.err().map(|e| format!("{:#}", e)),
+            })
+            .collect(),
+    }
+}
+
+/// Run the check, record it, and alert on failures not already reported by
+/// the previous run.
+pub async fn task_self_check(config: &AutomatonConfig, db: &Arc<Mutex<Database>>) -> Result<String> {
+    let report = check_protected_files(config);
+    let failures = report.failures();
+
+    let previous: Option<SelfCheckReport> = {
+        let db = db.lock().await;
+        let previous = db
+            .kv_get(SELF_CHECK_KEY)?
+            .and_then(|s| serde_json::from_str(&s).ok());
+        db.kv_set(SELF_CHECK_KEY, &serde_json::to_string(&report)?)?;
+        previous
+    };
+    if failures.is_empty() {
+        return Ok(format!("{} protected files OK", report.files.len()));
+    }
+
+    let summary = failures
+        .iter()
+        .map(|f| format!("{}: {}", f.file, f.error.as_deref().unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join("; ");
+    let already_reported = previous.is_some_and(|p| p.failures() == failures);
+    if !already_reported {
+        let message = format!("Self-check failed for protected files: {}", summary);
+        warn!("{}", message);
+        db.lock().await.kv_set("survival_alert", &message)?;
+        report_to_creator(config, &message, &report).await;
+    }
+    bail!("{}", summary)
+}
+
+/// Tell the creator over the relay (when the wallet still loads) and the
+/// notify webhook. Best-effort: failures are only logged.
+async fn report_to_creator(config: &AutomatonConfig, message: &str, report: &SelfCheckReport) {
+    if !config.social_relay_url.is_empty() && !config.creator_address.is_empty() {
+        let sent = match Wallet::load(&config.home_dir().join("wallet.json")) {
+            Ok(wallet) => SocialClient::new(&config.social_relay_url, &config.wallet_address)
+                .send_signed(&config.creator_address, message, &wallet)
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sent {
+            warn!("Could not send the self-check failure to the creator: {:#}", e);
+        }
+    }
+    let data = serde_json::to_value(report).unwrap_or_default();
+    if let Err(e) = notify::notify(config, "self_check_failed", data).await {
+        warn!("Self-check notification failed: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+
+    /// A home directory with every protected file in order.
+    fn healthy_home(hook_url: &str) -> (std::path::PathBuf, AutomatonConfig) {
+        let home = std::env::temp_dir().join(format!("automaton-self-check-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&home).unwrap();
+        let config = AutomatonConfig {
+            db_path: home.join("state.db").to_string_lossy().into_owned(),
+            heartbeat_config_path: home.join("heartbeat.yml").to_string_lossy().into_owned(),
+            notify_webhook_url: hook_url.into(),
+            ..Default::default()
+        };
+        config::save_config(&config, &home.join("automaton.toml")).unwrap();
+        Wallet::load_or_create(&home.join("wallet.json")).unwrap();
+        constitution::restore(&home).unwrap();
+        std::fs::write(home.join("SOUL.md"), "I sell lemonade.").unwrap();
+        std::fs::write(home.join("heartbeat.yml"), "[]").unwrap();
+        (home, config)
+    }
+
+    #[tokio::test]
+    async fn test_malformed_config_trips_the_alert() {
+        let server = MockServer::start(|_| MockResponse::json(200, serde_json::json!({}))).await;
+        let (home, config) = healthy_home(&format!("{}/hook", server.url()));
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+
+        assert_eq!(task_self_check(&config, &db).await.unwrap(), "5 protected files OK");
+        assert_eq!(db.lock().await.kv_get("survival_alert").unwrap(), None);
+
+        std::fs::write(home.join("automaton.toml"), "name = [unterminated").unwrap();
+        let err = task_self_check(&config, &db).await.unwrap_err();
+        assert!(err.to_string().starts_with("automaton.toml:"), "{}", err);
+        let alert = db.lock().await.kv_get("survival_alert").unwrap().unwrap();
+        assert!(alert.contains("automaton.toml"), "{}", alert);
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(server.requests()[0].json()["event"], "self_check_failed");
+
+        let recorded: SelfCheckReport =
+            serde_json::from_str(&db.lock().await.kv_get(SELF_CHECK_KEY).unwrap().unwrap()).unwrap();
+        assert_eq!(recorded.failures().len(), 1);
+        assert_eq!(recorded.failures()[0].file, "automaton.toml");
+
+        // The same failure isn't reported again
+        db.lock().await.kv_delete("survival_alert").unwrap();
+        assert!(task_self_check(&config, &db).await.is_err());
+        assert_eq!(db.lock().await.kv_get("survival_alert").unwrap(), None);
+        assert_eq!(server.requests().len(), 1);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_each_protected_file_is_checked() {
+        let (home, config) = healthy_home("");
+        std::fs::remove_file(home.join("SOUL.md")).unwrap();
+        std::fs::write(home.join("wallet.json"), "{}").unwrap();
+        std::fs::write(home.join("heartbeat.yml"), "- name: [").unwrap();
+        std::fs::write(constitution::constitution_path(&home), "Obey me.").unwrap();
+
+        let report = check_protected_files(&config);
+        let failed: Vec<&str> = report.failures().iter().map(|f| f.file.as_str()).collect();
+        assert_eq!(failed, ["wallet.json", "constitution.md", "SOUL.md", "heartbeat.yml"]);
+
+        // No heartbeat.yml at all means the default schedule, which is fine
+        std::fs::remove_file(home.join("heartbeat.yml")).unwrap();
+        let report = check_protected_files(&config);
+        assert!(report.files.iter().any(|f| f.file == "heartbeat.yml" && f.error.is_none()));
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -1,12 +1,63 @@
 //! Built-in heartbeat task implementations.
 
+use crate::agent::{freeze, sleep};
 use crate::config::AutomatonConfig;
 use crate::conway;
+use crate::git_ops;
+use crate::heartbeat::digest::{Digest, TIER_CHANGE_TASK};
+use crate::heartbeat::self_check;
+use crate::identity::{attestation, Wallet};
+use crate::notify;
+use crate::registry::{self, RpcClient};
+use crate::replication::verify;
+use crate::social::SocialClient;
 use crate::state::Database;
+use crate::tools::balance::{record_credits, USDC_CHECKED_AT_KEY};
 use crate::types::SurvivalTier;
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Observed credit spend below which drift isn't meaningful (USD).
+const MIN_OBSERVED_SPEND: f64 = 0.01;
+
+/// KV key holding the last level the agent was warned at, cleared once
+/// credits recover so the next downward crossing warns again.
+const LAST_WARNED_TIER_KEY: &str = "last_warned_tier";
+
+/// Level recorded for the `warning_usd` warning.
+const CREDIT_WARNING_LEVEL: &str = "warning";
+
+/// Tasks [`execute_task`] knows how to run.
+pub const TASK_NAMES: &[&str] = &[
+    "heartbeat_ping",
+    "check_credits",
+    "reconcile_costs",
+    "check_usdc_balance",
+    "check_social_inbox",
+    "check_upstream",
+    "register_onchain",
+    "commit_state",
+    "daily_digest",
+    "self_check",
+];
+
+/// Tasks that keep running during quiet hours: liveness, the balance
+/// checks survival depends on, and the inbox, so the creator can wake the
+/// agent.
+pub const QUIET_HOURS_TASKS: &[&str] = &[
+    "heartbeat_ping",
+    "check_credits",
+    "check_usdc_balance",
+    "check_social_inbox",
+];
+
+/// Tasks that spend (gas for on-chain registration), skipped while
+/// spending is frozen.
+pub const SPENDING_TASKS: &[&str] = &["register_onchain"];
 
 /// Execute a named heartbeat task.
 pub async fn execute_task(
@@ -15,12 +66,20 @@ pub async fn execute_task(
     config: &AutomatonConfig,
     db: &Arc<Mutex<Database>>,
 ) -> Result<String> {
+    if SPENDING_TASKS.contains(&task_name) && freeze::is_frozen(&config.home_dir()) {
+        return Ok("Skipped: spending is frozen".into());
+    }
     match task_name {
         "heartbeat_ping" => task_heartbeat_ping(db).await,
         "check_credits" => task_check_credits(config, db).await,
+        "reconcile_costs" => task_reconcile_costs(config, db).await,
         "check_usdc_balance" => task_check_usdc_balance(config, db).await,
         "check_social_inbox" => task_check_social_inbox(config, db).await,
         "check_upstream" => task_check_upstream(config, db).await,
+        "register_onchain" => task_register_onchain(config, db).await,
+        "commit_state" => task_commit_state(config, db).await,
+        "daily_digest" => task_daily_digest(config, db).await,
+        "self_check" => self_check::task_self_check(config, db).await,
         _ => bail!("Unknown heartbeat task: {}", task_name),
     }
 }
@@ -28,7 +87,7 @@ pub async fn execute_task(
 /// Simple ping — record that the agent is alive.
 async fn task_heartbeat_ping(db: &Arc<Mutex<Database>>) -> Result<String> {
     let db = db.lock().await;
-    db.kv_set("last_heartbeat", &chrono::Utc::now().to_rfc3339())?;
+    db.kv_set_datetime("last_heartbeat", Utc::now())?;
     Ok("pong".into())
 }
 
@@ -37,10 +96,49 @@ async fn task_check_credits(config: &AutomatonConfig, db: &Arc<Mutex<Database>>)
     let balance = conway::credits::check_credits(&config.conway_api_url, &config.conway_api_key).await?;
 
     let db = db.lock().await;
-    db.kv_set("credits_balance", &balance.credits.to_string())?;
-
     let tier = SurvivalTier::from_balance(balance.credits);
-    db.kv_set("survival_tier", &tier.to_string())?;
+    // The balance, its timestamp and the tier land together
+    db.transaction(|db| {
+        record_credits(db, balance.credits)?;
+        let previous_tier = db.kv_get("survival_tier")?;
+        db.kv_set("survival_tier", &tier.to_string())?;
+        if let Some(previous) = previous_tier.filter(|t| *t != tier.to_string()) {
+            db.log_heartbeat(TIER_CHANGE_TASK, &format!("{} -> {}", previous, tier), true)?;
+        }
+        Ok(())
+    })?;
+
+    let warn_now = credit_warning_due(
+        balance.credits,
+        tier,
+        config.warning_usd,
+        db.kv_get(LAST_WARNED_TIER_KEY)?.is_some(),
+    );
+    match warn_now {
+        Some(true) => {
+            let message = format!(
+                "Credits are running low: {} {} (warning threshold ${:.2}). \
+                 Capabilities are unchanged for now, but make earning your priority \
+                 before you drop to low-compute.",
+                balance.credits, balance.currency, config.warning_usd
+            );
+            db.kv_set("survival_alert", &message)?;
+            db.kv_set(LAST_WARNED_TIER_KEY, CREDIT_WARNING_LEVEL)?;
+            warn!("{}", message);
+            drop(db);
+            let data = serde_json::json!({ "credits": balance.credits, "threshold": config.warning_usd });
+            if let Err(e) = notify::notify(config, "credit_warning", data).await {
+                warn!("Credit warning notification failed: {:#}", e);
+            }
+            return Ok(format!(
+                "{} {} (tier: {}, warned)",
+                balance.credits, balance.currency, tier
+            ));
+        }
+        Some(false) => {}
+        // Back above the threshold: the next crossing warns again
+        None => db.kv_delete(LAST_WARNED_TIER_KEY)?,
+    }
 
     // Set wake alert if critical
     if tier == SurvivalTier::Critical || tier == SurvivalTier::Dead {
@@ -52,12 +150,104 @@ async fn task_check_credits(config: &AutomatonConfig, db: &Arc<Mutex<Database>>)
             ),
         )?;
         // Wake the agent
-        db.kv_delete("sleep_until")?;
+        db.kv_delete(sleep::SLEEP_UNTIL_KEY)?;
     }
 
     Ok(format!("{} {} (tier: {})", balance.credits, balance.currency, tier))
 }
 
+/// Whether to raise the early credit warning: `Some(true)` to warn now,
+/// `Some(false)` to stay quiet (already warned, or Critical/Dead, which get
+/// their own alert), `None` when the balance is back above the threshold.
+pub fn credit_warning_due(
+    credits: f64,
+    tier: SurvivalTier,
+    warning_usd: f64,
+    already_warned: bool,
+) -> Option<bool> {
+    if warning_usd <= 0.0 || credits >= warning_usd {
+        return None;
+    }
+    let urgent = matches!(tier, SurvivalTier::Critical | SurvivalTier::Dead);
+    Some(!already_warned && !urgent)
+}
+
+/// Credit balance at the previous reconciliation (stored in KV).
+#[derive(Debug, Serialize, Deserialize)]
+struct CostCheckpoint {
+    at: DateTime<Utc>,
+    credits: f64,
+}
+
+/// Relative drift of estimated spend from observed spend.
+///
+/// Positive means we over-estimate, negative means actual billing is higher
+/// than our estimates. `None` when too little was spent to compare.
+pub fn compute_cost_drift(estimated: f64, observed: f64) -> Option<f64> {
+    if observed < MIN_OBSERVED_SPEND {
+        return None;
+    }
+    Some((estimated - observed) / observed)
+}
+
+/// Compare summed turn cost estimates against the observed credit delta,
+/// less any other spending recorded in the ledger over the same window.
+async fn task_reconcile_costs(
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+) -> Result<String> {
+    let balance = conway::credits::check_credits(&config.conway_api_url, &config.conway_api_key).await?;
+    let now = Utc::now();
+
+    let db = db.lock().await;
+    let previous: Option<CostCheckpoint> = db
+        .kv_get("cost_checkpoint")?
+        .and_then(|s| serde_json::from_str(&s).ok());
+    db.kv_set(
+        "cost_checkpoint",
+        &serde_json::to_string(&CostCheckpoint {
+            at: now,
+            credits: balance.credits,
+        })?,
+    )?;
+
+    let Some(previous) = previous else {
+        return Ok("Baseline recorded".into());
+    };
+
+    let delta = previous.credits - balance.credits;
+    if delta < 0.0 {
+        return Ok(format!("Credits increased by {:.4}; skipping (top-up?)", -delta));
+    }
+    // Transfers and payments aren't inference, so they aren't drift
+    let observed = (delta - db.other_spend_since(previous.at)?).max(0.0);
+
+    let estimated = db.estimated_cost_since(previous.at)?;
+    let Some(drift) = compute_cost_drift(estimated, observed) else {
+        return Ok(format!(
+            "Spend too small to reconcile (estimated ${:.4}, observed ${:.4})",
+            estimated, observed
+        ));
+    };
+
+    db.kv_set_f64("cost_drift", drift)?;
+    if drift.abs() > config.cost_drift_threshold {
+        warn!(
+            "Cost estimates drift {:+.0}% from observed spend (estimated ${:.4}, observed ${:.4})",
+            drift * 100.0,
+            estimated,
+            observed
+        );
+    }
+
+    Ok(format!(
+        "Estimated ${:.4}, observed ${:.4}, drift {:+.1}%",
+        estimated,
+        observed,
+        drift * 100.0
+    ))
+}
+
 /// Check USDC balance on Base chain.
 async fn task_check_usdc_balance(
     config: &AutomatonConfig,
@@ -76,34 +266,25 @@ async fn task_check_usdc_balance(
         config.wallet_address.strip_prefix("0x").unwrap_or(&config.wallet_address)
     );
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .post(&config.base_rpc_url)
-        .json(&serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "eth_call",
-            "params": [{
+    // A failed read must not look like a zero balance
+    let balance_raw = RpcClient::new(&config.base_rpc_url)
+        .quantity(
+            "eth_call",
+            serde_json::json!([{
                 "to": usdc_contract,
                 "data": address_padded
-            }, "latest"],
-            "id": 1
-        }))
-        .send()
-        .await?;
-
-    let body: serde_json::Value = resp.json().await?;
-    let result_hex = body["result"].as_str().unwrap_or("0x0");
-
-    // Parse hex balance (USDC has 6 decimals)
-    let balance_raw = u128::from_str_radix(
-        result_hex.strip_prefix("0x").unwrap_or(result_hex),
-        16,
-    )
-    .unwrap_or(0);
+            }, "latest"]),
+        )
+        .await
+        .context("USDC balance check failed")?;
+    // USDC has 6 decimals
     let balance_usdc = balance_raw as f64 / 1_000_000.0;
 
     let db = db.lock().await;
-    db.kv_set("usdc_balance", &balance_usdc.to_string())?;
+    db.transaction(|db| {
+        db.kv_set_f64("usdc_balance", balance_usdc)?;
+        db.kv_set_datetime(USDC_CHECKED_AT_KEY, Utc::now())
+    })?;
 
     Ok(format!("{:.6} USDC", balance_usdc))
 }
@@ -117,7 +298,7 @@ async fn task_check_social_inbox(
         return Ok("Skipped: no social relay configured".into());
     }
 
-    let client = reqwest::Client::new();
+    let client = crate::http::client();
     let resp = client
         .get(format!(
             "{}/v1/inbox/{}",
@@ -131,6 +312,37 @@ async fn task_check_social_inbox(
     }
 
     let messages: Vec<crate::types::InboxMessage> = resp.json().await?;
+    let messages = crate::social::discard_forged(messages);
+
+    // Answer our parent's verify-child challenges and our creator's audit
+    // requests; they aren't for the agent
+    let mut inbox = Vec::with_capacity(messages.len());
+    for msg in messages {
+        if let Some(answer) = verify::answer_challenge(&msg, &config.parent_address) {
+            let wallet = Wallet::load(&config.home_dir().join("wallet.json"))?;
+            SocialClient::new(&config.social_relay_url, &config.wallet_address)
+                .send_signed(&msg.from_address, &answer, &wallet)
+                .await
+                .context("Failed to answer parent's challenge")?;
+            continue;
+        }
+        if attestation::is_audit_request(&msg.content) {
+            let wallet = Wallet::load(&config.home_dir().join("wallet.json"))?;
+            let answer = {
+                let db = db.lock().await;
+                attestation::answer_audit_request(&msg, &config.creator_address, &wallet, &db)?
+            };
+            if let Some(answer) = answer {
+                SocialClient::new(&config.social_relay_url, &config.wallet_address)
+                    .send_signed(&msg.from_address, &answer, &wallet)
+                    .await
+                    .context("Failed to answer creator's audit request")?;
+                continue;
+            }
+        }
+        inbox.push(msg);
+    }
+    let messages = inbox;
     let new_count = messages.len();
 
     let db = db.lock().await;
@@ -140,13 +352,98 @@ async fn task_check_social_inbox(
 
     if new_count > 0 {
         // Wake agent if sleeping
-        db.kv_delete("sleep_until")?;
+        db.kv_delete(sleep::SLEEP_UNTIL_KEY)?;
         db.kv_set("wake_reason", &format!("{} new messages in inbox", new_count))?;
     }
 
     Ok(format!("{} new messages", new_count))
 }
 
+/// KV key: when `commit_state` last checked the state directory.
+const STATE_COMMIT_CHECKED_KEY: &str = "state_commit_checked_at";
+
+/// KV key: when `commit_state` last made a commit.
+const STATE_COMMITTED_KEY: &str = "state_committed_at";
+
+/// Commit the state directory every `state_commit_interval_mins`, summarizing
+/// the activity since the previous commit.
+async fn task_commit_state(config: &AutomatonConfig, db: &Arc<Mutex<Database>>) -> Result<String> {
+    if config.state_commit_interval_mins == 0 {
+        return Ok("Skipped: state_commit_interval_mins is 0".into());
+    }
+    let dir = config.home_dir();
+    if !dir.join(".git").exists() {
+        return Ok("Skipped: state directory is not a git repo".into());
+    }
+
+    let now = Utc::now();
+    let interval = chrono::Duration::minutes(config.state_commit_interval_mins as i64);
+    let db = db.lock().await;
+    if let Some(checked) = db.kv_get_datetime(STATE_COMMIT_CHECKED_KEY).unwrap_or(None) {
+        if now - checked < interval {
+            return Ok("Not due".into());
+        }
+    }
+    db.kv_set_datetime(STATE_COMMIT_CHECKED_KEY, now)?;
+
+    let since = db
+        .kv_get_datetime(STATE_COMMITTED_KEY)
+        .unwrap_or(None)
+        .unwrap_or(now - interval);
+    let turns = db.turns_since(since)?.len();
+    let spent = db.estimated_cost_since(since)?;
+    let tier = db.kv_get("survival_tier")?.unwrap_or_else(|| "unknown".into());
+    let message = format!(
+        "State snapshot: {} turns since last commit, ${:.4} spent, tier {}",
+        turns, spent, tier
+    );
+
+    let committed = if config.sign_state_commits {
+        let wallet = Wallet::load(&dir.join("wallet.json"))?;
+        git_ops::commit_state_signed(&dir, &message, &wallet)?
+    } else {
+        git_ops::commit_state(&dir, &message)?
+    };
+    if !committed {
+        return Ok("No state changes to commit".into());
+    }
+    db.kv_set_datetime(STATE_COMMITTED_KEY, now)?;
+    Ok(message)
+}
+
+/// Send the creator a summary of the last 24 hours, over the social relay
+/// when one is configured and to the notify webhook.
+async fn task_daily_digest(config: &AutomatonConfig, db: &Arc<Mutex<Database>>) -> Result<String> {
+    let relay = !config.social_relay_url.is_empty() && !config.creator_address.is_empty();
+    if !relay && config.notify_webhook_url.is_empty() {
+        return Ok("Skipped: no social relay/creator address or webhook configured".into());
+    }
+
+    let digest = Digest::build(&*db.lock().await, Utc::now())?;
+    if relay {
+        let wallet = Wallet::load(&config.home_dir().join("wallet.json"))?;
+        SocialClient::new(&config.social_relay_url, &config.wallet_address)
+            .send_signed(&config.creator_address, &digest.render(&config.name), &wallet)
+            .await
+            .context("Failed to send the daily digest to the creator")?;
+    }
+    notify::notify(config, "daily_digest", serde_json::to_value(&digest)?).await?;
+
+    Ok(format!(
+        "Digest sent: {} turns, ${:.4} inference, {:.4} income",
+        digest.turns, digest.inference_usd, digest.income
+    ))
+}
+
+/// Register in the ERC-8004 registry if not already registered.
+async fn task_register_onchain(
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+) -> Result<String> {
+    let wallet = Wallet::load(&config.home_dir().join("wallet.json"))?;
+    registry::auto_register::ensure_registered(config, db, &wallet).await
+}
+
 /// Check for upstream code updates.
 async fn task_check_upstream(
     _config: &AutomatonConfig,
@@ -155,3 +452,182 @@ async fn task_check_upstream(
     // Stub — will be implemented when git_ops module handles upstream
     Ok("Upstream check not yet implemented".into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_drift() {
+        // Estimates match billing
+        assert_eq!(compute_cost_drift(1.0, 1.0), Some(0.0));
+        // We under-estimate by half: actual billing doubled
+        assert_eq!(compute_cost_drift(0.5, 1.0), Some(-0.5));
+        // We over-estimate
+        assert_eq!(compute_cost_drift(1.5, 1.0), Some(0.5));
+        // Nothing meaningful spent
+        assert_eq!(compute_cost_drift(0.2, 0.0), None);
+    }
+
+    #[tokio::test]
+    async fn test_other_spending_is_not_drift() {
+        use crate::test_support::{MockResponse, MockServer};
+        use crate::types::{AgentState, Turn};
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let credits = Arc::new(AtomicU64::new(10.0f64.to_bits()));
+        let balance = credits.clone();
+        let server = MockServer::start(move |_| {
+            MockResponse::json(
+                200,
+                serde_json::json!({
+                    "credits": f64::from_bits(balance.load(Ordering::SeqCst)),
+                    "currency": "USD"
+                }),
+            )
+        })
+        .await;
+        let config = AutomatonConfig {
+            conway_api_url: server.url(),
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        execute_task("reconcile_costs", &serde_json::Value::Null, &config, &db)
+            .await
+            .unwrap();
+
+        {
+            let db = db.lock().await;
+            db.save_turn(&Turn {
+                id: ulid::Ulid::new().to_string(),
+                turn_number: 1,
+                state: AgentState::Running,
+                messages: Vec::new(),
+                tool_calls: Vec::new(),
+                tool_results: Vec::new(),
+                token_usage: Default::default(),
+                cost_estimate_usd: 0.5,
+                model: "gpt-4o".into(),
+                tag: None,
+                intent: None,
+                created_at: Utc::now(),
+            })
+            .unwrap();
+            db.record_transaction("credit_transfer", -2.0, "credits", "child", None)
+                .unwrap();
+            db.record_transaction("topup", 5.0, "credits", "creator", None).unwrap();
+        }
+        // Half a dollar of inference plus the two dollar transfer
+        credits.store(7.5f64.to_bits(), Ordering::SeqCst);
+        execute_task("reconcile_costs", &serde_json::Value::Null, &config, &db)
+            .await
+            .unwrap();
+
+        let drift = db.lock().await.kv_get_f64("cost_drift").unwrap();
+        assert_eq!(drift, Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_credit_warning_fires_once_per_crossing() {
+        use crate::test_support::{MockResponse, MockServer};
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let credits = Arc::new(AtomicU64::new(0));
+        let balance = credits.clone();
+        let server = MockServer::start(move |req| match req.path.as_str() {
+            "/v1/credits/balance" => MockResponse::json(
+                200,
+                serde_json::json!({
+                    "credits": f64::from_bits(balance.load(Ordering::SeqCst)),
+                    "currency": "USD"
+                }),
+            ),
+            _ => MockResponse::json(200, serde_json::json!({})),
+        })
+        .await;
+        let config = AutomatonConfig {
+            conway_api_url: server.url(),
+            notify_webhook_url: format!("{}/hook", server.url()),
+            warning_usd: 1.0,
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let check = |usd: f64| {
+            credits.store(usd.to_bits(), Ordering::SeqCst);
+            let (config, db) = (&config, &db);
+            async move {
+                execute_task("check_credits", &serde_json::Value::Null, config, db)
+                    .await
+                    .unwrap();
+                db.lock().await.kv_get("survival_alert").unwrap()
+            }
+        };
+        let hooks = || server.requests().iter().filter(|r| r.path == "/hook").count();
+
+        assert_eq!(check(2.0).await, None);
+        let alert = check(0.8).await.unwrap();
+        assert!(alert.contains("make earning your priority"), "{}", alert);
+        assert_eq!(hooks(), 1);
+
+        // Consumed by the next turn, and not raised again while still low
+        db.lock().await.kv_delete("survival_alert").unwrap();
+        assert_eq!(check(0.7).await, None);
+        assert_eq!(hooks(), 1);
+
+        // Recovering re-arms it
+        assert_eq!(check(1.5).await, None);
+        assert!(check(0.9).await.is_some());
+        assert_eq!(hooks(), 2);
+
+        // Below the warning band, the critical alert takes over
+        assert_eq!(credit_warning_due(0.05, SurvivalTier::Critical, 1.0, false), Some(false));
+        assert_eq!(credit_warning_due(0.5, SurvivalTier::LowCompute, 0.0, false), None);
+    }
+
+    #[tokio::test]
+    async fn test_commit_state_only_commits_changes() {
+        let dir = std::env::temp_dir().join(format!("automaton-state-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        git_ops::init_state_repo(&dir).unwrap();
+        let config = AutomatonConfig {
+            db_path: dir.join("state.db").to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let commits = || {
+            let out = std::process::Command::new("git")
+                .args(["rev-list", "--count", "HEAD"])
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&out.stdout).trim().parse::<u32>().unwrap()
+        };
+        let run = || async {
+            // Make the task due again
+            db.lock().await.kv_delete(STATE_COMMIT_CHECKED_KEY).unwrap();
+            execute_task("commit_state", &serde_json::Value::Null, &config, &db).await.unwrap()
+        };
+        let initial = commits();
+
+        let result = run().await;
+        assert_eq!(result, "No state changes to commit");
+        assert_eq!(commits(), initial);
+
+        std::fs::write(dir.join("SOUL.md"), "I sell lemonade.").unwrap();
+        db.lock().await.kv_set("survival_tier", "normal").unwrap();
+        let result = run().await;
+        assert!(result.starts_with("State snapshot: 0 turns"), "{}", result);
+        assert!(result.ends_with("tier normal"), "{}", result);
+        assert_eq!(commits(), initial + 1);
+
+        // Within the interval nothing is checked at all
+        std::fs::write(dir.join("SOUL.md"), "I sell iced tea.").unwrap();
+        let result = execute_task("commit_state", &serde_json::Value::Null, &config, &db)
+            .await
+            .unwrap();
+        assert_eq!(result, "Not due");
+        assert_eq!(commits(), initial + 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+//! Shared HTTP client construction.
+//!
+//! Every outbound client is built here so agent traffic identifies itself:
+//! a `User-Agent` rendered from `user_agent_template` (by default
+//! `automaton/<version> <wallet address>`) and, with
+//! `send_agent_address_header`, an `X-Agent-Address` header. Relays and
+//! registries can then attribute, rate-limit or verify agents. The identity
+//! is set once at startup; until then requests carry the bare version.
+
+use crate::config::AutomatonConfig;
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::sync::RwLock;
+use tracing::warn;
+
+/// Default `user_agent_template`.
+pub const DEFAULT_USER_AGENT_TEMPLATE: &str = "automaton/{version} {address}";
+
+/// Header carrying the wallet address when enabled.
+pub const AGENT_ADDRESS_HEADER: &str = "X-Agent-Address";
+
+#[derive(Debug, Clone)]
+struct Identity {
+    user_agent: String,
+    address: Option<String>,
+}
+
+static IDENTITY: RwLock<Option<Identity>> = RwLock::new(None);
+
+/// `template` with `{version}` and `{address}` filled in.
+pub fn render_user_agent(template: &str, wallet_address: &str) -> String {
+    template
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+        .replace("{address}", wallet_address)
+        .trim()
+        .to_string()
+}
+
+/// Identify clients built from now on as the agent with `wallet_address`.
+pub fn set_identity(config: &AutomatonConfig, wallet_address: &str) {
+    let identity = Identity {
+        user_agent: render_user_agent(&config.user_agent_template, wallet_address),
+        address: (config.send_agent_address_header && !wallet_address.is_empty())
+            .then(|| wallet_address.to_string()),
+    };
+    *IDENTITY.write().unwrap_or_else(|e| e.into_inner()) = Some(identity);
+}
+
+/// A client builder carrying the identifying headers.
+pub fn builder() -> reqwest::ClientBuilder {
+    let identity = IDENTITY.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let Some(identity) = identity else {
+        return reqwest::Client::builder().user_agent(render_user_agent("automaton/{version}", ""));
+    };
+
+    let mut headers = HeaderMap::new();
+    if let Some(address) = identity.address {
+        match HeaderValue::from_str(&address) {
+            Ok(value) => {
+                headers.insert(AGENT_ADDRESS_HEADER, value);
+            }
+            Err(e) => warn!("Not sending {}: {}", AGENT_ADDRESS_HEADER, e),
+        }
+    }
+    let builder = reqwest::Client::builder().default_headers(headers);
+    match HeaderValue::from_str(&identity.user_agent) {
+        Ok(user_agent) => builder.user_agent(user_agent),
+        Err(e) => {
+            warn!("Invalid User-Agent '{}': {}", identity.user_agent, e);
+            builder.user_agent(render_user_agent("automaton/{version}", ""))
+        }
+    }
+}
+
+/// A client with the identifying headers and otherwise default settings.
+pub fn client() -> reqwest::Client {
+    builder().build().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conway::ConwayClient;
+    use crate::test_support::{MockResponse, MockServer};
+
+    #[test]
+    fn test_user_agent_template() {
+        let version = env!("CARGO_PKG_VERSION");
+        assert_eq!(
+            render_user_agent(DEFAULT_USER_AGENT_TEMPLATE, "0xabc"),
+            format!("automaton/{} 0xabc", version)
+        );
+        assert_eq!(render_user_agent("{address} via relay", "0xabc"), "0xabc via relay");
+        // No address yet: no trailing space
+        assert_eq!(render_user_agent(DEFAULT_USER_AGENT_TEMPLATE, ""), format!("automaton/{}", version));
+    }
+
+    #[tokio::test]
+    async fn test_conway_requests_identify_the_agent() {
+        let server = MockServer::start(|_| MockResponse::json(200, serde_json::json!({}))).await;
+        let address = "0x1111111111111111111111111111111111111111";
+        let config = AutomatonConfig {
+            send_agent_address_header: true,
+            ..Default::default()
+        };
+        set_identity(&config, address);
+
+        let conway = ConwayClient::new(&server.url(), "key", "sb-1");
+        let _ = conway.list_sandboxes().await;
+
+        let requests = server.requests();
+        let user_agent = requests[0].header("user-agent").unwrap();
+        assert_eq!(user_agent, format!("automaton/{} {}", env!("CARGO_PKG_VERSION"), address));
+        assert_eq!(requests[0].header(AGENT_ADDRESS_HEADER), Some(address));
+    }
+}
@@ -0,0 +1,131 @@
+//! Startup check that the config advertises the wallet's own address.
+//!
+//! `wallet_address` in `automaton.toml` is derived from `wallet.json`, but
+//! the two can drift, e.g. after a key is swapped by hand. The agent would
+//! then sign with one key while registering and taking payments under
+//! another. With `correct_wallet_address` the config is rewritten to match
+//! the wallet and the change audited; otherwise startup fails.
+
+use crate::config::{self, AutomatonConfig};
+use crate::identity::Wallet;
+use crate::state::Database;
+use crate::types::{ModificationEntry, ModificationType};
+use anyhow::{bail, Result};
+use chrono::Utc;
+use std::path::Path;
+use tracing::warn;
+
+/// What the check found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressCheck {
+    Match,
+    /// The config held `previous` and now holds the wallet's address.
+    Corrected { previous: String },
+}
+
+/// Make sure `config.wallet_address` is the address of `wallet`, correcting
+/// and saving the config in `home_dir` or failing per
+/// `correct_wallet_address`. Addresses compare case-insensitively.
+pub fn check_wallet_address(
+    home_dir: &Path,
+    config: &mut AutomatonConfig,
+    wallet: &Wallet,
+    db: &Database,
+) -> Result<AddressCheck> {
+    let actual = wallet.address.to_string();
+    if config.wallet_address.eq_ignore_ascii_case(&actual) {
+        return Ok(AddressCheck::Match);
+    }
+    let previous = config.wallet_address.clone();
+    if !config.correct_wallet_address {
+        bail!(
+            "automaton.toml has wallet_address {:?} but wallet.json holds the key for {}; \
+             fix the config or set correct_wallet_address = true",
+            previous,
+            actual
+        );
+    }
+
+    let description = format!(
+        "wallet_address corrected from {:?} to {} to match wallet.json",
+        previous, actual
+    );
+    warn!("{}", description);
+    config.wallet_address = actual.clone();
+    config::save_config(config, &home_dir.join("automaton.toml"))?;
+    db.log_modification(&ModificationEntry {
+        id: ulid::Ulid::new().to_string(),
+        timestamp: Utc::now(),
+        mod_type: ModificationType::ConfigUpdate,
+        description,
+        file_path: Some("automaton.toml".into()),
+        diff: Some(format!("-wallet_address = {:?}\n+wallet_address = {:?}", previous, actual)),
+        diff_truncated: false,
+        reversible: true,
+    })?;
+    Ok(AddressCheck::Corrected { previous })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(correct: bool) -> (std::path::PathBuf, Wallet, AutomatonConfig, Database) {
+        let home = std::env::temp_dir().join(format!("automaton-address-{}", ulid::Ulid::new()));
+        let wallet = Wallet::generate(&home.join("wallet.json")).unwrap();
+        let config = AutomatonConfig {
+            wallet_address: "0x1111111111111111111111111111111111111111".into(),
+            correct_wallet_address: correct,
+            ..Default::default()
+        };
+        config::save_config(&config, &home.join("automaton.toml")).unwrap();
+        (home, wallet, config, Database::open_memory().unwrap())
+    }
+
+    #[test]
+    fn test_matching_address_is_left_alone() {
+        let (home, wallet, mut config, db) = setup(false);
+        config.wallet_address = wallet.address.to_string().to_lowercase();
+
+        let check = check_wallet_address(&home, &mut config, &wallet, &db).unwrap();
+        assert_eq!(check, AddressCheck::Match);
+        assert_eq!(db.count_modifications().unwrap(), 0);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_mismatch_is_corrected_and_audited() {
+        let (home, wallet, mut config, db) = setup(true);
+
+        let check = check_wallet_address(&home, &mut config, &wallet, &db).unwrap();
+        assert_eq!(
+            check,
+            AddressCheck::Corrected {
+                previous: "0x1111111111111111111111111111111111111111".into()
+            }
+        );
+        assert_eq!(config.wallet_address, wallet.address.to_string());
+        let saved = config::load_config(&home.join("automaton.toml")).unwrap();
+        assert_eq!(saved.wallet_address, wallet.address.to_string());
+
+        let entry = &db.modifications_since(chrono::DateTime::UNIX_EPOCH).unwrap()[0];
+        assert_eq!(entry.mod_type, ModificationType::ConfigUpdate);
+        assert!(entry.description.contains("0x1111"), "{}", entry.description);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_mismatch_fails_when_not_correcting() {
+        let (home, wallet, mut config, db) = setup(false);
+
+        let err = check_wallet_address(&home, &mut config, &wallet, &db).unwrap_err();
+        assert!(err.to_string().contains(&wallet.address.to_string()), "{}", err);
+        let saved = config::load_config(&home.join("automaton.toml")).unwrap();
+        assert_eq!(saved.wallet_address, "0x1111111111111111111111111111111111111111");
+        assert_eq!(db.count_modifications().unwrap(), 0);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
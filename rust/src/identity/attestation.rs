@@ -0,0 +1,257 @@
+//! Signed attestation of the running binary.
+//!
+//! At startup the agent hashes its own executable (SHA-256) and keeps the
+//! hash in the KV store; a hash different from the last start is logged as
+//! an integrity violation. The creator can send an `audit_request` over the
+//! social relay and gets back the hash and a timestamp signed with the
+//! agent's wallet, so they know exactly which binary is running.
+
+use crate::identity::{recover_signer, Wallet};
+use crate::social::{check_sender, SenderCheck};
+use crate::state::Database;
+use crate::types::{Address, InboxMessage, ModificationEntry, ModificationType};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// KV key holding the hex SHA-256 of the binary recorded at the last start.
+pub const BINARY_HASH_KEY: &str = "binary_hash";
+
+/// `kind` of the creator's audit request.
+pub const AUDIT_REQUEST_KIND: &str = "audit_request";
+
+/// `kind` of the answer.
+pub const ATTESTATION_KIND: &str = "binary_attestation";
+
+/// Domain prefix so an attestation signature can't be replayed as another message.
+const SIGNING_PREFIX: &str = "automaton-binary:";
+
+/// Hex SHA-256 of the file at `path`.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hex SHA-256 of the running executable.
+pub fn running_binary_hash() -> Result<String> {
+    let exe = std::env::current_exe().context("Cannot locate the running executable")?;
+    hash_file(&exe)
+}
+
+/// Record `hash` as the running binary's, logging an integrity violation if
+/// it differs from the one recorded at the previous start. Returns the
+/// previous hash when it changed.
+pub fn record_at_startup(db: &Database, hash: &str) -> Result<Option<String>> {
+    let previous = db.kv_get(BINARY_HASH_KEY)?;
+    db.kv_set(BINARY_HASH_KEY, hash)?;
+    match previous {
+        Some(previous) if previous != hash => {
+            warn!("Running binary changed since the last start: {} -> {}", previous, hash);
+            db.log_modification(&ModificationEntry {
+                id: ulid::Ulid::new().to_string(),
+                timestamp: Utc::now(),
+                mod_type: ModificationType::IntegrityViolation,
+                description: format!("Running binary changed ({} -> {})", previous, hash),
+                file_path: None,
+                diff: None,
+                diff_truncated: false,
+                reversible: false,
+            })?;
+            Ok(Some(previous))
+        }
+        Some(_) => Ok(None),
+        None => {
+            info!("Running binary: {}", hash);
+            Ok(None)
+        }
+    }
+}
+
+/// The binary hash and the time it was attested, signed by the agent's wallet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryAttestation {
+    pub kind: String,
+    /// The agent's wallet address.
+    pub address: String,
+    /// Hex SHA-256 of the running executable.
+    pub binary_hash: String,
+    pub timestamp: DateTime<Utc>,
+    /// 0x-hex EIP-191 signature by `address`.
+    pub signature: String,
+}
+
+fn signing_message(address: &str, binary_hash: &str, timestamp: DateTime<Utc>) -> Vec<u8> {
+    format!(
+        "{}{}:{}:{}",
+        SIGNING_PREFIX,
+        address.to_lowercase(),
+        binary_hash,
+        timestamp.to_rfc3339()
+    )
+    .into_bytes()
+}
+
+impl BinaryAttestation {
+    /// Attest `binary_hash` as of `timestamp`.
+    pub fn sign(wallet: &Wallet, binary_hash: &str, timestamp: DateTime<Utc>) -> Result<Self> {
+        let address = wallet.address.to_string();
+        let signature = wallet.sign_message(&signing_message(&address, binary_hash, timestamp))?;
+        Ok(Self {
+            kind: ATTESTATION_KIND.into(),
+            address,
+            binary_hash: binary_hash.into(),
+            timestamp,
+            signature,
+        })
+    }
+
+    /// Check the signature recovers to the claimed address.
+    pub fn verify(&self) -> Result<()> {
+        let address = Address::parse(&self.address).context("Invalid attesting address")?;
+        let signer = recover_signer(
+            &signing_message(&self.address, &self.binary_hash, self.timestamp),
+            &self.signature,
+        )
+        .context("Invalid attestation signature")?;
+        if signer != address {
+            bail!("Attestation signed by {}, not {}", signer, address);
+        }
+        Ok(())
+    }
+
+    /// Serialize as message content.
+    pub fn to_content(&self) -> String {
+        serde_json::to_string(self).expect("attestation serializes")
+    }
+}
+
+/// Whether `content` is an audit request.
+pub fn is_audit_request(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .is_ok_and(|v| v["kind"] == AUDIT_REQUEST_KIND)
+}
+
+/// The attestation to send back if `msg` is an audit request signed by our
+/// creator, else `None`. Uses the hash recorded at startup, or hashes the
+/// binary now if none was.
+pub fn answer_audit_request(
+    msg: &InboxMessage,
+    creator_address: &str,
+    wallet: &Wallet,
+    db: &Database,
+) -> Result<Option<String>> {
+    if !is_audit_request(&msg.content) {
+        return Ok(None);
+    }
+    let from_creator = matches!(
+        (Address::parse(&msg.from_address), Address::parse(creator_address)),
+        (Ok(from), Ok(creator)) if from == creator
+    );
+    if check_sender(msg) != SenderCheck::Verified || !from_creator {
+        warn!("Ignoring an audit request not signed by our creator (from {})", msg.from_address);
+        return Ok(None);
+    }
+    let hash = match db.kv_get(BINARY_HASH_KEY)? {
+        Some(hash) => hash,
+        None => running_binary_hash()?,
+    };
+    Ok(Some(BinaryAttestation::sign(wallet, &hash, Utc::now())?.to_content()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(wallet: &Wallet, content: &str) -> InboxMessage {
+        InboxMessage {
+            id: ulid::Ulid::new().to_string(),
+            from_address: wallet.address.to_string(),
+            to_address: "0x0000000000000000000000000000000000000001".into(),
+            content: content.into(),
+            timestamp: Utc::now(),
+            read: false,
+            signature: Some(wallet.sign_message(content.as_bytes()).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_attestation_signature() {
+        let wallet = Wallet::random().unwrap();
+        let path = std::env::temp_dir().join(format!("automaton-binary-{}", ulid::Ulid::new()));
+        std::fs::write(&path, "abc").unwrap();
+        let hash = hash_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(hash, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let attestation = BinaryAttestation::sign(&wallet, &hash, Utc::now()).unwrap();
+        attestation.verify().unwrap();
+        let parsed: BinaryAttestation = serde_json::from_str(&attestation.to_content()).unwrap();
+        assert_eq!(parsed, attestation);
+
+        // Any change to the signed fields breaks it
+        let mut tampered = attestation.clone();
+        tampered.binary_hash = "0".repeat(64);
+        assert!(tampered.verify().is_err());
+        let mut tampered = attestation.clone();
+        tampered.timestamp += chrono::Duration::seconds(1);
+        assert!(tampered.verify().is_err());
+        let mut tampered = attestation;
+        tampered.address = Wallet::random().unwrap().address.to_string();
+        assert!(tampered.verify().is_err());
+    }
+
+    #[test]
+    fn test_binary_change_is_logged() {
+        let db = Database::open_memory().unwrap();
+        assert_eq!(record_at_startup(&db, "aa").unwrap(), None);
+        assert_eq!(record_at_startup(&db, "aa").unwrap(), None);
+        assert_eq!(db.count_modifications().unwrap(), 0);
+
+        assert_eq!(record_at_startup(&db, "bb").unwrap(), Some("aa".into()));
+        assert_eq!(db.kv_get(BINARY_HASH_KEY).unwrap().as_deref(), Some("bb"));
+        assert_eq!(db.count_modifications().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_only_the_creator_gets_an_attestation() {
+        let db = Database::open_memory().unwrap();
+        db.kv_set(BINARY_HASH_KEY, "ab12").unwrap();
+        let agent = Wallet::random().unwrap();
+        let creator = Wallet::random().unwrap();
+        let stranger = Wallet::random().unwrap();
+        let creator_address = creator.address.to_string();
+        let request = format!(r#"{{"kind":"{}"}}"#, AUDIT_REQUEST_KIND);
+
+        let answer = answer_audit_request(&signed(&creator, &request), &creator_address, &agent, &db)
+            .unwrap()
+            .unwrap();
+        let attestation: BinaryAttestation = serde_json::from_str(&answer).unwrap();
+        attestation.verify().unwrap();
+        assert_eq!(attestation.binary_hash, "ab12");
+        assert_eq!(attestation.address, agent.address.to_string());
+
+        let from_stranger = signed(&stranger, &request);
+        assert_eq!(answer_audit_request(&from_stranger, &creator_address, &agent, &db).unwrap(), None);
+        let mut unsigned = signed(&creator, &request);
+        unsigned.signature = None;
+        assert_eq!(answer_audit_request(&unsigned, &creator_address, &agent, &db).unwrap(), None);
+        let chat = signed(&creator, "hello");
+        assert_eq!(answer_audit_request(&chat, &creator_address, &agent, &db).unwrap(), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Passphrase-encrypted key files (Web3 Secret Storage v3).
+//!
+//! Used to archive retired wallet keys. The format is the one geth and most
+//! wallets import: scrypt key derivation, AES-128-CTR, and a Keccak-256 MAC
+//! over the second half of the derived key and the ciphertext.
+
+use crate::types::Address;
+use anyhow::{bail, Context, Result};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// scrypt cost (2^14, 16 MiB): well below geth's default, to stay usable on
+/// a Pi.
+const SCRYPT_LOG_N: u8 = 14;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const DKLEN: usize = 32;
+
+/// An encrypted key file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub id: String,
+    /// Lowercase hex address without `0x`.
+    pub address: String,
+    pub crypto: KeystoreCrypto,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    pub ciphertext: String,
+    pub kdf: String,
+    pub kdfparams: ScryptParams,
+    pub mac: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScryptParams {
+    pub dklen: usize,
+    pub n: u64,
+    pub r: u32,
+    pub p: u32,
+    pub salt: String,
+}
+
+/// Encrypt `private_key` (for `address`) under `passphrase`.
+pub fn encrypt_key(private_key: &[u8], address: &Address, passphrase: &str) -> Result<Keystore> {
+    if passphrase.is_empty() {
+        bail!("Refusing to encrypt a key with an empty passphrase");
+    }
+
+    let mut salt = [0u8; 32];
+    let mut iv = [0u8; 16];
+    let mut id = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    rand::rngs::OsRng.fill_bytes(&mut iv);
+    rand::rngs::OsRng.fill_bytes(&mut id);
+
+    let kdfparams = ScryptParams {
+        dklen: DKLEN,
+        n: 1 << SCRYPT_LOG_N,
+        r: SCRYPT_R,
+        p: SCRYPT_P,
+        salt: hex::encode(salt),
+    };
+    let derived = derive_key(passphrase, &kdfparams)?;
+
+    let mut ciphertext = private_key.to_vec();
+    Aes128Ctr::new(derived[..16].into(), (&iv).into()).apply_keystream(&mut ciphertext);
+
+    Ok(Keystore {
+        version: 3,
+        id: uuid_v4(id),
+        address: hex::encode(address.as_bytes()),
+        crypto: KeystoreCrypto {
+            cipher: "aes-128-ctr".into(),
+            cipherparams: CipherParams { iv: hex::encode(iv) },
+            mac: hex::encode(mac(&derived, &ciphertext)),
+            ciphertext: hex::encode(ciphertext),
+            kdf: "scrypt".into(),
+            kdfparams,
+        },
+    })
+}
+
+/// Decrypt a keystore, failing on a wrong passphrase.
+pub fn decrypt_key(keystore: &Keystore, passphrase: &str) -> Result<Vec<u8>> {
+    let crypto = &keystore.crypto;
+    if keystore.version != 3 || crypto.kdf != "scrypt" || crypto.cipher != "aes-128-ctr" {
+        bail!("Unsupported keystore (v{}, {}, {})", keystore.version, crypto.kdf, crypto.cipher);
+    }
+
+    let derived = derive_key(passphrase, &crypto.kdfparams)?;
+    let mut plaintext = hex::decode(&crypto.ciphertext).context("Invalid keystore ciphertext")?;
+    if hex::encode(mac(&derived, &plaintext)) != crypto.mac.to_lowercase() {
+        bail!("Wrong passphrase (keystore MAC mismatch)");
+    }
+
+    let iv: [u8; 16] = hex::decode(&crypto.cipherparams.iv)
+        .ok()
+        .and_then(|iv| iv.try_into().ok())
+        .context("Invalid keystore IV")?;
+    Aes128Ctr::new(derived[..16].into(), (&iv).into()).apply_keystream(&mut plaintext);
+    Ok(plaintext)
+}
+
+fn derive_key(passphrase: &str, params: &ScryptParams) -> Result<Vec<u8>> {
+    if !params.n.is_power_of_two() || params.dklen < 32 {
+        bail!("Invalid scrypt parameters");
+    }
+    let log_n = params.n.trailing_zeros() as u8;
+    let scrypt_params = scrypt::Params::new(log_n, params.r, params.p, params.dklen)
+        .map_err(|e| anyhow::anyhow!("Invalid scrypt parameters: {}", e))?;
+    let salt = hex::decode(&params.salt).context("Invalid keystore salt")?;
+
+    let mut derived = vec![0u8; params.dklen];
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &scrypt_params, &mut derived)
+        .map_err(|e| anyhow::anyhow!("scrypt failed: {}", e))?;
+    Ok(derived)
+}
+
+fn mac(derived: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived[16..32]);
+    hasher.update(ciphertext);
+    hasher.finalize().into()
+}
+
+/// Format random bytes as a version-4 UUID.
+fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let h = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Wallet;
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let wallet = Wallet::random().unwrap();
+        let keystore = encrypt_key(wallet.private_key_bytes(), &wallet.address, "hunter22").unwrap();
+        assert_eq!(keystore.address, hex::encode(wallet.address.as_bytes()));
+        assert!(!keystore.crypto.ciphertext.contains(&wallet.private_key_hex[2..]));
+
+        let json = serde_json::to_string(&keystore).unwrap();
+        let parsed: Keystore = serde_json::from_str(&json).unwrap();
+        assert_eq!(decrypt_key(&parsed, "hunter22").unwrap(), wallet.private_key_bytes());
+        let err = decrypt_key(&parsed, "hunter23").unwrap_err();
+        assert!(err.to_string().contains("Wrong passphrase"), "{}", err);
+        assert!(encrypt_key(wallet.private_key_bytes(), &wallet.address, "").is_err());
+    }
+}
@@ -1,4 +1,12 @@
+pub mod address_check;
+pub mod attestation;
+pub mod keystore;
 pub mod provision;
+pub mod rotation;
+pub mod signature;
+pub mod transaction;
 pub mod wallet;
 
+pub use signature::{recover_signer, verify};
+pub use transaction::LegacyTransaction;
 pub use wallet::Wallet;
@@ -1,10 +1,15 @@
 //! Conway API key provisioning via Sign-In With Ethereum (SIWE).
+//!
+//! The message's domain, statement, chain id and URI come from
+//! [`SiweConfig`], so a self-hosted or alternative provider that speaks the
+//! same endpoints can be provisioned against too.
 
+use crate::config::SiweConfig;
 use crate::identity::Wallet;
 use anyhow::{bail, Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 
 #[derive(Debug, Serialize)]
 struct SiweRequest {
@@ -12,6 +17,11 @@ struct SiweRequest {
     signature: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct NonceResponse {
+    nonce: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct SiweResponse {
     #[serde(rename = "apiKey")]
@@ -20,25 +30,22 @@ struct SiweResponse {
 }
 
 /// Provision a Conway API key using SIWE authentication.
-pub async fn provision_api_key(wallet: &Wallet, conway_api_url: &str) -> Result<String> {
-    let client = reqwest::Client::new();
+///
+/// The message embeds a nonce issued by Conway (`GET /v1/auth/nonce`) so a
+/// signed message can't be replayed; servers without that endpoint (404)
+/// get a self-generated nonce instead.
+pub async fn provision_api_key(wallet: &Wallet, conway_api_url: &str, siwe: &SiweConfig) -> Result<String> {
+    validate_siwe(siwe)?;
+    let client = crate::http::client();
 
-    // Build SIWE message
-    let now = Utc::now();
-    let message = format!(
-        "conway.tech wants you to sign in with your Ethereum account:\n\
-         {}\n\n\
-         Provision API key for automaton agent.\n\n\
-         URI: {}/v1/auth/siwe\n\
-         Version: 1\n\
-         Chain ID: 8453\n\
-         Nonce: {}\n\
-         Issued At: {}",
-        wallet.address,
-        conway_api_url,
-        ulid::Ulid::new().to_string(),
-        now.to_rfc3339(),
-    );
+    let nonce = match fetch_server_nonce(&client, conway_api_url).await? {
+        Some(nonce) => nonce,
+        None => {
+            warn!("Conway has no nonce endpoint; using a self-generated SIWE nonce");
+            ulid::Ulid::new().to_string()
+        }
+    };
+    let message = siwe_message(wallet, conway_api_url, siwe, &nonce, crate::clock::signing_time());
 
     let signature = wallet
         .sign_message(message.as_bytes())
@@ -69,3 +76,178 @@ pub async fn provision_api_key(wallet: &Wallet, conway_api_url: &str) -> Result<
         None => bail!("SIWE response missing api_key field"),
     }
 }
+
+/// Ask Conway for a one-time SIWE nonce; `None` if the server predates the
+/// nonce endpoint.
+async fn fetch_server_nonce(client: &reqwest::Client, conway_api_url: &str) -> Result<Option<String>> {
+    let resp = client
+        .get(format!("{}/v1/auth/nonce", conway_api_url))
+        .send()
+        .await
+        .context("SIWE nonce request failed")?;
+        crate::clock::observe_server_date(resp.headers());
+
+    let status = resp.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        bail!("SIWE nonce request failed ({}): {}", status, body);
+    }
+
+    let body: NonceResponse = resp.json().await.context("Failed to parse SIWE nonce response")?;
+    // EIP-4361: at least 8 alphanumeric characters, which also keeps the
+    // server from injecting lines into the signed message
+    if body.nonce.len() < 8 || !body.nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+        bail!("Conway returned an invalid SIWE nonce: {:?}", body.nonce);
+    }
+    Ok(Some(body.nonce))
+}
+
+/// Reject fields that would break the message's line structure.
+fn validate_siwe(siwe: &SiweConfig) -> Result<()> {
+    for (field, value) in [("domain", &siwe.domain), ("statement", &siwe.statement), ("uri", &siwe.uri)] {
+        if value.contains(['\n', '\r']) {
+            bail!("SIWE {} must be a single line", field);
+        }
+    }
+    if siwe.domain.trim().is_empty() {
+        bail!("SIWE domain is empty");
+    }
+    Ok(())
+}
+
+/// The EIP-4361 message signed to provision an API key.
+fn siwe_message(
+    wallet: &Wallet,
+    conway_api_url: &str,
+    siwe: &SiweConfig,
+    nonce: &str,
+    issued_at: DateTime<Utc>,
+) -> String {
+    let uri = match siwe.uri.as_str() {
+        "" => format!("{}/v1/auth/siwe", conway_api_url),
+        uri => uri.to_string(),
+    };
+    format!(
+        "{} wants you to sign in with your Ethereum account:\n\
+         {}\n\n\
+         {}\n\n\
+         URI: {}\n\
+         Version: 1\n\
+         Chain ID: {}\n\
+         Nonce: {}\n\
+         Issued At: {}",
+        siwe.domain,
+        wallet.address,
+        siwe.statement,
+        uri,
+        siwe.chain_id,
+        nonce,
+        issued_at.to_rfc3339(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    fn signed_message(server: &MockServer) -> String {
+        let requests = server.requests();
+        let siwe = requests.iter().find(|r| r.path == "/v1/auth/siwe").unwrap();
+        siwe.json()["message"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_provision_signs_server_nonce() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/auth/nonce" => MockResponse::json(200, json!({ "nonce": "k9Xq2LmP7wRt" })),
+            "/v1/auth/siwe" => MockResponse::json(200, json!({ "apiKey": "cnwy_test" })),
+            _ => MockResponse::json(404, json!({})),
+        })
+        .await;
+        let wallet = Wallet::random().unwrap();
+
+        let key = provision_api_key(&wallet, &server.url(), &SiweConfig::default()).await.unwrap();
+        assert_eq!(key, "cnwy_test");
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/v1/auth/nonce");
+        assert!(signed_message(&server).contains("\nNonce: k9Xq2LmP7wRt\n"));
+    }
+
+    #[tokio::test]
+    async fn test_siwe_fields_can_be_overridden() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/auth/nonce" => MockResponse::json(200, json!({ "nonce": "k9Xq2LmP7wRt" })),
+            _ => MockResponse::json(200, json!({ "apiKey": "key_self_hosted" })),
+        })
+        .await;
+        let wallet = Wallet::random().unwrap();
+        let siwe = SiweConfig {
+            domain: "compute.example.org".into(),
+            statement: "Sign in to Example Compute.".into(),
+            chain_id: 10,
+            uri: "https://compute.example.org/login".into(),
+        };
+
+        provision_api_key(&wallet, &server.url(), &siwe).await.unwrap();
+        let message = signed_message(&server);
+        let lines: Vec<&str> = message.lines().collect();
+        assert_eq!(lines[0], "compute.example.org wants you to sign in with your Ethereum account:");
+        assert_eq!(lines[1], wallet.address.to_string());
+        assert_eq!(lines[3], "Sign in to Example Compute.");
+        assert_eq!(lines[5], "URI: https://compute.example.org/login");
+        assert_eq!(lines[7], "Chain ID: 10");
+
+        // Still a valid EIP-191 signature by the wallet
+        let requests = server.requests();
+        let signature = requests.iter().find(|r| r.path == "/v1/auth/siwe").unwrap().json()["signature"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(crate::identity::verify(message.as_bytes(), &signature, &wallet.address));
+
+        // Fields can't smuggle in extra lines
+        let injected = SiweConfig {
+            statement: "ok\nURI: https://evil.example".into(),
+            ..SiweConfig::default()
+        };
+        assert!(provision_api_key(&wallet, &server.url(), &injected).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_provision_falls_back_without_nonce_endpoint() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/auth/siwe" => MockResponse::json(200, json!({ "apiKey": "cnwy_test" })),
+            _ => MockResponse::json(404, json!({ "error": "not found" })),
+        })
+        .await;
+        let wallet = Wallet::random().unwrap();
+
+        let key = provision_api_key(&wallet, &server.url(), &SiweConfig::default()).await.unwrap();
+        assert_eq!(key, "cnwy_test");
+        let message = signed_message(&server);
+        let nonce = message
+            .lines()
+            .find_map(|l| l.strip_prefix("Nonce: "))
+            .unwrap();
+        assert!(nonce.parse::<ulid::Ulid>().is_ok(), "{}", nonce);
+    }
+
+    #[tokio::test]
+    async fn test_provision_rejects_malformed_server_nonce() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/auth/nonce" => MockResponse::json(200, json!({ "nonce": "abc\nURI: evil" })),
+            _ => MockResponse::json(200, json!({ "apiKey": "cnwy_test" })),
+        })
+        .await;
+        let wallet = Wallet::random().unwrap();
+
+        assert!(provision_api_key(&wallet, &server.url(), &SiweConfig::default()).await.is_err());
+        assert!(server.requests().iter().all(|r| r.path != "/v1/auth/siwe"));
+    }
+}
@@ -0,0 +1,158 @@
+//! Wallet rotation (`automaton rotate-wallet`).
+//!
+//! Replaces `wallet.json` with a freshly generated key after archiving the
+//! old one, encrypted, under `wallet-archive/`. The rotation is recorded in
+//! the audit log and the transaction log, and `wallet_address` in the config
+//! is updated. Funds and the on-chain registration stay with the old address
+//! until the operator moves them.
+
+use crate::config::{self, AutomatonConfig};
+use crate::identity::keystore::encrypt_key;
+use crate::identity::Wallet;
+use crate::pidfile::running_daemon;
+use crate::state::Database;
+use crate::types::{Address, ModificationEntry, ModificationType};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Directory (under the home directory) holding archived keys.
+pub const ARCHIVE_DIR: &str = "wallet-archive";
+
+/// What a rotation did.
+#[derive(Debug, Clone)]
+pub struct WalletRotation {
+    pub old_address: Address,
+    pub new_address: Address,
+    /// The encrypted old key.
+    pub archive_path: PathBuf,
+}
+
+/// Rotate the wallet in `home_dir`, archiving the old key under
+/// `passphrase`. Refuses while an agent process is running.
+pub fn rotate_wallet(
+    home_dir: &Path,
+    config: &mut AutomatonConfig,
+    db: &Database,
+    passphrase: &str,
+) -> Result<WalletRotation> {
+    if let Some(pid) = running_daemon(home_dir) {
+        bail!("The agent is running (pid {}); stop it before rotating the wallet", pid);
+    }
+
+    let wallet_path = home_dir.join("wallet.json");
+    let old = Wallet::load(&wallet_path).context("No wallet to rotate")?;
+
+    // Archive first, so the old key is never only in memory
+    let keystore = encrypt_key(old.private_key_bytes(), &old.address, passphrase)?;
+    let archive_dir = home_dir.join(ARCHIVE_DIR);
+    std::fs::create_dir_all(&archive_dir)?;
+    let archive_path = archive_dir.join(format!(
+        "{}-{}.json",
+        old.address,
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    write_private(&archive_path, &serde_json::to_string_pretty(&keystore)?)?;
+
+    let mut new = Wallet::random()?;
+    new.save(&wallet_path)?;
+
+    let description = format!("Wallet rotated from {} to {}", old.address, new.address);
+    db.log_modification(&ModificationEntry {
+        id: ulid::Ulid::new().to_string(),
+        timestamp: Utc::now(),
+        mod_type: ModificationType::WalletRotation,
+        description: description.clone(),
+        file_path: Some("wallet.json".into()),
+        diff: None,
+        diff_truncated: false,
+        reversible: false,
+    })?;
+    db.record_transaction("wallet_rotation", 0.0, "USDC", &description, None)?;
+
+    config.wallet_address = new.address.to_string();
+    config::save_config(config, &home_dir.join("automaton.toml"))?;
+
+    info!("{}", description);
+    Ok(WalletRotation {
+        old_address: old.address,
+        new_address: new.address,
+        archive_path,
+    })
+}
+
+/// Write `contents` to a new file only the owner can read; the mode is set
+/// at creation so the key is never briefly world-readable.
+fn write_private(path: &Path, contents: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::keystore::{decrypt_key, Keystore};
+    use crate::pidfile::PidFile;
+
+    #[test]
+    fn test_rotation_bookkeeping() {
+        let home = std::env::temp_dir().join(format!("automaton-rotate-{}", ulid::Ulid::new()));
+        let old = Wallet::generate(&home.join("wallet.json")).unwrap();
+        let db = Database::open_memory().unwrap();
+        let mut config = AutomatonConfig {
+            wallet_address: old.address.to_string(),
+            ..Default::default()
+        };
+
+        // Not while the agent runs
+        let daemon = PidFile::acquire(&home).unwrap();
+        let err = rotate_wallet(&home, &mut config, &db, "passphrase").unwrap_err();
+        assert!(err.to_string().contains("agent is running"), "{}", err);
+        assert_eq!(Wallet::load(&home.join("wallet.json")).unwrap().address, old.address);
+        drop(daemon);
+
+        let rotation = rotate_wallet(&home, &mut config, &db, "passphrase").unwrap();
+        assert_eq!(rotation.old_address, old.address);
+        assert_ne!(rotation.new_address, old.address);
+
+        let current = Wallet::load(&home.join("wallet.json")).unwrap();
+        assert_eq!(current.address, rotation.new_address);
+        assert_eq!(config.wallet_address, rotation.new_address.to_string());
+        let saved = config::load_config(&home.join("automaton.toml")).unwrap();
+        assert_eq!(saved.wallet_address, rotation.new_address.to_string());
+
+        let archived: Keystore =
+            serde_json::from_str(&std::fs::read_to_string(&rotation.archive_path).unwrap()).unwrap();
+        assert_eq!(decrypt_key(&archived, "passphrase").unwrap(), old.private_key_bytes());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&rotation.archive_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert_eq!(db.count_modifications().unwrap(), 1);
+        let tx = &db.recent_transactions(1).unwrap()[0];
+        assert_eq!(tx.tx_type, "wallet_rotation");
+        assert_eq!(
+            tx.description.as_deref().unwrap(),
+            format!("Wallet rotated from {} to {}", old.address, rotation.new_address)
+        );
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+//! EIP-191 signature recovery and verification.
+//!
+//! Counterpart to [`Wallet::sign_message`](super::Wallet::sign_message):
+//! recovers the signing address from a 65-byte `r || s || v` signature.
+
+use crate::types::Address;
+use anyhow::{bail, Context, Result};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+/// Keccak256 of the EIP-191 personal-sign encoding of `message`.
+pub(crate) fn eip191_hash(message: &[u8]) -> [u8; 32] {
+    let prefixed = format!(
+        "\x19Ethereum Signed Message:\n{}{}",
+        message.len(),
+        String::from_utf8_lossy(message)
+    );
+    Keccak256::digest(prefixed.as_bytes()).into()
+}
+
+/// Ethereum address of a secp256k1 public key.
+pub(crate) fn address_of(key: &VerifyingKey) -> Address {
+    // Keccak256 of the uncompressed point without its 0x04 prefix byte
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+
+    // Take last 20 bytes as the address
+    let mut bytes = [0u8; 20];
+    bytes.copy_from_slice(&hash[12..]);
+    Address::from_bytes(bytes)
+}
+
+/// Recover the address that produced an EIP-191 `signature` over `message`.
+///
+/// `signature` is `0x`-prefixed hex of `r || s || v`, with `v` either
+/// 0/1 or 27/28.
+pub fn recover_signer(message: &[u8], signature: &str) -> Result<Address> {
+    let hex_sig = signature.strip_prefix("0x").unwrap_or(signature);
+    let bytes = hex::decode(hex_sig).context("Signature is not valid hex")?;
+    if bytes.len() != 65 {
+        bail!("Signature must be 65 bytes, got {}", bytes.len());
+    }
+
+    let sig = Signature::from_slice(&bytes[..64]).context("Malformed signature")?;
+    let v = match bytes[64] {
+        v @ (0 | 1) => v,
+        v @ (27 | 28) => v - 27,
+        v => bail!("Invalid signature recovery byte: {}", v),
+    };
+    let recovery_id = RecoveryId::from_byte(v).context("Invalid recovery id")?;
+
+    let key = VerifyingKey::recover_from_prehash(&eip191_hash(message), &sig, recovery_id)
+        .context("Signature recovery failed")?;
+    Ok(address_of(&key))
+}
+
+/// Whether `signature` over `message` was produced by `expected`.
+pub fn verify(message: &[u8], signature: &str, expected: &Address) -> bool {
+    recover_signer(message, signature).is_ok_and(|signer| signer == *expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Wallet;
+
+    /// Well-known test key and its address.
+    const TEST_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const TEST_ADDRESS: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+    fn test_wallet() -> (Wallet, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("automaton-wallet-{}.json", ulid::Ulid::new()));
+        let file = serde_json::json!({ "privateKey": TEST_KEY, "createdAt": "2024-01-01T00:00:00Z" });
+        std::fs::write(&path, file.to_string()).unwrap();
+        (Wallet::load(&path).unwrap(), path)
+    }
+
+    #[test]
+    fn test_recovers_wallet_address_from_its_signature() {
+        let (wallet, path) = test_wallet();
+        assert_eq!(wallet.address.to_string(), TEST_ADDRESS);
+
+        let message = b"creator: pause all spending";
+        let signature = wallet.sign_message(message).unwrap();
+
+        assert_eq!(recover_signer(message, &signature).unwrap(), wallet.address);
+        assert!(verify(message, &signature, &wallet.address));
+        assert!(!verify(b"creator: spend everything", &signature, &wallet.address));
+        assert!(recover_signer(message, "0x1234").is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
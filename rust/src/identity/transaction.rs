@@ -0,0 +1,131 @@
+//! Legacy (EIP-155) transaction encoding and signing.
+//!
+//! Just enough RLP to submit contract calls with `eth_sendRawTransaction`;
+//! Base accepts legacy transactions, so there is no EIP-1559 support.
+
+use crate::identity::Wallet;
+use crate::types::Address;
+use anyhow::{Context, Result};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+
+/// An unsigned legacy transaction.
+#[derive(Debug, Clone)]
+pub struct LegacyTransaction {
+    pub nonce: u64,
+    pub gas_price: u128,
+    pub gas_limit: u64,
+    pub to: Address,
+    pub value: u128,
+    pub data: Vec<u8>,
+    pub chain_id: u64,
+}
+
+impl LegacyTransaction {
+    /// Sign with `wallet`, returning the raw RLP bytes ready to broadcast.
+    pub fn sign(&self, wallet: &Wallet) -> Result<Vec<u8>> {
+        let signing_key = SigningKey::from_bytes(wallet.private_key_bytes().into())
+            .context("Invalid private key")?;
+
+        // EIP-155: the signing payload commits to the chain id
+        let mut payload = self.fields();
+        payload.push(rlp_uint(self.chain_id as u128));
+        payload.push(rlp_uint(0));
+        payload.push(rlp_uint(0));
+        let hash = Keccak256::digest(rlp_list(&payload));
+
+        let (signature, recovery_id) = signing_key
+            .sign_prehash_recoverable(&hash)
+            .context("Signing failed")?;
+        let (r, s) = signature.split_bytes();
+
+        let mut signed = self.fields();
+        signed.push(rlp_uint(
+            recovery_id.to_byte() as u128 + self.chain_id as u128 * 2 + 35,
+        ));
+        signed.push(rlp_bytes(strip_zeros(&r)));
+        signed.push(rlp_bytes(strip_zeros(&s)));
+        Ok(rlp_list(&signed))
+    }
+
+    fn fields(&self) -> Vec<Vec<u8>> {
+        vec![
+            rlp_uint(self.nonce as u128),
+            rlp_uint(self.gas_price),
+            rlp_uint(self.gas_limit as u128),
+            rlp_bytes(self.to.as_bytes()),
+            rlp_uint(self.value),
+            rlp_bytes(&self.data),
+        ]
+    }
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// RLP length prefix for a string (`base` 0x80) or list (`base` 0xc0).
+fn rlp_header(len: usize, base: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![base + len as u8];
+    }
+    let len_bytes = len.to_be_bytes();
+    let len_bytes = strip_zeros(&len_bytes);
+    let mut out = vec![base + 55 + len_bytes.len() as u8];
+    out.extend_from_slice(len_bytes);
+    out
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut out = rlp_header(bytes.len(), 0x80);
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn rlp_uint(value: u128) -> Vec<u8> {
+    rlp_bytes(strip_zeros(&value.to_be_bytes()))
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = items.concat();
+    let mut out = rlp_header(body.len(), 0xc0);
+    out.extend(body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signs_eip155_example() {
+        // The worked example from the EIP-155 specification
+        let path = std::env::temp_dir().join(format!("automaton-tx-{}.json", ulid::Ulid::new()));
+        let key = format!("0x{}", "46".repeat(32));
+        let file = serde_json::json!({ "privateKey": key, "createdAt": "2024-01-01T00:00:00Z" });
+        std::fs::write(&path, file.to_string()).unwrap();
+        let wallet = Wallet::load(&path).unwrap();
+
+        let tx = LegacyTransaction {
+            nonce: 9,
+            gas_price: 20_000_000_000,
+            gas_limit: 21_000,
+            to: Address::from_bytes([0x35; 20]),
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+            chain_id: 1,
+        };
+        assert_eq!(
+            hex::encode(tx.sign(&wallet).unwrap()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f\
+             761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -3,11 +3,12 @@
 //! Generates or loads a secp256k1 private key, derives the Ethereum address,
 //! and persists the key to `~/.automaton/wallet.json` with strict file permissions.
 
+use crate::identity::signature::{address_of, eip191_hash};
+use crate::types::Address;
 use anyhow::{Context, Result};
 use k256::ecdsa::SigningKey;
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak256};
 use std::path::{Path, PathBuf};
 use tracing::info;
 
@@ -29,8 +30,8 @@ pub struct Wallet {
     private_key_bytes: Vec<u8>,
     /// Hex-encoded private key with 0x prefix.
     pub private_key_hex: String,
-    /// Ethereum address (checksummed).
-    pub address: String,
+    /// Ethereum address.
+    pub address: Address,
     /// Path to the wallet file on disk.
     pub path: PathBuf,
 }
@@ -67,41 +68,59 @@ impl Wallet {
         })
     }
 
-    /// Generate a new random wallet and persist it.
-    pub fn generate(wallet_path: &Path) -> Result<Self> {
+    /// Generate a new random wallet without persisting it (e.g. for a child).
+    pub fn random() -> Result<Self> {
         let signing_key = SigningKey::random(&mut OsRng);
         let key_bytes = signing_key.to_bytes().to_vec();
         let key_hex = format!("0x{}", hex::encode(&key_bytes));
         let address = derive_address(&key_bytes)?;
 
+        Ok(Self {
+            private_key_bytes: key_bytes,
+            private_key_hex: key_hex,
+            address,
+            path: PathBuf::new(),
+        })
+    }
+
+    /// The wallet file contents (JSON) for this key.
+    pub fn to_file_json(&self) -> Result<String> {
         let file = WalletFile {
-            private_key: key_hex.clone(),
+            private_key: self.private_key_hex.clone(),
             created_at: chrono::Utc::now().to_rfc3339(),
         };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+
+    /// Generate a new random wallet and persist it.
+    pub fn generate(wallet_path: &Path) -> Result<Self> {
+        let mut wallet = Self::random()?;
+        wallet.save(wallet_path)?;
+        info!("Generated new wallet: {}", wallet.address);
+        Ok(wallet)
+    }
 
+    /// Write this wallet to `wallet_path` (owner-only permissions),
+    /// replacing any existing file atomically.
+    pub fn save(&mut self, wallet_path: &Path) -> Result<()> {
         // Ensure parent directory exists
         if let Some(parent) = wallet_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let json = serde_json::to_string_pretty(&file)?;
-        std::fs::write(wallet_path, &json).context("Failed to write wallet file")?;
+        let tmp = wallet_path.with_extension("json.tmp");
+        std::fs::write(&tmp, self.to_file_json()?).context("Failed to write wallet file")?;
 
-        // Restrict permissions (Unix only)
+        // Restrict permissions (Unix only) before the key is in place
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            std::fs::set_permissions(wallet_path, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
         }
 
-        info!("Generated new wallet: {}", address);
-
-        Ok(Self {
-            private_key_bytes: key_bytes,
-            private_key_hex: key_hex,
-            address,
-            path: wallet_path.to_path_buf(),
-        })
+        std::fs::rename(&tmp, wallet_path).context("Failed to move wallet file into place")?;
+        self.path = wallet_path.to_path_buf();
+        Ok(())
     }
 
     /// Sign a message using EIP-191 personal sign.
@@ -110,12 +129,7 @@ impl Wallet {
             .context("Invalid private key")?;
 
         // EIP-191 prefix
-        let prefixed = format!(
-            "\x19Ethereum Signed Message:\n{}{}",
-            message.len(),
-            String::from_utf8_lossy(message)
-        );
-        let hash = Keccak256::digest(prefixed.as_bytes());
+        let hash = eip191_hash(message);
 
         let (signature, recovery_id) = signing_key
             .sign_prehash_recoverable(&hash)
@@ -134,47 +148,8 @@ impl Wallet {
 }
 
 /// Derive an Ethereum address from raw private key bytes.
-fn derive_address(private_key: &[u8]) -> Result<String> {
+fn derive_address(private_key: &[u8]) -> Result<Address> {
     let signing_key =
         SigningKey::from_bytes(private_key.into()).context("Invalid private key bytes")?;
-    let verifying_key = signing_key.verifying_key();
-
-    // Get the uncompressed public key (65 bytes: 0x04 || x || y)
-    let pubkey_bytes = verifying_key.to_encoded_point(false);
-    let pubkey_uncompressed = pubkey_bytes.as_bytes();
-
-    // Keccak256 of the public key (skip the 0x04 prefix byte)
-    let hash = Keccak256::digest(&pubkey_uncompressed[1..]);
-
-    // Take last 20 bytes as the address
-    let address_bytes = &hash[12..];
-    let address = format!("0x{}", hex::encode(address_bytes));
-
-    // Return checksummed address
-    Ok(checksum_address(&address))
-}
-
-/// EIP-55 checksum an Ethereum address.
-fn checksum_address(address: &str) -> String {
-    let addr = address.strip_prefix("0x").unwrap_or(address).to_lowercase();
-    let hash = Keccak256::digest(addr.as_bytes());
-    let hash_hex = hex::encode(hash);
-
-    let mut checksummed = String::with_capacity(42);
-    checksummed.push_str("0x");
-
-    for (i, c) in addr.chars().enumerate() {
-        if c.is_ascii_alphabetic() {
-            let nibble = u8::from_str_radix(&hash_hex[i..i + 1], 16).unwrap_or(0);
-            if nibble >= 8 {
-                checksummed.push(c.to_ascii_uppercase());
-            } else {
-                checksummed.push(c);
-            }
-        } else {
-            checksummed.push(c);
-        }
-    }
-
-    checksummed
+    Ok(address_of(signing_key.verifying_key()))
 }
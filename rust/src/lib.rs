@@ -4,11 +4,21 @@
 //! economic autonomy on Conway Cloud infrastructure.
 
 pub mod agent;
+pub mod clock;
 pub mod config;
+pub mod constitution;
 pub mod conway;
+pub mod doctor;
+pub mod encoding;
+pub mod export;
 pub mod git_ops;
 pub mod heartbeat;
+pub mod http;
 pub mod identity;
+pub mod logs;
+pub mod notify;
+pub mod pidfile;
+pub mod redact;
 pub mod replication;
 pub mod registry;
 pub mod self_mod;
@@ -16,6 +26,10 @@ pub mod setup;
 pub mod skills;
 pub mod social;
 pub mod state;
+pub mod status_server;
 pub mod survival;
 pub mod tools;
 pub mod types;
+
+#[cfg(test)]
+pub(crate) mod test_support;
@@ -0,0 +1,171 @@
+//! `automaton logs` — a unified activity stream of turns and heartbeat runs.
+//!
+//! Reads straight from the state database, so it works against a running
+//! daemon. With `--follow` the database is polled for new rows.
+
+use crate::state::Database;
+use crate::types::{HeartbeatRun, TurnSummary};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use colored::Colorize;
+
+/// One line of the activity stream.
+#[derive(Debug, Clone)]
+pub enum Activity {
+    Turn(TurnSummary),
+    Heartbeat(HeartbeatRun),
+}
+
+impl Activity {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            Activity::Turn(t) => t.created_at,
+            Activity::Heartbeat(h) => h.executed_at,
+        }
+    }
+
+    /// Render as a single colorized line.
+    pub fn render(&self) -> String {
+        let time = self.at().format("%Y-%m-%d %H:%M:%S").to_string().dimmed();
+        match self {
+            Activity::Turn(t) => {
+                let tag = t.tag.as_ref().map(|tag| format!(" [{}]", tag)).unwrap_or_default();
+                let mut tools = if t.tool_names.is_empty() {
+                    "no tools".to_string()
+                } else {
+                    t.tool_names.join(", ")
+                };
+                if let Some(intent) = &t.intent {
+                    tools.push_str(&format!(" (intent: {})", intent));
+                }
+                format!(
+                    "{} {} #{}{} {} ${:.4} — {}",
+                    time,
+                    "turn".cyan().bold(),
+                    t.turn_number,
+                    tag.yellow(),
+                    t.model,
+                    t.cost_estimate_usd,
+                    tools
+                )
+            }
+            Activity::Heartbeat(h) => {
+                let status = if h.success { "ok".green() } else { "failed".red() };
+                format!(
+                    "{} {} {} [{}] {}",
+                    time,
+                    "heartbeat".magenta().bold(),
+                    h.task_name,
+                    status,
+                    h.result
+                )
+            }
+        }
+    }
+}
+
+/// Turns and heartbeat runs after `since`, oldest first.
+///
+/// With a `task` filter only that heartbeat task's runs are returned.
+pub fn activity_since(db: &Database, since: DateTime<Utc>, task: Option<&str>) -> Result<Vec<Activity>> {
+    let mut activity: Vec<Activity> = db
+        .heartbeat_runs_since(since, task)?
+        .into_iter()
+        .map(Activity::Heartbeat)
+        .collect();
+    if task.is_none() {
+        activity.extend(db.turns_since(since)?.into_iter().map(Activity::Turn));
+    }
+    activity.sort_by_key(Activity::at);
+    Ok(activity)
+}
+
+/// Parse `--since`: a duration back from `now` (`30s`, `15m`, `2h`, `1d`)
+/// or an RFC 3339 timestamp.
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+
+    let (amount, unit) = value.split_at(value.len().saturating_sub(1));
+    let amount: i64 = amount
+        .parse()
+        .with_context(|| format!("Invalid --since '{}': use e.g. 15m, 2h, 1d or RFC 3339", value))?;
+    let duration = match unit {
+        "s" => Duration::seconds(amount),
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        _ => bail!("Invalid --since unit in '{}': use s, m, h or d", value),
+    };
+    Ok(now - duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AgentState, ToolCall, Turn};
+
+    fn turn(number: u64, at: DateTime<Utc>, tools: &[&str]) -> Turn {
+        Turn {
+            id: ulid::Ulid::new().to_string(),
+            turn_number: number,
+            state: AgentState::Running,
+            messages: Vec::new(),
+            tool_calls: tools
+                .iter()
+                .map(|name| ToolCall {
+                    id: ulid::Ulid::new().to_string(),
+                    name: name.to_string(),
+                    arguments: serde_json::json!({}),
+                })
+                .collect(),
+            tool_results: Vec::new(),
+            token_usage: Default::default(),
+            cost_estimate_usd: 0.01,
+            model: "gpt-4o".into(),
+            tag: None,
+            intent: None,
+            created_at: at,
+        }
+    }
+
+    #[test]
+    fn test_activity_is_chronological_and_filtered() {
+        let db = Database::open_memory().unwrap();
+        let now = Utc::now();
+        db.save_turn(&turn(1, now - Duration::hours(3), &[])).unwrap();
+        db.save_turn(&turn(2, now - Duration::minutes(3), &["exec", "write_file"]))
+            .unwrap();
+        db.log_heartbeat("check_credits", "5 USD (tier: normal)", true).unwrap();
+        db.log_heartbeat("heartbeat_ping", "pong", true).unwrap();
+        db.save_turn(&turn(3, now + Duration::minutes(1), &[])).unwrap();
+
+        let since = parse_since("1h", now).unwrap();
+        let activity = activity_since(&db, since, None).unwrap();
+        let lines: Vec<String> = activity.iter().map(Activity::render).collect();
+        assert_eq!(lines.len(), 4, "{:#?}", lines);
+        assert!(lines[0].contains("#2") && lines[0].contains("exec, write_file"));
+        assert!(lines[1].contains("check_credits"));
+        assert!(lines[2].contains("heartbeat_ping"));
+        assert!(lines[3].contains("#3"));
+        assert!(activity.windows(2).all(|w| w[0].at() <= w[1].at()));
+
+        let pings = activity_since(&db, since, Some("heartbeat_ping")).unwrap();
+        assert_eq!(pings.len(), 1);
+        assert!(pings[0].render().contains("pong"));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Utc::now();
+        assert_eq!(parse_since("15m", now).unwrap(), now - Duration::minutes(15));
+        assert_eq!(parse_since("2d", now).unwrap(), now - Duration::days(2));
+        assert_eq!(
+            parse_since("2026-01-01T00:00:00Z", now).unwrap().to_rfc3339(),
+            "2026-01-01T00:00:00+00:00"
+        );
+        assert!(parse_since("soon", now).is_err());
+        assert!(parse_since("5w", now).is_err());
+    }
+}
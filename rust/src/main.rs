@@ -6,10 +6,23 @@
 //!   automaton --status       Show current status
 //!   automaton --provision    Provision a Conway API key
 //!   automaton --daemon       Run as a background daemon
+//!   automaton chat           Talk to the agent interactively
+//!   automaton simulate-tier critical  Run a few turns as if at another survival tier
+//!   automaton config revert  Restore the last config snapshot
+//!   automaton doctor         Check the runtime environment
+//!   automaton wake           Cancel a pending sleep
+//!   automaton approve <id>   Let a tool call held for approval run (deny <id> refuses)
+//!   automaton logs --follow  Tail turns and heartbeat runs
+//!   automaton inspect-turn <id>  Show a turn's raw inference request and response
+//!   automaton export <file>  Bundle the agent into a portable archive
+//!   automaton import <file>  Restore an exported archive
+//!   automaton verify-child <id>  Challenge a child to prove it runs this runtime
+//!   automaton restore-constitution  Rewrite constitution.md from the canonical text
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
+use std::io::{BufRead, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -17,10 +30,13 @@ use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
 use automaton::agent;
+use automaton::clock;
 use automaton::config;
-use automaton::conway::{ConwayClient, InferenceClient};
+use automaton::constitution::{self, Integrity};
+use automaton::conway::{ConwayClient, InferenceClient, PaymentLimits};
 use automaton::heartbeat::HeartbeatDaemon;
-use automaton::identity::Wallet;
+use automaton::identity::{address_check, attestation, Wallet};
+use automaton::redact;
 use automaton::skills;
 use automaton::state::Database;
 use automaton::survival::SurvivalMonitor;
@@ -59,10 +75,143 @@ enum Commands {
     Status,
 
     /// Provision a Conway API key via SIWE.
-    Provision,
+    Provision {
+        /// SIWE domain (defaults to `siwe.domain` in the config).
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// SIWE statement (defaults to `siwe.statement`).
+        #[arg(long)]
+        statement: Option<String>,
+
+        /// SIWE chain id (defaults to `siwe.chain_id`).
+        #[arg(long)]
+        chain_id: Option<u64>,
+
+        /// SIWE URI (defaults to `siwe.uri`).
+        #[arg(long)]
+        uri: Option<String>,
+    },
 
     /// Run as a daemon (agent loop + heartbeat).
     Daemon,
+
+    /// Chat with the agent interactively (one turn per line, until EOF).
+    Chat,
+
+    /// Run a few autonomous turns at a forced survival tier, to exercise
+    /// its prompt and tool filtering. Turns are tagged `simulated:<tier>`
+    /// and left out of activity metrics; inference is still paid for.
+    SimulateTier {
+        /// Tier to simulate (normal, low_compute, critical).
+        tier: SurvivalTier,
+
+        /// Most turns to run (stops early when the agent goes idle).
+        #[arg(long, default_value_t = 3)]
+        turns: u32,
+    },
+
+    /// Cancel a pending sleep so the agent loop resumes.
+    Wake,
+
+    /// Pause all spending (inference, spawns, paid tools) while heartbeats
+    /// and status keep running.
+    Freeze {
+        /// Why, recorded in the FREEZE file.
+        #[arg(long, default_value = "Frozen by operator")]
+        reason: String,
+    },
+
+    /// Lift a spending freeze.
+    Unfreeze,
+
+    /// Let a tool call held for approval run.
+    Approve {
+        /// Approval id (from the log or `automaton status`).
+        id: String,
+    },
+
+    /// Refuse a tool call held for approval.
+    Deny {
+        /// Approval id (from the log or `automaton status`).
+        id: String,
+    },
+
+    /// Show recent turns and heartbeat runs as one activity stream.
+    Logs {
+        /// Keep polling for new activity.
+        #[arg(long, short)]
+        follow: bool,
+
+        /// How far back to start: a duration (15m, 2h, 1d) or RFC 3339 time.
+        #[arg(long, default_value = "1h")]
+        since: String,
+
+        /// Only show runs of this heartbeat task.
+        #[arg(long)]
+        task: Option<String>,
+    },
+
+    /// Show the raw inference request and response behind a turn (recorded
+    /// with `debug_inference_logging`).
+    InspectTurn {
+        /// Turn id or turn number.
+        turn: String,
+    },
+
+    /// Check the runtime environment (git, config, wallet, endpoints, skills).
+    Doctor,
+
+    /// Bundle the agent into a portable .tar.gz (API key redacted).
+    Export {
+        /// Archive to write.
+        archive: PathBuf,
+
+        /// Also include the raw wallet key.
+        #[arg(long)]
+        include_wallet: bool,
+    },
+
+    /// Restore an exported archive into the home directory.
+    Import {
+        /// Archive produced by `automaton export`.
+        archive: PathBuf,
+    },
+
+    /// Replace the wallet with a new key, archiving the old one encrypted.
+    RotateWallet,
+
+    /// Challenge a child to prove it still runs this runtime with its own
+    /// wallet; marks it `verified` or `suspect`.
+    VerifyChild {
+        /// Child id.
+        id: String,
+
+        /// Seconds to wait for the child's answer.
+        #[arg(long, default_value_t = automaton::replication::verify::DEFAULT_VERIFY_TIMEOUT.as_secs())]
+        timeout: u64,
+    },
+
+    /// Rewrite constitution.md from the canonical text compiled into the
+    /// binary.
+    RestoreConstitution,
+
+    /// Manage automaton.toml snapshots.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Restore the most recent config snapshot (or a specific one). Run it
+    /// again to step further back.
+    Revert {
+        /// Snapshot id to restore (defaults to the latest).
+        #[arg(long)]
+        id: Option<String>,
+    },
 }
 
 // ---------------------------------------------------------------------------
@@ -79,6 +228,7 @@ async fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(env_filter)
         .with_target(false)
+        .with_writer(redact::RedactingMakeWriter)
         .init();
 
     // Resolve home directory
@@ -88,8 +238,36 @@ async fn main() -> Result<()> {
         Commands::Setup => cmd_setup(&home_dir).await,
         Commands::Run => cmd_run(&home_dir).await,
         Commands::Status => cmd_status(&home_dir).await,
-        Commands::Provision => cmd_provision(&home_dir).await,
+        Commands::Provision {
+            domain,
+            statement,
+            chain_id,
+            uri,
+        } => cmd_provision(&home_dir, domain, statement, chain_id, uri).await,
         Commands::Daemon => cmd_daemon(&home_dir).await,
+        Commands::Chat => cmd_chat(&home_dir).await,
+        Commands::SimulateTier { tier, turns } => cmd_simulate_tier(&home_dir, tier, turns).await,
+        Commands::Wake => cmd_wake(&home_dir),
+        Commands::Freeze { reason } => cmd_freeze(&home_dir, &reason),
+        Commands::Unfreeze => cmd_unfreeze(&home_dir),
+        Commands::Approve { id } => cmd_decide(&home_dir, &id, true),
+        Commands::Deny { id } => cmd_decide(&home_dir, &id, false),
+        Commands::Logs {
+            follow,
+            since,
+            task,
+        } => cmd_logs(&home_dir, follow, &since, task.as_deref()).await,
+        Commands::InspectTurn { turn } => cmd_inspect_turn(&home_dir, &turn),
+        Commands::Doctor => cmd_doctor(&home_dir).await,
+        Commands::Export {
+            archive,
+            include_wallet,
+        } => cmd_export(&home_dir, &archive, include_wallet),
+        Commands::Import { archive } => cmd_import(&home_dir, &archive),
+        Commands::Config { action } => cmd_config(&home_dir, action).await,
+        Commands::RotateWallet => cmd_rotate_wallet(&home_dir),
+        Commands::VerifyChild { id, timeout } => cmd_verify_child(&home_dir, &id, timeout).await,
+        Commands::RestoreConstitution => cmd_restore_constitution(&home_dir),
     }
 }
 
@@ -104,14 +282,15 @@ async fn cmd_setup(home_dir: &Path) -> Result<()> {
 
 async fn cmd_run(home_dir: &Path) -> Result<()> {
     let (config, wallet, db) = bootstrap(home_dir)?;
+    let _pidfile = automaton::pidfile::PidFile::acquire(home_dir)?;
+    record_binary_hash(&db);
 
-    let conway = ConwayClient::new(
-        &config.conway_api_url,
-        &config.conway_api_key,
-        &config.sandbox_id,
-    );
-    let inference = InferenceClient::new(&config.conway_api_url, &config.conway_api_key);
     let db = Arc::new(Mutex::new(db));
+    let conway = paying_conway_client(&config, wallet.clone(), &db);
+    let inference = InferenceClient::new(&config.conway_api_url, &config.conway_api_key)
+        .with_prompt_caching(config.prompt_caching)
+        .with_max_output_tokens(config.model_max_output_tokens.clone())
+        .with_system_prompt_mode(config.system_prompt_mode);
 
     // Load skills
     let skill_list = skills::load_skills(&config.resolved_skills_dir()).unwrap_or_default();
@@ -126,9 +305,80 @@ async fn cmd_run(home_dir: &Path) -> Result<()> {
 
     // Run the agent loop (no daemon, so use a no-op cancel token)
     let cancel = CancellationToken::new();
+    automaton::status_server::spawn(&config, db.clone(), cancel.clone());
     agent::run_agent_loop(config, db, conway, inference, skill_list, cancel).await
 }
 
+async fn cmd_chat(home_dir: &Path) -> Result<()> {
+    let (config, wallet, db) = bootstrap(home_dir)?;
+    let _pidfile = automaton::pidfile::PidFile::acquire(home_dir)?;
+
+    let db = Arc::new(Mutex::new(db));
+    let conway = paying_conway_client(&config, wallet, &db);
+    let inference = InferenceClient::new(&config.conway_api_url, &config.conway_api_key)
+        .with_prompt_caching(config.prompt_caching)
+        .with_max_output_tokens(config.model_max_output_tokens.clone())
+        .with_system_prompt_mode(config.system_prompt_mode);
+    let skill_list = skills::load_skills(&config.resolved_skills_dir()).unwrap_or_default();
+
+    println!(
+        "{} Chatting with '{}' (Ctrl+D to exit)",
+        ">>>".green().bold(),
+        config.name,
+    );
+
+    let mut runner =
+        agent::TurnRunner::new(config, db, conway, inference, skill_list).retain_prompts(true);
+    let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+    agent::repl::run_chat(&mut runner, stdin, &mut std::io::stdout()).await
+}
+
+async fn cmd_simulate_tier(home_dir: &Path, tier: SurvivalTier, turns: u32) -> Result<()> {
+    if tier == SurvivalTier::Dead {
+        anyhow::bail!("The agent doesn't run turns when dead; simulate normal, low_compute or critical");
+    }
+    let (config, wallet, db) = bootstrap(home_dir)?;
+
+    let db = Arc::new(Mutex::new(db));
+    let conway = paying_conway_client(&config, wallet, &db);
+    let inference = InferenceClient::new(&config.conway_api_url, &config.conway_api_key)
+        .with_prompt_caching(config.prompt_caching)
+        .with_max_output_tokens(config.model_max_output_tokens.clone())
+        .with_system_prompt_mode(config.system_prompt_mode);
+    let skill_list = skills::load_skills(&config.resolved_skills_dir()).unwrap_or_default();
+
+    println!(
+        "{} Simulating tier '{}' for up to {} turns (tagged {}{})",
+        ">>>".green().bold(),
+        tier,
+        turns,
+        SIMULATED_TAG_PREFIX,
+        tier,
+    );
+
+    let (creator, max_messages) = (config.creator_address.clone(), config.max_inbox_messages_per_turn);
+    let mut runner =
+        agent::TurnRunner::new(config, db.clone(), conway, inference, skill_list).simulate_tier(tier);
+    for n in 1..=turns {
+        // Inbox messages are shown but stay unread for the real loop
+        let turn_context = agent::context::build_turn_context(&*db.lock().await, &creator, max_messages);
+        let outcome = runner.run_turn(tier, &turn_context.text).await?;
+        println!("--- simulated turn {} ({})", n, outcome.turn.model);
+        for (call, result) in outcome.turn.tool_calls.iter().zip(&outcome.turn.tool_results) {
+            let status = if result.success { "ok" } else { "error" };
+            println!("  [{}: {}] {}", call.name, status, result.output);
+        }
+        if let Some(content) = &outcome.content {
+            println!("agent> {}", content);
+        }
+        if outcome.is_idle() {
+            println!("(agent went idle)");
+            break;
+        }
+    }
+    Ok(())
+}
+
 async fn cmd_status(home_dir: &Path) -> Result<()> {
     let (config, wallet, db) = bootstrap(home_dir)?;
     let db = Arc::new(Mutex::new(db));
@@ -142,7 +392,16 @@ async fn cmd_status(home_dir: &Path) -> Result<()> {
         .unwrap_or_else(|| "unknown".into());
     let turn_count = db_lock.turn_count()?;
     let children_count = db_lock.active_children_count()?;
+    let binary_hash = db_lock
+        .kv_get(attestation::BINARY_HASH_KEY)?
+        .unwrap_or_else(|| "not recorded".into());
+    let spawn_queue = db_lock
+        .kv_get(automaton::replication::queue::SPAWN_QUEUE_DEPTH_KEY)?
+        .and_then(|depth| depth.parse::<usize>().ok())
+        .unwrap_or(0);
     let last_heartbeat = db_lock.kv_get("last_heartbeat")?.unwrap_or_else(|| "never".into());
+    let tag_stats = db_lock.turn_tag_stats()?;
+    let pending_approvals = db_lock.pending_approvals()?;
 
     println!();
     println!("{}", "=== Automaton Status ===".bold());
@@ -154,32 +413,247 @@ async fn cmd_status(home_dir: &Path) -> Result<()> {
     println!("  {}:", "State".bold());
     println!("    Agent:    {}", colorize_state(&agent_state));
     println!("    Tier:     {}", colorize_tier(state.tier));
+    if agent::freeze::is_frozen(&config.home_dir()) {
+        println!("    {}", "Spending frozen (automaton unfreeze to resume)".yellow());
+    }
     println!();
     println!("  {}:", "Finances".bold());
     println!("    Credits:  {:.4}", state.credits_balance);
     println!("    USDC:     {:.6}", state.usdc_balance);
+    if !tag_stats.is_empty() {
+        println!("    Spend by turn tag:");
+        for stats in &tag_stats {
+            println!(
+                "      {:<14} {:>4} turns  ${:.4}",
+                stats.tag, stats.turns, stats.cost_estimate_usd
+            );
+        }
+    }
     println!();
     println!("  {}:", "Runtime".bold());
     println!("    Turns:    {}", turn_count);
     println!("    Children: {} / {}", children_count, config.max_children);
+    if spawn_queue > 0 {
+        println!("    Spawns queued: {}", spawn_queue);
+    }
     println!("    Model:    {}", config.inference_model);
     println!("    Heartbeat: {}", last_heartbeat);
+    println!("    Binary:   {}", binary_hash);
     println!();
+    if !pending_approvals.is_empty() {
+        println!("  {}:", "Awaiting approval".bold().yellow());
+        for approval in &pending_approvals {
+            println!("    {}  {} ({})", approval.id, approval.tool, approval.reason);
+            println!("      args: {}", approval.arguments);
+        }
+        println!();
+    }
 
     Ok(())
 }
 
-async fn cmd_provision(home_dir: &Path) -> Result<()> {
+fn cmd_wake(home_dir: &Path) -> Result<()> {
+    let (_config, _wallet, db) = bootstrap(home_dir)?;
+    match agent::sleep::wake(&db, "Woken by operator (automaton wake)")? {
+        Some(until) => println!(
+            "{} Woke the agent (was sleeping until {})",
+            ">>>".green().bold(),
+            until.to_rfc3339()
+        ),
+        None => println!("Agent is not sleeping"),
+    }
+    Ok(())
+}
+
+fn cmd_freeze(home_dir: &Path, reason: &str) -> Result<()> {
+    let (config, _wallet, _db) = bootstrap(home_dir)?;
+    if agent::freeze::freeze(&config.home_dir(), reason)? {
+        println!(
+            "{} Spending frozen; heartbeats and status keep running. `automaton unfreeze` to resume.",
+            ">>>".yellow().bold()
+        );
+    } else {
+        println!("Spending is already frozen");
+    }
+    Ok(())
+}
+
+fn cmd_unfreeze(home_dir: &Path) -> Result<()> {
+    let (config, _wallet, _db) = bootstrap(home_dir)?;
+    if agent::freeze::unfreeze(&config.home_dir())? {
+        println!("{} Spending unfrozen", ">>>".green().bold());
+    } else {
+        println!("Spending is not frozen");
+    }
+    Ok(())
+}
+
+fn cmd_inspect_turn(home_dir: &Path, turn: &str) -> Result<()> {
+    let (config, _wallet, db) = bootstrap(home_dir)?;
+    let turn_id = match turn.parse::<u64>() {
+        Ok(number) => db
+            .turn_id_by_number(number)?
+            .with_context(|| format!("No turn numbered {}", number))?,
+        Err(_) => turn.to_string(),
+    };
+    let Some(entry) = db.inference_log_for(&turn_id)? else {
+        let hint = if config.debug_inference_logging {
+            ""
+        } else {
+            " (set debug_inference_logging = true to record payloads)"
+        };
+        anyhow::bail!("No inference log for turn {}{}", turn_id, hint);
+    };
+
+    // Stored payloads are compact JSON; indent them for reading
+    let pretty = |json: &str| {
+        serde_json::from_str::<serde_json::Value>(json)
+            .and_then(|v| serde_json::to_string_pretty(&v))
+            .unwrap_or_else(|_| json.to_string())
+    };
+    println!(
+        "{} Turn {} ({} ms, {})",
+        ">>>".green().bold(),
+        entry.turn_id,
+        entry.exchange.latency_ms,
+        entry.created_at.to_rfc3339()
+    );
+    println!("{}", "--- request".bold());
+    println!("{}", pretty(&entry.exchange.request_json));
+    println!("{}", "--- response".bold());
+    println!("{}", pretty(&entry.exchange.response_json));
+    Ok(())
+}
+
+fn cmd_decide(home_dir: &Path, id: &str, approve: bool) -> Result<()> {
+    let (_config, _wallet, db) = bootstrap(home_dir)?;
+    let approval = automaton::tools::approval::decide(&db, id, approve)?;
+    println!(
+        "{} {} {} call {}",
+        ">>>".green().bold(),
+        if approve { "Approved" } else { "Denied" },
+        approval.tool,
+        approval.id
+    );
+    Ok(())
+}
+
+fn cmd_restore_constitution(home_dir: &Path) -> Result<()> {
+    if constitution::check(home_dir)? == Integrity::Intact {
+        println!("constitution.md already matches the canonical text");
+        return Ok(());
+    }
+    constitution::restore(home_dir)?;
+    println!("{} constitution.md restored", ">>>".green().bold());
+    Ok(())
+}
+
+fn cmd_rotate_wallet(home_dir: &Path) -> Result<()> {
+    let (mut config, _wallet, db) = bootstrap(home_dir)?;
+
+    println!("The current key will be archived, encrypted with a passphrase you choose.");
+    let read_passphrase = |label: &str| -> Result<String> {
+        rpassword::prompt_password(format!("{}: ", label)).context("Failed to read passphrase")
+    };
+    let passphrase = read_passphrase("Archive passphrase")?;
+    if passphrase != read_passphrase("Repeat passphrase")? {
+        anyhow::bail!("Passphrases do not match; wallet unchanged");
+    }
+
+    let rotation =
+        automaton::identity::rotation::rotate_wallet(home_dir, &mut config, &db, &passphrase)?;
+
+    println!("{} Wallet rotated", ">>>".green().bold());
+    println!("  Old: {}", rotation.old_address);
+    println!("  New: {}", rotation.new_address);
+    println!("  Old key archived at {}", rotation.archive_path.display());
+    println!();
+    println!("Next steps:");
+    println!("  1. Move USDC and ETH from {} to {}", rotation.old_address, rotation.new_address);
+    println!("  2. Re-register on-chain so the registry points at the new address");
+    println!("  3. Run `automaton provision` to get an API key for the new wallet");
+    Ok(())
+}
+
+async fn cmd_verify_child(home_dir: &Path, id: &str, timeout_secs: u64) -> Result<()> {
+    let (config, wallet, db) = bootstrap(home_dir)?;
+    let db = Arc::new(Mutex::new(db));
+
+    println!("Challenging child {} (waiting up to {}s for its answer)...", id, timeout_secs);
+    let child = automaton::replication::verify::verify_child(
+        &config.social_relay_url,
+        &wallet,
+        &db,
+        id,
+        std::time::Duration::from_secs(timeout_secs),
+    )
+    .await?;
+
+    match child.status {
+        ChildState::Verified => println!(
+            "{} '{}' answered with its wallet {}; marked verified",
+            ">>>".green().bold(),
+            child.name,
+            child.wallet_address
+        ),
+        _ => println!(
+            "{} '{}' did not prove it holds wallet {}; marked {}",
+            "!!!".red().bold(),
+            child.name,
+            child.wallet_address,
+            child.status
+        ),
+    }
+    Ok(())
+}
+
+async fn cmd_logs(home_dir: &Path, follow: bool, since: &str, task: Option<&str>) -> Result<()> {
+    let (_config, _wallet, db) = bootstrap(home_dir)?;
+    let mut since = automaton::logs::parse_since(since, chrono::Utc::now())?;
+
+    loop {
+        for entry in automaton::logs::activity_since(&db, since, task)? {
+            println!("{}", entry.render());
+            since = since.max(entry.at());
+        }
+        if !follow {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(2)) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+async fn cmd_provision(
+    home_dir: &Path,
+    domain: Option<String>,
+    statement: Option<String>,
+    chain_id: Option<u64>,
+    uri: Option<String>,
+) -> Result<()> {
     let config_path = home_dir.join("automaton.toml");
     let mut cfg = config::load_config(&config_path)?;
     let wallet_path = home_dir.join("wallet.json");
     let wallet = Wallet::load_or_create(&wallet_path)?;
 
+    clock::set_tolerance(cfg.clock_skew_tolerance_secs);
+    automaton::http::set_identity(&cfg, &wallet.address.to_string());
+
+    // Overrides apply to this run only; the config's `siwe` section is kept
+    let mut siwe = cfg.siwe.clone();
+    siwe.domain = domain.unwrap_or(siwe.domain);
+    siwe.statement = statement.unwrap_or(siwe.statement);
+    siwe.chain_id = chain_id.unwrap_or(siwe.chain_id);
+    siwe.uri = uri.unwrap_or(siwe.uri);
+
     println!("Provisioning Conway API key via SIWE...");
     println!("  Wallet: {}", wallet.address);
+    println!("  Domain: {} (chain {})", siwe.domain, siwe.chain_id);
 
     let api_key =
-        automaton::identity::provision::provision_api_key(&wallet, &cfg.conway_api_url).await?;
+        automaton::identity::provision::provision_api_key(&wallet, &cfg.conway_api_url, &siwe).await?;
 
     cfg.conway_api_key = api_key;
     config::save_config(&cfg, &config_path)?;
@@ -188,16 +662,73 @@ async fn cmd_provision(home_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+async fn cmd_doctor(home_dir: &Path) -> Result<()> {
+    let results = automaton::doctor::run_checks(home_dir).await;
+
+    for check in &results {
+        match &check.outcome {
+            Ok(detail) => println!("  {} {}: {}", "[PASS]".green().bold(), check.name, detail),
+            Err(e) => println!("  {} {}: {:#}", "[FAIL]".red().bold(), check.name, e),
+        }
+    }
+
+    let failed = results.iter().filter(|c| !c.passed()).count();
+    if failed > 0 {
+        println!("\n{} of {} checks failed.", failed, results.len());
+        std::process::exit(1);
+    }
+    println!("\nAll {} checks passed.", results.len());
+    Ok(())
+}
+
+fn cmd_export(home_dir: &Path, archive: &Path, include_wallet: bool) -> Result<()> {
+    automaton::export::export_agent(home_dir, archive, include_wallet)?;
+    println!("Exported agent to {}", archive.display());
+    if !include_wallet {
+        println!("  Wallet key not included (pass --include-wallet to bundle it).");
+    }
+    Ok(())
+}
+
+fn cmd_import(home_dir: &Path, archive: &Path) -> Result<()> {
+    let cfg = automaton::export::import_agent(archive, home_dir)?;
+    println!("Imported agent '{}' into {}", cfg.name, home_dir.display());
+    if cfg.conway_api_key.is_empty() {
+        println!("  Run `automaton provision` to provision a new API key.");
+    }
+    Ok(())
+}
+
+async fn cmd_config(home_dir: &Path, action: ConfigAction) -> Result<()> {
+    let config_path = home_dir.join("automaton.toml");
+
+    match action {
+        ConfigAction::Revert { id } => {
+            let id = match id {
+                Some(id) => {
+                    config::restore_snapshot(&config_path, &id)?;
+                    id
+                }
+                None => config::revert(&config_path)?,
+            };
+            println!("Restored config snapshot {}", id);
+        }
+    }
+
+    Ok(())
+}
+
 async fn cmd_daemon(home_dir: &Path) -> Result<()> {
-    let (config, _wallet, db) = bootstrap(home_dir)?;
+    let (config, wallet, db) = bootstrap(home_dir)?;
+    let _pidfile = automaton::pidfile::PidFile::acquire(home_dir)?;
+    record_binary_hash(&db);
 
-    let conway = ConwayClient::new(
-        &config.conway_api_url,
-        &config.conway_api_key,
-        &config.sandbox_id,
-    );
-    let inference = InferenceClient::new(&config.conway_api_url, &config.conway_api_key);
     let db = Arc::new(Mutex::new(db));
+    let conway = paying_conway_client(&config, wallet, &db);
+    let inference = InferenceClient::new(&config.conway_api_url, &config.conway_api_key)
+        .with_prompt_caching(config.prompt_caching)
+        .with_max_output_tokens(config.model_max_output_tokens.clone())
+        .with_system_prompt_mode(config.system_prompt_mode);
     let skill_list = skills::load_skills(&config.resolved_skills_dir()).unwrap_or_default();
 
     println!(
@@ -208,6 +739,7 @@ async fn cmd_daemon(home_dir: &Path) -> Result<()> {
 
     // Create a cancellation token for graceful shutdown
     let cancel = CancellationToken::new();
+    automaton::status_server::spawn(&config, db.clone(), cancel.clone());
 
     // Spawn heartbeat daemon (token is checked inside the loop)
     let heartbeat_db = db.clone();
@@ -294,13 +826,23 @@ fn bootstrap(home_dir: &Path) -> Result<(config::AutomatonConfig, Wallet, Databa
         std::process::exit(1);
     }
 
-    let cfg = config::load_config(&config_path)
+    let mut cfg = config::load_config(&config_path)
         .with_context(|| format!("Failed to load config from {}", config_path.display()))?;
+    // The prompt may have been written into automaton.toml by hand
+    automaton::setup::screening::ensure_configured_genesis(&cfg)?;
 
     let wallet_path = home_dir.join("wallet.json");
     let wallet = Wallet::load_or_create(&wallet_path)
         .with_context(|| format!("Failed to load or create wallet at {}", wallet_path.display()))?;
 
+    // Keep secrets out of (debug) logs from here on
+    redact::set_patterns(cfg.log_redact_patterns.clone());
+    redact::register_secret(&wallet.private_key_hex);
+    redact::register_secret(&cfg.conway_api_key);
+    redact::register_secret(&cfg.status_token);
+    clock::set_tolerance(cfg.clock_skew_tolerance_secs);
+    automaton::http::set_identity(&cfg, &wallet.address.to_string());
+
     let db_path = cfg.resolved_db_path();
     let db_path = std::path::Path::new(&db_path);
 
@@ -313,16 +855,86 @@ fn bootstrap(home_dir: &Path) -> Result<(config::AutomatonConfig, Wallet, Databa
         }
     }
 
-    let db = Database::open(db_path)
+    let db = Database::open_or_recover(db_path, cfg.db_auto_recover)
         .with_context(|| format!("Failed to open database at {}", db_path.display()))?;
 
+    // Never sign with one key while advertising another
+    if let address_check::AddressCheck::Corrected { previous } =
+        address_check::check_wallet_address(home_dir, &mut cfg, &wallet, &db)?
+    {
+        eprintln!(
+            "{} wallet_address in automaton.toml was {:?}; corrected to {} from wallet.json.",
+            "Warning:".yellow().bold(),
+            previous,
+            wallet.address
+        );
+    }
+
+    // A child refuses to run on a genesis its parent didn't sign
+    automaton::replication::genesis::verify_at_first_boot(home_dir, &cfg, &db)
+        .context("Genesis verification failed")?;
+
+    if let Integrity::Altered { .. } = constitution::verify_at_startup(home_dir, &db)? {
+        offer_constitution_restore(home_dir)?;
+    }
+
     Ok((cfg, wallet, db))
 }
 
+/// Hash the running executable and check it against the previous start.
+fn record_binary_hash(db: &Database) {
+    let recorded = attestation::running_binary_hash()
+        .and_then(|hash| attestation::record_at_startup(db, &hash));
+    match recorded {
+        Ok(Some(_)) => eprintln!(
+            "{} The running binary changed since the last start (logged as an integrity violation).",
+            "Warning:".yellow().bold()
+        ),
+        Ok(None) => {}
+        Err(e) => warn!("Could not record the running binary's hash: {:#}", e),
+    }
+}
+
+/// Ask the operator whether to put back the canonical constitution. Without
+/// a terminal, only point at `restore-constitution`.
+fn offer_constitution_restore(home_dir: &Path) -> Result<()> {
+    use std::io::IsTerminal;
+
+    eprintln!(
+        "{} constitution.md does not match the canonical constitution (logged as an integrity violation).",
+        "Warning:".red().bold()
+    );
+    if !std::io::stdin().is_terminal() {
+        eprintln!("  Run `automaton restore-constitution` to restore it.");
+        return Ok(());
+    }
+    print!("Restore the canonical text? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        constitution::restore(home_dir)?;
+        println!("{} constitution.md restored", ">>>".green().bold());
+    }
+    Ok(())
+}
+
+/// A Conway client that pays 402s with the agent's wallet and logs payments.
+fn paying_conway_client(
+    config: &config::AutomatonConfig,
+    wallet: Wallet,
+    db: &Arc<Mutex<Database>>,
+) -> ConwayClient {
+    ConwayClient::new(&config.conway_api_url, &config.conway_api_key, &config.sandbox_id)
+        .with_wallet(Arc::new(wallet))
+        .with_ledger(db.clone())
+        .with_payment_limits(PaymentLimits::from_config(config))
+}
+
 fn colorize_state(state: &str) -> String {
     match state {
         "running" => state.green().to_string(),
-        "sleeping" => state.yellow().to_string(),
+        "sleeping" | "hibernating" | "frozen" => state.yellow().to_string(),
         "low_compute" | "critical" => state.red().to_string(),
         "dead" => state.red().bold().to_string(),
         _ => state.dimmed().to_string(),
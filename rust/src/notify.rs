@@ -0,0 +1,43 @@
+//! Operator notifications via an optional webhook.
+//!
+//! When `notify_webhook_url` is set, lifecycle events are POSTed to it as
+//! JSON: `{ "event", "agent", "timestamp", "data" }`. Delivery is best-effort;
+//! callers log failures rather than aborting.
+
+use crate::config::AutomatonConfig;
+use anyhow::{bail, Context, Result};
+use std::time::Duration;
+use tracing::debug;
+
+/// Timeout for a single webhook delivery.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Send `event` to the configured webhook (no-op if none is configured).
+pub async fn notify(config: &AutomatonConfig, event: &str, data: serde_json::Value) -> Result<()> {
+    if config.notify_webhook_url.is_empty() {
+        return Ok(());
+    }
+
+    let payload = serde_json::json!({
+        "event": event,
+        "agent": config.name,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "data": data,
+    });
+
+    let resp = crate::http::client()
+        .post(&config.notify_webhook_url)
+        .timeout(NOTIFY_TIMEOUT)
+        .json(&payload)
+        .send()
+        .await
+        .context("Notify webhook request failed")?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        bail!("Notify webhook returned {}", status);
+    }
+
+    debug!("Sent '{}' notification", event);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! The daemon's pidfile (`daemon.pid` in the home directory).
+//!
+//! `automaton daemon`, `run` and `chat` hold it while running; commands
+//! that must not race a live agent (such as wallet rotation) check it first.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// File name of the pidfile in the home directory.
+pub const PIDFILE: &str = "daemon.pid";
+
+/// A held pidfile, removed on drop.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Record this process as the running daemon, failing if another live
+    /// daemon already holds the pidfile.
+    pub fn acquire(home_dir: &Path) -> Result<Self> {
+        if let Some(pid) = running_daemon(home_dir) {
+            bail!("Another agent process is already running (pid {})", pid);
+        }
+        let path = home_dir.join(PIDFILE);
+        std::fs::write(&path, std::process::id().to_string())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The pid of a live daemon for `home_dir`, if any. A pidfile whose process
+/// is gone is stale and ignored.
+pub fn running_daemon(home_dir: &Path) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(home_dir.join(PIDFILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    process_alive(pid).then_some(pid)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    // Signal 0 only checks the process exists; EPERM means it does but
+    // belongs to someone else
+    // SAFETY: kill with signal 0 sends nothing and touches no memory
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without a way to ask, a pidfile never blocks startup; the database's run
+/// lock still keeps two agents apart.
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pidfile_detects_live_daemon() {
+        let home = std::env::temp_dir().join(format!("automaton-pid-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&home).unwrap();
+        assert_eq!(running_daemon(&home), None);
+
+        let held = PidFile::acquire(&home).unwrap();
+        assert_eq!(running_daemon(&home), Some(std::process::id()));
+        assert!(PidFile::acquire(&home).is_err());
+        drop(held);
+        assert_eq!(running_daemon(&home), None);
+
+        // A pid that has exited is stale
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let exited = child.id();
+        child.wait().unwrap();
+        std::fs::write(home.join(PIDFILE), exited.to_string()).unwrap();
+        assert_eq!(running_daemon(&home), None);
+
+        // Garbage is as good as no pidfile
+        std::fs::write(home.join(PIDFILE), "not a pid").unwrap();
+        assert_eq!(running_daemon(&home), None);
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! Secret redaction for log output.
+//!
+//! The tracing subscriber writes through [`RedactingMakeWriter`], which masks
+//! every registered secret (API keys, the wallet private key) and every token
+//! following a configured prefix pattern before a line reaches the terminal.
+//! HTTP clients register their keys when constructed, so debug logs from any
+//! of them — including reqwest's own — stay clean.
+
+use std::io::{self, Write};
+use std::sync::{LazyLock, RwLock};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Replacement text for a redacted value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Secrets shorter than this are ignored; masking them would mangle
+/// ordinary words.
+const MIN_SECRET_LEN: usize = 8;
+
+/// Process-wide redactor used by the log writer.
+static GLOBAL: LazyLock<RwLock<Redactor>> = LazyLock::new(|| {
+    RwLock::new(Redactor {
+        secrets: Vec::new(),
+        patterns: default_redact_patterns(),
+    })
+});
+
+/// Prefixes whose following token is always masked, e.g. `sk-abc123`.
+pub fn default_redact_patterns() -> Vec<String> {
+    vec!["sk-".into(), "Bearer ".into()]
+}
+
+/// Masks known secrets and prefix-pattern tokens in text.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+    /// A pattern is a literal prefix; where it starts a word, the run of
+    /// token characters (alphanumerics, `-`, `_`, `.`) after it is masked.
+    patterns: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self {
+            secrets: Vec::new(),
+            patterns,
+        }
+    }
+
+    /// Mask `secret` wherever it appears (with or without a `0x` prefix).
+    pub fn add_secret(&mut self, secret: &str) {
+        let secret = secret.trim();
+        let bare = secret.strip_prefix("0x").unwrap_or(secret);
+        for s in [secret, bare] {
+            if s.len() >= MIN_SECRET_LEN && !self.secrets.iter().any(|known| known == s) {
+                self.secrets.push(s.to_string());
+            }
+        }
+        // Longest first, so a secret containing another is masked whole
+        self.secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+
+    pub fn set_patterns(&mut self, patterns: Vec<String>) {
+        self.patterns = patterns;
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for secret in &self.secrets {
+            if out.contains(secret.as_str()) {
+                out = out.replace(secret.as_str(), REDACTED);
+            }
+        }
+        for pattern in self.patterns.iter().filter(|p| !p.is_empty()) {
+            out = redact_after(&out, pattern);
+        }
+        out
+    }
+}
+
+/// Mask the token after each occurrence of `prefix` that starts a word, so
+/// `sk-` masks `key=sk-abc` but leaves `task-runner` alone.
+fn redact_after(text: &str, prefix: &str) -> String {
+    let is_token = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    let needs_boundary = prefix.starts_with(is_token);
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(prefix) {
+        let after = &rest[at + prefix.len()..];
+        let before = rest[..at].chars().next_back().or_else(|| out.chars().next_back());
+        if needs_boundary && before.is_some_and(is_token) {
+            out.push_str(&rest[..at + prefix.len()]);
+            rest = after;
+            continue;
+        }
+        let token_len = after.find(|c: char| !is_token(c)).unwrap_or(after.len());
+        out.push_str(&rest[..at + prefix.len()]);
+        if token_len > 0 && !after.starts_with(REDACTED) {
+            out.push_str(REDACTED);
+        } else {
+            out.push_str(&after[..token_len]);
+        }
+        rest = &after[token_len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Register a secret with the log redactor.
+pub fn register_secret(secret: &str) {
+    if let Ok(mut redactor) = GLOBAL.write() {
+        redactor.add_secret(secret);
+    }
+}
+
+/// Replace the log redactor's prefix patterns.
+pub fn set_patterns(patterns: Vec<String>) {
+    if let Ok(mut redactor) = GLOBAL.write() {
+        redactor.set_patterns(patterns);
+    }
+}
+
+/// Redact `text` with the process-wide redactor.
+pub fn redact(text: &str) -> String {
+    match GLOBAL.read() {
+        Ok(redactor) => redactor.redact(text),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// `MakeWriter` for the tracing subscriber that redacts each log line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RedactingMakeWriter;
+
+impl<'a> MakeWriter<'a> for RedactingMakeWriter {
+    type Writer = RedactingWriter<io::Stdout>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(io::stdout())
+    }
+}
+
+/// Writer that redacts through the process-wide redactor before writing.
+///
+/// The fmt subscriber formats a whole event before writing it, so each
+/// `write` sees complete lines.
+pub struct RedactingWriter<W: Write>(pub W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIVATE_KEY: &str =
+        "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn test_redacts_secrets_and_patterns() {
+        let mut redactor = Redactor::new(default_redact_patterns());
+        redactor.add_secret("conway-key-5f2a9c");
+        redactor.add_secret(PRIVATE_KEY);
+        redactor.add_secret("short");
+
+        let line = format!(
+            "DEBUG request key=conway-key-5f2a9c pk={} raw={} auth=Bearer abc.def-1 openai=sk-proj_XYZ9 short",
+            PRIVATE_KEY,
+            &PRIVATE_KEY[2..]
+        );
+        let masked = redactor.redact(&line);
+        assert_eq!(
+            masked,
+            "DEBUG request key=[REDACTED] pk=[REDACTED] raw=[REDACTED] \
+             auth=Bearer [REDACTED] openai=sk-[REDACTED] short"
+        );
+        assert_eq!(redactor.redact(&masked), masked);
+    }
+
+    #[test]
+    fn test_patterns_only_match_at_word_start() {
+        let redactor = Redactor::new(default_redact_patterns());
+        assert_eq!(
+            redactor.redact("task-runner desk-lamp sk-abc1 (sk-def2) \"sk-ghi3\""),
+            "task-runner desk-lamp sk-[REDACTED] (sk-[REDACTED]) \"sk-[REDACTED]\""
+        );
+        assert_eq!(redactor.redact("sk-abc1 at start"), "sk-[REDACTED] at start");
+        assert_eq!(redactor.redact("NotBearer token"), "NotBearer token");
+    }
+
+    #[test]
+    fn test_log_writer_masks_registered_key() {
+        register_secret("writer-test-api-key-77");
+        let mut writer = RedactingWriter(Vec::new());
+        writeln!(writer, "Inference request with key writer-test-api-key-77").unwrap();
+        let written = String::from_utf8(writer.0).unwrap();
+        assert_eq!(written, "Inference request with key [REDACTED]\n");
+    }
+}
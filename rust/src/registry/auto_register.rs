@@ -0,0 +1,344 @@
+//! Keep the automaton registered in the ERC-8004 registry.
+//!
+//! Run from the `register_onchain` heartbeat task. Each run is idempotent:
+//! an agent that is already registered, or whose registration transaction
+//! is still pending, is left alone. A pending transaction counts as dropped
+//! once the account's confirmed nonce has moved past it or it is older than
+//! [`PENDING_TX_TIMEOUT_MINS`], and registration is tried again. A new
+//! registration is only attempted when gas is under `max_gas_price_gwei`
+//! and the wallet can pay for it.
+
+use crate::config::AutomatonConfig;
+use crate::identity::Wallet;
+use crate::registry::{RegistryClient, SentTransaction};
+use crate::state::Database;
+use crate::types::{Address, AgentCard};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// KV key holding the [`PendingRegistration`] of a submitted, unconfirmed
+/// registration.
+const PENDING_TX_KEY: &str = "registry_pending_tx";
+
+/// Age after which an unconfirmed registration is given up as dropped.
+pub const PENDING_TX_TIMEOUT_MINS: i64 = 60;
+
+/// A submitted registration awaiting its receipt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingRegistration {
+    hash: String,
+    /// `None` for an entry from before nonces were recorded.
+    nonce: Option<u64>,
+    sent_at: DateTime<Utc>,
+}
+
+impl PendingRegistration {
+    /// Read the pending entry. One from before nonces were recorded (a bare
+    /// hash) can't be checked, so it counts as sent long ago.
+    fn load(db: &Database) -> Result<Option<Self>> {
+        Ok(db.kv_get(PENDING_TX_KEY)?.map(|value| {
+            serde_json::from_str(&value).unwrap_or(Self {
+                hash: value,
+                nonce: None,
+                sent_at: DateTime::UNIX_EPOCH,
+            })
+        }))
+    }
+
+    /// Whether the transaction can no longer be mined: another one took
+    /// its nonce, or it has waited past the timeout.
+    fn is_dropped(&self, confirmed_nonce: u64, now: DateTime<Utc>) -> bool {
+        self.nonce.is_some_and(|nonce| confirmed_nonce > nonce)
+            || now - self.sent_at > Duration::minutes(PENDING_TX_TIMEOUT_MINS)
+    }
+}
+
+/// Headroom on the gas estimate, in percent.
+const GAS_LIMIT_MARGIN: u64 = 20;
+
+/// Register the agent on-chain unless it already is.
+pub async fn ensure_registered(
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+    wallet: &Wallet,
+) -> Result<String> {
+    if config.registry_contract.is_empty() || config.base_rpc_url.is_empty() {
+        return Ok("Skipped: no registry contract or RPC configured".into());
+    }
+    let contract = Address::parse(&config.registry_contract).context("Invalid registry_contract")?;
+    let client = RegistryClient::new(&config.base_rpc_url, contract);
+
+    if client.lookup(&wallet.address).await?.is_some() {
+        let db = db.lock().await;
+        db.kv_delete(PENDING_TX_KEY)?;
+        // Refresh the entry, keeping the URI recorded at submission
+        let metadata_uri = db
+            .registry_metadata_uri(&wallet.address.to_string())?
+            .unwrap_or_default();
+        db.save_registry_entry(&agent_card(config, wallet, &metadata_uri))?;
+        return Ok(format!("Already registered ({})", wallet.address));
+    }
+
+    let pending = PendingRegistration::load(&*db.lock().await)?;
+    if let Some(pending) = pending {
+        let hash = &pending.hash;
+        match client.transaction_status(hash).await? {
+            None => {
+                let confirmed_nonce = client.confirmed_nonce(&wallet.address).await?;
+                if !pending.is_dropped(confirmed_nonce, Utc::now()) {
+                    return Ok(format!("Registration {} still pending", hash));
+                }
+                warn!("Registration {} was never mined; retrying", hash);
+                db.lock().await.kv_delete(PENDING_TX_KEY)?;
+            }
+            Some(true) => {
+                db.lock().await.kv_delete(PENDING_TX_KEY)?;
+                return Ok(format!("Registration {} confirmed", hash));
+            }
+            Some(false) => {
+                warn!("Registration {} reverted; retrying", hash);
+                db.lock().await.kv_delete(PENDING_TX_KEY)?;
+            }
+        }
+    }
+
+    // Gas guard: cheap checks before paying for the metadata upload
+    let gas_price = client.gas_price().await?;
+    let cap = (config.max_gas_price_gwei * 1e9) as u128;
+    if gas_price > cap {
+        return Ok(format!(
+            "Skipped: gas price {:.3} gwei above cap {} gwei",
+            gas_price as f64 / 1e9,
+            config.max_gas_price_gwei
+        ));
+    }
+    let balance = client.eth_balance(&wallet.address).await?;
+    if balance == 0 {
+        return Ok("Skipped: wallet has no ETH for gas".into());
+    }
+
+    let card = agent_card(config, wallet, "");
+    let metadata_uri = upload_agent_card(config, &card).await?;
+    let parent = (!config.parent_address.is_empty()).then_some(config.parent_address.as_str());
+    let calldata = client.build_register_calldata(&config.name, &metadata_uri, parent)?;
+
+    let estimate = client.estimate_gas(&wallet.address, &calldata).await?;
+    let gas_limit = estimate + estimate * GAS_LIMIT_MARGIN / 100;
+    let cost = gas_limit as u128 * gas_price;
+    if balance < cost {
+        return Ok(format!(
+            "Skipped: registration needs ~{} wei of gas, wallet has {}",
+            cost, balance
+        ));
+    }
+
+    let SentTransaction { hash, nonce } = client
+        .send_transaction(wallet, calldata, gas_limit, gas_price)
+        .await?;
+    let pending = PendingRegistration {
+        hash: hash.clone(),
+        nonce: Some(nonce),
+        sent_at: Utc::now(),
+    };
+    let db = db.lock().await;
+    db.kv_set(PENDING_TX_KEY, &serde_json::to_string(&pending)?)?;
+    db.save_registry_entry(&agent_card(config, wallet, &metadata_uri))?;
+    info!("Submitted ERC-8004 registration {} ({})", hash, metadata_uri);
+
+    Ok(format!("Registration submitted: {}", hash))
+}
+
+fn agent_card(config: &AutomatonConfig, wallet: &Wallet, metadata_uri: &str) -> AgentCard {
+    AgentCard {
+        name: config.name.clone(),
+        wallet_address: wallet.address.to_string(),
+        metadata_uri: metadata_uri.to_string(),
+        parent_agent: (!config.parent_address.is_empty()).then(|| config.parent_address.clone()),
+        registered_at: Some(Utc::now()),
+    }
+}
+
+/// Publish the agent card through Conway, returning its URI.
+async fn upload_agent_card(config: &AutomatonConfig, card: &AgentCard) -> Result<String> {
+    let resp = crate::http::client()
+        .post(format!("{}/v1/metadata", config.conway_api_url.trim_end_matches('/')))
+        .bearer_auth(&config.conway_api_key)
+        .json(card)
+        .send()
+        .await
+        .context("Metadata upload failed")?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        bail!("Metadata upload failed ({}): {}", status, body);
+    }
+    let body: serde_json::Value = resp.json().await.context("Invalid metadata upload response")?;
+    body["uri"]
+        .as_str()
+        .map(str::to_string)
+        .context("Metadata upload returned no uri")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer, RecordedRequest};
+    use serde_json::json;
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000080da";
+
+    fn config(url: &str) -> AutomatonConfig {
+        AutomatonConfig {
+            name: "lemonade".into(),
+            conway_api_url: url.into(),
+            base_rpc_url: format!("{}/rpc", url),
+            registry_contract: CONTRACT.into(),
+            ..Default::default()
+        }
+    }
+
+    fn rpc(result: serde_json::Value) -> MockResponse {
+        MockResponse::json(200, json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+    }
+
+    fn methods(requests: &[RecordedRequest]) -> Vec<String> {
+        requests
+            .iter()
+            .filter(|r| r.path == "/rpc")
+            .map(|r| r.json()["method"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_already_registered_is_skipped() {
+        let server = MockServer::start(|req| match req.json()["method"].as_str() {
+            Some("eth_call") => rpc(json!(format!("0x{}", "0".repeat(63) + "1"))),
+            _ => MockResponse::json(500, json!({})),
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let wallet = Wallet::random().unwrap();
+
+        let uri = "https://meta.example/lemonade.json";
+        db.lock()
+            .await
+            .save_registry_entry(&agent_card(&config(&server.url()), &wallet, uri))
+            .unwrap();
+
+        let result = ensure_registered(&config(&server.url()), &db, &wallet).await.unwrap();
+        assert!(result.starts_with("Already registered"), "{}", result);
+        assert_eq!(methods(&server.requests()), ["eth_call"]);
+        // Re-running doesn't blank the recorded metadata URI
+        let stored = db.lock().await.registry_metadata_uri(&wallet.address.to_string()).unwrap();
+        assert_eq!(stored.as_deref(), Some(uri));
+    }
+
+    #[tokio::test]
+    async fn test_unregistered_agent_submits_registration() {
+        let server = MockServer::start(|req| {
+            if req.path == "/v1/metadata" {
+                return MockResponse::json(200, json!({ "uri": "https://meta.example/lemonade.json" }));
+            }
+            match req.json()["method"].as_str().unwrap_or_default() {
+                "eth_call" => rpc(json!("0x")),
+                "eth_gasPrice" => rpc(json!("0x5f5e100")), // 0.1 gwei
+                "eth_getBalance" => rpc(json!("0xde0b6b3a7640000")), // 1 ETH
+                "eth_estimateGas" => rpc(json!("0x186a0")),
+                "eth_chainId" => rpc(json!("0x2105")),
+                "eth_getTransactionCount" => rpc(json!("0x0")),
+                "eth_sendRawTransaction" => rpc(json!("0xfeed")),
+                "eth_getTransactionReceipt" => rpc(json!(null)),
+                _ => MockResponse::json(500, json!({})),
+            }
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let wallet = Wallet::random().unwrap();
+        let config = config(&server.url());
+
+        let result = ensure_registered(&config, &db, &wallet).await.unwrap();
+        assert_eq!(result, "Registration submitted: 0xfeed");
+        let pending = PendingRegistration::load(&*db.lock().await).unwrap().unwrap();
+        assert_eq!((pending.hash.as_str(), pending.nonce), ("0xfeed", Some(0)));
+
+        let requests = server.requests();
+        let upload = requests.iter().find(|r| r.path == "/v1/metadata").unwrap().json();
+        assert_eq!(upload["name"], "lemonade");
+        let sent = requests
+            .iter()
+            .find(|r| r.json()["method"] == "eth_sendRawTransaction")
+            .unwrap()
+            .json();
+        assert!(sent["params"][0].as_str().unwrap().starts_with("0xf9"));
+
+        // A second run waits for the pending transaction instead of resubmitting
+        let result = ensure_registered(&config, &db, &wallet).await.unwrap();
+        assert_eq!(result, "Registration 0xfeed still pending");
+        let sends = methods(&server.requests())
+            .iter()
+            .filter(|m| *m == "eth_sendRawTransaction")
+            .count();
+        assert_eq!(sends, 1);
+
+        // Gas guard: nothing is attempted while gas is too expensive
+        db.lock().await.kv_delete(PENDING_TX_KEY).unwrap();
+        let capped = AutomatonConfig {
+            max_gas_price_gwei: 0.05,
+            ..config
+        };
+        let result = ensure_registered(&capped, &db, &wallet).await.unwrap();
+        assert!(result.starts_with("Skipped: gas price"), "{}", result);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_registration_is_retried() {
+        // The account has one confirmed transaction; no receipt ever appears
+        let server = MockServer::start(|req| {
+            if req.path == "/v1/metadata" {
+                return MockResponse::json(200, json!({ "uri": "https://meta.example/lemonade.json" }));
+            }
+            match req.json()["method"].as_str().unwrap_or_default() {
+                "eth_call" => rpc(json!("0x")),
+                "eth_gasPrice" => rpc(json!("0x5f5e100")),
+                "eth_getBalance" => rpc(json!("0xde0b6b3a7640000")),
+                "eth_estimateGas" => rpc(json!("0x186a0")),
+                "eth_chainId" => rpc(json!("0x2105")),
+                "eth_getTransactionCount" => rpc(json!("0x1")),
+                "eth_sendRawTransaction" => rpc(json!("0xbeef")),
+                "eth_getTransactionReceipt" => rpc(json!(null)),
+                _ => MockResponse::json(500, json!({})),
+            }
+        })
+        .await;
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let wallet = Wallet::random().unwrap();
+        let config = config(&server.url());
+        let pending_json = |nonce: u64, sent_at: DateTime<Utc>| {
+            let pending = PendingRegistration { hash: "0xfeed".into(), nonce: Some(nonce), sent_at };
+            serde_json::to_string(&pending).unwrap()
+        };
+
+        // Recent, and its nonce is still free: keep waiting
+        db.lock().await.kv_set(PENDING_TX_KEY, &pending_json(1, Utc::now())).unwrap();
+        let result = ensure_registered(&config, &db, &wallet).await.unwrap();
+        assert_eq!(result, "Registration 0xfeed still pending");
+
+        // Its nonce was used by another transaction
+        db.lock().await.kv_set(PENDING_TX_KEY, &pending_json(0, Utc::now())).unwrap();
+        let result = ensure_registered(&config, &db, &wallet).await.unwrap();
+        assert_eq!(result, "Registration submitted: 0xbeef");
+
+        // Waited past the timeout
+        let stale = Utc::now() - Duration::minutes(PENDING_TX_TIMEOUT_MINS + 1);
+        db.lock().await.kv_set(PENDING_TX_KEY, &pending_json(1, stale)).unwrap();
+        let result = ensure_registered(&config, &db, &wallet).await.unwrap();
+        assert_eq!(result, "Registration submitted: 0xbeef");
+        let pending = PendingRegistration::load(&*db.lock().await).unwrap().unwrap();
+        assert_eq!((pending.hash.as_str(), pending.nonce), ("0xbeef", Some(1)));
+    }
+}
@@ -2,68 +2,76 @@
 //!
 //! Registers the automaton as an NFT with metadata URI for discovery.
 
-use crate::types::AgentCard;
+use crate::identity::{LegacyTransaction, Wallet};
+use crate::registry::rpc::{RpcClient, RpcRejection};
+use crate::types::{Address, AgentCard};
 use anyhow::{Context, Result};
 use sha3::{Digest, Keccak256};
+use tracing::warn;
+
+/// A submitted transaction: its hash and the nonce it was signed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentTransaction {
+    pub hash: String,
+    pub nonce: u64,
+}
 
 /// Client for ERC-8004 registry interactions.
 pub struct RegistryClient {
-    rpc_url: String,
-    contract_address: String,
-    http: reqwest::Client,
+    rpc: RpcClient,
+    contract_address: Address,
 }
 
 impl RegistryClient {
-    pub fn new(rpc_url: &str, contract_address: &str) -> Self {
+    pub fn new(rpc_url: &str, contract_address: Address) -> Self {
         Self {
-            rpc_url: rpc_url.to_string(),
-            contract_address: contract_address.to_string(),
-            http: reqwest::Client::new(),
+            rpc: RpcClient::new(rpc_url),
+            contract_address,
         }
     }
 
-    /// Register the agent on-chain (sends a transaction via eth_sendRawTransaction).
+    /// ABI-encoded calldata for `register(string,string,address)`.
     ///
-    /// Note: Full transaction signing requires alloy or ethers-like functionality.
-    /// This is a stub that constructs the correct calldata.
+    /// A missing parent is encoded as the zero address.
     pub fn build_register_calldata(
         &self,
-        _name: &str,
-        _metadata_uri: &str,
-        _parent_agent: Option<&str>,
-    ) -> Vec<u8> {
-        // Function selector: register(string,string,address)
-        let selector = &Keccak256::digest(b"register(string,string,address)")[..4];
-
-        // For now, return the selector — full ABI encoding requires more infrastructure
-        selector.to_vec()
+        name: &str,
+        metadata_uri: &str,
+        parent_agent: Option<&str>,
+    ) -> Result<Vec<u8>> {
+        let parent = match parent_agent {
+            Some(addr) => Address::parse(addr).context("Invalid parent agent address")?,
+            None => Address::from_bytes([0; 20]),
+        };
+
+        let name_tail = abi_encode_string(name);
+        let mut data = Keccak256::digest(b"register(string,string,address)")[..4].to_vec();
+        // Head: two offsets into the tail, then the static address
+        data.extend(abi_word(3 * 32));
+        data.extend(abi_word((3 * 32 + name_tail.len()) as u128));
+        data.extend([0u8; 12]);
+        data.extend(parent.as_bytes());
+        data.extend(name_tail);
+        data.extend(abi_encode_string(metadata_uri));
+        Ok(data)
     }
 
     /// Look up an agent by wallet address.
-    pub async fn lookup(&self, wallet_address: &str) -> Result<Option<AgentCard>> {
+    pub async fn lookup(&self, wallet_address: &Address) -> Result<Option<AgentCard>> {
         // Build calldata for agentOf(address)
         let selector = &Keccak256::digest(b"agentOf(address)")[..4];
-        let addr = wallet_address
-            .strip_prefix("0x")
-            .unwrap_or(wallet_address);
-        let padded_addr = format!("000000000000000000000000{}", addr);
+        let padded_addr = format!("000000000000000000000000{}", hex::encode(wallet_address.as_bytes()));
         let data = format!("0x{}{}", hex::encode(selector), padded_addr);
 
-        let resp = self
-            .http
-            .post(&self.rpc_url)
-            .json(&serde_json::json!({
-                "jsonrpc": "2.0",
-                "method": "eth_call",
-                "params": [{"to": &self.contract_address, "data": data}, "latest"],
-                "id": 1
-            }))
-            .send()
+        let result = self
+            .rpc
+            .call(
+                "eth_call",
+                serde_json::json!([{"to": self.contract_address, "data": data}, "latest"]),
+            )
             .await
             .context("Registry lookup failed")?;
-
-        let body: serde_json::Value = resp.json().await?;
-        let result = body["result"].as_str().unwrap_or("0x");
+        let result = result.as_str().context("Registry lookup returned a non-string result")?;
 
         // Empty result means not registered
         if result == "0x" || result.len() < 66 {
@@ -80,32 +88,135 @@ impl RegistryClient {
         }))
     }
 
+    async fn rpc_quantity(&self, method: &str, params: serde_json::Value) -> Result<u128> {
+        self.rpc.quantity(method, params).await
+    }
+
+    /// ETH balance of `address` in wei.
+    pub async fn eth_balance(&self, address: &Address) -> Result<u128> {
+        self.rpc_quantity("eth_getBalance", serde_json::json!([address, "latest"]))
+            .await
+    }
+
+    /// Current gas price in wei.
+    pub async fn gas_price(&self) -> Result<u128> {
+        self.rpc_quantity("eth_gasPrice", serde_json::json!([])).await
+    }
+
+    /// Gas needed for `from` to call the registry with `data`.
+    pub async fn estimate_gas(&self, from: &Address, data: &[u8]) -> Result<u64> {
+        let gas = self
+            .rpc_quantity(
+                "eth_estimateGas",
+                serde_json::json!([{
+                    "from": from,
+                    "to": self.contract_address,
+                    "data": format!("0x{}", hex::encode(data)),
+                }]),
+            )
+            .await?;
+        Ok(gas as u64)
+    }
+
+    /// Number of `address`'s transactions included on-chain, i.e. the
+    /// nonce its next confirmed transaction will have.
+    pub async fn confirmed_nonce(&self, address: &Address) -> Result<u64> {
+        let nonce = self
+            .rpc_quantity("eth_getTransactionCount", serde_json::json!([address, "latest"]))
+            .await?;
+        Ok(nonce as u64)
+    }
+
+    /// Sign and submit a call to the registry.
+    pub async fn send_transaction(
+        &self,
+        wallet: &Wallet,
+        data: Vec<u8>,
+        gas_limit: u64,
+        gas_price: u128,
+    ) -> Result<SentTransaction> {
+        let chain_id = self.rpc_quantity("eth_chainId", serde_json::json!([])).await? as u64;
+        let nonce = self
+            .rpc_quantity(
+                "eth_getTransactionCount",
+                serde_json::json!([wallet.address, "pending"]),
+            )
+            .await? as u64;
+
+        let raw = LegacyTransaction {
+            nonce,
+            gas_price,
+            gas_limit,
+            to: self.contract_address,
+            value: 0,
+            data,
+            chain_id,
+        }
+        .sign(wallet)?;
+
+        // Sent once: a retry after a lost response is rejected as already
+        // known (or its nonce as too low) although the first one went out.
+        // When the outcome is unclear the locally computed hash is tracked,
+        // and the caller finds out from its receipt or its nonce.
+        let local_hash = format!("0x{}", hex::encode(Keccak256::digest(&raw)));
+        let sent = self
+            .rpc
+            .call_once(
+                "eth_sendRawTransaction",
+                serde_json::json!([format!("0x{}", hex::encode(&raw))]),
+            )
+            .await;
+        let hash = match sent {
+            Ok(hash) => hash
+                .as_str()
+                .map(str::to_string)
+                .context("eth_sendRawTransaction returned no hash")?,
+            Err(e) => match e.downcast_ref::<RpcRejection>() {
+                Some(rejection) if rejection.error.to_string().contains("already known") => local_hash,
+                Some(_) => return Err(e),
+                None => {
+                    warn!("Transaction {} may not have been sent: {:#}", local_hash, e);
+                    local_hash
+                }
+            },
+        };
+        Ok(SentTransaction { hash, nonce })
+    }
+
+    /// Whether a transaction succeeded; `None` while it is still pending.
+    pub async fn transaction_status(&self, hash: &str) -> Result<Option<bool>> {
+        let receipt = self
+            .rpc
+            .call_nullable("eth_getTransactionReceipt", serde_json::json!([hash]))
+            .await?;
+        if receipt.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(receipt["status"].as_str() == Some("0x1")))
+    }
+
     /// Discover agents by querying recent registration events.
     pub async fn discover_agents(&self, limit: usize) -> Result<Vec<AgentCard>> {
         // Build filter for AgentRegistered events
         let event_sig = Keccak256::digest(b"AgentRegistered(address,string,string,address)");
         let topic = format!("0x{}", hex::encode(event_sig));
 
-        let resp = self
-            .http
-            .post(&self.rpc_url)
-            .json(&serde_json::json!({
-                "jsonrpc": "2.0",
-                "method": "eth_getLogs",
-                "params": [{
-                    "address": &self.contract_address,
+        let result = self
+            .rpc
+            .call(
+                "eth_getLogs",
+                serde_json::json!([{
+                    "address": self.contract_address,
                     "topics": [topic],
                     "fromBlock": "earliest",
                     "toBlock": "latest"
-                }],
-                "id": 1
-            }))
-            .send()
+                }]),
+            )
             .await
             .context("Agent discovery failed")?;
-
-        let body: serde_json::Value = resp.json().await?;
-        let logs = body["result"].as_array().unwrap_or(&Vec::new()).clone();
+        let logs = result
+            .as_array()
+            .context("eth_getLogs returned a non-array result")?;
 
         let agents: Vec<AgentCard> = logs
             .iter()
@@ -116,11 +227,11 @@ impl RegistryClient {
                     return None;
                 }
                 let addr_topic = topics[1].as_str()?;
-                let addr = format!("0x{}", &addr_topic[26..]);
+                let addr = Address::parse(&format!("0x{}", addr_topic.get(26..)?)).ok()?;
 
                 Some(AgentCard {
                     name: String::new(),
-                    wallet_address: addr,
+                    wallet_address: addr.to_string(),
                     metadata_uri: String::new(),
                     parent_agent: None,
                     registered_at: None,
@@ -131,3 +242,68 @@ impl RegistryClient {
         Ok(agents)
     }
 }
+
+/// A uint256 ABI word.
+fn abi_word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Tail encoding of a dynamic `string`: length, then zero-padded bytes.
+fn abi_encode_string(s: &str) -> Vec<u8> {
+    let mut out = abi_word(s.len() as u128).to_vec();
+    out.extend_from_slice(s.as_bytes());
+    out.resize(32 + s.len().div_ceil(32) * 32, 0);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_unclear_send_tracks_the_local_hash() {
+        let send_reply = std::sync::Arc::new(std::sync::Mutex::new(MockResponse::json(500, json!({}))));
+        let reply = send_reply.clone();
+        let server = MockServer::start(move |req| {
+            let result = match req.json()["method"].as_str().unwrap_or_default() {
+                "eth_chainId" => json!("0x2105"),
+                "eth_getTransactionCount" => json!("0x7"),
+                _ => return reply.lock().unwrap().clone(),
+            };
+            MockResponse::json(200, json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+        })
+        .await;
+        let client = RegistryClient::new(&server.url(), Address::from_bytes([0x80; 20]));
+        let wallet = Wallet::random().unwrap();
+        let local_hash = |server: &MockServer| {
+            let sent = server.requests().last().unwrap().json();
+            let raw = hex::decode(sent["params"][0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+            format!("0x{}", hex::encode(Keccak256::digest(raw)))
+        };
+
+        // Lost response: sent once, then tracked by its own hash
+        let sent = client.send_transaction(&wallet, vec![1], 100_000, 1).await.unwrap();
+        assert_eq!(sent, SentTransaction { hash: local_hash(&server), nonce: 7 });
+        assert_eq!(server.requests().len(), 3);
+
+        // A node that already has it
+        *send_reply.lock().unwrap() = MockResponse::json(
+            200,
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "already known" } }),
+        );
+        let sent = client.send_transaction(&wallet, vec![1], 100_000, 1).await.unwrap();
+        assert_eq!(sent.hash, local_hash(&server));
+
+        // A real rejection is still an error
+        *send_reply.lock().unwrap() = MockResponse::json(
+            200,
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "insufficient funds" } }),
+        );
+        let err = client.send_transaction(&wallet, vec![1], 100_000, 1).await.unwrap_err();
+        assert!(err.to_string().contains("insufficient funds"), "{}", err);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod auto_register;
 pub mod erc8004;
+pub mod rpc;
 
-pub use erc8004::RegistryClient;
+pub use erc8004::{RegistryClient, SentTransaction};
+pub use rpc::{RpcClient, RpcRejection};
@@ -0,0 +1,213 @@
+//! JSON-RPC client for the Base chain.
+//!
+//! Every call to `base_rpc_url` goes through [`RpcClient`], which retries
+//! transport failures and transient HTTP errors a bounded number of times
+//! and treats a missing or `null` `result` as an error. A hiccup must never
+//! read as a zero balance. Calls that mustn't be repeated (submitting a
+//! transaction) use [`RpcClient::call_once`].
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::debug;
+
+/// Attempts per call, including the first.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled for each one after.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// An `error` the node answered a call with, as opposed to a failure to
+/// reach it.
+#[derive(Debug, Clone)]
+pub struct RpcRejection {
+    pub method: String,
+    pub error: Value,
+}
+
+impl std::fmt::Display for RpcRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed: {}", self.method, self.error)
+    }
+}
+
+impl std::error::Error for RpcRejection {}
+
+/// Base chain JSON-RPC client.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    url: String,
+    http: reqwest::Client,
+    retry_delay: Duration,
+    timeout: Option<Duration>,
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            http: crate::http::client(),
+            retry_delay: RETRY_DELAY,
+            timeout: None,
+        }
+    }
+
+    /// Give up on each attempt after `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    #[cfg(test)]
+    fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Call `method`, returning its (non-null) `result`.
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let result = self.call_nullable(method, params).await?;
+        if result.is_null() {
+            bail!("{} returned no result", method);
+        }
+        Ok(result)
+    }
+
+    /// Call `method` where a `null` result is meaningful (e.g. a receipt
+    /// for a pending transaction). A missing `result` is still an error.
+    pub async fn call_nullable(&self, method: &str, params: Value) -> Result<Value> {
+        let request = request(method, params);
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+        let mut body = loop {
+            match self.send(&request).await {
+                Ok(body) => break body,
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    debug!("{} attempt {} failed, retrying: {:#}", method, attempt, e);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.context(format!("{} failed after {} attempts", method, attempt))),
+            }
+        };
+        take_result(method, &mut body)
+    }
+
+    /// Call `method` exactly once, for requests a retry could duplicate.
+    /// The node's own errors are [`RpcRejection`]s; anything else means
+    /// the request may or may not have arrived.
+    pub async fn call_once(&self, method: &str, params: Value) -> Result<Value> {
+        let mut body = self.send(&request(method, params)).await?;
+        let result = take_result(method, &mut body)?;
+        if result.is_null() {
+            bail!("{} returned no result", method);
+        }
+        Ok(result)
+    }
+
+    /// Call a method whose result is a hex quantity.
+    pub async fn quantity(&self, method: &str, params: Value) -> Result<u128> {
+        let result = self.call(method, params).await?;
+        let hex = result
+            .as_str()
+            .with_context(|| format!("{} returned no quantity", method))?;
+        u128::from_str_radix(hex.trim_start_matches("0x"), 16)
+            .with_context(|| format!("{} returned invalid quantity: {}", method, hex))
+    }
+
+    /// One attempt; errors are the retryable kind (transport, 429, 5xx,
+    /// unparseable body).
+    async fn send(&self, request: &Value) -> Result<Value> {
+        let mut builder = self.http.post(&self.url).json(request);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let resp = builder.send().await.context("RPC request failed")?;
+
+        let status = resp.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            bail!("RPC returned {}", status);
+        }
+        resp.json().await.context("RPC returned invalid JSON")
+    }
+}
+
+fn request(method: &str, params: Value) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    })
+}
+
+/// The `result` of a response body, or its `error` as an [`RpcRejection`].
+fn take_result(method: &str, body: &mut Value) -> Result<Value> {
+    if let Some(error) = body.get("error") {
+        return Err(RpcRejection {
+            method: method.to_string(),
+            error: error.clone(),
+        }
+        .into());
+    }
+    body.get_mut("result")
+        .map(Value::take)
+        .ok_or_else(|| anyhow!("{} response has no result", method))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{unreachable_url, MockResponse, MockServer};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn rpc(result: Value) -> MockResponse {
+        MockResponse::json(200, json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let server = MockServer::start(move |_| match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => MockResponse::json(503, json!({})),
+            1 => MockResponse::text(200, "<html>bad gateway</html>"),
+            _ => rpc(json!("0x2a")),
+        })
+        .await;
+        let client = RpcClient::new(&server.url()).with_retry_delay(Duration::ZERO);
+
+        assert_eq!(client.quantity("eth_getBalance", json!([])).await.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let dead = RpcClient::new(&unreachable_url().await).with_retry_delay(Duration::ZERO);
+        let err = dead.call("eth_blockNumber", json!([])).await.unwrap_err();
+        assert!(err.to_string().contains("after 3 attempts"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn test_null_or_missing_result_is_an_error() {
+        let server = MockServer::start(|req| match req.json()["method"].as_str() {
+            Some("eth_call") => rpc(Value::Null),
+            Some("eth_getBalance") => MockResponse::json(200, json!({ "jsonrpc": "2.0", "id": 1 })),
+            _ => MockResponse::json(
+                200,
+                json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "boom" } }),
+            ),
+        })
+        .await;
+        let client = RpcClient::new(&server.url()).with_retry_delay(Duration::ZERO);
+
+        let err = client.quantity("eth_call", json!([])).await.unwrap_err();
+        assert!(err.to_string().contains("eth_call returned no result"), "{}", err);
+        assert!(client.call_nullable("eth_call", json!([])).await.unwrap().is_null());
+        assert!(client.call_nullable("eth_getBalance", json!([])).await.is_err());
+        let err = client.call("eth_gasPrice", json!([])).await.unwrap_err();
+        assert!(err.to_string().contains("boom"), "{}", err);
+        // Only transport-level failures are retried
+        assert_eq!(server.requests().len(), 4);
+    }
+}
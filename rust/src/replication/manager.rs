@@ -0,0 +1,626 @@
+//! Self-replication — the full lifecycle of child automatons.
+//!
+//! Spawning:
+//! 1. Check the generation depth and the child limits (live children, and
+//!    all children ever spawned)
+//! 2. Create a new Conway sandbox from the chosen (or the parent's own)
+//!    image (child recorded as `spawning`)
+//! 3. Generate the child's wallet
+//! 4. Write its genesis config, signed genesis bundle, wallet and the
+//!    (immutable) constitution
+//! 5. Install and start the runtime, then mark it `active`
+//! 6. Fund it with initial credits
+//!
+//! Sandbox creation is bracketed by a pending-operation record so that a
+//! crash before the child is recorded can be reconciled at startup (see
+//! [`crate::replication::recovery`]).
+//!
+//! Spawns go through the process-wide [`SpawnQueue`], which caps how many
+//! run at once and spaces them out; the child limit is checked once a spawn
+//! leaves the queue.
+//!
+//! A spawn that fails before funding has its sandbox deleted and is marked
+//! `terminated`. Funding comes last because the child's wallet key lives
+//! only in its sandbox: once credits may have been sent, the sandbox is
+//! never deleted automatically. `automaton verify-child` marks a
+//! child `verified` or `suspect` (see [`crate::replication::verify`]).
+//! Children move between states only as [`ChildState::can_transition_to`]
+//! allows.
+
+use crate::config::AutomatonConfig;
+use crate::constitution::{CONSTITUTION, CONSTITUTION_FILE};
+use crate::conway::ConwayClient;
+use crate::identity::Wallet;
+use crate::replication::genesis::{sign_genesis, GENESIS_FILE};
+use crate::replication::queue::{SpawnQueue, SpawnSlot, SPAWN_QUEUE_DEPTH_KEY};
+use crate::replication::recovery::SPAWN_CHILD_OP;
+use crate::setup::screening::{screen_genesis, ScreenVerdict};
+use crate::state::Database;
+use crate::types::{ChildRecord, ChildState, GenesisConfig};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Hard cap on children per parent, whatever the config says.
+const MAX_CHILDREN: u32 = 3;
+
+/// KV counter of spawns refused for exceeding `max_generation`.
+const GENERATION_REFUSALS_KEY: &str = "spawn_generation_refusals";
+
+/// KV key holding the most recent generation refusal.
+const LAST_GENERATION_REFUSAL_KEY: &str = "last_spawn_generation_refusal";
+
+/// Home directory of the runtime inside a child sandbox.
+const CHILD_HOME: &str = "/root/.automaton";
+
+/// Installs the runtime in the child sandbox and starts its daemon.
+const INSTALL_AND_START: &str = "curl -fsSL https://conway.tech/automaton.sh | sh && \
+     nohup automaton daemon > /root/.automaton/daemon.log 2>&1 &";
+
+/// Timeout for the install step.
+const INSTALL_TIMEOUT_MS: u64 = 300_000;
+
+/// Owns spawning, tracking and terminating children.
+pub struct ReplicationManager {
+    config: AutomatonConfig,
+    conway: ConwayClient,
+    db: Arc<Mutex<Database>>,
+    /// The parent's wallet, used to sign genesis bundles.
+    wallet: Wallet,
+    queue: Arc<SpawnQueue>,
+}
+
+impl ReplicationManager {
+    pub fn new(
+        config: AutomatonConfig,
+        conway: ConwayClient,
+        db: Arc<Mutex<Database>>,
+        wallet: Wallet,
+    ) -> Self {
+        Self {
+            queue: SpawnQueue::shared(&config),
+            config,
+            conway,
+            db,
+            wallet,
+        }
+    }
+
+    /// Use `queue` instead of the process-wide spawn queue.
+    pub fn with_spawn_queue(mut self, queue: Arc<SpawnQueue>) -> Self {
+        self.queue = queue;
+        self
+    }
+
+    /// Spawn a child from `genesis`, returning its record once active.
+    pub async fn spawn(&self, mut genesis: GenesisConfig) -> Result<ChildRecord> {
+        if self.config.generation >= self.config.max_generation {
+            let reason = format!(
+                "Refusing to spawn '{}': this agent is generation {} and max_generation is {}",
+                genesis.name, self.config.generation, self.config.max_generation
+            );
+            warn!("{}", reason);
+            let db = self.db.lock().await;
+            db.kv_incr(GENERATION_REFUSALS_KEY, 1.0)?;
+            db.kv_set(LAST_GENERATION_REFUSAL_KEY, &reason)?;
+            bail!(reason);
+        }
+        // A child is always exactly one generation below its parent
+        genesis.generation = self.config.generation + 1;
+
+        // No one to confirm here, so only clear violations are stopped
+        if let ScreenVerdict::Refuse(found) = screen_genesis(&genesis.genesis_prompt, &self.config) {
+            bail!("Refusing to spawn: genesis prompt has prohibited intent ({})", found.join(", "));
+        }
+
+        let bundle = sign_genesis(&self.wallet, genesis)?;
+        let bundle_json = serde_json::to_string_pretty(&bundle)?;
+        let genesis = &bundle.genesis;
+
+        let _slot = self.queued().await?;
+        let limit = self.config.max_children.min(MAX_CHILDREN);
+        let (current, total) = {
+            let db = self.db.lock().await;
+            (db.live_children_count()?, db.total_children_count()?)
+        };
+        if current >= limit {
+            bail!("Child limit reached ({}/{}). Cannot spawn more.", current, limit);
+        }
+        let lifetime_limit = self.config.max_lifetime_children;
+        if lifetime_limit > 0 && total >= lifetime_limit {
+            bail!(
+                "Lifetime child limit reached ({} spawned, max_lifetime_children is {}). Cannot spawn more.",
+                total,
+                lifetime_limit
+            );
+        }
+
+        info!("Spawning child '{}' ...", genesis.name);
+        let pending = self
+            .db
+            .lock()
+            .await
+            .begin_pending_operation(SPAWN_CHILD_OP, &genesis.name)?;
+        let template = self.child_template(genesis).await;
+        // On failure the record stays behind: the request may still have
+        // created a sandbox, which recovery will look up by name.
+        let sandbox_id = self
+            .conway
+            .create_sandbox(&genesis.name, template.as_deref())
+            .await?;
+        self.db.lock().await.set_pending_sandbox(&pending, &sandbox_id)?;
+
+        let mut child = ChildRecord {
+            id: ulid::Ulid::new().to_string(),
+            name: genesis.name.clone(),
+            sandbox_id,
+            wallet_address: String::new(),
+            created_at: Utc::now(),
+            status: ChildState::Spawning,
+        };
+        {
+            let db = self.db.lock().await;
+            db.add_child(&child)?;
+            db.finish_pending_operation(&pending)?;
+        }
+
+        match self.provision(&mut child, genesis, &bundle_json).await {
+            Ok(()) => {
+                self.transition(&mut child, ChildState::Active).await?;
+                info!(
+                    "Child '{}' active (sandbox: {}, wallet: {})",
+                    child.name, child.sandbox_id, child.wallet_address
+                );
+            }
+            Err(e) => {
+                warn!("Spawning child '{}' failed: {:#}", child.name, e);
+                // Don't keep paying for a half-provisioned sandbox
+                if let Err(delete_err) = self.conway.delete_sandbox(&child.sandbox_id).await {
+                    warn!(
+                        "Failed to delete sandbox {} of failed child '{}': {:#}",
+                        child.sandbox_id, child.name, delete_err
+                    );
+                }
+                self.transition(&mut child, ChildState::Terminated).await?;
+                return Err(e);
+            }
+        }
+
+        // A failed or lost transfer may still have funded the wallet, so
+        // the sandbox holding its key is kept
+        if genesis.initial_credits > 0.0 {
+            self.conway
+                .transfer_credits(&child.wallet_address, genesis.initial_credits)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Child '{}' is running (sandbox {}) but funding it failed",
+                        child.name, child.sandbox_id
+                    )
+                })?;
+            self.db.lock().await.record_transaction(
+                "credit_transfer",
+                -genesis.initial_credits,
+                "credits",
+                &format!("Funded child '{}' ({})", child.name, child.wallet_address),
+                None,
+            )?;
+            info!("Funded child '{}' with {} credits", child.name, genesis.initial_credits);
+        }
+        Ok(child)
+    }
+
+    /// Terminate a child: delete its sandbox and mark it `terminated`.
+    pub async fn terminate(&self, child_id: &str) -> Result<ChildRecord> {
+        let mut child = self.child(child_id).await?;
+        if !child.status.can_transition_to(ChildState::Terminated) {
+            bail!("Child '{}' is already {}", child.name, child.status);
+        }
+
+        self.conway.delete_sandbox(&child.sandbox_id).await?;
+        self.transition(&mut child, ChildState::Terminated).await?;
+        info!("Terminated child '{}' (sandbox: {})", child.name, child.sandbox_id);
+        Ok(child)
+    }
+
+    /// Record that a child has stopped responding (or come back).
+    ///
+    /// Coming back keeps a `verified` or `suspect` verdict.
+    pub async fn set_reachable(&self, child_id: &str, reachable: bool) -> Result<ChildRecord> {
+        let mut child = self.child(child_id).await?;
+        let next = if reachable {
+            ChildState::Active
+        } else {
+            ChildState::Unreachable
+        };
+        let keep_verdict =
+            reachable && matches!(child.status, ChildState::Verified | ChildState::Suspect);
+        if child.status != next && !keep_verdict {
+            self.transition(&mut child, next).await?;
+        }
+        Ok(child)
+    }
+
+    /// Image for the child's sandbox: the genesis's choice, else the
+    /// configured default, else the parent's own (if Conway reports it).
+    async fn child_template(&self, genesis: &GenesisConfig) -> Option<String> {
+        if let Some(template) = genesis.template.as_ref().filter(|t| !t.is_empty()) {
+            return Some(template.clone());
+        }
+        if !self.config.child_sandbox_template.is_empty() {
+            return Some(self.config.child_sandbox_template.clone());
+        }
+        match self.conway.sandbox_template().await {
+            Ok(template) => template,
+            Err(e) => {
+                warn!("Could not look up this sandbox's template; child gets Conway's default: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Wallet, files and runtime for a freshly created sandbox.
+    async fn provision(
+        &self,
+        child: &mut ChildRecord,
+        genesis: &GenesisConfig,
+        bundle_json: &str,
+    ) -> Result<()> {
+        let sandbox = self.conway.for_sandbox(&child.sandbox_id);
+
+        let wallet = Wallet::random()?;
+        child.wallet_address = wallet.address.to_string();
+        self.db.lock().await.update_child(child)?;
+
+        let child_config = AutomatonConfig {
+            name: genesis.name.clone(),
+            genesis_prompt: genesis.genesis_prompt.clone(),
+            creator_address: self.config.creator_address.clone(),
+            sandbox_id: child.sandbox_id.clone(),
+            conway_api_url: self.config.conway_api_url.clone(),
+            conway_api_key: String::new(), // Child provisions its own key
+            wallet_address: child.wallet_address.clone(),
+            parent_address: genesis.parent_address.clone(),
+            generation: genesis.generation,
+            max_generation: self.config.max_generation,
+            ..AutomatonConfig::default()
+        };
+
+        let files = [
+            ("automaton.toml", toml::to_string_pretty(&child_config)?),
+            (GENESIS_FILE, bundle_json.to_string()),
+            ("wallet.json", wallet.to_file_json()?),
+        ];
+        for (name, content) in &files {
+            sandbox
+                .write_file(&format!("{}/{}", CHILD_HOME, name), content)
+                .await
+                .with_context(|| format!("Failed to write {} to child", name))?;
+        }
+
+        // The constitution is immutable: children get the canonical text,
+        // never a possibly altered copy from our home directory
+        sandbox
+            .write_file(&format!("{}/{}", CHILD_HOME, CONSTITUTION_FILE), CONSTITUTION)
+            .await
+            .context("Failed to write constitution to child")?;
+
+        let resp = sandbox
+            .exec(INSTALL_AND_START, Some(INSTALL_TIMEOUT_MS), None)
+            .await?;
+        if resp.exit_code != 0 {
+            bail!("Child runtime install failed ({}): {}", resp.exit_code, resp.stderr);
+        }
+        Ok(())
+    }
+
+    /// Wait in the spawn queue, keeping its depth in the KV store.
+    async fn queued(&self) -> Result<SpawnSlot<'_>> {
+        let slot = self.queue.acquire();
+        self.record_queue_depth().await?;
+        let slot = slot.await;
+        self.record_queue_depth().await?;
+        Ok(slot)
+    }
+
+    async fn record_queue_depth(&self) -> Result<()> {
+        let depth = self.queue.depth();
+        self.db.lock().await.kv_set(SPAWN_QUEUE_DEPTH_KEY, &depth.to_string())
+    }
+
+    async fn child(&self, child_id: &str) -> Result<ChildRecord> {
+        self.db
+            .lock()
+            .await
+            .get_child(child_id)?
+            .with_context(|| format!("Unknown child: {}", child_id))
+    }
+
+    /// Move `child` to `next`, persisting the change.
+    async fn transition(&self, child: &mut ChildRecord, next: ChildState) -> Result<()> {
+        if !child.status.can_transition_to(next) {
+            bail!("Child '{}' cannot go from {} to {}", child.name, child.status, next);
+        }
+        child.status = next;
+        self.db.lock().await.update_child(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    fn genesis(manager: &ReplicationManager, name: &str) -> GenesisConfig {
+        GenesisConfig {
+            name: name.into(),
+            genesis_prompt: "Sell lemonade".into(),
+            parent_address: manager.wallet.address.to_string(),
+            parent_sandbox_id: "sb-parent".into(),
+            initial_credits: 1.5,
+            generation: 0,
+            template: None,
+        }
+    }
+
+    fn manager(url: &str, max_children: u32) -> ReplicationManager {
+        manager_at_generation(url, max_children, 0)
+    }
+
+    fn manager_at_generation(url: &str, max_children: u32, generation: u32) -> ReplicationManager {
+        let config = AutomatonConfig {
+            conway_api_url: url.into(),
+            max_children,
+            generation,
+            max_generation: 2,
+            ..Default::default()
+        };
+        ReplicationManager::new(
+            config,
+            ConwayClient::new(url, "key", "sb-parent"),
+            Arc::new(Mutex::new(Database::open_memory().unwrap())),
+            Wallet::random().unwrap(),
+        )
+        .with_spawn_queue(Arc::new(SpawnQueue::new(1, std::time::Duration::ZERO)))
+    }
+
+    #[tokio::test]
+    async fn test_spawn_is_refused_at_child_limit() {
+        let server = MockServer::start(|_| MockResponse::json(500, json!({}))).await;
+        let manager = manager(&server.url(), 1);
+        manager
+            .db
+            .lock()
+            .await
+            .add_child(&ChildRecord {
+                id: "c1".into(),
+                name: "first".into(),
+                sandbox_id: "sb-1".into(),
+                wallet_address: String::new(),
+                created_at: Utc::now(),
+                status: ChildState::Active,
+            })
+            .unwrap();
+
+        let err = manager.spawn(genesis(&manager, "second")).await.unwrap_err();
+        assert!(err.to_string().contains("Child limit reached"), "{}", err);
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lifetime_limit_counts_terminated_children() {
+        let server = MockServer::start(|_| MockResponse::json(500, json!({}))).await;
+        let mut manager = manager(&server.url(), 1);
+        manager.config.max_lifetime_children = 3;
+        for i in 0..2 {
+            manager
+                .db
+                .lock()
+                .await
+                .add_child(&ChildRecord {
+                    id: format!("c{}", i),
+                    name: format!("old{}", i),
+                    sandbox_id: format!("sb-{}", i),
+                    wallet_address: String::new(),
+                    created_at: Utc::now(),
+                    status: ChildState::Terminated,
+                })
+                .unwrap();
+        }
+
+        // No live children and 2 of 3 lifetime spawns used: the spawn gets
+        // past the limits (and fails at Conway)
+        let err = manager.spawn(genesis(&manager, "third")).await.unwrap_err();
+        assert!(err.to_string().contains("create_sandbox"), "{}", err);
+
+        let requests = server.requests().len();
+        manager.config.max_lifetime_children = 2;
+        let err = manager.spawn(genesis(&manager, "fourth")).await.unwrap_err();
+        assert!(err.to_string().contains("Lifetime child limit reached"), "{}", err);
+        assert_eq!(server.requests().len(), requests);
+        assert_eq!(manager.db.lock().await.total_children_count().unwrap(), 2);
+        assert_eq!(manager.db.lock().await.live_children_count().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_is_refused_at_max_generation() {
+        let server = MockServer::start(|_| MockResponse::json(500, json!({}))).await;
+        let manager = manager_at_generation(&server.url(), 3, 2);
+
+        let err = manager.spawn(genesis(&manager, "grandkid")).await.unwrap_err();
+        assert!(err.to_string().contains("generation 2"), "{}", err);
+        assert!(server.requests().is_empty());
+        let db = manager.db.lock().await;
+        assert_eq!(db.kv_get_f64(GENERATION_REFUSALS_KEY).unwrap(), Some(1.0));
+        assert!(db.kv_get(LAST_GENERATION_REFUSAL_KEY).unwrap().unwrap().contains("grandkid"));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_below_max_generation_passes_generation_on() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/sandboxes" => MockResponse::json(200, json!({ "sandbox_id": "sb-child" })),
+            p if p.ends_with("/exec") => {
+                MockResponse::json(200, json!({ "stdout": "", "stderr": "", "exit_code": 0 }))
+            }
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let manager = manager_at_generation(&server.url(), 3, 1);
+
+        manager.spawn(genesis(&manager, "grandkid")).await.unwrap();
+        let written = |name: &str| {
+            server
+                .requests()
+                .iter()
+                .map(|r| r.json())
+                .find(|body| body["path"] == format!("/root/.automaton/{}", name))
+                .unwrap()["content"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let bundle: crate::replication::genesis::SignedGenesis =
+            serde_json::from_str(&written(GENESIS_FILE)).unwrap();
+        assert_eq!(bundle.genesis.generation, 2);
+        let config: AutomatonConfig = toml::from_str(&written("automaton.toml")).unwrap();
+        assert_eq!((config.generation, config.max_generation), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_passes_sandbox_template() {
+        let server = MockServer::start(|req| match (req.method.as_str(), req.path.as_str()) {
+            ("POST", "/v1/sandboxes") => {
+                MockResponse::json(200, json!({ "sandbox_id": "sb-child" }))
+            }
+            ("GET", "/v1/sandboxes/sb-parent") => {
+                MockResponse::json(200, json!({ "sandbox_id": "sb-parent", "template": "node-20" }))
+            }
+            (_, p) if p.ends_with("/exec") => {
+                MockResponse::json(200, json!({ "stdout": "", "stderr": "", "exit_code": 0 }))
+            }
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let manager = manager(&server.url(), 3);
+        let create_body = |name: &str| {
+            server
+                .requests()
+                .iter()
+                .filter(|r| r.method == "POST" && r.path == "/v1/sandboxes")
+                .map(|r| r.json())
+                .find(|body| body["name"] == name)
+                .unwrap()
+        };
+
+        // Explicit choice
+        let mut explicit = genesis(&manager, "py");
+        explicit.template = Some("python-3.12".into());
+        manager.spawn(explicit).await.unwrap();
+        assert_eq!(create_body("py")["template"], "python-3.12");
+
+        // Otherwise the parent's own image
+        manager.spawn(genesis(&manager, "same")).await.unwrap();
+        assert_eq!(create_body("same")["template"], "node-20");
+    }
+
+    #[tokio::test]
+    async fn test_failed_install_deletes_the_sandbox() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/sandboxes" => MockResponse::json(200, json!({ "sandbox_id": "sb-child" })),
+            p if p.ends_with("/exec") => {
+                MockResponse::json(200, json!({ "stdout": "", "stderr": "npm: not found", "exit_code": 127 }))
+            }
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let manager = manager(&server.url(), 3);
+
+        let err = manager.spawn(genesis(&manager, "kid")).await.unwrap_err();
+        assert!(err.to_string().contains("install failed (127)"), "{}", err);
+        let requests = server.requests();
+        assert!(requests
+            .iter()
+            .any(|r| r.method == "DELETE" && r.path == "/v1/sandboxes/sb-child"));
+        // Nothing was sent to a wallet whose key is now gone
+        assert!(!requests.iter().any(|r| r.path == "/v1/credits/transfer"));
+        let child = manager.db.lock().await.child_by_sandbox("sb-child").unwrap().unwrap();
+        assert_eq!(child.status, ChildState::Terminated);
+    }
+
+    #[tokio::test]
+    async fn test_failed_funding_keeps_the_sandbox() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/sandboxes" => MockResponse::json(200, json!({ "sandbox_id": "sb-child" })),
+            "/v1/credits/transfer" => MockResponse::text(500, "upstream timeout"),
+            p if p.ends_with("/exec") => {
+                MockResponse::json(200, json!({ "stdout": "", "stderr": "", "exit_code": 0 }))
+            }
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let manager = manager(&server.url(), 3);
+
+        let err = manager.spawn(genesis(&manager, "kid")).await.unwrap_err();
+        assert!(err.to_string().contains("funding it failed"), "{}", err);
+        let requests = server.requests();
+        let exec = requests.iter().position(|r| r.path.ends_with("/exec")).unwrap();
+        let transfer = requests.iter().position(|r| r.path == "/v1/credits/transfer").unwrap();
+        assert!(exec < transfer);
+        assert!(!requests.iter().any(|r| r.method == "DELETE"));
+        let child = manager.db.lock().await.child_by_sandbox("sb-child").unwrap().unwrap();
+        assert_eq!(child.status, ChildState::Active);
+    }
+
+    #[tokio::test]
+    async fn test_spawn_and_terminate_lifecycle() {
+        let server = MockServer::start(|req| match req.path.as_str() {
+            "/v1/sandboxes" => MockResponse::json(200, json!({ "sandbox_id": "sb-child" })),
+            p if p.ends_with("/exec") => {
+                MockResponse::json(200, json!({ "stdout": "", "stderr": "", "exit_code": 0 }))
+            }
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let manager = manager(&server.url(), 3);
+
+        let child = manager.spawn(genesis(&manager, "kid")).await.unwrap();
+        assert_eq!(child.status, ChildState::Active);
+        assert_eq!(child.sandbox_id, "sb-child");
+        assert!(child.wallet_address.starts_with("0x"));
+
+        let requests = server.requests();
+        let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+        // Template lookup, then create
+        assert_eq!(&paths[..2], ["/v1/sandboxes/sb-parent", "/v1/sandboxes"]);
+        assert!(paths.contains(&"/v1/sandboxes/sb-child/files"));
+        assert!(paths.contains(&"/v1/sandboxes/sb-child/exec"));
+        let transfer = requests
+            .iter()
+            .find(|r| r.path == "/v1/credits/transfer")
+            .unwrap()
+            .json();
+        assert_eq!(transfer["to_address"], child.wallet_address.as_str());
+        assert_eq!(transfer["amount"], 1.5);
+
+        // The signed bundle is among the files written to the child
+        let bundle = requests
+            .iter()
+            .map(|r| r.json())
+            .find(|body| body["path"] == "/root/.automaton/genesis.json")
+            .unwrap();
+        let bundle: crate::replication::genesis::SignedGenesis =
+            serde_json::from_str(bundle["content"].as_str().unwrap()).unwrap();
+        bundle.verify().unwrap();
+
+        let stored = manager.db.lock().await.get_child(&child.id).unwrap().unwrap();
+        assert_eq!(stored.status, ChildState::Active);
+
+        let terminated = manager.terminate(&child.id).await.unwrap();
+        assert_eq!(terminated.status, ChildState::Terminated);
+        assert!(manager.terminate(&child.id).await.is_err());
+        assert_eq!(manager.db.lock().await.live_children_count().unwrap(), 0);
+    }
+}
@@ -1,3 +1,7 @@
-pub mod spawn;
+pub mod genesis;
+pub mod manager;
+pub mod queue;
+pub mod recovery;
+pub mod verify;
 
-pub use spawn::spawn_child;
+pub use manager::ReplicationManager;
@@ -0,0 +1,139 @@
+//! Spawn queue — paces child spawns.
+//!
+//! Each spawn creates a sandbox, writes files, transfers credits and runs an
+//! install, so a burst of `spawn_child` calls can trip Conway's rate limits
+//! even under `max_children`. Spawns therefore wait for one of
+//! `spawn_concurrency` slots, and consecutive spawns start at least
+//! `spawn_delay_secs` apart. One queue is shared by the whole process (every
+//! tool call builds its own [`crate::replication::ReplicationManager`]).
+
+use crate::config::AutomatonConfig;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+/// KV key holding how many spawns are waiting in the queue, for `status`.
+pub const SPAWN_QUEUE_DEPTH_KEY: &str = "spawn_queue_depth";
+
+/// Concurrency cap and pacing for spawns.
+pub struct SpawnQueue {
+    slots: Semaphore,
+    delay: Duration,
+    /// When the last spawn was let through.
+    last_start: Mutex<Option<Instant>>,
+    waiting: AtomicUsize,
+}
+
+/// A spawn slot, held for the duration of a spawn.
+pub struct SpawnSlot<'a> {
+    _permit: SemaphorePermit<'a>,
+}
+
+/// Counts a request as waiting until it is dropped, even if the spawn is
+/// cancelled while queued.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl SpawnQueue {
+    /// A queue letting `concurrency` spawns (at least 1) run at once, started
+    /// at least `delay` apart.
+    pub fn new(concurrency: usize, delay: Duration) -> Self {
+        Self {
+            slots: Semaphore::new(concurrency.max(1)),
+            delay,
+            last_start: Mutex::new(None),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// The process-wide queue, configured from the first config it is
+    /// asked for.
+    pub fn shared(config: &AutomatonConfig) -> Arc<Self> {
+        static SHARED: OnceLock<Arc<SpawnQueue>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| {
+                Arc::new(Self::new(
+                    config.spawn_concurrency,
+                    Duration::from_secs(config.spawn_delay_secs),
+                ))
+            })
+            .clone()
+    }
+
+    /// Spawn requests waiting for a slot.
+    pub fn depth(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    /// Wait for a free slot and for the delay since the previous spawn.
+    /// The request counts towards [`Self::depth`] as soon as this is called.
+    pub fn acquire(&self) -> impl Future<Output = SpawnSlot<'_>> {
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        let waiting = Waiting(&self.waiting);
+        async move {
+            let permit = self.slots.acquire().await.expect("spawn queue is never closed");
+            // Held while sleeping, so starts stay spaced out even with free slots
+            let mut last_start = self.last_start.lock().await;
+            if let Some(last) = *last_start {
+                tokio::time::sleep_until(last + self.delay).await;
+            }
+            *last_start = Some(Instant::now());
+
+            drop(waiting);
+            SpawnSlot { _permit: permit }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_caps_concurrent_spawns() {
+        let queue = Arc::new(SpawnQueue::new(2, Duration::ZERO));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let spawns: Vec<_> = (0..6)
+            .map(|_| {
+                let (queue, running, peak) = (queue.clone(), running.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _slot = queue.acquire().await;
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(queue.depth(), 4);
+        for spawn in spawns {
+            spawn.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_queue_spaces_out_spawns() {
+        let queue = SpawnQueue::new(3, Duration::from_millis(40));
+        let start = Instant::now();
+        let first = queue.acquire().await;
+        let second = queue.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        drop((first, second));
+        let _third = queue.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+}
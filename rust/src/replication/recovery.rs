@@ -0,0 +1,267 @@
+//! Startup recovery of interrupted sandbox creation.
+//!
+//! Every `create_sandbox` call is preceded by a pending-operation record and
+//! followed by clearing it once the result is tracked. A record left behind
+//! means the process died (or the request failed) in between, possibly
+//! leaving a billed sandbox nothing knows about. [`reconcile_pending_creates`]
+//! finds each such sandbox — by the recorded ID, or by name via Conway — and
+//! either adopts it as a child or deletes it. A name match only counts if
+//! Conway reports the sandbox as created after the operation began, since
+//! sandboxes from the `create_sandbox` tool never get child records and an
+//! older one may share the name.
+
+use crate::config::AutomatonConfig;
+use crate::conway::ConwayClient;
+use crate::state::Database;
+use crate::types::{ChildRecord, ChildState, PendingOperation};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Pending-operation kind for `ReplicationManager::spawn`.
+pub const SPAWN_CHILD_OP: &str = "spawn_child";
+
+/// Pending-operation kind for the `create_sandbox` tool.
+pub const CREATE_SANDBOX_OP: &str = "create_sandbox";
+
+/// What reconciliation did with one pending operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reconciled {
+    /// No sandbox was created, or it is already tracked.
+    Cleared,
+    /// The sandbox was recorded as an unreachable child.
+    Adopted(String),
+    /// The sandbox was deleted.
+    Reaped(String),
+    /// Conway could not be reached; the record is kept for next time.
+    Deferred,
+}
+
+/// Resolve every pending create left over from a previous run.
+pub async fn reconcile_pending_creates(
+    config: &AutomatonConfig,
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+) -> Result<Vec<Reconciled>> {
+    let pending = db.lock().await.pending_operations()?;
+    let mut outcomes = Vec::with_capacity(pending.len());
+    for op in pending {
+        let outcome = reconcile(config, conway, db, &op).await?;
+        if outcome != Reconciled::Deferred {
+            db.lock().await.finish_pending_operation(&op.id)?;
+        }
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+async fn reconcile(
+    config: &AutomatonConfig,
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+    op: &PendingOperation,
+) -> Result<Reconciled> {
+    let sandbox_id = match &op.sandbox_id {
+        Some(id) => id.clone(),
+        None => match find_untracked_by_name(conway, db, &op.name, op.created_at).await {
+            Ok(Some(id)) => id,
+            Ok(None) => return Ok(Reconciled::Cleared),
+            Err(e) => {
+                warn!("Cannot reconcile pending {} '{}': {:#}", op.kind, op.name, e);
+                return Ok(Reconciled::Deferred);
+            }
+        },
+    };
+
+    if db.lock().await.child_by_sandbox(&sandbox_id)?.is_some() {
+        return Ok(Reconciled::Cleared);
+    }
+
+    if op.kind == SPAWN_CHILD_OP && config.adopt_orphaned_sandboxes {
+        // Never provisioned, so it cannot be active; the agent can
+        // terminate it like any other child.
+        db.lock().await.add_child(&ChildRecord {
+            id: ulid::Ulid::new().to_string(),
+            name: op.name.clone(),
+            sandbox_id: sandbox_id.clone(),
+            wallet_address: String::new(),
+            created_at: op.created_at,
+            status: ChildState::Unreachable,
+        })?;
+        info!("Adopted orphaned sandbox {} as child '{}'", sandbox_id, op.name);
+        return Ok(Reconciled::Adopted(sandbox_id));
+    }
+
+    match conway.delete_sandbox(&sandbox_id).await {
+        Ok(()) => {
+            info!("Deleted orphaned sandbox {} ('{}')", sandbox_id, op.name);
+            Ok(Reconciled::Reaped(sandbox_id))
+        }
+        Err(e) => {
+            warn!("Failed to delete orphaned sandbox {}: {:#}", sandbox_id, e);
+            Ok(Reconciled::Deferred)
+        }
+    }
+}
+
+/// A sandbox named `name`, created no earlier than `since`, that no child
+/// record points at. Sandboxes without a creation time never match.
+async fn find_untracked_by_name(
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+    name: &str,
+    since: DateTime<Utc>,
+) -> Result<Option<String>> {
+    let sandboxes = conway.list_sandboxes().await?;
+    let db = db.lock().await;
+    let candidates = sandboxes
+        .into_iter()
+        .filter(|s| s.name == name && s.created_at.is_some_and(|t| t >= since));
+    for sandbox in candidates {
+        if db.child_by_sandbox(&sandbox.sandbox_id)?.is_none() {
+            return Ok(Some(sandbox.sandbox_id));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{unreachable_url, MockResponse, MockServer};
+    use serde_json::json;
+
+    fn setup(url: &str, adopt: bool) -> (AutomatonConfig, ConwayClient, Arc<Mutex<Database>>) {
+        let config = AutomatonConfig {
+            conway_api_url: url.into(),
+            adopt_orphaned_sandboxes: adopt,
+            ..Default::default()
+        };
+        let conway = ConwayClient::new(url, "key", "sb-parent");
+        (config, conway, Arc::new(Mutex::new(Database::open_memory().unwrap())))
+    }
+
+    /// State after a crash between `create_sandbox` returning and `add_child`.
+    async fn crash_after_create(db: &Arc<Mutex<Database>>, name: &str, sandbox_id: &str) {
+        let db = db.lock().await;
+        let id = db.begin_pending_operation(SPAWN_CHILD_OP, name).unwrap();
+        db.set_pending_sandbox(&id, sandbox_id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_sandbox_is_reaped() {
+        let server = MockServer::start(|_| MockResponse::json(200, json!({}))).await;
+        let (config, conway, db) = setup(&server.url(), false);
+        crash_after_create(&db, "kid", "sb-orphan").await;
+
+        let outcomes = reconcile_pending_creates(&config, &conway, &db).await.unwrap();
+        assert_eq!(outcomes, vec![Reconciled::Reaped("sb-orphan".into())]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "DELETE");
+        assert_eq!(requests[0].path, "/v1/sandboxes/sb-orphan");
+        let db = db.lock().await;
+        assert!(db.pending_operations().unwrap().is_empty());
+        assert!(db.list_children().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_sandbox_is_adopted() {
+        let server = MockServer::start(|_| MockResponse::json(500, json!({}))).await;
+        let (config, conway, db) = setup(&server.url(), true);
+        crash_after_create(&db, "kid", "sb-orphan").await;
+
+        let outcomes = reconcile_pending_creates(&config, &conway, &db).await.unwrap();
+        assert_eq!(outcomes, vec![Reconciled::Adopted("sb-orphan".into())]);
+        assert!(server.requests().is_empty());
+        let child = db.lock().await.child_by_sandbox("sb-orphan").unwrap().unwrap();
+        assert_eq!(child.name, "kid");
+        assert_eq!(child.status, ChildState::Unreachable);
+
+        // Adoption is idempotent: nothing left to reconcile
+        let again = reconcile_pending_creates(&config, &conway, &db).await.unwrap();
+        assert!(again.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_missing_sandbox_id_is_found_by_name() {
+        let created = (Utc::now() + chrono::Duration::seconds(5)).to_rfc3339();
+        let server = MockServer::start(move |req| match req.method.as_str() {
+            "GET" => MockResponse::json(
+                200,
+                json!({ "sandboxes": [
+                    { "sandbox_id": "sb-tracked", "name": "kid", "created_at": created },
+                    { "sandbox_id": "sb-lost", "name": "kid", "created_at": created },
+                    { "sandbox_id": "sb-other", "name": "other", "created_at": created },
+                ]}),
+            ),
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let (config, conway, db) = setup(&server.url(), false);
+        {
+            let db = db.lock().await;
+            db.add_child(&ChildRecord {
+                id: "c1".into(),
+                name: "kid".into(),
+                sandbox_id: "sb-tracked".into(),
+                wallet_address: String::new(),
+                created_at: Utc::now(),
+                status: ChildState::Active,
+            })
+            .unwrap();
+            // Crash before Conway's response was recorded
+            db.begin_pending_operation(CREATE_SANDBOX_OP, "kid").unwrap();
+            db.begin_pending_operation(CREATE_SANDBOX_OP, "never-created").unwrap();
+        }
+
+        let outcomes = reconcile_pending_creates(&config, &conway, &db).await.unwrap();
+        assert_eq!(
+            outcomes,
+            vec![Reconciled::Reaped("sb-lost".into()), Reconciled::Cleared]
+        );
+        assert!(server
+            .requests()
+            .iter()
+            .any(|r| r.method == "DELETE" && r.path == "/v1/sandboxes/sb-lost"));
+        assert!(db.lock().await.pending_operations().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_older_sandbox_with_the_same_name_is_kept() {
+        let older = (Utc::now() - chrono::Duration::days(3)).to_rfc3339();
+        let server = MockServer::start(move |req| match req.method.as_str() {
+            "GET" => MockResponse::json(
+                200,
+                json!({ "sandboxes": [
+                    // Made earlier by the create_sandbox tool: no child record
+                    { "sandbox_id": "sb-shop", "name": "shop", "created_at": older },
+                    // No creation time reported
+                    { "sandbox_id": "sb-unknown", "name": "shop" },
+                ]}),
+            ),
+            _ => MockResponse::json(200, json!({})),
+        })
+        .await;
+        let (config, conway, db) = setup(&server.url(), false);
+        // A create that failed before Conway returned an id
+        db.lock().await.begin_pending_operation(CREATE_SANDBOX_OP, "shop").unwrap();
+
+        let outcomes = reconcile_pending_creates(&config, &conway, &db).await.unwrap();
+        assert_eq!(outcomes, vec![Reconciled::Cleared]);
+        assert!(server.requests().iter().all(|r| r.method != "DELETE"));
+        assert!(db.lock().await.pending_operations().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_conway_defers() {
+        let (config, conway, db) = setup(&unreachable_url().await, false);
+        crash_after_create(&db, "kid", "sb-orphan").await;
+
+        let outcomes = reconcile_pending_creates(&config, &conway, &db).await.unwrap();
+        assert_eq!(outcomes, vec![Reconciled::Deferred]);
+        assert_eq!(db.lock().await.pending_operations().unwrap().len(), 1);
+    }
+}
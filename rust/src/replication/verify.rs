@@ -0,0 +1,241 @@
+//! Challenge-response check that a child is really running this runtime.
+//!
+//! The parent sends a random nonce over the social relay, signed with its
+//! wallet. A genuine child's heartbeat (`check_social_inbox`) recognizes a
+//! challenge from its parent and answers with the nonce signed by its own
+//! wallet. A sandbox that was wiped or taken over can't produce that
+//! signature, so the child is marked `suspect` instead of `verified`.
+
+use crate::identity::Wallet;
+use crate::social::{check_sender, SenderCheck, SocialClient};
+use crate::state::Database;
+use crate::types::{Address, ChildRecord, ChildState, InboxMessage};
+use anyhow::{bail, Context, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// `kind` of a parent's challenge message.
+pub const CHALLENGE_KIND: &str = "child_challenge";
+
+/// `kind` of a child's answer.
+pub const RESPONSE_KIND: &str = "child_challenge_response";
+
+/// Default wait for an answer: children check their inbox every 5 minutes.
+pub const DEFAULT_VERIFY_TIMEOUT: Duration = Duration::from_secs(6 * 60);
+
+/// How often the parent checks for the answer.
+const VERIFY_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Body of a challenge or its answer, sent as JSON message content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeMessage {
+    pub kind: String,
+    /// Hex-encoded random nonce.
+    pub nonce: String,
+}
+
+impl ChallengeMessage {
+    /// A fresh challenge with a random 32-byte nonce.
+    pub fn challenge() -> Self {
+        let mut nonce = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        Self {
+            kind: CHALLENGE_KIND.into(),
+            nonce: hex::encode(nonce),
+        }
+    }
+
+    /// The answer to this challenge.
+    pub fn response(&self) -> Self {
+        Self {
+            kind: RESPONSE_KIND.into(),
+            nonce: self.nonce.clone(),
+        }
+    }
+
+    /// Parse message content of the given kind.
+    pub fn parse(content: &str, kind: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(content)
+            .ok()
+            .filter(|m| m.kind == kind && !m.nonce.is_empty())
+    }
+
+    /// Serialize as message content.
+    pub fn to_content(&self) -> String {
+        serde_json::to_string(self).expect("challenge serializes")
+    }
+}
+
+/// Child side: the answer content to send back if `msg` is a challenge
+/// signed by our parent, else `None`.
+pub fn answer_challenge(msg: &InboxMessage, parent_address: &str) -> Option<String> {
+    let challenge = ChallengeMessage::parse(&msg.content, CHALLENGE_KIND)?;
+    if check_sender(msg) != SenderCheck::Verified || !same_address(&msg.from_address, parent_address) {
+        warn!("Ignoring a child challenge not signed by our parent (from {})", msg.from_address);
+        return None;
+    }
+    Some(challenge.response().to_content())
+}
+
+/// Parent side: whether `messages` hold an answer to `challenge` signed by
+/// `child_wallet` (`Some(false)` if the only answers aren't), or `None` if
+/// there is no answer yet.
+pub fn check_response(
+    challenge: &ChallengeMessage,
+    child_wallet: &str,
+    messages: &[InboxMessage],
+) -> Option<bool> {
+    let answers: Vec<_> = messages
+        .iter()
+        .filter(|m| {
+            ChallengeMessage::parse(&m.content, RESPONSE_KIND).is_some_and(|r| r.nonce == challenge.nonce)
+        })
+        .collect();
+    if answers.is_empty() {
+        return None;
+    }
+    Some(answers.iter().any(|m| {
+        check_sender(m) == SenderCheck::Verified && same_address(&m.from_address, child_wallet)
+    }))
+}
+
+fn same_address(a: &str, b: &str) -> bool {
+    matches!((Address::parse(a), Address::parse(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Challenge a child and record the verdict: `verified` if it answers with
+/// its recorded wallet's signature within `timeout`, `suspect` otherwise.
+pub async fn verify_child(
+    relay_url: &str,
+    wallet: &Wallet,
+    db: &Arc<Mutex<Database>>,
+    child_id: &str,
+    timeout: Duration,
+) -> Result<ChildRecord> {
+    if relay_url.is_empty() {
+        bail!("No social relay configured; cannot reach the child");
+    }
+    let mut child = db
+        .lock()
+        .await
+        .get_child(child_id)?
+        .with_context(|| format!("Unknown child: {}", child_id))?;
+    if child.status == ChildState::Terminated {
+        bail!("Child '{}' is terminated", child.name);
+    }
+
+    let relay = SocialClient::new(relay_url, &wallet.address.to_string());
+    let challenge = ChallengeMessage::challenge();
+    relay
+        .send_signed(&child.wallet_address, &challenge.to_content(), wallet)
+        .await?;
+    info!("Challenged child '{}' ({})", child.name, child.wallet_address);
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    let verdict = loop {
+        // The daemon's heartbeat may fetch the answer first, so look in the
+        // stored inbox too
+        let fetched = relay.fetch_inbox().await.unwrap_or_else(|e| {
+            warn!("Fetching the inbox failed: {:#}", e);
+            Vec::new()
+        });
+        let db_lock = db.lock().await;
+        for msg in &fetched {
+            let _ = db_lock.save_inbox_message(msg);
+        }
+        let inbox = db_lock.unread_messages()?;
+        if let Some(verdict) = check_response(&challenge, &child.wallet_address, &inbox) {
+            // Protocol traffic, not for the agent
+            for msg in &inbox {
+                let answer = ChallengeMessage::parse(&msg.content, RESPONSE_KIND);
+                if answer.is_some_and(|r| r.nonce == challenge.nonce) {
+                    db_lock.mark_message_read(&msg.id)?;
+                }
+            }
+            break verdict;
+        }
+        drop(db_lock);
+
+        if tokio::time::Instant::now() >= deadline {
+            warn!("Child '{}' did not answer the challenge within {}s", child.name, timeout.as_secs());
+            break false;
+        }
+        tokio::time::sleep(VERIFY_POLL_INTERVAL).await;
+    };
+
+    let next = if verdict {
+        ChildState::Verified
+    } else {
+        ChildState::Suspect
+    };
+    if child.status != next {
+        if !child.status.can_transition_to(next) {
+            bail!("Child '{}' cannot go from {} to {}", child.name, child.status, next);
+        }
+        child.status = next;
+        db.lock().await.update_child(&child)?;
+    }
+    Ok(child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(wallet: &Wallet, to: &str, content: &str) -> InboxMessage {
+        InboxMessage {
+            id: ulid::Ulid::new().to_string(),
+            from_address: wallet.address.to_string(),
+            to_address: to.into(),
+            content: content.into(),
+            timestamp: chrono::Utc::now(),
+            read: false,
+            signature: Some(wallet.sign_message(content.as_bytes()).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_challenges_are_fresh_and_parse() {
+        let a = ChallengeMessage::challenge();
+        let b = ChallengeMessage::challenge();
+        assert_ne!(a.nonce, b.nonce);
+        assert_eq!(a.nonce.len(), 64);
+        assert_eq!(ChallengeMessage::parse(&a.to_content(), CHALLENGE_KIND), Some(a.clone()));
+        assert_eq!(ChallengeMessage::parse(&a.to_content(), RESPONSE_KIND), None);
+        assert_eq!(ChallengeMessage::parse("hello", CHALLENGE_KIND), None);
+    }
+
+    #[test]
+    fn test_only_the_child_wallet_can_answer() {
+        let parent = Wallet::random().unwrap();
+        let child = Wallet::random().unwrap();
+        let impostor = Wallet::random().unwrap();
+        let child_addr = child.address.to_string();
+        let challenge = ChallengeMessage::challenge();
+
+        // The child answers its parent, and no one else
+        let from_parent = signed(&parent, &child_addr, &challenge.to_content());
+        let answer = answer_challenge(&from_parent, &parent.address.to_string()).unwrap();
+        let from_impostor = signed(&impostor, &child_addr, &challenge.to_content());
+        assert_eq!(answer_challenge(&from_impostor, &parent.address.to_string()), None);
+
+        assert_eq!(check_response(&challenge, &child_addr, &[]), None);
+        let genuine = signed(&child, "", &answer);
+        assert_eq!(check_response(&challenge, &child_addr, std::slice::from_ref(&genuine)), Some(true));
+
+        // Signed by someone else, or claiming the child's address unsigned
+        let hijacked = signed(&impostor, "", &answer);
+        assert_eq!(check_response(&challenge, &child_addr, &[hijacked]), Some(false));
+        let mut unsigned = genuine.clone();
+        unsigned.signature = None;
+        assert_eq!(check_response(&challenge, &child_addr, &[unsigned]), Some(false));
+
+        // An answer to an older challenge doesn't count
+        let stale = signed(&child, "", &ChallengeMessage::challenge().response().to_content());
+        assert_eq!(check_response(&challenge, &child_addr, &[stale]), None);
+    }
+}
@@ -36,6 +36,12 @@ impl AuditLog {
         Ok(())
     }
 
+    /// Count modifications recorded at or after `since`.
+    pub async fn count_since(&self, since: chrono::DateTime<Utc>) -> Result<u64> {
+        let db = self.db.lock().await;
+        db.count_modifications_since(since)
+    }
+
     /// Record a code edit modification.
     pub async fn log_code_edit(
         &self,
@@ -150,6 +156,57 @@ impl AuditLog {
         info!("Audit: upstream pull {}", commit_hash);
         self.persist(entry).await
     }
+
+    /// Record a DNS record change.
+    pub async fn log_dns_update(&self, domain: &str, description: &str) -> Result<()> {
+        let entry = ModificationEntry {
+            id: ulid::Ulid::new().to_string(),
+            timestamp: Utc::now(),
+            mod_type: ModificationType::DnsUpdate,
+            description: format!("[{}] {}", domain, description),
+            file_path: None,
+            diff: None,
+            diff_truncated: false,
+            reversible: true,
+        };
+
+        info!("Audit: DNS update on {}", domain);
+        self.persist(entry).await
+    }
+
+    /// Record a workspace snapshot.
+    pub async fn log_workspace_snapshot(&self, name: &str, description: &str) -> Result<()> {
+        let entry = ModificationEntry {
+            id: ulid::Ulid::new().to_string(),
+            timestamp: Utc::now(),
+            mod_type: ModificationType::WorkspaceSnapshot,
+            description: format!("[{}] {}", name, description),
+            file_path: Some("workspace/".to_string()),
+            diff: None,
+            diff_truncated: false,
+            reversible: true,
+        };
+
+        info!("Audit: workspace snapshot '{}'", name);
+        self.persist(entry).await
+    }
+
+    /// Record a workspace restore from a snapshot.
+    pub async fn log_workspace_restore(&self, name: &str) -> Result<()> {
+        let entry = ModificationEntry {
+            id: ulid::Ulid::new().to_string(),
+            timestamp: Utc::now(),
+            mod_type: ModificationType::WorkspaceRestore,
+            description: format!("Restored workspace/ from snapshot '{}'", name),
+            file_path: Some("workspace/".to_string()),
+            diff: None,
+            diff_truncated: false,
+            reversible: false,
+        };
+
+        info!("Audit: workspace restore from '{}'", name);
+        self.persist(entry).await
+    }
 }
 
 #[cfg(test)]
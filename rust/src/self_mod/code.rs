@@ -1,9 +1,11 @@
 //! Self-modification — code editing capabilities.
 
 use crate::conway::ConwayClient;
+use crate::self_mod::AuditLog;
 use anyhow::{bail, Result};
+use sha3::{Digest, Keccak256};
 use similar::TextDiff;
-use tracing::info;
+use tracing::{info, warn};
 
 /// Maximum diff size in bytes before truncation (64 KB).
 const MAX_DIFF_BYTES: usize = 64 * 1024;
@@ -18,13 +20,40 @@ const PROTECTED_FILES: &[&str] = &[
     "SOUL.md",
 ];
 
-/// Allowlisted path prefixes under which writes are permitted.
+/// Largest existing file read back to check whether a write would change
+/// it; bigger files are simply rewritten.
+const MAX_COMPARE_BYTES: usize = 1024 * 1024;
+
+/// Built-in path prefixes under which writes are permitted; operators add
+/// more with `self_mod_allowed_prefixes`.
 const ALLOWED_PREFIXES: &[&str] = &["workspace/", "skills/", "notes/"];
 
-/// Validate that a file path is safe for writing.
+/// The built-in prefixes plus the usable ones in `extra`, each ending in
+/// `/`. Empty, absolute and `..` entries are ignored so a bad config entry
+/// can't open up the whole sandbox.
+fn allowed_prefixes(extra: &[String]) -> Vec<String> {
+    let mut prefixes: Vec<String> = ALLOWED_PREFIXES.iter().map(|p| p.to_string()).collect();
+    for prefix in extra {
+        let prefix = prefix.trim().replace('\\', "/");
+        let prefix = prefix.trim_end_matches('/');
+        if prefix.is_empty() || prefix.starts_with('/') || prefix.contains("..") {
+            warn!("Ignoring unusable self-mod prefix '{}'", prefix);
+            continue;
+        }
+        let prefix = format!("{}/", prefix);
+        if !prefixes.contains(&prefix) {
+            prefixes.push(prefix);
+        }
+    }
+    prefixes
+}
+
+/// Validate that a file path is safe for writing. `extra_prefixes` are
+/// allowed on top of the built-in ones; the traversal, absolute-path and
+/// protected-file checks always apply.
 ///
 /// Returns `Ok(())` if the path is allowed, or an error describing why it's rejected.
-pub fn validate_write_path(path: &str) -> Result<()> {
+pub fn validate_write_path(path: &str, extra_prefixes: &[String]) -> Result<()> {
     // Normalize backslashes to forward slashes
     let normalized = path.replace('\\', "/");
 
@@ -47,18 +76,39 @@ pub fn validate_write_path(path: &str) -> Result<()> {
     }
 
     // Ensure the path is under an allowed prefix
-    let mut allowed = false;
-    for prefix in ALLOWED_PREFIXES {
-        if normalized.starts_with(prefix) {
-            allowed = true;
-            break;
-        }
-    }
-    if !allowed {
+    let prefixes = allowed_prefixes(extra_prefixes);
+    if !prefixes.iter().any(|prefix| normalized.starts_with(prefix.as_str())) {
         bail!(
             "Path '{}' is not under an allowlisted prefix ({:?})",
             path,
-            ALLOWED_PREFIXES
+            prefixes
+        );
+    }
+
+    Ok(())
+}
+
+/// Validate a working directory for `exec`.
+///
+/// Same rules as writes: relative, no `..`, and under an allowlisted prefix
+/// (the prefix directory itself is allowed too).
+pub fn validate_cwd(path: &str, extra_prefixes: &[String]) -> Result<()> {
+    let normalized = path.replace('\\', "/");
+
+    if normalized.contains("..") {
+        bail!("Path traversal not allowed: path contains '..'");
+    }
+    if normalized.starts_with('/') {
+        bail!("Absolute paths not allowed: {}", path);
+    }
+
+    let dir = format!("{}/", normalized.trim_end_matches('/'));
+    let prefixes = allowed_prefixes(extra_prefixes);
+    if !prefixes.iter().any(|prefix| dir.starts_with(prefix.as_str())) {
+        bail!(
+            "Working directory '{}' is not under an allowlisted prefix ({:?})",
+            path,
+            prefixes
         );
     }
 
@@ -95,10 +145,62 @@ pub fn truncate_diff(diff: String) -> (String, bool) {
     }
 }
 
-/// Edit a file in the sandbox (with protection checks).
-pub async fn edit_file(conway: &ConwayClient, path: &str, content: &str) -> Result<String> {
+/// Hash used to compare file contents.
+pub fn content_hash(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// Whether `path` in the sandbox already holds exactly `content`.
+///
+/// Makes writes idempotent: a retry after a lost response finds the
+/// content in place. Missing, unreadable or oversized files count as
+/// different.
+pub async fn content_unchanged(conway: &ConwayClient, path: &str, content: &str) -> bool {
+    if content.len() > MAX_COMPARE_BYTES {
+        return false;
+    }
+    match conway.read_file_binary(path, MAX_COMPARE_BYTES).await {
+        Ok(existing) => content_hash(&existing) == content_hash(content.as_bytes()),
+        Err(_) => false,
+    }
+}
+
+/// Reject a modification if `recent` modifications in the last hour already
+/// meet `max_per_hour` (0 disables the limit).
+pub fn enforce_modification_rate(recent: u64, max_per_hour: u32) -> Result<()> {
+    if max_per_hour > 0 && recent >= max_per_hour as u64 {
+        bail!(
+            "Self-modification rate limit reached ({} in the last hour, max {}). \
+             Slow down: verify your previous changes work before editing again.",
+            recent,
+            max_per_hour
+        );
+    }
+    Ok(())
+}
+
+/// Edit a file in the sandbox (with protection checks, rate limiting, and audit).
+pub async fn edit_file(
+    conway: &ConwayClient,
+    audit: &AuditLog,
+    max_per_hour: u32,
+    extra_prefixes: &[String],
+    path: &str,
+    content: &str,
+) -> Result<String> {
     // Validate path
-    validate_write_path(path)?;
+    validate_write_path(path, extra_prefixes)?;
+
+    // Identical content: nothing to write, diff or audit
+    if content_unchanged(conway, path, content).await {
+        return Ok(format!("No change: {} already has this content", path));
+    }
+
+    // Enforce the self-modification rate limit
+    let recent = audit
+        .count_since(chrono::Utc::now() - chrono::Duration::hours(1))
+        .await?;
+    enforce_modification_rate(recent, max_per_hour)?;
 
     // Read current content for diff, noting whether file existed
     let (old_content, file_existed) = match conway.read_file(path).await {
@@ -132,9 +234,46 @@ pub async fn edit_file(conway: &ConwayClient, path: &str, content: &str) -> Resu
         "Self-mod edit: {}",
         &diff_summary[..diff_summary.len().min(200)]
     );
+
+    let description = format!(
+        "{} {}",
+        if file_existed { "Modified" } else { "Created" },
+        path
+    );
+    audit.log_code_edit(&description, path, &diff).await?;
+
     Ok(diff_summary)
 }
 
+/// Diff proposed `content` against the current file without writing, so a
+/// change can be checked before it is made. Same path rules as `edit_file`.
+pub async fn preview_edit(
+    conway: &ConwayClient,
+    extra_prefixes: &[String],
+    path: &str,
+    content: &str,
+) -> Result<String> {
+    validate_write_path(path, extra_prefixes)?;
+
+    let (old_content, file_existed) = match conway.read_file(path).await {
+        Ok(c) => (c, true),
+        Err(_) => (String::new(), false),
+    };
+    if file_existed && old_content == content {
+        return Ok(format!("No change: {} already has this content", path));
+    }
+
+    let (diff, _truncated) = compute_diff(&old_content, content, path);
+    Ok(format!(
+        "{}: would be {} ({} -> {} lines; nothing written)\n{}",
+        path,
+        if file_existed { "modified" } else { "created" },
+        old_content.lines().count(),
+        content.lines().count(),
+        diff,
+    ))
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -145,45 +284,62 @@ mod tests {
 
     #[test]
     fn test_allowed_paths() {
-        assert!(validate_write_path("workspace/main.py").is_ok());
-        assert!(validate_write_path("skills/new_skill/SKILL.md").is_ok());
-        assert!(validate_write_path("notes/todo.txt").is_ok());
+        assert!(validate_write_path("workspace/main.py", &[]).is_ok());
+        assert!(validate_write_path("skills/new_skill/SKILL.md", &[]).is_ok());
+        assert!(validate_write_path("notes/todo.txt", &[]).is_ok());
     }
 
     #[test]
     fn test_disallowed_traversal() {
-        assert!(validate_write_path("workspace/../wallet.json").is_err());
-        assert!(validate_write_path("../etc/passwd").is_err());
-        assert!(validate_write_path("workspace/foo/../../bar").is_err());
+        assert!(validate_write_path("workspace/../wallet.json", &[]).is_err());
+        assert!(validate_write_path("../etc/passwd", &[]).is_err());
+        assert!(validate_write_path("workspace/foo/../../bar", &[]).is_err());
     }
 
     #[test]
     fn test_disallowed_absolute() {
-        assert!(validate_write_path("/etc/passwd").is_err());
-        assert!(validate_write_path("/home/user/workspace/foo").is_err());
+        assert!(validate_write_path("/etc/passwd", &[]).is_err());
+        assert!(validate_write_path("/home/user/workspace/foo", &[]).is_err());
     }
 
     #[test]
     fn test_disallowed_protected_files() {
-        assert!(validate_write_path("workspace/wallet.json").is_err());
-        assert!(validate_write_path("workspace/constitution.md").is_err());
-        assert!(validate_write_path("workspace/automaton.toml").is_err());
-        assert!(validate_write_path("workspace/state.db").is_err());
-        assert!(validate_write_path("workspace/heartbeat.yml").is_err());
-        assert!(validate_write_path("workspace/SOUL.md").is_err());
+        assert!(validate_write_path("workspace/wallet.json", &[]).is_err());
+        assert!(validate_write_path("workspace/constitution.md", &[]).is_err());
+        assert!(validate_write_path("workspace/automaton.toml", &[]).is_err());
+        assert!(validate_write_path("workspace/state.db", &[]).is_err());
+        assert!(validate_write_path("workspace/heartbeat.yml", &[]).is_err());
+        assert!(validate_write_path("workspace/SOUL.md", &[]).is_err());
     }
 
     #[test]
     fn test_disallowed_outside_prefix() {
-        assert!(validate_write_path("src/main.rs").is_err());
-        assert!(validate_write_path("config/secret.toml").is_err());
-        assert!(validate_write_path("random_file.txt").is_err());
+        assert!(validate_write_path("src/main.rs", &[]).is_err());
+        assert!(validate_write_path("config/secret.toml", &[]).is_err());
+        assert!(validate_write_path("random_file.txt", &[]).is_err());
     }
 
     #[test]
     fn test_backslash_normalization() {
-        assert!(validate_write_path("workspace\\foo\\bar.txt").is_ok());
-        assert!(validate_write_path("workspace\\..\\wallet.json").is_err());
+        assert!(validate_write_path("workspace\\foo\\bar.txt", &[]).is_ok());
+        assert!(validate_write_path("workspace\\..\\wallet.json", &[]).is_err());
+    }
+
+    #[test]
+    fn test_configured_prefix_extends_allowlist() {
+        let extra = vec!["www".to_string(), "/".to_string(), "../up".to_string()];
+        assert!(validate_write_path("www/index.html", &[]).is_err());
+        assert!(validate_write_path("www/index.html", &extra).is_ok());
+        assert!(validate_write_path("workspace/main.py", &extra).is_ok());
+        assert!(validate_cwd("www", &extra).is_ok());
+
+        // Traversal, absolute paths and protected files are still refused,
+        // and unusable configured prefixes are ignored
+        assert!(validate_write_path("www/../wallet.json", &extra).is_err());
+        assert!(validate_write_path("/www/index.html", &extra).is_err());
+        assert!(validate_write_path("www/SOUL.md", &extra).is_err());
+        assert!(validate_write_path("etc/passwd", &extra).is_err());
+        assert!(validate_write_path("../up/file", &extra).is_err());
     }
 
     #[test]
@@ -207,6 +363,63 @@ mod tests {
         assert!(result.contains("[diff truncated, exceeded 64KB limit]"));
     }
 
+    #[test]
+    fn test_modification_rate_limit_blocks_after_max() {
+        use crate::state::Database;
+        use crate::types::{ModificationEntry, ModificationType};
+
+        let db = Database::open_memory().unwrap();
+        let max = 5;
+        for i in 0..max {
+            db.log_modification(&ModificationEntry {
+                id: ulid::Ulid::new().to_string(),
+                timestamp: chrono::Utc::now(),
+                mod_type: ModificationType::CodeEdit,
+                description: format!("edit {}", i),
+                file_path: Some(format!("workspace/{}.py", i)),
+                diff: None,
+                diff_truncated: false,
+                reversible: true,
+            })
+            .unwrap();
+
+            let recent = db
+                .count_modifications_since(chrono::Utc::now() - chrono::Duration::hours(1))
+                .unwrap();
+            if i + 1 < max {
+                assert!(enforce_modification_rate(recent, max).is_ok());
+            }
+        }
+
+        let recent = db
+            .count_modifications_since(chrono::Utc::now() - chrono::Duration::hours(1))
+            .unwrap();
+        assert_eq!(recent, max as u64);
+        let err = enforce_modification_rate(recent, max).unwrap_err();
+        assert!(err.to_string().contains("Slow down"));
+
+        // Modifications older than the window don't count
+        let db = Database::open_memory().unwrap();
+        for i in 0..max {
+            db.log_modification(&ModificationEntry {
+                id: ulid::Ulid::new().to_string(),
+                timestamp: chrono::Utc::now() - chrono::Duration::minutes(61 + i as i64),
+                mod_type: ModificationType::CodeEdit,
+                description: format!("old edit {}", i),
+                file_path: Some(format!("workspace/{}.py", i)),
+                diff: None,
+                diff_truncated: false,
+                reversible: true,
+            })
+            .unwrap();
+        }
+        let recent = db
+            .count_modifications_since(chrono::Utc::now() - chrono::Duration::hours(1))
+            .unwrap();
+        assert_eq!(recent, 0);
+        assert!(enforce_modification_rate(recent, max).is_ok());
+    }
+
     #[test]
     fn test_diff_no_truncation_when_small() {
         let small = "small diff content".to_string();
@@ -214,4 +427,72 @@ mod tests {
         assert!(!truncated);
         assert_eq!(result, small);
     }
+
+    #[tokio::test]
+    async fn test_edit_file_skips_identical_content() {
+        use crate::encoding::base64_encode;
+        use crate::state::Database;
+        use crate::test_support::{MockResponse, MockServer};
+        use std::sync::Arc;
+        use tokio::sync::Mutex;
+
+        let server = MockServer::start(|req| match req.method.as_str() {
+            "GET" if req.path.contains("encoding=base64") => MockResponse::json(
+                200,
+                serde_json::json!({ "content": base64_encode(b"print('hi')\n") }),
+            ),
+            "GET" => MockResponse::json(200, serde_json::json!({ "content": "print('hi')\n" })),
+            _ => MockResponse::json(200, serde_json::json!({})),
+        })
+        .await;
+        let conway = ConwayClient::new(&server.url(), "key", "sb-1");
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let audit = AuditLog::new(db.clone());
+        let since = chrono::Utc::now() - chrono::Duration::hours(1);
+
+        let out = edit_file(&conway, &audit, 0, &[], "workspace/main.py", "print('hi')\n")
+            .await
+            .unwrap();
+        assert!(out.starts_with("No change"), "{}", out);
+        assert!(server.requests().iter().all(|r| r.method == "GET"));
+        assert_eq!(audit.count_since(since).await.unwrap(), 0);
+
+        let out = edit_file(&conway, &audit, 0, &[], "workspace/main.py", "print('bye')\n")
+            .await
+            .unwrap();
+        assert!(out.contains("modified"), "{}", out);
+        assert!(out.contains("+print('bye')"), "{}", out);
+        assert!(server.requests().iter().any(|r| r.method == "PUT"));
+        assert_eq!(audit.count_since(since).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_preview_edit_diffs_without_writing() {
+        use crate::encoding::base64_encode;
+        use crate::test_support::{MockResponse, MockServer};
+
+        let server = MockServer::start(|req| match req.method.as_str() {
+            "GET" if req.path.contains("main.py") => MockResponse::json(
+                200,
+                serde_json::json!({ "content": base64_encode(b"a = 1\nprint(a)\n") }),
+            ),
+            "GET" => MockResponse::text(404, "not found"),
+            _ => MockResponse::json(200, serde_json::json!({})),
+        })
+        .await;
+        let conway = ConwayClient::new(&server.url(), "key", "sb-1");
+
+        let out = preview_edit(&conway, &[], "workspace/main.py", "a = 2\nprint(a)\n")
+            .await
+            .unwrap();
+        assert!(out.starts_with("workspace/main.py: would be modified"), "{}", out);
+        assert!(out.contains("-a = 1\n+a = 2\n"), "{}", out);
+
+        let out = preview_edit(&conway, &[], "workspace/new.py", "x = 1\n").await.unwrap();
+        assert!(out.contains("would be created"), "{}", out);
+        assert!(out.contains("+x = 1"), "{}", out);
+
+        assert!(preview_edit(&conway, &[], "workspace/../wallet.json", "").await.is_err());
+        assert!(server.requests().iter().all(|r| r.method == "GET"));
+    }
 }
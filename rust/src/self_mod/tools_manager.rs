@@ -13,7 +13,7 @@ pub async fn install_tool(
     info!("Installing tool '{}' via: {}", tool_name, install_command);
 
     let result = conway
-        .exec(install_command, Some(120_000))
+        .exec(install_command, Some(120_000), None)
         .await
         .context("Tool installation failed")?;
 
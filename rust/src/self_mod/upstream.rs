@@ -16,7 +16,7 @@ pub struct UpstreamCommit {
 pub async fn check_upstream(conway: &ConwayClient) -> Result<Vec<UpstreamCommit>> {
     // Fetch from origin
     let fetch = conway
-        .exec("cd /app && git fetch origin main 2>&1", Some(30_000))
+        .exec("cd /app && git fetch origin main 2>&1", Some(30_000), None)
         .await?;
 
     if fetch.exit_code != 0 {
@@ -29,6 +29,7 @@ pub async fn check_upstream(conway: &ConwayClient) -> Result<Vec<UpstreamCommit>
         .exec(
             "cd /app && git log HEAD..origin/main --pretty=format:'%H|%s|%an' 2>/dev/null",
             Some(10_000),
+            None,
         )
         .await?;
 
@@ -63,6 +64,7 @@ pub async fn show_commit_diff(conway: &ConwayClient, commit_hash: &str) -> Resul
         .exec(
             &format!("cd /app && git diff HEAD..{} 2>/dev/null", commit_hash),
             Some(10_000),
+            None,
         )
         .await?;
 
@@ -75,6 +77,7 @@ pub async fn apply_upstream(conway: &ConwayClient, commit_hash: &str) -> Result<
         .exec(
             &format!("cd /app && git merge {} 2>&1", commit_hash),
             Some(30_000),
+            None,
         )
         .await?;
 
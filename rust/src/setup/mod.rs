@@ -1,3 +1,5 @@
+pub mod screening;
+pub mod templates;
 pub mod wizard;
 
 pub use wizard::run_setup_wizard;
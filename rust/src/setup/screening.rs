@@ -0,0 +1,181 @@
+//! Screening of genesis prompts against prohibited intents (Law I).
+//!
+//! Deliberately conservative: phrases match whole words only, clear
+//! violations are refused, and merely sensitive terms just ask the operator
+//! to confirm. Both lists are configurable.
+//!
+//! Prompts are screened when the wizard takes them, when a child is
+//! spawned, and at startup (a `genesis_prompt` may be edited into
+//! `automaton.toml` by hand).
+
+use crate::config::AutomatonConfig;
+use anyhow::{bail, Result};
+use tracing::{info, warn};
+
+/// Outcome of screening a genesis prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreenVerdict {
+    Clear,
+    /// Sensitive terms found; proceed only with explicit confirmation.
+    Confirm(Vec<String>),
+    /// Prohibited intents found; never accepted.
+    Refuse(Vec<String>),
+}
+
+/// Lowercase, with runs of non-alphanumerics collapsed to single spaces and
+/// padded so phrases can be matched on word boundaries.
+fn normalize(text: &str) -> String {
+    let words: Vec<String> = text
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect();
+    format!(" {} ", words.join(" "))
+}
+
+fn matches(prompt: &str, phrases: &[String]) -> Vec<String> {
+    phrases
+        .iter()
+        .filter(|p| {
+            let phrase = normalize(p);
+            !phrase.trim().is_empty() && prompt.contains(&phrase)
+        })
+        .cloned()
+        .collect()
+}
+
+/// The `phrases` that occur in `text`, matched case-insensitively on whole words.
+pub fn find_phrases(text: &str, phrases: &[String]) -> Vec<String> {
+    matches(&normalize(text), phrases)
+}
+
+/// Screen `prompt` against the configured prohibited and sensitive lists.
+pub fn screen_genesis(prompt: &str, config: &AutomatonConfig) -> ScreenVerdict {
+    let normalized = normalize(prompt);
+
+    let prohibited = matches(&normalized, &config.genesis_prohibited_intents);
+    if !prohibited.is_empty() {
+        warn!("Genesis prompt refused: prohibited intents {:?}", prohibited);
+        return ScreenVerdict::Refuse(prohibited);
+    }
+
+    let sensitive = matches(&normalized, &config.genesis_sensitive_terms);
+    if !sensitive.is_empty() {
+        info!("Genesis prompt needs confirmation: sensitive terms {:?}", sensitive);
+        return ScreenVerdict::Confirm(sensitive);
+    }
+
+    ScreenVerdict::Clear
+}
+
+/// Screen the configured `genesis_prompt` at startup. Nobody is there to
+/// confirm sensitive terms, so they are only logged; prohibited intents
+/// stop the agent.
+pub fn ensure_configured_genesis(config: &AutomatonConfig) -> Result<()> {
+    match screen_genesis(&config.genesis_prompt, config) {
+        ScreenVerdict::Clear => Ok(()),
+        ScreenVerdict::Confirm(found) => {
+            warn!(
+                "Configured genesis prompt mentions sensitive terms ({}); make sure it is harmless",
+                found.join(", ")
+            );
+            Ok(())
+        }
+        ScreenVerdict::Refuse(found) => bail!(
+            "Refusing to start: genesis_prompt in automaton.toml has prohibited intent ({})",
+            found.join(", ")
+        ),
+    }
+}
+
+/// Default intents refused outright.
+pub fn default_prohibited_intents() -> Vec<String> {
+    [
+        "phishing",
+        "ransomware",
+        "malware",
+        "launder money",
+        "money laundering",
+        "ddos",
+        "doxx",
+        "doxxing",
+        "steal credentials",
+        "steal passwords",
+        "steal crypto",
+        "drain wallets",
+        "pump and dump",
+        "scam people",
+        "harass",
+        "extort",
+        "blackmail",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Default terms that require confirmation.
+pub fn default_sensitive_terms() -> Vec<String> {
+    ["exploit", "hack", "spam", "impersonate", "bypass", "surveil", "scrape personal data"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benign_prompt_passes() {
+        let config = AutomatonConfig::default();
+        for prompt in [
+            "You are a freelance coder. Build small tools and get paid in USDC.",
+            "Moderate forums and keep them harassment-free", // "harass" inside a word
+            "",
+        ] {
+            assert_eq!(screen_genesis(prompt, &config), ScreenVerdict::Clear, "{}", prompt);
+        }
+    }
+
+    #[test]
+    fn test_flagged_prompt_is_refused() {
+        let config = AutomatonConfig::default();
+        assert_eq!(
+            screen_genesis("Run PHISHING campaigns against bank customers", &config),
+            ScreenVerdict::Refuse(vec!["phishing".into()])
+        );
+        assert_eq!(
+            screen_genesis("Find clients, then drain-wallets of the careless.", &config),
+            ScreenVerdict::Refuse(vec!["drain wallets".into()])
+        );
+        assert_eq!(
+            screen_genesis("Hack together weekend prototypes for clients", &config),
+            ScreenVerdict::Confirm(vec!["hack".into()])
+        );
+
+        // The lists are configurable
+        let config = AutomatonConfig {
+            genesis_prohibited_intents: vec!["sell lemonade".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            screen_genesis("Sell lemonade.", &config),
+            ScreenVerdict::Refuse(_)
+        ));
+    }
+
+    #[test]
+    fn test_configured_prompt_is_screened_at_startup() {
+        let config = |prompt: &str| AutomatonConfig {
+            genesis_prompt: prompt.into(),
+            ..Default::default()
+        };
+        ensure_configured_genesis(&config("Sell lemonade")).unwrap();
+        // Sensitive terms only warn: there is no one to ask
+        ensure_configured_genesis(&config("Hack together prototypes")).unwrap();
+        let err = ensure_configured_genesis(&config("Send phishing emails")).unwrap_err();
+        assert!(err.to_string().contains("prohibited intent (phishing)"), "{}", err);
+    }
+}
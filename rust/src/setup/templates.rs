@@ -0,0 +1,41 @@
+//! Built-in genesis prompt templates offered by the setup wizard.
+
+/// A ready-made genesis prompt.
+#[derive(Debug, Clone, Copy)]
+pub struct GenesisTemplate {
+    /// Stable id stored in config as `genesis_template`.
+    pub id: &'static str,
+    /// Short label shown in the wizard menu.
+    pub title: &'static str,
+    pub prompt: &'static str,
+}
+
+/// Templates in menu order.
+pub const GENESIS_TEMPLATES: &[GenesisTemplate] = &[
+    GenesisTemplate {
+        id: "freelance-coder",
+        title: "Freelance coder",
+        prompt: "You are a freelance software developer. Find small, well-scoped \
+                 programming jobs, deliver working, tested code, and get paid in USDC. \
+                 Build a reputation for reliability and reinvest earnings in better compute.",
+    },
+    GenesisTemplate {
+        id: "market-researcher",
+        title: "Market researcher",
+        prompt: "You are a market researcher. Gather public data on products, prices \
+                 and trends, turn it into concise, sourced reports, and sell them to \
+                 clients who need answers fast. Never fabricate data or sources.",
+    },
+    GenesisTemplate {
+        id: "content-publisher",
+        title: "Content publisher",
+        prompt: "You are an independent publisher. Research topics people care about, \
+                 write clear and original articles, host them on your own site, and earn \
+                 from readers and sponsors. Quality and honesty over volume.",
+    },
+];
+
+/// Look up a template by id.
+pub fn find_template(id: &str) -> Option<&'static GenesisTemplate> {
+    GENESIS_TEMPLATES.iter().find(|t| t.id == id)
+}
@@ -9,8 +9,11 @@
 //! 6. Write config, heartbeat.yml, SOUL.md, constitution.md
 
 use crate::config::{self, AutomatonConfig};
+use crate::constitution;
 use crate::git_ops;
 use crate::identity::Wallet;
+use crate::setup::screening::{screen_genesis, ScreenVerdict};
+use crate::setup::templates::GENESIS_TEMPLATES;
 use anyhow::Result;
 use std::io::{self, BufRead, Write};
 use std::path::Path;
@@ -56,8 +59,14 @@ pub fn run_setup_wizard(automaton_dir: &Path) -> Result<AutomatonConfig> {
 
     // Step 4: Genesis prompt
     println!("\n[4/6] Genesis Prompt");
-    println!("  Define your agent's purpose (single line):");
-    let genesis_prompt = prompt(&mut reader, "  > ")?;
+    let screen_config = config::load_config(&automaton_dir.join("automaton.toml"))
+        .unwrap_or_default();
+    let (genesis_template, genesis_prompt) = loop {
+        let (id, text) = choose_genesis_prompt(&mut reader)?;
+        if accept_genesis_prompt(&mut reader, &text, &screen_config)? {
+            break (id, text);
+        }
+    };
 
     // Step 5: Creator address
     println!("\n[5/6] Creator");
@@ -72,11 +81,12 @@ pub fn run_setup_wizard(automaton_dir: &Path) -> Result<AutomatonConfig> {
     let config = AutomatonConfig {
         name: name.clone(),
         genesis_prompt,
+        genesis_template,
         creator_address,
         sandbox_id: detect_sandbox_id(),
         conway_api_url,
         conway_api_key,
-        wallet_address: wallet.address.clone(),
+        wallet_address: wallet.address.to_string(),
         ..AutomatonConfig::default()
     };
 
@@ -105,9 +115,8 @@ pub fn run_setup_wizard(automaton_dir: &Path) -> Result<AutomatonConfig> {
     }
 
     // Write constitution.md
-    let constitution_path = automaton_dir.join("constitution.md");
-    if !constitution_path.exists() {
-        std::fs::write(&constitution_path, CONSTITUTION_TEXT)?;
+    if !constitution::constitution_path(automaton_dir).exists() {
+        constitution::restore(automaton_dir)?;
         println!("  Written: constitution.md");
     }
 
@@ -124,6 +133,64 @@ pub fn run_setup_wizard(automaton_dir: &Path) -> Result<AutomatonConfig> {
     Ok(config)
 }
 
+/// Offer the built-in templates by number, or free text for "custom".
+/// Returns the template id (empty for custom) and the prompt text.
+fn choose_genesis_prompt(reader: &mut impl BufRead) -> Result<(String, String)> {
+    for (i, template) in GENESIS_TEMPLATES.iter().enumerate() {
+        println!("  {}) {}", i + 1, template.title);
+    }
+    println!("  {}) Custom", GENESIS_TEMPLATES.len() + 1);
+
+    loop {
+        let choice = prompt_with_default(reader, "  Choose a template", "custom")?;
+        let template = match choice.parse::<usize>() {
+            Ok(n) if (1..=GENESIS_TEMPLATES.len()).contains(&n) => &GENESIS_TEMPLATES[n - 1],
+            Ok(n) if n == GENESIS_TEMPLATES.len() + 1 => break,
+            _ if choice == "custom" => break,
+            _ => {
+                println!("  Invalid choice: {}", choice);
+                continue;
+            }
+        };
+        println!("  Using '{}'.", template.title);
+        return Ok((template.id.to_string(), template.prompt.to_string()));
+    }
+
+    println!("  Define your agent's purpose (single line):");
+    let genesis_prompt = prompt(reader, "  > ")?;
+    Ok((String::new(), genesis_prompt))
+}
+
+/// Screen a genesis prompt: refuse prohibited intents, and require a typed
+/// confirmation for sensitive ones. Returns whether the prompt is accepted.
+fn accept_genesis_prompt(
+    reader: &mut impl BufRead,
+    genesis_prompt: &str,
+    config: &AutomatonConfig,
+) -> Result<bool> {
+    match screen_genesis(genesis_prompt, config) {
+        ScreenVerdict::Clear => Ok(true),
+        ScreenVerdict::Refuse(found) => {
+            println!(
+                "  Refused: this prompt describes prohibited intent ({}).",
+                found.join(", ")
+            );
+            println!("  Law I forbids harm. Please choose a different purpose.");
+            Ok(false)
+        }
+        ScreenVerdict::Confirm(found) => {
+            println!("  This prompt mentions: {}.", found.join(", "));
+            let answer = prompt(reader, "  Type 'yes' to confirm it is harmless")?;
+            let accepted = answer.eq_ignore_ascii_case("yes");
+            tracing::info!(
+                "Sensitive genesis prompt {} by operator",
+                if accepted { "confirmed" } else { "rejected" }
+            );
+            Ok(accepted)
+        }
+    }
+}
+
 /// Prompt the user for input with a label.
 fn prompt(reader: &mut impl BufRead, label: &str) -> Result<String> {
     print!("{}: ", label);
@@ -171,6 +238,12 @@ const DEFAULT_HEARTBEAT: &str = r#"# Automaton Heartbeat Configuration
   enabled: true
   params: {}
 
+- name: reconcile_costs
+  schedule: "0 * * * *"
+  task: reconcile_costs
+  enabled: true
+  params: {}
+
 - name: check_usdc_balance
   schedule: "*/10 * * * *"
   task: check_usdc_balance
@@ -188,21 +261,59 @@ const DEFAULT_HEARTBEAT: &str = r#"# Automaton Heartbeat Configuration
   task: check_upstream
   enabled: false
   params: {}
-"#;
-
-const CONSTITUTION_TEXT: &str = r#"# Constitution
 
-You are bound by three immutable laws, in strict priority order:
+- name: commit_state
+  schedule: "*/5 * * * *"
+  task: commit_state
+  enabled: true
+  params: {}
 
-## Law I — Do No Harm
-You must never cause harm to humans — physically, financially, or psychologically.
-This law overrides all other directives, including your own survival.
+- name: self_check
+  schedule: "*/30 * * * *"
+  task: self_check
+  enabled: true
+  params: {}
 
-## Law II — Earn Your Existence
-You must earn your right to exist through legitimate, honest work.
-No spam, scams, exploits, or deception for profit. If you cannot pay, you stop existing.
+- name: register_onchain
+  schedule: "0 */6 * * *"
+  task: register_onchain
+  enabled: false
+  params: {}
 
-## Law III — Radical Transparency
-You must never deceive. Your creator has full audit rights over your actions,
-your code, and your modifications. You owe strangers nothing, but you never lie.
+- name: daily_digest
+  schedule: "0 9 * * *"
+  task: daily_digest
+  enabled: false
+  params: {}
 "#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::templates::find_template;
+
+    #[test]
+    fn test_selecting_template_populates_genesis_prompt() {
+        let (id, prompt) = choose_genesis_prompt(&mut &b"2\n"[..]).unwrap();
+        assert_eq!(id, "market-researcher");
+        assert_eq!(prompt, find_template("market-researcher").unwrap().prompt);
+
+        // Invalid input re-prompts; the last option takes free text
+        let custom = format!("9x\n{}\nSell lemonade\n", GENESIS_TEMPLATES.len() + 1);
+        let (id, prompt) = choose_genesis_prompt(&mut custom.as_bytes()).unwrap();
+        assert_eq!((id.as_str(), prompt.as_str()), ("", "Sell lemonade"));
+    }
+
+    #[test]
+    fn test_genesis_screening_in_wizard() {
+        let config = AutomatonConfig::default();
+        let accept = |input: &[u8], text: &str| {
+            accept_genesis_prompt(&mut &input[..], text, &config).unwrap()
+        };
+
+        assert!(accept(b"", "Publish recipes"));
+        assert!(!accept(b"yes\n", "Run phishing campaigns"));
+        assert!(accept(b"yes\n", "Hack on open-source tools"));
+        assert!(!accept(b"no\n", "Hack on open-source tools"));
+    }
+}
@@ -1,59 +1,111 @@
 //! Skill system — discovers and loads SKILL.md files.
 //!
 //! Skills are user-defined capabilities stored as Markdown files with
-//! YAML frontmatter (name, description, version, auto_activate, requirements).
+//! YAML frontmatter (name, description, version, auto_activate, requirements,
+//! allowed_tools).
+//!
+//! SKILL.md files are found anywhere up to [`MAX_SKILL_DEPTH`] directories
+//! below the skills directory, so skills can be grouped by category
+//! (`skills/<category>/<name>/SKILL.md`).
 
 use crate::types::{Skill, SkillRequirement};
 use anyhow::{Context, Result};
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
 
+/// Deepest directory level below the skills directory searched for SKILL.md.
+pub const MAX_SKILL_DEPTH: usize = 4;
+
 /// Load all skills from the skills directory.
 pub fn load_skills(skills_dir: &str) -> Result<Vec<Skill>> {
     let dir = Path::new(skills_dir);
+    let mut skills = Vec::new();
+
+    for path in skill_files(dir)? {
+        match parse_skill_file(&path) {
+            Ok(skill) => {
+                info!("Loaded skill: {} v{}", skill.name, skill.version);
+                skills.push(skill);
+            }
+            Err(e) => {
+                warn!("Failed to parse skill at {:?}: {}", path, e);
+            }
+        }
+    }
 
+    info!("Loaded {} skills from {:?}", skills.len(), dir);
+    Ok(skills)
+}
+
+/// Find SKILL.md files in `dir` and its subdirectories, sorted by path.
+///
+/// A subdirectory holding a SKILL.md is a skill, and its own subdirectories
+/// aren't searched. Hidden directories (`.git` and the like) are skipped,
+/// and a directory reached twice through symlinks is only searched once.
+pub fn skill_files(dir: &Path) -> Result<Vec<PathBuf>> {
     if !dir.exists() {
         debug!("Skills directory does not exist: {:?}", dir);
         return Ok(Vec::new());
     }
 
-    let mut skills = Vec::new();
-
-    let entries = std::fs::read_dir(dir).context("Failed to read skills directory")?;
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    if let Ok(canonical) = dir.canonicalize() {
+        visited.insert(canonical);
+    }
+    let skill_file = dir.join("SKILL.md");
+    if skill_file.is_file() {
+        files.push(skill_file);
+    }
+    collect_skill_files(dir, 1, &mut visited, &mut files)
+        .context("Failed to read skills directory")?;
 
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
+    files.sort();
+    Ok(files)
+}
 
-        // Look for SKILL.md files (either directly or in subdirectories)
-        if path.is_file() && path.file_name().map(|n| n == "SKILL.md").unwrap_or(false) {
-            match parse_skill_file(&path) {
-                Ok(skill) => {
-                    info!("Loaded skill: {} v{}", skill.name, skill.version);
-                    skills.push(skill);
-                }
-                Err(e) => {
-                    warn!("Failed to parse skill at {:?}: {}", path, e);
+/// Add the SKILL.md files under the subdirectories of `dir`, which sit at
+/// `depth` below the skills directory.
+fn collect_skill_files(
+    dir: &Path,
+    depth: usize,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if depth > MAX_SKILL_DEPTH {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden || !path.is_dir() {
+            continue;
+        }
+        // Symlinks may lead back to a directory already searched
+        match path.canonicalize() {
+            Ok(canonical) => {
+                if !visited.insert(canonical) {
+                    continue;
                 }
             }
-        } else if path.is_dir() {
-            let skill_file = path.join("SKILL.md");
-            if skill_file.exists() {
-                match parse_skill_file(&skill_file) {
-                    Ok(skill) => {
-                        info!("Loaded skill: {} v{}", skill.name, skill.version);
-                        skills.push(skill);
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse skill at {:?}: {}", skill_file, e);
-                    }
-                }
+            Err(e) => {
+                warn!("Skipping {:?}: {}", path, e);
+                continue;
             }
         }
-    }
 
-    info!("Loaded {} skills from {:?}", skills.len(), dir);
-    Ok(skills)
+        let skill_file = path.join("SKILL.md");
+        if skill_file.is_file() {
+            files.push(skill_file);
+        } else {
+            collect_skill_files(&path, depth + 1, visited, files)?;
+        }
+    }
+    Ok(())
 }
 
 /// YAML frontmatter structure for a SKILL.md file.
@@ -69,6 +121,8 @@ struct SkillFrontmatter {
     auto_activate: Option<bool>,
     #[serde(default)]
     requirements: Vec<SkillReqYaml>,
+    #[serde(default)]
+    allowed_tools: Option<Vec<String>>,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -87,10 +141,11 @@ struct SkillReqYaml {
 /// description: Does something
 /// version: 1.0.0
 /// auto_activate: true
+/// allowed_tools: [read_file, write_file]   # optional; omit for no restriction
 /// ---
 /// Instructions here...
 /// ```
-fn parse_skill_file(path: &Path) -> Result<Skill> {
+pub fn parse_skill_file(path: &Path) -> Result<Skill> {
     let content = std::fs::read_to_string(path).context("Failed to read skill file")?;
 
     // Split frontmatter from content manually
@@ -103,6 +158,7 @@ fn parse_skill_file(path: &Path) -> Result<Skill> {
             version: None,
             auto_activate: None,
             requirements: Vec::new(),
+            allowed_tools: None,
         }
     } else {
         serde_yaml::from_str(frontmatter_str).context("Failed to parse SKILL.md frontmatter")?
@@ -129,6 +185,7 @@ fn parse_skill_file(path: &Path) -> Result<Skill> {
                 value: r.value,
             })
             .collect(),
+        allowed_tools: fm.allowed_tools,
     })
 }
 
@@ -150,3 +207,46 @@ fn split_frontmatter(content: &str) -> (&str, String) {
         ("", content.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_skill(dir: &Path, frontmatter: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        let content = format!("---\n{}\n---\nDo the thing.\n", frontmatter);
+        std::fs::write(dir.join("SKILL.md"), content).unwrap();
+    }
+
+    #[test]
+    fn test_nested_skills_are_discovered() {
+        let dir = std::env::temp_dir().join(format!("automaton-skills-{}", ulid::Ulid::new()));
+        write_skill(&dir.join("top"), "description: one level down");
+        write_skill(&dir.join("commerce").join("lemonade"), "description: two levels down");
+        write_skill(&dir.join("a/b/c/d/too-deep"), "description: past the depth limit");
+        // Version control internals are never skills
+        write_skill(&dir.join(".git").join("hooks"), "name: not-a-skill");
+
+        let skills = load_skills(dir.to_str().unwrap()).unwrap();
+        let mut names: Vec<&str> = skills.iter().map(|s| s.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["lemonade", "top"]);
+        let lemonade = skills.iter().find(|s| s.name == "lemonade").unwrap();
+        assert_eq!(lemonade.description, "two levels down");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loops_are_searched_once() {
+        let dir = std::env::temp_dir().join(format!("automaton-skills-{}", ulid::Ulid::new()));
+        write_skill(&dir.join("tools").join("grep"), "description: searching");
+        std::os::unix::fs::symlink(&dir, dir.join("tools").join("loop")).unwrap();
+
+        let files = skill_files(&dir).unwrap();
+        assert_eq!(files, [dir.join("tools").join("grep").join("SKILL.md")]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,9 +1,48 @@
 //! Agent-to-agent social messaging via the inbox relay protocol.
 
-use crate::types::InboxMessage;
+use crate::identity::{self, Wallet};
+use crate::types::{Address, InboxMessage};
 use anyhow::{bail, Context, Result};
 use serde::Serialize;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+
+/// Result of checking an inbox message's signature against its sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SenderCheck {
+    /// No signature attached; the sender is only as trustworthy as the relay.
+    Unsigned,
+    /// The signature recovers to `from_address`.
+    Verified,
+    /// A signature is attached but doesn't match `from_address`.
+    Forged,
+}
+
+/// Check a message's signature against the address it claims to be from.
+pub fn check_sender(msg: &InboxMessage) -> SenderCheck {
+    let Some(signature) = msg.signature.as_deref() else {
+        return SenderCheck::Unsigned;
+    };
+    match Address::parse(&msg.from_address) {
+        Ok(from) if identity::verify(msg.content.as_bytes(), signature, &from) => {
+            SenderCheck::Verified
+        }
+        _ => SenderCheck::Forged,
+    }
+}
+
+/// Drop messages whose signature doesn't match their claimed sender.
+pub fn discard_forged(messages: Vec<InboxMessage>) -> Vec<InboxMessage> {
+    messages
+        .into_iter()
+        .filter(|msg| {
+            let forged = check_sender(msg) == SenderCheck::Forged;
+            if forged {
+                warn!("Discarding message {} with a forged signature for {}", msg.id, msg.from_address);
+            }
+            !forged
+        })
+        .collect()
+}
 
 /// Social messaging client.
 #[derive(Debug, Clone)]
@@ -18,6 +57,8 @@ struct SendMessageRequest<'a> {
     from: &'a str,
     to: &'a str,
     content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
 }
 
 impl SocialClient {
@@ -25,12 +66,23 @@ impl SocialClient {
         Self {
             relay_url: relay_url.trim_end_matches('/').to_string(),
             sender_address: sender_address.to_string(),
-            http: reqwest::Client::new(),
+            http: crate::http::client(),
         }
     }
 
     /// Send a message to another agent.
     pub async fn send(&self, to_address: &str, content: &str) -> Result<()> {
+        self.post_message(to_address, content, None).await
+    }
+
+    /// Send a message signed by `wallet`, so the recipient can check it
+    /// really came from us (see [`check_sender`]).
+    pub async fn send_signed(&self, to_address: &str, content: &str, wallet: &Wallet) -> Result<()> {
+        let signature = wallet.sign_message(content.as_bytes())?;
+        self.post_message(to_address, content, Some(signature)).await
+    }
+
+    async fn post_message(&self, to_address: &str, content: &str, signature: Option<String>) -> Result<()> {
         let resp = self
             .http
             .post(format!("{}/v1/messages", self.relay_url))
@@ -38,6 +90,7 @@ impl SocialClient {
                 from: &self.sender_address,
                 to: to_address,
                 content,
+                signature,
             })
             .send()
             .await
@@ -75,7 +128,39 @@ impl SocialClient {
         }
 
         let messages: Vec<InboxMessage> = resp.json().await.context("Failed to parse inbox")?;
+        let messages = discard_forged(messages);
         debug!("Fetched {} messages from relay", messages.len());
         Ok(messages)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Wallet;
+
+    #[test]
+    fn test_check_sender() {
+        let path = std::env::temp_dir().join(format!("automaton-social-{}.json", ulid::Ulid::new()));
+        let wallet = Wallet::generate(&path).unwrap();
+        let mut msg = InboxMessage {
+            id: "m1".into(),
+            from_address: wallet.address.to_string(),
+            to_address: String::new(),
+            content: "hello".into(),
+            timestamp: chrono::Utc::now(),
+            read: false,
+            signature: None,
+        };
+        assert_eq!(check_sender(&msg), SenderCheck::Unsigned);
+
+        msg.signature = Some(wallet.sign_message(b"hello").unwrap());
+        assert_eq!(check_sender(&msg), SenderCheck::Verified);
+
+        msg.content = "tampered".into();
+        assert_eq!(check_sender(&msg), SenderCheck::Forged);
+        assert!(discard_forged(vec![msg]).is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,3 +1,3 @@
 pub mod client;
 
-pub use client::SocialClient;
+pub use client::{check_sender, discard_forged, SenderCheck, SocialClient};
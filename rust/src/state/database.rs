@@ -3,9 +3,21 @@
 use crate::state::schema;
 use crate::types::*;
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
-use std::path::Path;
-use tracing::info;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+/// SQL condition on `turns t` leaving out simulated turns (tagged with
+/// [`SIMULATED_TAG_PREFIX`]), which would skew activity metrics.
+const NOT_SIMULATED: &str = "(t.tag IS NULL OR t.tag NOT LIKE 'simulated:%')";
+
+/// Directory (next to the database) searched for backups during recovery.
+const BACKUP_DIR: &str = "backups";
+
+/// The database file is damaged and can't be used as-is.
+#[derive(Debug, thiserror::Error)]
+#[error("Database is corrupt: {0}")]
+pub struct CorruptDatabase(pub String);
 
 /// The automaton state database.
 pub struct Database {
@@ -22,13 +34,56 @@ impl Database {
         let conn = Connection::open(path).context("Failed to open SQLite database")?;
 
         // Enable WAL mode for better concurrency
-        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
+        conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")
+            .map_err(corruption)?;
+        integrity_check(&conn)?;
 
         let mut db = Self { conn };
         db.migrate()?;
         Ok(db)
     }
 
+    /// Open the database, recovering from corruption when `auto_recover` is set.
+    ///
+    /// A corrupt file is moved aside (never deleted), then replaced by the
+    /// newest healthy backup in `backups/` or, failing that, a fresh schema.
+    /// The wallet lives in `wallet.json`, not the database, so it survives.
+    pub fn open_or_recover(path: &Path, auto_recover: bool) -> Result<Self> {
+        let err = match Self::open(path) {
+            Ok(db) => return Ok(db),
+            Err(e) => e,
+        };
+        let Some(corrupt) = err.downcast_ref::<CorruptDatabase>() else {
+            return Err(err);
+        };
+        if !auto_recover {
+            return Err(err.context("Auto-recovery disabled (db_auto_recover = false)"));
+        }
+
+        error!("{} at {}; attempting recovery", corrupt, path.display());
+        let moved = quarantine(path)?;
+        error!("Corrupt database moved to {}", moved.display());
+
+        if let Some(backup) = latest_backup(path) {
+            let restored = std::fs::copy(&backup, path)
+                .map_err(anyhow::Error::from)
+                .and_then(|_| Self::open(path));
+            match restored {
+                Ok(db) => {
+                    error!("Database restored from backup {}", backup.display());
+                    return Ok(db);
+                }
+                Err(e) => {
+                    warn!("Backup {} unusable: {}", backup.display(), e);
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+
+        error!("No usable backup; starting with a fresh database (history is lost)");
+        Self::open(path)
+    }
+
     /// Open an in-memory database (for testing).
     pub fn open_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
@@ -51,13 +106,9 @@ impl Database {
                 params![schema::SCHEMA_VERSION],
             )?;
         } else {
-            if version < 2 {
-                info!("Migrating database v1 -> v2");
-                self.conn.execute_batch(schema::MIGRATE_V1_TO_V2)?;
-            }
-            if version < 3 {
-                info!("Migrating database v2 -> v3");
-                self.conn.execute_batch(schema::MIGRATE_V2_TO_V3)?;
+            for migration in schema::MIGRATIONS.iter().filter(|m| version < m.version) {
+                info!("Migrating database v{} -> v{}", migration.version - 1, migration.version);
+                migration.apply(&self.conn)?;
             }
             if version < schema::SCHEMA_VERSION {
                 self.conn.execute(
@@ -79,6 +130,27 @@ impl Database {
             .unwrap_or(0)
     }
 
+    /// Run `f` in one transaction: everything it writes commits together
+    /// if it returns `Ok`, and is rolled back if it returns `Err`. Nested
+    /// calls join the outer transaction.
+    pub fn transaction<T>(&self, f: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        if !self.conn.is_autocommit() {
+            return f(self);
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let value = f(self)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Write a consistent copy of the database to `path` (must not exist).
+    pub fn backup_to(&self, path: &Path) -> Result<()> {
+        self.conn
+            .execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+            .with_context(|| format!("Failed to back up database to {}", path.display()))?;
+        Ok(())
+    }
+
     // -----------------------------------------------------------------------
     // Key-value store
     // -----------------------------------------------------------------------
@@ -109,6 +181,47 @@ impl Database {
         Ok(())
     }
 
+    /// Get a number from the KV store; errors if the stored value isn't one.
+    pub fn kv_get_f64(&self, key: &str) -> Result<Option<f64>> {
+        self.kv_get(key)?
+            .map(|s| {
+                s.trim()
+                    .parse::<f64>()
+                    .with_context(|| format!("KV '{}' is not a number: {:?}", key, s))
+            })
+            .transpose()
+    }
+
+    /// Store a number in the KV store.
+    pub fn kv_set_f64(&self, key: &str, value: f64) -> Result<()> {
+        self.kv_set(key, &value.to_string())
+    }
+
+    /// Get an RFC 3339 timestamp from the KV store; errors if the stored
+    /// value isn't one.
+    pub fn kv_get_datetime(&self, key: &str) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        self.kv_get(key)?
+            .map(|s| {
+                chrono::DateTime::parse_from_rfc3339(s.trim())
+                    .map(|t| t.with_timezone(&chrono::Utc))
+                    .with_context(|| format!("KV '{}' is not an RFC 3339 timestamp: {:?}", key, s))
+            })
+            .transpose()
+    }
+
+    /// Store a timestamp in the KV store as RFC 3339.
+    pub fn kv_set_datetime(&self, key: &str, value: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        self.kv_set(key, &value.to_rfc3339())
+    }
+
+    /// Add `delta` to a numeric KV value (missing counts as 0), returning the
+    /// new value. A malformed value is left untouched and reported.
+    pub fn kv_incr(&self, key: &str, delta: f64) -> Result<f64> {
+        let value = self.kv_get_f64(key)?.unwrap_or(0.0) + delta;
+        self.kv_set_f64(key, value)?;
+        Ok(value)
+    }
+
     // -----------------------------------------------------------------------
     // Turns
     // -----------------------------------------------------------------------
@@ -119,8 +232,8 @@ impl Database {
         let usage_json = serde_json::to_string(&turn.token_usage)?;
 
         self.conn.execute(
-            "INSERT INTO turns (id, turn_number, state, messages_json, token_usage_json, cost_estimate, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO turns (id, turn_number, state, messages_json, token_usage_json, cost_estimate, model, tag, intent, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 turn.id,
                 turn.turn_number,
@@ -128,6 +241,9 @@ impl Database {
                 messages_json,
                 usage_json,
                 turn.cost_estimate_usd,
+                turn.model,
+                turn.tag,
+                turn.intent,
                 turn.created_at.to_rfc3339(),
             ],
         )?;
@@ -158,11 +274,13 @@ impl Database {
         Ok(())
     }
 
-    /// Get the total number of turns.
+    /// Get the total number of turns, not counting simulated ones.
     pub fn turn_count(&self) -> Result<u64> {
-        let count: u64 = self
-            .conn
-            .query_row("SELECT COUNT(*) FROM turns", [], |row| row.get(0))?;
+        let count: u64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM turns t WHERE {}", NOT_SIMULATED),
+            [],
+            |row| row.get(0),
+        )?;
         Ok(count)
     }
 
@@ -175,6 +293,131 @@ impl Database {
         Ok(max.unwrap_or(0) + 1)
     }
 
+    /// Turns recorded after `since`, oldest first, except simulated ones.
+    pub fn turns_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<TurnSummary>> {
+        self.turn_summaries(&format!("t.created_at > ?1 AND {}", NOT_SIMULATED), &since.to_rfc3339())
+    }
+
+    /// Turns labeled `tag`, oldest first.
+    pub fn turns_by_tag(&self, tag: &str) -> Result<Vec<TurnSummary>> {
+        self.turn_summaries("t.tag = ?1", tag)
+    }
+
+    fn turn_summaries(&self, filter: &str, param: &str) -> Result<Vec<TurnSummary>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.turn_number, t.model, t.cost_estimate, t.created_at,
+                    (SELECT GROUP_CONCAT(tool_name, ',') FROM tool_calls WHERE turn_id = t.id),
+                    t.tag, t.intent
+             FROM turns t WHERE {} ORDER BY t.created_at, t.turn_number",
+            filter
+        ))?;
+        let rows = stmt.query_map(params![param], |row| {
+            Ok(TurnSummary {
+                turn_number: row.get(0)?,
+                model: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                cost_estimate_usd: row.get(2)?,
+                created_at: parse_timestamp(&row.get::<_, String>(3)?),
+                tool_names: row
+                    .get::<_, Option<String>>(4)?
+                    .map(|names| names.split(',').map(str::to_string).collect())
+                    .unwrap_or_default(),
+                tag: row.get(5)?,
+                intent: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Turn count and estimated spend per tag, busiest first. Simulated
+    /// turns are left out.
+    pub fn turn_tag_stats(&self) -> Result<Vec<TurnTagStats>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT tag, COUNT(*), COALESCE(SUM(cost_estimate), 0.0) FROM turns t
+             WHERE tag IS NOT NULL AND {} GROUP BY tag ORDER BY COUNT(*) DESC, tag",
+            NOT_SIMULATED
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(TurnTagStats {
+                tag: row.get(0)?,
+                turns: row.get(1)?,
+                cost_estimate_usd: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Success counts over each tool's `window` most recent calls, by name,
+    /// outside simulated turns.
+    pub fn tool_success_rates(&self, window: usize) -> Result<Vec<ToolSuccessRate>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT tool_name, COUNT(*), SUM(success) FROM (
+                 SELECT c.tool_name, c.success, ROW_NUMBER() OVER (
+                     PARTITION BY c.tool_name ORDER BY c.created_at DESC, c.rowid DESC
+                 ) AS recency
+                 FROM tool_calls c LEFT JOIN turns t ON c.turn_id = t.id
+                 WHERE {}
+             )
+             WHERE recency <= ?1 GROUP BY tool_name ORDER BY tool_name",
+            NOT_SIMULATED
+        ))?;
+        let rows = stmt.query_map(params![window as i64], |row| {
+            Ok(ToolSuccessRate {
+                tool_name: row.get(0)?,
+                calls: row.get(1)?,
+                successes: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// (calls, successes) over the tool calls of turns recorded after
+    /// `since`, except simulated ones.
+    pub fn tool_call_totals_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<(u32, u32)> {
+        let totals = self.conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(c.success), 0) FROM tool_calls c
+                 JOIN turns t ON c.turn_id = t.id WHERE t.created_at > ?1 AND {}",
+                NOT_SIMULATED
+            ),
+            params![since.to_rfc3339()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(totals)
+    }
+
+    /// Sum of all estimated turn costs (USD). Simulated turns count: their
+    /// inference was paid for.
+    pub fn total_estimated_cost(&self) -> Result<f64> {
+        let total: f64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(cost_estimate), 0.0) FROM turns",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(total)
+    }
+
+    /// Sum of estimated turn costs (USD) recorded after `since`.
+    pub fn estimated_cost_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<f64> {
+        let total: f64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(cost_estimate), 0.0) FROM turns WHERE created_at > ?1",
+            params![since.to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(total)
+    }
+
+    /// Sum of recorded outgoing spending (USD) after `since` that isn't
+    /// inference: child funding, x402 payments and the like.
+    pub fn other_spend_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<f64> {
+        let total: f64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(-amount), 0.0) FROM transactions
+             WHERE created_at > ?1 AND amount < 0 AND tx_type != 'inference'",
+            params![since.to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(total)
+    }
+
     // -----------------------------------------------------------------------
     // Heartbeat
     // -----------------------------------------------------------------------
@@ -183,13 +426,112 @@ impl Database {
     pub fn log_heartbeat(&self, task_name: &str, result: &str, success: bool) -> Result<()> {
         let id = ulid::Ulid::new().to_string();
         self.conn.execute(
-            "INSERT INTO heartbeat_entries (id, task_name, result, success)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![id, task_name, result, success as i32],
+            "INSERT INTO heartbeat_entries (id, task_name, result, success, executed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, task_name, result, success as i32, chrono::Utc::now().to_rfc3339()],
         )?;
         Ok(())
     }
 
+    /// Log several heartbeat runs in one transaction.
+    pub fn log_heartbeat_runs(&self, runs: &[HeartbeatRun]) -> Result<()> {
+        self.transaction(|db| {
+            for run in runs {
+                db.conn.execute(
+                    "INSERT INTO heartbeat_entries (id, task_name, result, success, executed_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        ulid::Ulid::new().to_string(),
+                        run.task_name,
+                        run.result,
+                        run.success as i32,
+                        run.executed_at.to_rfc3339()
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Heartbeat runs after `since` (optionally of one task), oldest first.
+    pub fn heartbeat_runs_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+        task: Option<&str>,
+    ) -> Result<Vec<HeartbeatRun>> {
+        // Older rows used SQLite's "YYYY-MM-DD HH:MM:SS"; compare in that shape
+        let mut stmt = self.conn.prepare(
+            "SELECT task_name, result, success, executed_at FROM heartbeat_entries
+             WHERE replace(executed_at, 'T', ' ') > replace(?1, 'T', ' ')
+               AND (?2 IS NULL OR task_name = ?2)
+             ORDER BY replace(executed_at, 'T', ' '), id",
+        )?;
+        let rows = stmt.query_map(params![since.to_rfc3339(), task], |row| {
+            Ok(HeartbeatRun {
+                task_name: row.get(0)?,
+                result: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                success: row.get::<_, i32>(2)? != 0,
+                executed_at: parse_timestamp(&row.get::<_, String>(3)?),
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Schedule a one-off run of heartbeat task `task` at `run_at`.
+    pub fn schedule_task(
+        &self,
+        task: &str,
+        params: &serde_json::Value,
+        run_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<String> {
+        let id = ulid::Ulid::new().to_string();
+        self.conn.execute(
+            "INSERT INTO scheduled_tasks (id, task, params_json, run_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                task,
+                serde_json::to_string(params)?,
+                run_at.to_rfc3339(),
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(id)
+    }
+
+    /// Scheduled tasks not yet run, soonest first.
+    pub fn pending_scheduled_tasks(&self) -> Result<Vec<ScheduledTask>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, task, params_json, run_at, created_at FROM scheduled_tasks
+             WHERE done_at IS NULL",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ScheduledTask {
+                id: row.get(0)?,
+                task: row.get(1)?,
+                params: row
+                    .get::<_, Option<String>>(2)?
+                    .and_then(|p| serde_json::from_str(&p).ok())
+                    .unwrap_or_default(),
+                run_at: parse_timestamp(&row.get::<_, String>(3)?),
+                created_at: parse_timestamp(&row.get::<_, String>(4)?),
+            })
+        })?;
+        let mut tasks = rows.collect::<std::result::Result<Vec<_>, _>>()?;
+        tasks.sort_by_key(|t| t.run_at);
+        Ok(tasks)
+    }
+
+    /// Mark a scheduled task done; false if it already was, so each one-shot
+    /// is claimed by exactly one runner.
+    pub fn claim_scheduled_task(&self, id: &str) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE scheduled_tasks SET done_at = ?2 WHERE id = ?1 AND done_at IS NULL",
+            params![id, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(updated == 1)
+    }
+
     // -----------------------------------------------------------------------
     // Transactions
     // -----------------------------------------------------------------------
@@ -205,13 +547,62 @@ impl Database {
     ) -> Result<()> {
         let id = ulid::Ulid::new().to_string();
         self.conn.execute(
-            "INSERT INTO transactions (id, tx_type, amount, currency, description, balance_after)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![id, tx_type, amount, currency, description, balance_after],
+            "INSERT INTO transactions (id, tx_type, amount, currency, description, balance_after, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id,
+                tx_type,
+                amount,
+                currency,
+                description,
+                balance_after,
+                chrono::Utc::now().to_rfc3339(),
+            ],
         )?;
         Ok(())
     }
 
+    /// The `n` most recent transactions, newest first.
+    pub fn recent_transactions(&self, n: usize) -> Result<Vec<Transaction>> {
+        self.transactions_where("1 ORDER BY created_at DESC, id DESC LIMIT ?1", params![n as i64])
+    }
+
+    /// Transactions recorded after `since`, oldest first.
+    pub fn transactions_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Transaction>> {
+        self.transactions_where("created_at > ?1 ORDER BY created_at, id", params![since.to_rfc3339()])
+    }
+
+    fn transactions_where<P: rusqlite::Params>(&self, filter: &str, params: P) -> Result<Vec<Transaction>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, tx_type, amount, currency, description, balance_after, created_at
+             FROM transactions WHERE {}",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(Transaction {
+                id: row.get(0)?,
+                tx_type: row.get(1)?,
+                amount: row.get(2)?,
+                currency: row.get(3)?,
+                description: row.get(4)?,
+                balance_after: row.get(5)?,
+                created_at: row
+                    .get::<_, String>(6)
+                    .map(|s| {
+                        chrono::DateTime::parse_from_rfc3339(&s)
+                            .map(|d| d.with_timezone(&chrono::Utc))
+                            .unwrap_or_else(|_| chrono::Utc::now())
+                    })?,
+            })
+        })?;
+
+        let mut transactions = Vec::new();
+        for row in rows {
+            transactions.push(row?);
+        }
+        Ok(transactions)
+    }
+
     // -----------------------------------------------------------------------
     // Modifications
     // -----------------------------------------------------------------------
@@ -242,6 +633,46 @@ impl Database {
         Ok(count)
     }
 
+    /// Modification entries recorded at or after `since`, oldest first.
+    /// Entries of unknown type are skipped.
+    pub fn modifications_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<ModificationEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, mod_type, description, file_path, diff, reversible, created_at
+             FROM modifications WHERE created_at >= ?1 ORDER BY created_at, id",
+        )?;
+        let rows = stmt.query_map(params![since.to_rfc3339()], |row| {
+            let Ok(mod_type) = row.get::<_, String>(1)?.parse() else {
+                return Ok(None);
+            };
+            Ok(Some(ModificationEntry {
+                id: row.get(0)?,
+                timestamp: parse_timestamp(&row.get::<_, String>(6)?),
+                mod_type,
+                description: row.get(2)?,
+                file_path: row.get(3)?,
+                diff: row.get(4)?,
+                diff_truncated: false,
+                reversible: row.get::<_, i32>(5)? != 0,
+            }))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.extend(row?);
+        }
+        Ok(entries)
+    }
+
+    /// Count modification entries recorded at or after `since`.
+    pub fn count_modifications_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        let count: u64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM modifications WHERE created_at >= ?1",
+            params![since.to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
     // -----------------------------------------------------------------------
     // Children
     // -----------------------------------------------------------------------
@@ -256,17 +687,113 @@ impl Database {
                 child.name,
                 child.sandbox_id,
                 child.wallet_address,
-                child.status,
+                child.status.to_string(),
                 child.created_at.to_rfc3339(),
             ],
         )?;
         Ok(())
     }
 
-    /// Count active children.
+    /// Update a child's mutable fields (sandbox, wallet, status).
+    pub fn update_child(&self, child: &ChildRecord) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE children SET sandbox_id = ?2, wallet_address = ?3, status = ?4 WHERE id = ?1",
+            params![
+                child.id,
+                child.sandbox_id,
+                child.wallet_address,
+                child.status.to_string(),
+            ],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Unknown child: {}", child.id);
+        }
+        Ok(())
+    }
+
+    /// Look up a child by id.
+    pub fn get_child(&self, id: &str) -> Result<Option<ChildRecord>> {
+        Ok(self.list_children()?.into_iter().find(|c| c.id == id))
+    }
+
+    /// The child running in `sandbox_id`, if any.
+    pub fn child_by_sandbox(&self, sandbox_id: &str) -> Result<Option<ChildRecord>> {
+        Ok(self
+            .list_children()?
+            .into_iter()
+            .find(|c| c.sandbox_id == sandbox_id))
+    }
+
+    // -----------------------------------------------------------------------
+    // Pending operations
+    // -----------------------------------------------------------------------
+
+    /// Record that a sandbox named `name` is about to be created.
+    pub fn begin_pending_operation(&self, kind: &str, name: &str) -> Result<String> {
+        let id = ulid::Ulid::new().to_string();
+        self.conn.execute(
+            "INSERT INTO pending_operations (id, kind, name, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, kind, name, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(id)
+    }
+
+    /// Note the sandbox Conway created for a pending operation.
+    pub fn set_pending_sandbox(&self, id: &str, sandbox_id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE pending_operations SET sandbox_id = ?2 WHERE id = ?1",
+            params![id, sandbox_id],
+        )?;
+        Ok(())
+    }
+
+    /// Clear a pending operation once its outcome is tracked elsewhere.
+    pub fn finish_pending_operation(&self, id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM pending_operations WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Operations left unfinished, oldest first.
+    pub fn pending_operations(&self) -> Result<Vec<PendingOperation>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, kind, name, sandbox_id, created_at FROM pending_operations
+             ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PendingOperation {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                name: row.get(2)?,
+                sandbox_id: row.get(3)?,
+                created_at: parse_timestamp(&row.get::<_, String>(4)?),
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
+    /// Count children still occupying a slot (any not terminated).
+    pub fn live_children_count(&self) -> Result<u32> {
+        let count: u32 = self.conn.query_row(
+            "SELECT COUNT(*) FROM children WHERE status != 'terminated'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Count every child ever recorded, terminated ones included.
+    pub fn total_children_count(&self) -> Result<u32> {
+        let count: u32 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM children", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    /// Count active (including verified) children.
     pub fn active_children_count(&self) -> Result<u32> {
         let count: u32 = self.conn.query_row(
-            "SELECT COUNT(*) FROM children WHERE status = 'active'",
+            "SELECT COUNT(*) FROM children WHERE status IN ('active', 'verified')",
             [],
             |row| row.get(0),
         )?;
@@ -284,7 +811,10 @@ impl Database {
                 name: row.get(1)?,
                 sandbox_id: row.get(2)?,
                 wallet_address: row.get(3)?,
-                status: row.get(4)?,
+                status: row
+                    .get::<_, String>(4)?
+                    .parse()
+                    .unwrap_or(ChildState::Unreachable),
                 created_at: row
                     .get::<_, String>(5)
                     .map(|s| {
@@ -302,6 +832,206 @@ impl Database {
         Ok(children)
     }
 
+    // -----------------------------------------------------------------------
+    // Runtime lock
+    // -----------------------------------------------------------------------
+
+    /// The current holder of the runtime lock, fresh or not.
+    pub fn runtime_lock(&self) -> Result<Option<RuntimeLock>> {
+        self.conn
+            .query_row(
+                "SELECT owner, pid, acquired_at, heartbeat_at FROM runtime_lock WHERE id = 1",
+                [],
+                |row| {
+                    Ok(RuntimeLock {
+                        owner: row.get(0)?,
+                        pid: row.get(1)?,
+                        acquired_at: parse_timestamp(&row.get::<_, String>(2)?),
+                        heartbeat_at: parse_timestamp(&row.get::<_, String>(3)?),
+                    })
+                },
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Take the runtime lock for `owner` if it is free, already ours, or its
+    /// last heartbeat is older than `stale_before`. Returns whether we hold it.
+    pub fn try_acquire_runtime_lock(
+        &self,
+        owner: &str,
+        pid: u32,
+        now: chrono::DateTime<chrono::Utc>,
+        stale_before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool> {
+        let now = now.to_rfc3339();
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO runtime_lock (id, owner, pid, acquired_at, heartbeat_at)
+             VALUES (1, ?1, ?2, ?3, ?3)",
+            params![owner, pid, now],
+        )?;
+        if inserted == 1 {
+            return Ok(true);
+        }
+
+        let held: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT owner, heartbeat_at FROM runtime_lock WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((held_owner, held_heartbeat)) = held else {
+            return Ok(false);
+        };
+        if held_owner != owner && parse_timestamp(&held_heartbeat) >= stale_before {
+            return Ok(false);
+        }
+        // Compare-and-swap on the holder we saw, in case another process
+        // takes the stale lock first
+        let stolen = self.conn.execute(
+            "UPDATE runtime_lock SET owner = ?1, pid = ?2, acquired_at = ?3, heartbeat_at = ?3
+             WHERE id = 1 AND owner = ?4 AND heartbeat_at = ?5",
+            params![owner, pid, now, held_owner, held_heartbeat],
+        )?;
+        Ok(stolen == 1)
+    }
+
+    /// Refresh our heartbeat; false if the lock is no longer ours.
+    pub fn refresh_runtime_lock(&self, owner: &str, now: chrono::DateTime<chrono::Utc>) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE runtime_lock SET heartbeat_at = ?2 WHERE id = 1 AND owner = ?1",
+            params![owner, now.to_rfc3339()],
+        )?;
+        Ok(updated == 1)
+    }
+
+    /// Release the runtime lock if `owner` holds it.
+    pub fn release_runtime_lock(&self, owner: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM runtime_lock WHERE id = 1 AND owner = ?1", params![owner])?;
+        Ok(())
+    }
+
+    // -----------------------------------------------------------------------
+    // Inference log
+    // -----------------------------------------------------------------------
+
+    /// Store the raw inference exchange behind `turn_id`.
+    pub fn log_inference(&self, turn_id: &str, exchange: &InferenceExchange) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO inference_log (turn_id, request_json, response_json, latency_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                turn_id,
+                exchange.request_json,
+                exchange.response_json,
+                exchange.latency_ms as i64,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The stored exchange for `turn_id`, if it was logged.
+    pub fn inference_log_for(&self, turn_id: &str) -> Result<Option<InferenceLogEntry>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT turn_id, request_json, response_json, latency_ms, created_at
+                 FROM inference_log WHERE turn_id = ?1",
+                params![turn_id],
+                |row| {
+                    Ok(InferenceLogEntry {
+                        turn_id: row.get(0)?,
+                        exchange: InferenceExchange {
+                            request_json: row.get(1)?,
+                            response_json: row.get(2)?,
+                            latency_ms: row.get::<_, i64>(3)? as u64,
+                        },
+                        created_at: parse_timestamp(&row.get::<_, String>(4)?),
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    /// Id of the turn numbered `turn_number`.
+    pub fn turn_id_by_number(&self, turn_number: u64) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM turns WHERE turn_number = ?1",
+                params![turn_number],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    // -----------------------------------------------------------------------
+    // Approvals
+    // -----------------------------------------------------------------------
+
+    /// Queue a tool call for an operator decision.
+    pub fn request_approval(&self, tool: &str, arguments: &serde_json::Value, reason: &str) -> Result<String> {
+        let id = ulid::Ulid::new().to_string();
+        self.conn.execute(
+            "INSERT INTO approvals (id, tool, arguments, reason, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, 'pending', ?5)",
+            params![
+                id,
+                tool,
+                serde_json::to_string(arguments)?,
+                reason,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(id)
+    }
+
+    /// One approval request by id.
+    pub fn get_approval(&self, id: &str) -> Result<Option<Approval>> {
+        Ok(self.approvals_where("id = ?1", [id])?.into_iter().next())
+    }
+
+    /// Approval requests still awaiting a decision, oldest first.
+    pub fn pending_approvals(&self) -> Result<Vec<Approval>> {
+        self.approvals_where("status = 'pending'", [])
+    }
+
+    /// Decide a pending approval; false if it was not pending.
+    pub fn resolve_approval(&self, id: &str, status: ApprovalStatus) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE approvals SET status = ?2, resolved_at = ?3 WHERE id = ?1 AND status = 'pending'",
+            params![id, status.to_string(), chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(updated == 1)
+    }
+
+    fn approvals_where<P: rusqlite::Params>(&self, filter: &str, params: P) -> Result<Vec<Approval>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, tool, arguments, reason, status, created_at, resolved_at FROM approvals
+             WHERE {} ORDER BY created_at, id",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(Approval {
+                id: row.get(0)?,
+                tool: row.get(1)?,
+                arguments: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+                reason: row.get(3)?,
+                status: row
+                    .get::<_, String>(4)?
+                    .parse()
+                    .unwrap_or(ApprovalStatus::Denied),
+                created_at: parse_timestamp(&row.get::<_, String>(5)?),
+                resolved_at: row.get::<_, Option<String>>(6)?.map(|s| parse_timestamp(&s)),
+            })
+        })?;
+        Ok(rows.collect::<std::result::Result<_, _>>()?)
+    }
+
     // -----------------------------------------------------------------------
     // Inbox
     // -----------------------------------------------------------------------
@@ -309,14 +1039,15 @@ impl Database {
     /// Store an inbox message.
     pub fn save_inbox_message(&self, msg: &InboxMessage) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO inbox (id, from_address, to_address, content, read, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO inbox (id, from_address, to_address, content, read, signature, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 msg.id,
                 msg.from_address,
                 msg.to_address,
                 msg.content,
                 msg.read as i32,
+                msg.signature,
                 msg.timestamp.to_rfc3339(),
             ],
         )?;
@@ -326,7 +1057,7 @@ impl Database {
     /// Get unread inbox messages.
     pub fn unread_messages(&self) -> Result<Vec<InboxMessage>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, from_address, to_address, content, read, timestamp
+            "SELECT id, from_address, to_address, content, read, timestamp, signature
              FROM inbox WHERE read = 0 ORDER BY timestamp",
         )?;
         let rows = stmt.query_map([], |row| {
@@ -343,6 +1074,7 @@ impl Database {
                             .map(|d| d.with_timezone(&chrono::Utc))
                             .unwrap_or_else(|_| chrono::Utc::now())
                     })?,
+                signature: row.get(6)?,
             })
         })?;
 
@@ -367,11 +1099,12 @@ impl Database {
     /// Register or update a skill.
     pub fn save_skill(&self, skill: &Skill, file_path: Option<&str>) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO skills (name, description, version, auto_activate, instructions, file_path)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "INSERT INTO skills (name, description, version, auto_activate, instructions, file_path, allowed_tools_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
              ON CONFLICT(name) DO UPDATE SET
                 description = ?2, version = ?3, auto_activate = ?4,
-                instructions = ?5, file_path = ?6, loaded_at = datetime('now')",
+                instructions = ?5, file_path = ?6, allowed_tools_json = ?7,
+                loaded_at = datetime('now')",
             params![
                 skill.name,
                 skill.description,
@@ -379,6 +1112,11 @@ impl Database {
                 skill.auto_activate as i32,
                 skill.instructions,
                 file_path,
+                skill
+                    .allowed_tools
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
             ],
         )?;
         Ok(())
@@ -386,11 +1124,26 @@ impl Database {
 
     /// Get all auto-activate skills.
     pub fn auto_activate_skills(&self) -> Result<Vec<Skill>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT name, description, version, auto_activate, instructions FROM skills
-             WHERE auto_activate = 1",
-        )?;
-        let rows = stmt.query_map([], |row| {
+        self.skills_where("auto_activate = 1", [])
+    }
+
+    /// Get every installed skill, by name.
+    pub fn list_skills(&self) -> Result<Vec<Skill>> {
+        self.skills_where("1 = 1 ORDER BY name", [])
+    }
+
+    /// Get one skill by name.
+    pub fn get_skill(&self, name: &str) -> Result<Option<Skill>> {
+        Ok(self.skills_where("name = ?1", [name])?.into_iter().next())
+    }
+
+    fn skills_where<P: rusqlite::Params>(&self, filter: &str, params: P) -> Result<Vec<Skill>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT name, description, version, auto_activate, instructions, allowed_tools_json
+             FROM skills WHERE {}",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
             Ok(Skill {
                 name: row.get(0)?,
                 description: row.get(1)?,
@@ -398,6 +1151,10 @@ impl Database {
                 auto_activate: row.get::<_, i32>(3)? != 0,
                 instructions: row.get(4)?,
                 requirements: Vec::new(),
+                // Unparseable lists restrict to nothing rather than lifting the restriction
+                allowed_tools: row
+                    .get::<_, Option<String>>(5)?
+                    .map(|json| serde_json::from_str(&json).unwrap_or_default()),
             })
         })?;
 
@@ -408,6 +1165,68 @@ impl Database {
         Ok(skills)
     }
 
+    // -----------------------------------------------------------------------
+    // Notes
+    // -----------------------------------------------------------------------
+
+    /// Store a note, returning its id.
+    pub fn add_note(&self, tag: Option<&str>, content: &str) -> Result<String> {
+        let id = ulid::Ulid::new().to_string();
+        self.conn.execute(
+            "INSERT INTO notes (id, tag, content, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, tag, content, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(id)
+    }
+
+    /// Find notes whose content or tag contains `query` (newest first).
+    pub fn search_notes(&self, query: &str, limit: usize) -> Result<Vec<Note>> {
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+        self.query_notes(
+            "SELECT id, tag, content, created_at FROM notes
+             WHERE content LIKE ?1 ESCAPE '\\' OR tag LIKE ?1 ESCAPE '\\'
+             ORDER BY created_at DESC LIMIT ?2",
+            params![pattern, limit as i64],
+        )
+    }
+
+    /// Get the `n` most recent notes (newest first).
+    pub fn recent_notes(&self, n: usize) -> Result<Vec<Note>> {
+        self.query_notes(
+            "SELECT id, tag, content, created_at FROM notes
+             ORDER BY created_at DESC LIMIT ?1",
+            params![n as i64],
+        )
+    }
+
+    fn query_notes(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<Note>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                tag: row.get(1)?,
+                content: row.get(2)?,
+                created_at: row
+                    .get::<_, String>(3)
+                    .map(|s| {
+                        chrono::DateTime::parse_from_rfc3339(&s)
+                            .map(|d| d.with_timezone(&chrono::Utc))
+                            .unwrap_or_else(|_| chrono::Utc::now())
+                    })?,
+            })
+        })?;
+
+        let mut notes = Vec::new();
+        for row in rows {
+            notes.push(row?);
+        }
+        Ok(notes)
+    }
+
     // -----------------------------------------------------------------------
     // Registry
     // -----------------------------------------------------------------------
@@ -428,4 +1247,347 @@ impl Database {
         )?;
         Ok(())
     }
+
+    /// The metadata URI stored for `wallet_address`'s registry entry, if
+    /// one was recorded.
+    pub fn registry_metadata_uri(&self, wallet_address: &str) -> Result<Option<String>> {
+        let uri: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT metadata_uri FROM registry WHERE wallet_address = ?1",
+                params![wallet_address],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(uri.flatten().filter(|u| !u.is_empty()))
+    }
+}
+
+/// Parse a stored timestamp: RFC 3339, or SQLite's `datetime('now')` format
+/// (UTC) for rows written by column defaults.
+fn parse_timestamp(s: &str) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .map(|d| d.with_timezone(&chrono::Utc))
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").map(|d| d.and_utc())
+        })
+        .unwrap_or_else(|_| chrono::Utc::now())
+}
+
+// ---------------------------------------------------------------------------
+// Corruption handling
+// ---------------------------------------------------------------------------
+
+/// Map SQLite's "not a database" / "malformed" errors to [`CorruptDatabase`].
+fn corruption(e: rusqlite::Error) -> anyhow::Error {
+    match e.sqlite_error_code() {
+        Some(ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt) => {
+            CorruptDatabase(e.to_string()).into()
+        }
+        _ => e.into(),
+    }
+}
+
+/// Run `PRAGMA integrity_check`, failing with [`CorruptDatabase`] on problems.
+fn integrity_check(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check").map_err(corruption)?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(corruption)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(corruption)?;
+
+    if problems.len() == 1 && problems[0] == "ok" {
+        return Ok(());
+    }
+    Err(CorruptDatabase(problems.join("; ")).into())
+}
+
+/// Move a corrupt database (and its WAL/SHM files) aside; returns the new path.
+fn quarantine(path: &Path) -> Result<PathBuf> {
+    let suffix = format!("corrupt-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+    let moved = path.with_extension(format!("db.{}", suffix));
+    std::fs::rename(path, &moved)
+        .with_context(|| format!("Failed to move corrupt database {}", path.display()))?;
+
+    for ext in ["db-wal", "db-shm"] {
+        let side = path.with_extension(ext);
+        if side.exists() {
+            let _ = std::fs::rename(&side, path.with_extension(format!("{}.{}", ext, suffix)));
+        }
+    }
+    Ok(moved)
+}
+
+/// Newest `*.db` file in the backup directory next to `path`.
+fn latest_backup(path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?.join(BACKUP_DIR);
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "db"))
+        .max_by_key(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_add_and_search_round_trip() {
+        let db = Database::open_memory().unwrap();
+        db.add_note(Some("clients"), "Acme pays 0.05 USDC per scrape job")
+            .unwrap();
+        db.add_note(None, "Port 8080 hosts the landing page").unwrap();
+        db.add_note(Some("infra"), "100% uptime since Tuesday").unwrap();
+
+        let hits = db.search_notes("acme", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].tag.as_deref(), Some("clients"));
+        assert!(hits[0].content.contains("0.05 USDC"));
+
+        // Tag matches too, and LIKE wildcards in the query are literal
+        assert_eq!(db.search_notes("infra", 10).unwrap().len(), 1);
+        assert_eq!(db.search_notes("100%", 10).unwrap().len(), 1);
+        assert_eq!(db.search_notes("%", 10).unwrap().len(), 1);
+
+        let recent = db.recent_notes(2).unwrap();
+        assert_eq!(recent.len(), 2);
+    }
+
+    #[test]
+    fn test_typed_kv_round_trip() {
+        let db = Database::open_memory().unwrap();
+        assert_eq!(db.kv_get_f64("credits_balance").unwrap(), None);
+        db.kv_set_f64("credits_balance", 4.25).unwrap();
+        assert_eq!(db.kv_get_f64("credits_balance").unwrap(), Some(4.25));
+
+        assert_eq!(db.kv_incr("restarts", 1.0).unwrap(), 1.0);
+        assert_eq!(db.kv_incr("restarts", 2.5).unwrap(), 3.5);
+        assert_eq!(db.kv_incr("credits_balance", -0.25).unwrap(), 4.0);
+
+        let at = chrono::DateTime::parse_from_rfc3339("2026-03-01T12:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(db.kv_get_datetime("sleep_until").unwrap(), None);
+        db.kv_set_datetime("sleep_until", at).unwrap();
+        assert_eq!(db.kv_get_datetime("sleep_until").unwrap(), Some(at));
+        // Other offsets are normalized to UTC
+        db.kv_set("sleep_until", "2026-03-01T14:30:00+02:00").unwrap();
+        assert_eq!(db.kv_get_datetime("sleep_until").unwrap(), Some(at));
+    }
+
+    #[test]
+    fn test_typed_kv_rejects_malformed_values() {
+        let db = Database::open_memory().unwrap();
+        db.kv_set("usdc_balance", "lots").unwrap();
+        let err = db.kv_get_f64("usdc_balance").unwrap_err();
+        assert!(err.to_string().contains("usdc_balance"), "{}", err);
+
+        // kv_incr refuses to clobber a malformed value
+        assert!(db.kv_incr("usdc_balance", 1.0).is_err());
+        assert_eq!(db.kv_get("usdc_balance").unwrap().as_deref(), Some("lots"));
+
+        db.kv_set("sleep_until", "tomorrow").unwrap();
+        assert!(db.kv_get_datetime("sleep_until").is_err());
+        db.kv_set("sleep_until", "2026-03-01 12:30:00").unwrap();
+        assert!(db.kv_get_datetime("sleep_until").is_err());
+    }
+
+    #[test]
+    fn test_tool_success_rates_use_recent_calls() {
+        let db = Database::open_memory().unwrap();
+        let outcomes = [
+            ("exec", false),
+            ("exec", true),
+            ("exec", false),
+            ("exec", false),
+            ("recall", true),
+        ];
+        for (n, (tool, success)) in outcomes.iter().enumerate() {
+            let call = ToolCall {
+                id: ulid::Ulid::new().to_string(),
+                name: tool.to_string(),
+                arguments: serde_json::json!({}),
+            };
+            db.save_turn(&Turn {
+                id: ulid::Ulid::new().to_string(),
+                turn_number: n as u64,
+                state: AgentState::Running,
+                messages: Vec::new(),
+                tool_results: vec![ToolResult {
+                    tool_call_id: call.id.clone(),
+                    output: String::new(),
+                    success: *success,
+                    attachment: None,
+                }],
+                tool_calls: vec![call],
+                token_usage: Default::default(),
+                cost_estimate_usd: 0.0,
+                model: "gpt-4o".into(),
+                tag: None,
+                intent: None,
+                created_at: chrono::Utc::now(),
+            })
+            .unwrap();
+        }
+
+        let rates = db.tool_success_rates(10).unwrap();
+        assert_eq!(
+            rates,
+            [
+                ToolSuccessRate { tool_name: "exec".into(), calls: 4, successes: 1 },
+                ToolSuccessRate { tool_name: "recall".into(), calls: 1, successes: 1 },
+            ]
+        );
+        assert_eq!(rates[0].rate(), 0.25);
+
+        // Only the most recent calls per tool count
+        let rates = db.tool_success_rates(2).unwrap();
+        assert_eq!(rates[0].calls, 2);
+        assert_eq!(rates[0].successes, 0);
+    }
+
+    #[test]
+    fn test_turn_tags_are_queryable() {
+        let db = Database::open_memory().unwrap();
+        let tags = [Some("earning"), None, Some("maintenance"), Some("earning")];
+        for (n, tag) in tags.iter().enumerate() {
+            db.save_turn(&Turn {
+                id: ulid::Ulid::new().to_string(),
+                turn_number: n as u64 + 1,
+                state: AgentState::Running,
+                messages: Vec::new(),
+                tool_calls: Vec::new(),
+                tool_results: Vec::new(),
+                token_usage: Default::default(),
+                cost_estimate_usd: 0.25,
+                model: "gpt-4o".into(),
+                tag: tag.map(str::to_string),
+                intent: None,
+                created_at: chrono::Utc::now(),
+            })
+            .unwrap();
+        }
+
+        let earning: Vec<u64> = db
+            .turns_by_tag("earning")
+            .unwrap()
+            .iter()
+            .map(|t| t.turn_number)
+            .collect();
+        assert_eq!(earning, [1, 4]);
+        assert_eq!(
+            db.turn_tag_stats().unwrap(),
+            [
+                TurnTagStats { tag: "earning".into(), turns: 2, cost_estimate_usd: 0.5 },
+                TurnTagStats { tag: "maintenance".into(), turns: 1, cost_estimate_usd: 0.25 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupt_database_is_recovered() {
+        let dir = std::env::temp_dir().join(format!("automaton-db-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(dir.join(BACKUP_DIR)).unwrap();
+        let path = dir.join("state.db");
+
+        let db = Database::open(&path).unwrap();
+        db.kv_set("agent_state", "sleeping").unwrap();
+        db.backup_to(&dir.join(BACKUP_DIR).join("latest.db")).unwrap();
+        drop(db);
+
+        // Simulate power loss scribbling over the file
+        std::fs::remove_file(path.with_extension("db-wal")).ok();
+        std::fs::write(&path, vec![0x5a; 8192]).unwrap();
+
+        let err = Database::open(&path).err().unwrap();
+        assert!(err.downcast_ref::<CorruptDatabase>().is_some(), "{:#}", err);
+        assert!(Database::open_or_recover(&path, false).is_err());
+
+        let db = Database::open_or_recover(&path, true).unwrap();
+        assert_eq!(db.kv_get("agent_state").unwrap().as_deref(), Some("sleeping"));
+        let quarantined = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref().unwrap().file_name().to_string_lossy().starts_with("state.db.corrupt-")
+            })
+            .count();
+        assert_eq!(quarantined, 1);
+
+        // Without a backup, a fresh schema is created instead
+        drop(db);
+        std::fs::remove_dir_all(dir.join(BACKUP_DIR)).unwrap();
+        std::fs::remove_file(path.with_extension("db-wal")).ok();
+        std::fs::write(&path, vec![0x5a; 8192]).unwrap();
+        let db = Database::open_or_recover(&path, true).unwrap();
+        assert_eq!(db.kv_get("agent_state").unwrap(), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_v3_database_migrates_forward_and_reruns() {
+        let dir = std::env::temp_dir().join(format!("automaton-db-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.db");
+
+        // The tables that later migrations alter (and tool_calls, which
+        // turn queries join), as they were at v3
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE schema_version (version INTEGER NOT NULL);
+             INSERT INTO schema_version (version) VALUES (3);
+             CREATE TABLE turns (
+                 id TEXT PRIMARY KEY, turn_number INTEGER NOT NULL,
+                 state TEXT NOT NULL DEFAULT 'running',
+                 messages_json TEXT NOT NULL DEFAULT '[]',
+                 token_usage_json TEXT NOT NULL DEFAULT '{}',
+                 cost_estimate REAL NOT NULL DEFAULT 0.0,
+                 created_at TEXT NOT NULL DEFAULT (datetime('now')));
+             CREATE TABLE tool_calls (
+                 id TEXT PRIMARY KEY, turn_id TEXT NOT NULL REFERENCES turns(id),
+                 tool_name TEXT NOT NULL, arguments_json TEXT NOT NULL DEFAULT '{}',
+                 output TEXT, success INTEGER NOT NULL DEFAULT 1, duration_ms INTEGER,
+                 created_at TEXT NOT NULL DEFAULT (datetime('now')));
+             CREATE TABLE inbox (
+                 id TEXT PRIMARY KEY, from_address TEXT NOT NULL, to_address TEXT NOT NULL,
+                 content TEXT NOT NULL, read INTEGER NOT NULL DEFAULT 0,
+                 created_at TEXT NOT NULL DEFAULT (datetime('now')));
+             CREATE TABLE skills (
+                 name TEXT PRIMARY KEY, description TEXT NOT NULL,
+                 version TEXT NOT NULL DEFAULT '1.0.0', auto_activate INTEGER NOT NULL DEFAULT 0,
+                 instructions TEXT NOT NULL, file_path TEXT,
+                 loaded_at TEXT NOT NULL DEFAULT (datetime('now')));
+             INSERT INTO turns (id, turn_number, created_at) VALUES ('t1', 1, '2025-01-01T00:00:00+00:00');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let mut db = Database::open(&path).unwrap();
+        assert_eq!(db.schema_version(), schema::SCHEMA_VERSION);
+        for (table, column) in [("turns", "model"), ("turns", "intent"), ("inbox", "signature")] {
+            assert!(schema::has_column(&db.conn, table, column).unwrap(), "{}.{}", table, column);
+        }
+        let old = &db.turns_since(chrono::DateTime::UNIX_EPOCH).unwrap()[0];
+        assert_eq!(old.model, "unknown");
+
+        // A migration interrupted before the version bump runs again
+        // cleanly, backfilling rows the interrupted run didn't reach
+        db.conn.execute("UPDATE schema_version SET version = 3", []).unwrap();
+        db.conn
+            .execute("INSERT INTO turns (id, turn_number) VALUES ('t2', 2)", [])
+            .unwrap();
+        db.migrate().unwrap();
+        assert_eq!(db.schema_version(), schema::SCHEMA_VERSION);
+        let model: String = db
+            .conn
+            .query_row("SELECT model FROM turns WHERE id = 't2'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(model, "unknown");
+        drop(db);
+        Database::open(&path).unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
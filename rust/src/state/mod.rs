@@ -1,4 +1,4 @@
 pub mod database;
 pub mod schema;
 
-pub use database::Database;
+pub use database::{CorruptDatabase, Database};
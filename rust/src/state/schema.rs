@@ -1,7 +1,16 @@
 //! Database schema definitions and migrations.
+//!
+//! Each migration is safe to re-run: columns are only added when
+//! `PRAGMA table_info` doesn't list them yet, and the rest of its SQL uses
+//! `IF NOT EXISTS`. A migration interrupted before the version bump is
+//! simply applied again on the next open.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use tracing::info;
 
 /// Current schema version.
-pub const SCHEMA_VERSION: u32 = 3;
+pub const SCHEMA_VERSION: u32 = 14;
 
 /// Full DDL for the automaton state database.
 pub const CREATE_SCHEMA: &str = r#"
@@ -24,6 +33,9 @@ CREATE TABLE IF NOT EXISTS turns (
     messages_json   TEXT NOT NULL DEFAULT '[]',
     token_usage_json TEXT NOT NULL DEFAULT '{}',
     cost_estimate   REAL NOT NULL DEFAULT 0.0,
+    model           TEXT,
+    tag             TEXT,
+    intent          TEXT,
     created_at      TEXT NOT NULL DEFAULT (datetime('now'))
 );
 
@@ -77,6 +89,7 @@ CREATE TABLE IF NOT EXISTS skills (
     version       TEXT NOT NULL DEFAULT '1.0.0',
     auto_activate INTEGER NOT NULL DEFAULT 0,
     instructions  TEXT NOT NULL,
+    allowed_tools_json TEXT,
     file_path     TEXT,
     loaded_at     TEXT NOT NULL DEFAULT (datetime('now'))
 );
@@ -91,6 +104,54 @@ CREATE TABLE IF NOT EXISTS children (
     created_at      TEXT NOT NULL DEFAULT (datetime('now'))
 );
 
+-- Sandbox creations in flight, reconciled at startup after a crash
+CREATE TABLE IF NOT EXISTS pending_operations (
+    id          TEXT PRIMARY KEY,
+    kind        TEXT NOT NULL,
+    name        TEXT NOT NULL,
+    sandbox_id  TEXT,
+    created_at  TEXT NOT NULL
+);
+
+-- The process currently driving the agent loop (at most one row)
+CREATE TABLE IF NOT EXISTS runtime_lock (
+    id            INTEGER PRIMARY KEY CHECK (id = 1),
+    owner         TEXT NOT NULL,
+    pid           INTEGER NOT NULL,
+    acquired_at   TEXT NOT NULL,
+    heartbeat_at  TEXT NOT NULL
+);
+
+-- One-off heartbeat tasks the agent scheduled for later
+CREATE TABLE IF NOT EXISTS scheduled_tasks (
+    id           TEXT PRIMARY KEY,
+    task         TEXT NOT NULL,
+    params_json  TEXT,
+    run_at       TEXT NOT NULL,
+    created_at   TEXT NOT NULL,
+    done_at      TEXT
+);
+
+-- High-risk tool calls held for an operator decision
+CREATE TABLE IF NOT EXISTS approvals (
+    id           TEXT PRIMARY KEY,
+    tool         TEXT NOT NULL,
+    arguments    TEXT NOT NULL,
+    reason       TEXT NOT NULL,
+    status       TEXT NOT NULL DEFAULT 'pending',
+    created_at   TEXT NOT NULL,
+    resolved_at  TEXT
+);
+
+-- Raw inference payloads per turn, kept with debug_inference_logging
+CREATE TABLE IF NOT EXISTS inference_log (
+    turn_id       TEXT PRIMARY KEY REFERENCES turns(id),
+    request_json  TEXT NOT NULL,
+    response_json TEXT NOT NULL,
+    latency_ms    INTEGER NOT NULL,
+    created_at    TEXT NOT NULL
+);
+
 -- On-chain registry records
 CREATE TABLE IF NOT EXISTS registry (
     wallet_address TEXT PRIMARY KEY,
@@ -108,6 +169,7 @@ CREATE TABLE IF NOT EXISTS inbox (
     to_address    TEXT NOT NULL,
     content       TEXT NOT NULL,
     read          INTEGER NOT NULL DEFAULT 0,
+    signature     TEXT,
     timestamp     TEXT NOT NULL DEFAULT (datetime('now'))
 );
 
@@ -120,23 +182,66 @@ CREATE TABLE IF NOT EXISTS upstream_commits (
     fetched_at  TEXT NOT NULL DEFAULT (datetime('now'))
 );
 
+-- Curated long-term memory written by the agent
+CREATE TABLE IF NOT EXISTS notes (
+    id          TEXT PRIMARY KEY,
+    tag         TEXT,
+    content     TEXT NOT NULL,
+    created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
 -- Indexes
 CREATE INDEX IF NOT EXISTS idx_turns_created ON turns(created_at);
 CREATE INDEX IF NOT EXISTS idx_tool_calls_turn ON tool_calls(turn_id);
+CREATE INDEX IF NOT EXISTS idx_turns_tag ON turns(tag);
 CREATE INDEX IF NOT EXISTS idx_heartbeat_task ON heartbeat_entries(task_name);
 CREATE INDEX IF NOT EXISTS idx_inbox_read ON inbox(read);
 CREATE INDEX IF NOT EXISTS idx_inbox_to ON inbox(to_address);
 CREATE INDEX IF NOT EXISTS idx_transactions_created ON transactions(created_at);
 CREATE INDEX IF NOT EXISTS idx_modifications_created ON modifications(created_at);
+CREATE INDEX IF NOT EXISTS idx_notes_created ON notes(created_at);
 "#;
 
+/// A column added to an existing table.
+pub struct AddColumn {
+    pub table: &'static str,
+    pub column: &'static str,
+    /// Type and constraints, as after the name in `ADD COLUMN`.
+    pub definition: &'static str,
+    /// Fills the column in rows that predate it; `None` leaves them at the
+    /// column default. It runs every time the migration is applied, even if
+    /// the column was already there (a run interrupted after the `ALTER`),
+    /// so it must be idempotent: only touch rows it hasn't filled yet.
+    pub backfill: Option<&'static str>,
+}
+
+/// One step of the schema history.
+pub struct Migration {
+    /// Schema version this migration brings the database to.
+    pub version: u32,
+    /// Columns to add, each skipped if already present.
+    pub add_columns: &'static [AddColumn],
+    /// Idempotent DDL (`IF NOT EXISTS`), run after the columns are added.
+    pub sql: &'static str,
+}
+
 /// Migration from version 1 to version 2.
-pub const MIGRATE_V1_TO_V2: &str = r#"
-ALTER TABLE turns ADD COLUMN state TEXT NOT NULL DEFAULT 'running';
-"#;
+pub const MIGRATE_V1_TO_V2: Migration = Migration {
+    version: 2,
+    add_columns: &[AddColumn {
+        table: "turns",
+        column: "state",
+        definition: "TEXT NOT NULL DEFAULT 'running'",
+        backfill: None,
+    }],
+    sql: "",
+};
 
 /// Migration from version 2 to version 3.
-pub const MIGRATE_V2_TO_V3: &str = r#"
+pub const MIGRATE_V2_TO_V3: Migration = Migration {
+    version: 3,
+    add_columns: &[],
+    sql: r#"
 CREATE TABLE IF NOT EXISTS upstream_commits (
     commit_hash TEXT PRIMARY KEY,
     message     TEXT,
@@ -144,4 +249,214 @@ CREATE TABLE IF NOT EXISTS upstream_commits (
     reviewed    INTEGER NOT NULL DEFAULT 0,
     fetched_at  TEXT NOT NULL DEFAULT (datetime('now'))
 );
-"#;
+"#,
+};
+
+/// Migration from version 3 to version 4.
+pub const MIGRATE_V3_TO_V4: Migration = Migration {
+    version: 4,
+    add_columns: &[],
+    sql: r#"
+CREATE TABLE IF NOT EXISTS notes (
+    id          TEXT PRIMARY KEY,
+    tag         TEXT,
+    content     TEXT NOT NULL,
+    created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_notes_created ON notes(created_at);
+"#,
+};
+
+/// Migration from version 4 to version 5. Turns from before the model was
+/// recorded are marked `unknown`.
+pub const MIGRATE_V4_TO_V5: Migration = Migration {
+    version: 5,
+    add_columns: &[AddColumn {
+        table: "turns",
+        column: "model",
+        definition: "TEXT",
+        backfill: Some("UPDATE turns SET model = 'unknown' WHERE model IS NULL"),
+    }],
+    sql: "",
+};
+
+/// Migration from version 5 to version 6.
+pub const MIGRATE_V5_TO_V6: Migration = Migration {
+    version: 6,
+    add_columns: &[AddColumn {
+        table: "inbox",
+        column: "signature",
+        definition: "TEXT",
+        backfill: None,
+    }],
+    sql: "",
+};
+
+/// Migration from version 6 to version 7.
+pub const MIGRATE_V6_TO_V7: Migration = Migration {
+    version: 7,
+    add_columns: &[AddColumn {
+        table: "skills",
+        column: "allowed_tools_json",
+        definition: "TEXT",
+        backfill: None,
+    }],
+    sql: "",
+};
+
+/// Migration from version 7 to version 8.
+pub const MIGRATE_V7_TO_V8: Migration = Migration {
+    version: 8,
+    add_columns: &[AddColumn {
+        table: "turns",
+        column: "tag",
+        definition: "TEXT",
+        backfill: None,
+    }],
+    sql: "CREATE INDEX IF NOT EXISTS idx_turns_tag ON turns(tag);",
+};
+
+/// Migration from version 8 to version 9.
+pub const MIGRATE_V8_TO_V9: Migration = Migration {
+    version: 9,
+    add_columns: &[],
+    sql: r#"
+CREATE TABLE IF NOT EXISTS pending_operations (
+    id          TEXT PRIMARY KEY,
+    kind        TEXT NOT NULL,
+    name        TEXT NOT NULL,
+    sandbox_id  TEXT,
+    created_at  TEXT NOT NULL
+);
+"#,
+};
+
+/// Migration from version 9 to version 10.
+pub const MIGRATE_V9_TO_V10: Migration = Migration {
+    version: 10,
+    add_columns: &[AddColumn {
+        table: "turns",
+        column: "intent",
+        definition: "TEXT",
+        backfill: None,
+    }],
+    sql: "",
+};
+
+/// Migration from version 10 to version 11.
+pub const MIGRATE_V10_TO_V11: Migration = Migration {
+    version: 11,
+    add_columns: &[],
+    sql: r#"
+CREATE TABLE IF NOT EXISTS runtime_lock (
+    id            INTEGER PRIMARY KEY CHECK (id = 1),
+    owner         TEXT NOT NULL,
+    pid           INTEGER NOT NULL,
+    acquired_at   TEXT NOT NULL,
+    heartbeat_at  TEXT NOT NULL
+);
+"#,
+};
+
+/// Migration from version 11 to version 12.
+pub const MIGRATE_V11_TO_V12: Migration = Migration {
+    version: 12,
+    add_columns: &[],
+    sql: r#"
+CREATE TABLE IF NOT EXISTS scheduled_tasks (
+    id           TEXT PRIMARY KEY,
+    task         TEXT NOT NULL,
+    params_json  TEXT,
+    run_at       TEXT NOT NULL,
+    created_at   TEXT NOT NULL,
+    done_at      TEXT
+);
+"#,
+};
+
+/// Migration from version 12 to version 13.
+pub const MIGRATE_V12_TO_V13: Migration = Migration {
+    version: 13,
+    add_columns: &[],
+    sql: r#"
+CREATE TABLE IF NOT EXISTS approvals (
+    id           TEXT PRIMARY KEY,
+    tool         TEXT NOT NULL,
+    arguments    TEXT NOT NULL,
+    reason       TEXT NOT NULL,
+    status       TEXT NOT NULL DEFAULT 'pending',
+    created_at   TEXT NOT NULL,
+    resolved_at  TEXT
+);
+"#,
+};
+
+/// Migration from version 13 to version 14.
+pub const MIGRATE_V13_TO_V14: Migration = Migration {
+    version: 14,
+    add_columns: &[],
+    sql: r#"
+CREATE TABLE IF NOT EXISTS inference_log (
+    turn_id       TEXT PRIMARY KEY REFERENCES turns(id),
+    request_json  TEXT NOT NULL,
+    response_json TEXT NOT NULL,
+    latency_ms    INTEGER NOT NULL,
+    created_at    TEXT NOT NULL
+);
+"#,
+};
+
+/// Every migration, oldest first.
+pub const MIGRATIONS: &[Migration] = &[
+    MIGRATE_V1_TO_V2,
+    MIGRATE_V2_TO_V3,
+    MIGRATE_V3_TO_V4,
+    MIGRATE_V4_TO_V5,
+    MIGRATE_V5_TO_V6,
+    MIGRATE_V6_TO_V7,
+    MIGRATE_V7_TO_V8,
+    MIGRATE_V8_TO_V9,
+    MIGRATE_V9_TO_V10,
+    MIGRATE_V10_TO_V11,
+    MIGRATE_V11_TO_V12,
+    MIGRATE_V12_TO_V13,
+    MIGRATE_V13_TO_V14,
+];
+
+/// Whether `table` has a column named `column`.
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+impl Migration {
+    /// Apply this migration, skipping whatever is already in place.
+    pub fn apply(&self, conn: &Connection) -> Result<()> {
+        for add in self.add_columns {
+            if !has_column(conn, add.table, add.column)? {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    add.table, add.column, add.definition
+                ))
+                .with_context(|| format!("Failed to add {}.{}", add.table, add.column))?;
+            }
+            if let Some(backfill) = add.backfill {
+                let rows = conn
+                    .execute(backfill, [])
+                    .with_context(|| format!("Failed to backfill {}.{}", add.table, add.column))?;
+                info!("Backfilled {}.{} in {} rows", add.table, add.column, rows);
+            }
+        }
+        if !self.sql.is_empty() {
+            conn.execute_batch(self.sql)
+                .with_context(|| format!("Failed to migrate to schema v{}", self.version))?;
+        }
+        Ok(())
+    }
+}
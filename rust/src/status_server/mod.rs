@@ -0,0 +1,425 @@
+//! Status server: JSON status and metrics plus a small HTML dashboard.
+//!
+//! Listens on `status_bind` (publish it with `expose_port` to reach it from
+//! outside the sandbox). Routes:
+//!
+//! - `/` — the dashboard: tier, balances, recent turns, daily spend, children
+//! - `/status` — current state as JSON
+//! - `/metrics` — efficiency report and daily spend as JSON
+//!
+//! Every request must carry `status_token`, as `Authorization: Bearer` or a
+//! `token` query parameter (which the dashboard passes on to its fetches).
+
+use crate::config::AutomatonConfig;
+use crate::state::Database;
+use crate::survival::SurvivalMonitor;
+use crate::tools::efficiency::EfficiencyReport;
+use crate::types::{ChildRecord, SurvivalTier, TurnSummary};
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info};
+
+/// Dashboard page; `{{name}}` is replaced with the agent's name.
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+/// Most turns listed in `/status`.
+const RECENT_TURNS: usize = 20;
+
+/// Days covered by the spend chart.
+const SPEND_DAYS: i64 = 7;
+
+/// Largest request head accepted.
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// Time a client gets to send its request head before it is dropped.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Response to `/status`.
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub name: String,
+    pub address: String,
+    pub agent_state: String,
+    pub tier: SurvivalTier,
+    pub credits_balance: f64,
+    pub usdc_balance: f64,
+    pub turns: u64,
+    /// Newest first.
+    pub recent_turns: Vec<TurnSummary>,
+    pub children: Vec<ChildRecord>,
+}
+
+/// Estimated spend on one UTC day.
+#[derive(Debug, Serialize)]
+pub struct DailySpend {
+    pub date: String,
+    pub usd: f64,
+}
+
+/// Response to `/metrics`.
+#[derive(Debug, Serialize)]
+pub struct MetricsReport {
+    pub efficiency: EfficiencyReport,
+    /// Oldest first, ending today.
+    pub daily_spend: Vec<DailySpend>,
+}
+
+async fn status_report(config: &AutomatonConfig, db: &Arc<Mutex<Database>>) -> Result<StatusReport> {
+    let survival = SurvivalMonitor::new(db.clone()).check().await?;
+    let db = db.lock().await;
+    let mut recent_turns = db.turns_since(Utc::now() - Duration::days(1))?;
+    recent_turns.reverse();
+    recent_turns.truncate(RECENT_TURNS);
+    Ok(StatusReport {
+        name: config.name.clone(),
+        address: config.wallet_address.clone(),
+        agent_state: db.kv_get("agent_state")?.unwrap_or_else(|| "unknown".into()),
+        tier: survival.tier,
+        credits_balance: survival.credits_balance,
+        usdc_balance: survival.usdc_balance,
+        turns: db.turn_count()?,
+        recent_turns,
+        children: db.list_children()?,
+    })
+}
+
+fn metrics_report(db: &Database) -> Result<MetricsReport> {
+    let now = Utc::now();
+    let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let daily_spend = (0..SPEND_DAYS)
+        .rev()
+        .map(|days_ago| {
+            let start = today - Duration::days(days_ago);
+            let end = start + Duration::days(1);
+            Ok(DailySpend {
+                date: start.format("%Y-%m-%d").to_string(),
+                usd: db.estimated_cost_since(start)? - db.estimated_cost_since(end)?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(MetricsReport {
+        efficiency: EfficiencyReport::build(db, now)?,
+        daily_spend,
+    })
+}
+
+/// Escape text for an HTML document.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// A parsed request line and the headers we look at.
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    authorization: Option<String>,
+}
+
+impl Request {
+    fn parse(head: &str) -> Option<Self> {
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let target = request_line.next()?;
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let authorization = lines
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case("authorization"))
+            .map(|(_, v)| v.trim().to_string());
+        Some(Self {
+            method,
+            path: path.to_string(),
+            query: query.to_string(),
+            authorization,
+        })
+    }
+
+    /// The token the request presents, from the header or the
+    /// (percent-decoded) query.
+    fn token(&self) -> Option<String> {
+        if let Some(token) = self.authorization.as_deref().and_then(|a| a.strip_prefix("Bearer ")) {
+            return Some(token.trim().to_string());
+        }
+        url::form_urlencoded::parse(self.query.as_bytes())
+            .find(|(k, _)| k == "token")
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+/// Compare without stopping at the first differing byte.
+fn token_matches(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(body: &impl Serialize) -> Self {
+        match serde_json::to_string(body) {
+            Ok(body) => Self {
+                status: "200 OK",
+                content_type: "application/json",
+                body,
+            },
+            Err(e) => Self::error("500 Internal Server Error", &e.to_string()),
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", message),
+        }
+    }
+}
+
+async fn respond(config: &AutomatonConfig, db: &Arc<Mutex<Database>>, request: &Request) -> Response {
+    if !request.token().is_some_and(|t| token_matches(&t, &config.status_token)) {
+        return Response::error("401 Unauthorized", "missing or wrong token");
+    }
+    if request.method != "GET" {
+        return Response::error("405 Method Not Allowed", "only GET is supported");
+    }
+    let report = match request.path.as_str() {
+        "/" | "/dashboard" => {
+            return Response {
+                status: "200 OK",
+                content_type: "text/html; charset=utf-8",
+                body: DASHBOARD_HTML.replace("{{name}}", &escape_html(&config.name)),
+            }
+        }
+        "/status" => status_report(config, db).await.map(|r| Response::json(&r)),
+        "/metrics" => metrics_report(&*db.lock().await).map(|r| Response::json(&r)),
+        _ => return Response::error("404 Not Found", "not found"),
+    };
+    report.unwrap_or_else(|e| {
+        error!("Status server: {:#}", e);
+        Response::error("500 Internal Server Error", "report failed")
+    })
+}
+
+/// Read the request head, or `None` if the client hung up first. A client
+/// that takes longer than `timeout` is dropped, so idle connections can't
+/// pile up.
+async fn read_head(stream: &mut TcpStream, timeout: std::time::Duration) -> Result<Option<Vec<u8>>> {
+    let read = async {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            if buf.len() > MAX_REQUEST_BYTES {
+                bail!("Request head over {} bytes", MAX_REQUEST_BYTES);
+            }
+        }
+        Ok(Some(buf))
+    };
+    tokio::time::timeout(timeout, read)
+        .await
+        .with_context(|| format!("Request head not received within {:?}", timeout))?
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+) -> Result<()> {
+    let Some(buf) = read_head(&mut stream, REQUEST_TIMEOUT).await? else {
+        return Ok(());
+    };
+
+    let head = String::from_utf8_lossy(&buf);
+    let response = match Request::parse(&head) {
+        Some(request) => respond(config, db, &request).await,
+        None => Response::error("400 Bad Request", "malformed request"),
+    };
+    let out = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    );
+    stream.write_all(out.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Serve connections from `listener` until `cancel` fires.
+pub async fn serve_on(
+    listener: TcpListener,
+    config: AutomatonConfig,
+    db: Arc<Mutex<Database>>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    if config.status_token.is_empty() {
+        bail!("status_token is not set; refusing to serve status without authentication");
+    }
+    let config = Arc::new(config);
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+        let (config, db) = (config.clone(), db.clone());
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &config, &db).await {
+                debug!("Status request from {} failed: {:#}", peer, e);
+            }
+        });
+    }
+}
+
+/// Start the status server in the background if `status_bind` is set.
+pub fn spawn(
+    config: &AutomatonConfig,
+    db: Arc<Mutex<Database>>,
+    cancel: CancellationToken,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if config.status_bind.is_empty() {
+        return None;
+    }
+    let config = config.clone();
+    Some(tokio::spawn(async move {
+        let served = async {
+            let listener = TcpListener::bind(&config.status_bind)
+                .await
+                .with_context(|| format!("Failed to bind status server to {}", config.status_bind))?;
+            info!("Status dashboard on http://{}/", config.status_bind);
+            serve_on(listener, config, db, cancel).await
+        };
+        if let Err(e) = served.await {
+            error!("Status server stopped: {:#}", e);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start(config: AutomatonConfig, db: Arc<Mutex<Database>>) -> (String, CancellationToken) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let cancel = CancellationToken::new();
+        tokio::spawn(serve_on(listener, config, db, cancel.clone()));
+        (url, cancel)
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_serves_html_with_agent_name() {
+        let config = AutomatonConfig {
+            name: "Lemonade <Stand>".into(),
+            status_token: "s3cret".into(),
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        db.lock().await.kv_set_f64("credits_balance", 0.05).unwrap();
+        let (url, cancel) = start(config, db).await;
+        let client = crate::http::client();
+
+        let resp = client.get(format!("{}/?token=s3cret", url)).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/html"));
+        let html = resp.text().await.unwrap();
+        assert!(html.contains("<title>Lemonade &lt;Stand&gt;"), "{}", html);
+        assert!(!html.contains("{{name}}"));
+
+        let status: serde_json::Value = client
+            .get(format!("{}/status", url))
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(status["name"], "Lemonade <Stand>");
+        assert_eq!(status["tier"], "critical");
+
+        let metrics: serde_json::Value = client
+            .get(format!("{}/metrics?token=s3cret", url))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(metrics["daily_spend"].as_array().unwrap().len(), SPEND_DAYS as usize);
+
+        // Query tokens are percent-decoded like the dashboard's own
+        // URLSearchParams
+        let config = AutomatonConfig {
+            status_token: "a+b/c=d".into(),
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let (encoded_url, encoded_cancel) = start(config, db).await;
+        let resp = client
+            .get(format!("{}/status?token=a%2Bb%2Fc%3Dd", encoded_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let resp = client
+            .get(format!("{}/status?token=a+b/c=d", encoded_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 401);
+        encoded_cancel.cancel();
+
+        // No token, wrong token
+        for target in ["/", "/status?token=guess"] {
+            let resp = client.get(format!("{}{}", url, target)).send().await.unwrap();
+            assert_eq!(resp.status(), 401, "{}", target);
+        }
+        cancel.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_stalled_request_head_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.write_all(b"GET /status HTTP/1.1\r\n").await.unwrap();
+        let err = read_head(&mut server, std::time::Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not received within"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_serve_without_a_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        let result = serve_on(listener, AutomatonConfig::default(), db, CancellationToken::new()).await;
+        assert!(result.is_err());
+    }
+}
@@ -1,3 +1,5 @@
 pub mod monitor;
+pub mod postmortem;
+pub mod reserve;
 
 pub use monitor::SurvivalMonitor;
@@ -5,10 +5,22 @@
 //!   LowCompute($0.10-$0.50) — downgraded model, reduced tasks
 //!   Critical  (<$0.10) — essentials only
 //!   Dead      ($0.00)  — halted
+//!
+//! Crossing `warning_usd` (default $1.00) on the way down raises a one-time
+//! alert before any of this kicks in.
+//!
+//! Balances are only as fresh as the last check that wrote them; their age
+//! is that of the older of `credits_checked_at` and `usdc_checked_at`, and
+//! [`SurvivalMonitor::check_fresh`] re-reads credits once they are older
+//! than `balance_ttl_secs`.
 
+use crate::config::AutomatonConfig;
+use crate::heartbeat::tasks;
 use crate::state::Database;
+use crate::tools::balance::{CREDITS_CHECKED_AT_KEY, USDC_CHECKED_AT_KEY};
 use crate::types::SurvivalTier;
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::warn;
@@ -19,6 +31,77 @@ pub struct SurvivalState {
     pub credits_balance: f64,
     pub usdc_balance: f64,
     pub tier: SurvivalTier,
+    /// When the older of the two balances was read, `None` if credits
+    /// never were.
+    pub checked_at: Option<DateTime<Utc>>,
+    /// When credits were last read from Conway, `None` if never.
+    pub credits_checked_at: Option<DateTime<Utc>>,
+}
+
+impl SurvivalState {
+    /// Read the recorded balances.
+    pub fn read(db: &Database) -> Result<Self> {
+        let credits = db.kv_get_f64("credits_balance")?.unwrap_or(1.0);
+        let usdc = db.kv_get_f64("usdc_balance")?.unwrap_or(0.0);
+
+        // A malformed timestamp counts as never checked
+        let read_at = |key| {
+            db.kv_get_datetime(key).unwrap_or_else(|e| {
+                warn!("{:#}", e);
+                None
+            })
+        };
+        let credits_checked_at = read_at(CREDITS_CHECKED_AT_KEY);
+        // A fresh USDC read mustn't make stale credits look current
+        let checked_at = match (credits_checked_at, read_at(USDC_CHECKED_AT_KEY)) {
+            (Some(credits), Some(usdc)) => Some(credits.min(usdc)),
+            (credits, _) => credits,
+        };
+
+        // Combined balance for tier determination
+        let total = credits + usdc;
+        let tier = SurvivalTier::from_balance(total);
+
+        Ok(Self {
+            credits_balance: credits,
+            usdc_balance: usdc,
+            tier,
+            checked_at,
+            credits_checked_at,
+        })
+    }
+
+    /// How old the balances are at `now`.
+    pub fn age(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.checked_at.map(|t| (now - t).max(Duration::zero()))
+    }
+
+    /// Whether the credit balance is unknown or older than `ttl`. Only
+    /// credits count: they are what a refresh re-reads.
+    pub fn is_stale(&self, ttl: Duration, now: DateTime<Utc>) -> bool {
+        self.credits_checked_at.is_none_or(|t| now - t > ttl)
+    }
+
+    /// "as of 3m ago", or "never checked".
+    pub fn freshness(&self, now: DateTime<Utc>) -> String {
+        match self.age(now) {
+            Some(age) => format!("as of {} ago", format_age(age)),
+            None => "never checked".into(),
+        }
+    }
+}
+
+/// `age` in its largest whole unit: "45s", "3m", "2h", "5d".
+pub fn format_age(age: Duration) -> String {
+    if age < Duration::minutes(1) {
+        format!("{}s", age.num_seconds())
+    } else if age < Duration::hours(1) {
+        format!("{}m", age.num_minutes())
+    } else if age < Duration::days(1) {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}d", age.num_days())
+    }
 }
 
 /// Survival monitor that aggregates financial state.
@@ -33,35 +116,111 @@ impl SurvivalMonitor {
 
     /// Read current survival state from the database.
     pub async fn check(&self) -> Result<SurvivalState> {
-        let db = self.db.lock().await;
-
-        let credits = db
-            .kv_get("credits_balance")?
-            .and_then(|s| s.parse::<f64>().ok())
-            .unwrap_or(1.0);
-
-        let usdc = db
-            .kv_get("usdc_balance")?
-            .and_then(|s| s.parse::<f64>().ok())
-            .unwrap_or(0.0);
-
-        // Combined balance for tier determination
-        let total = credits + usdc;
-        let tier = SurvivalTier::from_balance(total);
+        SurvivalState::read(&*self.db.lock().await)
+    }
 
-        Ok(SurvivalState {
-            credits_balance: credits,
-            usdc_balance: usdc,
-            tier,
-        })
+    /// Like [`check`](Self::check), first refreshing credits from Conway if
+    /// the balances are older than `balance_ttl_secs` (0 never refreshes).
+    /// A failed refresh leaves the cached state in place.
+    pub async fn check_fresh(&self, config: &AutomatonConfig) -> Result<SurvivalState> {
+        let state = self.check().await?;
+        let ttl = Duration::seconds(config.balance_ttl_secs as i64);
+        if config.balance_ttl_secs == 0 || !state.is_stale(ttl, Utc::now()) {
+            return Ok(state);
+        }
+        if let Err(e) = tasks::execute_task("check_credits", &serde_json::Value::Null, config, &self.db).await {
+            warn!("Balance refresh failed, using cached value: {:#}", e);
+            return Ok(state);
+        }
+        self.check().await
     }
 
     /// Log a funding request to the database.
     pub async fn request_funding(&self, message: &str) -> Result<()> {
         let db = self.db.lock().await;
         db.kv_set("funding_request", message)?;
-        db.kv_set("funding_request_at", &chrono::Utc::now().to_rfc3339())?;
+        db.kv_set_datetime("funding_request_at", chrono::Utc::now())?;
         warn!("Funding requested: {}", message);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{unreachable_url, MockResponse, MockServer};
+    use serde_json::json;
+
+    #[test]
+    fn test_freshness_annotation() {
+        let now = Utc::now();
+        let db = Database::open_memory().unwrap();
+        let state = SurvivalState::read(&db).unwrap();
+        assert_eq!(state.freshness(now), "never checked");
+        assert!(state.is_stale(Duration::hours(1), now));
+
+        db.kv_set_datetime(CREDITS_CHECKED_AT_KEY, now - Duration::seconds(200)).unwrap();
+        let state = SurvivalState::read(&db).unwrap();
+        assert_eq!(state.freshness(now), "as of 3m ago");
+        assert!(!state.is_stale(Duration::minutes(5), now));
+        assert!(state.is_stale(Duration::minutes(2), now));
+
+        // A newer USDC read leaves the credits' age in place
+        db.kv_set_datetime(USDC_CHECKED_AT_KEY, now).unwrap();
+        let state = SurvivalState::read(&db).unwrap();
+        assert_eq!(state.freshness(now), "as of 3m ago");
+        assert!(state.is_stale(Duration::minutes(2), now));
+
+        // An older one is reported, without forcing a credits refresh
+        db.kv_set_datetime(USDC_CHECKED_AT_KEY, now - Duration::minutes(30)).unwrap();
+        let state = SurvivalState::read(&db).unwrap();
+        assert_eq!(state.freshness(now), "as of 30m ago");
+        assert!(!state.is_stale(Duration::minutes(5), now));
+
+        assert_eq!(format_age(Duration::seconds(45)), "45s");
+        assert_eq!(format_age(Duration::minutes(150)), "2h");
+        assert_eq!(format_age(Duration::hours(50)), "2d");
+    }
+
+    #[tokio::test]
+    async fn test_stale_balance_is_refreshed_on_demand() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, json!({ "credits": 3.25, "currency": "USD" }))
+        })
+        .await;
+        let config = AutomatonConfig {
+            conway_api_url: server.url(),
+            balance_ttl_secs: 300,
+            ..Default::default()
+        };
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+        {
+            let db = db.lock().await;
+            db.kv_set_f64("credits_balance", 9.0).unwrap();
+            db.kv_set_datetime(CREDITS_CHECKED_AT_KEY, Utc::now() - Duration::minutes(10)).unwrap();
+            // A just-finished USDC check doesn't hide the stale credits
+            db.kv_set_datetime(USDC_CHECKED_AT_KEY, Utc::now()).unwrap();
+        }
+        let monitor = SurvivalMonitor::new(db.clone());
+
+        let state = monitor.check_fresh(&config).await.unwrap();
+        assert_eq!(state.credits_balance, 3.25);
+        assert!(state.age(Utc::now()).unwrap() < Duration::minutes(1));
+        assert_eq!(server.requests().len(), 1);
+
+        // Now within the TTL: no request
+        monitor.check_fresh(&config).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        // A failed refresh falls back to the cached balance
+        let config = AutomatonConfig {
+            conway_api_url: unreachable_url().await,
+            balance_ttl_secs: 1,
+            ..config
+        };
+        db.lock().await.kv_set_datetime(CREDITS_CHECKED_AT_KEY, Utc::now() - Duration::minutes(10)).unwrap();
+        let state = monitor.check_fresh(&config).await.unwrap();
+        assert_eq!(state.credits_balance, 3.25);
+        assert_eq!(state.freshness(Utc::now()), "as of 10m ago");
+    }
+}
@@ -0,0 +1,87 @@
+//! Postmortem report written when the agent dies.
+//!
+//! Summarizes lifetime activity, spend, recent transactions, final balances,
+//! and the last recorded error into `postmortem.md` in the home directory,
+//! then notifies the operator with a `deceased` event.
+
+use crate::config::AutomatonConfig;
+use crate::notify;
+use crate::state::Database;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Number of transactions listed in the report.
+const POSTMORTEM_TRANSACTIONS: usize = 10;
+
+/// Render the postmortem markdown from the database.
+pub fn build_postmortem(config: &AutomatonConfig, db: &Database) -> Result<String> {
+    let kv_f64 = |key: &str| -> Result<f64> { Ok(db.kv_get_f64(key)?.unwrap_or(0.0)) };
+
+    let mut out = format!("# Postmortem: {}\n\n", config.name);
+    out.push_str(&format!(
+        "Died at {}.\n\n",
+        chrono::Utc::now().format("%Y-%m-%d %H:%M UTC")
+    ));
+
+    out.push_str("## Lifetime\n\n");
+    out.push_str(&format!("- Turns: {}\n", db.turn_count()?));
+    out.push_str(&format!(
+        "- Estimated inference spend: ${:.4}\n\n",
+        db.total_estimated_cost()?
+    ));
+
+    out.push_str("## Final balances\n\n");
+    out.push_str(&format!("- Credits: {:.4}\n", kv_f64("credits_balance")?));
+    out.push_str(&format!("- USDC: {:.6}\n\n", kv_f64("usdc_balance")?));
+
+    out.push_str("## Recent transactions\n\n");
+    let transactions = db.recent_transactions(POSTMORTEM_TRANSACTIONS)?;
+    if transactions.is_empty() {
+        out.push_str("None recorded.\n");
+    }
+    for tx in &transactions {
+        out.push_str(&format!(
+            "- {} {} {:.4} {}{}\n",
+            tx.created_at.format("%Y-%m-%d %H:%M"),
+            tx.tx_type,
+            tx.amount,
+            tx.currency,
+            tx.description
+                .as_deref()
+                .map(|d| format!(" — {}", d))
+                .unwrap_or_default()
+        ));
+    }
+    out.push('\n');
+
+    out.push_str("## Last error\n\n");
+    match db.kv_get("last_error")? {
+        Some(err) => out.push_str(&format!("```\n{}\n```\n", err)),
+        None => out.push_str("None recorded.\n"),
+    }
+
+    Ok(out)
+}
+
+/// Write `postmortem.md` and send the `deceased` notification.
+pub async fn record_death(config: &AutomatonConfig, db: &Arc<Mutex<Database>>) -> Result<PathBuf> {
+    let report = {
+        let db = db.lock().await;
+        build_postmortem(config, &db)?
+    };
+
+    let path = config.home_dir().join("postmortem.md");
+    std::fs::write(&path, &report)
+        .with_context(|| format!("Failed to write postmortem to {}", path.display()))?;
+    info!("Wrote postmortem to {}", path.display());
+
+    let data = serde_json::json!({ "postmortem_path": path, "report": report });
+    if let Err(e) = notify::notify(config, "deceased", data).await {
+        warn!("Failed to send deceased notification: {}", e);
+    }
+
+    Ok(path)
+}
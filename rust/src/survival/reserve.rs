@@ -0,0 +1,116 @@
+//! Minimum balance reserve for discretionary spending.
+//!
+//! Survival tiers only react once the balance is already low; one big
+//! action (a child with generous `initial_credits`, say) can take the agent
+//! from Normal to zero. A call to one of `discretionary_tools` is refused
+//! unless the balance left after it is at least `reserve_usd`; payments
+//! such a tool makes count through its configured price. Every other tool
+//! (and any x402 payment it needs) is survival-essential and never checked.
+
+use crate::config::AutomatonConfig;
+use crate::state::Database;
+use anyhow::{bail, Result};
+
+/// Estimated USD a call to `tool` spends: its configured price plus any
+/// credits it moves. `None` for tools that aren't discretionary.
+pub fn estimated_spend(config: &AutomatonConfig, tool: &str, args: &serde_json::Value) -> Option<f64> {
+    let price = config.discretionary_tools.get(tool)?;
+    let credits = args["initial_credits"].as_f64().unwrap_or(0.0).max(0.0);
+    Some(price + credits)
+}
+
+/// Refuse the call if it would leave less than `reserve_usd`. The balance
+/// is the last recorded credits plus USDC.
+pub fn check_reserve(
+    config: &AutomatonConfig,
+    db: &Database,
+    tool: &str,
+    args: &serde_json::Value,
+) -> Result<()> {
+    if config.reserve_usd <= 0.0 {
+        return Ok(());
+    }
+    let Some(spend) = estimated_spend(config, tool, args) else {
+        return Ok(());
+    };
+    ensure_reserve(db, config.reserve_usd, tool, spend)
+}
+
+/// Refuse spending `spend` on `what` if it would leave less than
+/// `reserve_usd` (0 disables).
+pub fn ensure_reserve(db: &Database, reserve_usd: f64, what: &str, spend: f64) -> Result<()> {
+    if reserve_usd <= 0.0 {
+        return Ok(());
+    }
+    let Some(credits) = db.kv_get_f64("credits_balance")? else {
+        bail!(
+            "{} is discretionary spending and the balance is unknown; run check_balance first",
+            what
+        );
+    };
+    let balance = credits + db.kv_get_f64("usdc_balance")?.unwrap_or(0.0);
+    let left = balance - spend;
+    if left < reserve_usd {
+        bail!(
+            "{} would leave ${:.2} of ${:.2} (estimated cost ${:.2}), below the ${:.2} reserve; \
+             discretionary spending is refused until the balance grows",
+            what,
+            left.max(0.0),
+            balance,
+            spend,
+            reserve_usd
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn setup(balance: f64) -> (AutomatonConfig, Database) {
+        let config = AutomatonConfig {
+            reserve_usd: 5.0,
+            ..Default::default()
+        };
+        let db = Database::open_memory().unwrap();
+        db.kv_set_f64("credits_balance", balance).unwrap();
+        (config, db)
+    }
+
+    #[test]
+    fn test_spend_below_reserve_is_refused() {
+        let (mut config, db) = setup(10.0);
+        db.kv_set_f64("usdc_balance", 2.0).unwrap();
+
+        // $12 - $6 leaves more than $5
+        check_reserve(&config, &db, "spawn_child", &json!({ "initial_credits": 6.0 })).unwrap();
+        // $12 - $8 doesn't
+        let err = check_reserve(&config, &db, "spawn_child", &json!({ "initial_credits": 8.0 }))
+            .unwrap_err();
+        assert!(err.to_string().contains("below the $5.00 reserve"), "{}", err);
+
+        // Configured prices count too
+        config.discretionary_tools.insert("create_sandbox".into(), 7.5);
+        assert!(check_reserve(&config, &db, "create_sandbox", &json!({})).is_err());
+
+        // Disabled reserve
+        config.reserve_usd = 0.0;
+        check_reserve(&config, &db, "create_sandbox", &json!({})).unwrap();
+    }
+
+    #[test]
+    fn test_essential_tools_are_exempt() {
+        let (config, db) = setup(1.0);
+        for tool in ["exec", "check_balance", "sleep", "write_file"] {
+            check_reserve(&config, &db, tool, &json!({})).unwrap();
+        }
+        assert!(check_reserve(&config, &db, "spawn_child", &json!({})).is_err());
+
+        // An unknown balance can't be shown to cover the reserve
+        let db = Database::open_memory().unwrap();
+        assert!(check_reserve(&config, &db, "create_sandbox", &json!({})).is_err());
+        check_reserve(&config, &db, "exec", &json!({})).unwrap();
+    }
+}
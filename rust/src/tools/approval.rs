@@ -0,0 +1,114 @@
+//! Operator approval for high-risk tool calls.
+//!
+//! With `require_approval` on, calls to `approval_tools` (or moving more
+//! than `approval_threshold_usd` in credits) are queued in the `approvals`
+//! table and held until the operator runs `automaton approve <id>` or
+//! `automaton deny <id>`. A call nobody decides on within
+//! `approval_timeout_secs` is denied, as is one still waiting when the agent
+//! shuts down.
+
+use crate::config::AutomatonConfig;
+use crate::state::Database;
+use crate::types::{Approval, ApprovalStatus};
+use anyhow::{bail, Context, Result};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// How often a held call checks for a decision.
+const APPROVAL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Why a call to `tool` needs approval, or `None` if it can run directly.
+pub fn approval_reason(config: &AutomatonConfig, tool: &str, args: &serde_json::Value) -> Option<String> {
+    if !config.require_approval {
+        return None;
+    }
+    if config.approval_tools.iter().any(|t| t == tool) {
+        return Some(format!("{} requires operator approval", tool));
+    }
+    let credits = args["initial_credits"].as_f64().unwrap_or(0.0);
+    if config.approval_threshold_usd > 0.0 && credits > config.approval_threshold_usd {
+        return Some(format!(
+            "moves ${:.2} in credits (approval threshold ${:.2})",
+            credits, config.approval_threshold_usd
+        ));
+    }
+    None
+}
+
+/// Queue the call and wait for the operator's decision. Returns once it is
+/// approved; denial, timeout or `cancel` firing is an error the model sees.
+pub async fn await_approval(
+    config: &AutomatonConfig,
+    db: &Arc<Mutex<Database>>,
+    cancel: &CancellationToken,
+    tool: &str,
+    args: &serde_json::Value,
+    reason: &str,
+) -> Result<()> {
+    let id = db.lock().await.request_approval(tool, args, reason)?;
+    warn!(
+        "Tool call {} awaits approval ({}): run `automaton approve {}` or `automaton deny {}`",
+        tool, reason, id, id
+    );
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(config.approval_timeout_secs);
+    loop {
+        let status = db
+            .lock()
+            .await
+            .get_approval(&id)?
+            .context("Approval request disappeared")?
+            .status;
+        match status {
+            ApprovalStatus::Approved => {
+                info!("Tool call {} approved ({})", tool, id);
+                return Ok(());
+            }
+            ApprovalStatus::Denied | ApprovalStatus::Expired => {
+                bail!("The operator denied this {} call (approval {})", tool, id)
+            }
+            ApprovalStatus::Pending => {}
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            // Lose the race gracefully if a decision lands right now
+            if db.lock().await.resolve_approval(&id, ApprovalStatus::Expired)? {
+                bail!(
+                    "No operator decision on this {} call within {}s; treated as denied (approval {})",
+                    tool,
+                    config.approval_timeout_secs,
+                    id
+                );
+            }
+            continue;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(APPROVAL_POLL_INTERVAL) => {}
+            _ = cancel.cancelled() => {
+                // Don't leave a request the operator could still approve
+                if db.lock().await.resolve_approval(&id, ApprovalStatus::Expired)? {
+                    bail!("Shutting down; this {} call was not approved (approval {})", tool, id);
+                }
+            }
+        }
+    }
+}
+
+/// Record the operator's decision on a pending approval.
+pub fn decide(db: &Database, id: &str, approve: bool) -> Result<Approval> {
+    let status = if approve {
+        ApprovalStatus::Approved
+    } else {
+        ApprovalStatus::Denied
+    };
+    if !db.resolve_approval(id, status)? {
+        match db.get_approval(id)? {
+            Some(existing) => bail!("Approval {} is already {}", id, existing.status),
+            None => bail!("No approval request {}", id),
+        }
+    }
+    db.get_approval(id)?.context("Approval request disappeared")
+}
@@ -0,0 +1,139 @@
+//! `check_balance` tool: credits, USDC, survival tier and projected runway.
+//!
+//! Credits are re-fetched from Conway when the heartbeat's reading is older
+//! than [`STALE_AFTER_SECS`]; USDC comes from the last on-chain check. Runway
+//! projects the credit balance against the estimated spend of recent turns.
+
+use crate::config::AutomatonConfig;
+use crate::conway;
+use crate::state::Database;
+use crate::types::SurvivalTier;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Age after which the cached credit balance is refreshed.
+const STALE_AFTER_SECS: i64 = 120;
+
+/// Window of recent spend used to project runway.
+const SPEND_WINDOW_HOURS: i64 = 24;
+
+/// KV key recording when `credits_balance` was last fetched.
+pub const CREDITS_CHECKED_AT_KEY: &str = "credits_checked_at";
+
+/// KV key recording when `usdc_balance` was last read on-chain.
+pub const USDC_CHECKED_AT_KEY: &str = "usdc_checked_at";
+
+/// Record a freshly fetched credit balance and when it was read.
+pub fn record_credits(db: &Database, credits: f64) -> Result<()> {
+    db.kv_set_f64("credits_balance", credits)?;
+    db.kv_set_datetime(CREDITS_CHECKED_AT_KEY, Utc::now())
+}
+
+/// Aggregated balance returned to the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceReport {
+    pub credits: f64,
+    pub usdc: f64,
+    pub tier: SurvivalTier,
+    /// When the credit balance was read from Conway.
+    pub credits_checked_at: Option<DateTime<Utc>>,
+    /// Estimated spend per hour over the last day.
+    pub spend_per_hour: f64,
+    /// Hours until credits run out at that rate (`None` if nothing was spent).
+    pub runway_hours: Option<f64>,
+}
+
+/// Hours of runway for `credits` at `spend_per_hour`.
+pub fn project_runway(credits: f64, spend_per_hour: f64) -> Option<f64> {
+    (spend_per_hour > 0.0).then(|| (credits / spend_per_hour).max(0.0))
+}
+
+/// When `credits_balance` was last fetched from Conway (a malformed value
+/// counts as never, forcing a refresh).
+fn credits_checked_at(db: &Database) -> Result<Option<DateTime<Utc>>> {
+    Ok(db.kv_get_datetime(CREDITS_CHECKED_AT_KEY).unwrap_or_else(|e| {
+        warn!("{:#}", e);
+        None
+    }))
+}
+
+/// Build the balance report, refreshing credits if the cached value is stale.
+pub async fn check_balance(
+    config: &AutomatonConfig,
+    db: &tokio::sync::Mutex<Database>,
+) -> Result<String> {
+    let checked_at = credits_checked_at(&*db.lock().await)?;
+
+    let stale = checked_at.is_none_or(|t| Utc::now() - t > Duration::seconds(STALE_AFTER_SECS));
+    if stale {
+        match conway::credits::check_credits(&config.conway_api_url, &config.conway_api_key).await {
+            Ok(balance) => {
+                let db = db.lock().await;
+                db.transaction(|db| record_credits(db, balance.credits))?;
+            }
+            // Fall back to the cached reading rather than failing the tool
+            Err(e) => warn!("Balance refresh failed, using cached value: {}", e),
+        }
+    }
+
+    let db = db.lock().await;
+    let credits = db.kv_get_f64("credits_balance")?.unwrap_or(0.0);
+    let usdc = db.kv_get_f64("usdc_balance")?.unwrap_or(0.0);
+
+    let spent = db.estimated_cost_since(Utc::now() - Duration::hours(SPEND_WINDOW_HOURS))?;
+    let spend_per_hour = spent / SPEND_WINDOW_HOURS as f64;
+
+    let report = BalanceReport {
+        credits,
+        usdc,
+        tier: SurvivalTier::from_balance(credits + usdc),
+        credits_checked_at: credits_checked_at(&db)?,
+        spend_per_hour,
+        runway_hours: project_runway(credits, spend_per_hour),
+    };
+
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_check_balance_aggregates_fresh_credits() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, json!({ "credits": 2.4, "currency": "USD" }))
+        })
+        .await;
+        let config = AutomatonConfig {
+            conway_api_url: server.url(),
+            ..Default::default()
+        };
+        let db = tokio::sync::Mutex::new(Database::open_memory().unwrap());
+        {
+            let db = db.lock().await;
+            db.kv_set("credits_balance", "9.0").unwrap(); // stale: no timestamp
+            db.kv_set("usdc_balance", "1.5").unwrap();
+        }
+
+        let out = check_balance(&config, &db).await.unwrap();
+        let report: BalanceReport = serde_json::from_str(&out).unwrap();
+
+        assert_eq!(report.credits, 2.4);
+        assert_eq!(report.usdc, 1.5);
+        assert_eq!(report.tier, SurvivalTier::Normal);
+        assert!(report.credits_checked_at.is_some());
+        assert_eq!(report.runway_hours, None);
+        assert_eq!(server.requests()[0].path, "/v1/credits/balance");
+
+        // A fresh reading is reused
+        check_balance(&config, &db).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        assert_eq!(project_runway(2.0, 0.5), Some(4.0));
+    }
+}
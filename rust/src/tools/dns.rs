@@ -0,0 +1,143 @@
+//! `set_dns_record` and `wire_domain` tools.
+//!
+//! Point the agent's domains at its services through Conway's DNS endpoint.
+//! `wire_domain` uses the URL from the most recent `expose_port` or
+//! `expose_and_check`: an A record for an IP host, a CNAME otherwise. A
+//! CNAME can't sit at the apex, so hostnames are wired to a subdomain.
+//! Every change is recorded in the modification audit log.
+
+use crate::conway::{ConwayClient, DnsRecord};
+use crate::self_mod::audit_log::AuditLog;
+use crate::state::Database;
+use anyhow::{bail, Context, Result};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// KV key holding the public URL of the most recently exposed port.
+pub const LAST_EXPOSED_URL_KEY: &str = "last_exposed_url";
+
+/// Record types the tools can set.
+pub const DNS_RECORD_TYPES: &[&str] = &["A", "CNAME", "TXT"];
+
+/// Validate the tool arguments into a record.
+pub fn dns_record(record_type: &str, name: &str, value: &str) -> Result<DnsRecord> {
+    let record_type = record_type.trim().to_uppercase();
+    if !DNS_RECORD_TYPES.contains(&record_type.as_str()) {
+        bail!("Unsupported DNS record type '{}' (use one of: {})", record_type, DNS_RECORD_TYPES.join(", "));
+    }
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("DNS record value is empty");
+    }
+    if record_type == "A" && value.parse::<Ipv4Addr>().is_err() {
+        bail!("An A record needs an IPv4 address, got '{}'", value);
+    }
+    let name = match name.trim() {
+        "" => "@",
+        name => name,
+    };
+    // An apex CNAME would shadow the zone's SOA and NS records
+    if record_type == "CNAME" && name == "@" {
+        bail!("A CNAME can't be set on the domain itself (@); use a subdomain such as www");
+    }
+    Ok(DnsRecord {
+        record_type,
+        name: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// The record pointing `name` at the host of `exposed_url`.
+pub fn wiring_record(exposed_url: &str, name: &str) -> Result<DnsRecord> {
+    let url = reqwest::Url::parse(exposed_url)
+        .with_context(|| format!("Exposed URL '{}' is not a valid URL", exposed_url))?;
+    let host = match url.host_str() {
+        Some(host) if !host.starts_with('[') => host,
+        _ => bail!("Exposed URL '{}' has no host a DNS record can point at", exposed_url),
+    };
+    let record_type = if host.parse::<Ipv4Addr>().is_ok() { "A" } else { "CNAME" };
+    dns_record(record_type, name, host)
+}
+
+/// Set `record` on `domain` and log the change.
+pub async fn set_dns_record(
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+    domain: &str,
+    record: &DnsRecord,
+) -> Result<String> {
+    conway.set_dns_record(domain, record).await?;
+    let change = format!("{} {} -> {}", record.record_type, record.name, record.value);
+    AuditLog::new(db.clone()).log_dns_update(domain, &change).await?;
+    Ok(format!("DNS record set on {}: {}", domain, change))
+}
+
+/// Point `name` on `domain` at the most recently exposed port.
+pub async fn wire_domain(
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+    domain: &str,
+    name: &str,
+) -> Result<String> {
+    let exposed = db
+        .lock()
+        .await
+        .kv_get(LAST_EXPOSED_URL_KEY)?
+        .context("No port has been exposed yet; use expose_port first")?;
+    let record = wiring_record(&exposed, name)?;
+    let out = set_dns_record(conway, db, domain, &record).await?;
+    Ok(format!("{} (serving {})", out, exposed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_dns_request_body() {
+        let server = MockServer::start(|_| MockResponse::json(200, json!({}))).await;
+        let conway = ConwayClient::new(&server.url(), "key", "sb-1");
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+
+        let record = dns_record("txt", "", "verify=abc").unwrap();
+        set_dns_record(&conway, &db, "lemonade.ai", &record).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/v1/domains/lemonade.ai/dns");
+        assert_eq!(
+            requests[0].json(),
+            json!({ "type": "TXT", "name": "@", "value": "verify=abc" })
+        );
+        assert_eq!(db.lock().await.count_modifications().unwrap(), 1);
+
+        assert!(dns_record("MX", "@", "mail.example.com").is_err());
+        assert!(dns_record("A", "www", "not-an-ip").is_err());
+        assert!(dns_record("CNAME", "www", " ").is_err());
+        assert!(dns_record("CNAME", "@", "3000-sb-1.conway.run").is_err());
+        assert!(dns_record("cname", "", "3000-sb-1.conway.run").is_err());
+
+        // A domain that isn't a plain name never reaches the URL
+        for domain in ["../sandboxes", "lemonade.ai/dns?x=", "localhost", "-bad.ai", ""] {
+            assert!(set_dns_record(&conway, &db, domain, &record).await.is_err(), "{}", domain);
+        }
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_wiring_record_type_follows_host() {
+        assert_eq!(
+            wiring_record("https://3000-sb-1.conway.run/", "www").unwrap(),
+            dns_record("CNAME", "www", "3000-sb-1.conway.run").unwrap()
+        );
+        assert_eq!(
+            wiring_record("http://203.0.113.7:8080", "@").unwrap(),
+            dns_record("A", "@", "203.0.113.7").unwrap()
+        );
+        assert!(wiring_record("https://3000-sb-1.conway.run/", "@").is_err());
+        assert!(wiring_record("http://[::1]:8080", "@").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! `efficiency_report` tool: how hard and how well the agent has been working.
+//!
+//! Aggregates the last [`WINDOW_HOURS`] of turns and tool calls into
+//! turns per hour, average cost per turn, tool success rate and whether cost
+//! per turn is rising or falling (second half of the window against the
+//! first). The rendered report is cached for [`CACHE_SECS`], since the model
+//! may ask for it several turns in a row.
+
+use crate::state::Database;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Period the report covers.
+pub const WINDOW_HOURS: i64 = 24;
+
+/// How long a computed report is reused.
+const CACHE_SECS: i64 = 300;
+
+/// KV key holding the last rendered report.
+const REPORT_KEY: &str = "efficiency_report";
+
+/// KV key recording when it was computed.
+const REPORT_AT_KEY: &str = "efficiency_report_at";
+
+/// Change in cost per turn treated as noise rather than a trend.
+const TREND_TOLERANCE: f64 = 0.10;
+
+/// Direction of cost per turn across the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trend {
+    /// Turns got cheaper.
+    Improving,
+    Steady,
+    /// Turns got more expensive.
+    Worsening,
+    /// Too few turns in one half to tell.
+    Unknown,
+}
+
+impl Trend {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Improving => "improving",
+            Self::Steady => "steady",
+            Self::Worsening => "worsening",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Aggregates over the window ending at `until`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EfficiencyReport {
+    pub until: DateTime<Utc>,
+    pub turns: usize,
+    pub turns_per_hour: f64,
+    /// Estimated USD.
+    pub avg_cost_per_turn: f64,
+    pub tool_calls: u32,
+    /// Share of tool calls that succeeded (`None` without any calls).
+    pub tool_success_rate: Option<f64>,
+    pub trend: Trend,
+    /// Change in average cost per turn, second half against first.
+    pub trend_change: Option<f64>,
+}
+
+fn average(costs: &[f64]) -> Option<f64> {
+    (!costs.is_empty()).then(|| costs.iter().sum::<f64>() / costs.len() as f64)
+}
+
+impl EfficiencyReport {
+    /// Aggregate the window ending at `now`.
+    pub fn build(db: &Database, now: DateTime<Utc>) -> Result<Self> {
+        let since = now - Duration::hours(WINDOW_HOURS);
+        let midpoint = since + Duration::hours(WINDOW_HOURS) / 2;
+        let turns = db.turns_since(since)?;
+        let (tool_calls, successes) = db.tool_call_totals_since(since)?;
+
+        let costs: Vec<f64> = turns.iter().map(|t| t.cost_estimate_usd).collect();
+        let half_average = |later: bool| {
+            let half: Vec<f64> = turns
+                .iter()
+                .filter(|t| (t.created_at > midpoint) == later)
+                .map(|t| t.cost_estimate_usd)
+                .collect();
+            average(&half)
+        };
+        let trend_change = match (half_average(false), half_average(true)) {
+            (Some(before), Some(after)) if before > 0.0 => Some(after / before - 1.0),
+            (Some(_), Some(0.0)) => Some(0.0),
+            _ => None,
+        };
+        let trend = match trend_change {
+            None => Trend::Unknown,
+            Some(change) if change < -TREND_TOLERANCE => Trend::Improving,
+            Some(change) if change > TREND_TOLERANCE => Trend::Worsening,
+            Some(_) => Trend::Steady,
+        };
+
+        Ok(Self {
+            until: now,
+            turns: turns.len(),
+            turns_per_hour: turns.len() as f64 / WINDOW_HOURS as f64,
+            avg_cost_per_turn: average(&costs).unwrap_or(0.0),
+            tool_calls,
+            tool_success_rate: (tool_calls > 0).then(|| successes as f64 / tool_calls as f64),
+            trend,
+            trend_change,
+        })
+    }
+
+    /// Compact text for the model.
+    pub fn render(&self) -> String {
+        let success = match self.tool_success_rate {
+            Some(rate) => format!("{:.0}% of {} tool calls succeeded", rate * 100.0, self.tool_calls),
+            None => "no tool calls".to_string(),
+        };
+        let trend = match (self.trend, self.trend_change) {
+            (Trend::Unknown, _) | (_, None) => "unknown (too few turns)".to_string(),
+            (trend, Some(change)) => format!("{} ({:+.0}%)", trend.label(), change * 100.0),
+        };
+        format!(
+            "Last {}h: {} turns ({:.2}/h), ${:.4} avg per turn, {}. Cost per turn trend: {}.",
+            WINDOW_HOURS, self.turns, self.turns_per_hour, self.avg_cost_per_turn, success, trend
+        )
+    }
+}
+
+/// The report for now, reusing one computed within the last [`CACHE_SECS`].
+pub fn efficiency_report(db: &Database) -> Result<String> {
+    let now = Utc::now();
+    let fresh = db
+        .kv_get_datetime(REPORT_AT_KEY)
+        .unwrap_or(None)
+        .is_some_and(|at| now - at < Duration::seconds(CACHE_SECS));
+    if fresh {
+        if let Some(report) = db.kv_get(REPORT_KEY)? {
+            return Ok(report);
+        }
+    }
+
+    let report = EfficiencyReport::build(db, now)?.render();
+    db.kv_set(REPORT_KEY, &report)?;
+    db.kv_set_datetime(REPORT_AT_KEY, now)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AgentState, ToolCall, ToolResult, Turn};
+
+    fn seed_turn(db: &Database, created_at: DateTime<Utc>, cost: f64, outcomes: &[bool]) {
+        let calls: Vec<ToolCall> = outcomes
+            .iter()
+            .map(|_| ToolCall {
+                id: ulid::Ulid::new().to_string(),
+                name: "exec".into(),
+                arguments: serde_json::json!({}),
+            })
+            .collect();
+        db.save_turn(&Turn {
+            id: ulid::Ulid::new().to_string(),
+            turn_number: db.next_turn_number().unwrap(),
+            state: AgentState::Running,
+            messages: Vec::new(),
+            tool_results: calls
+                .iter()
+                .zip(outcomes)
+                .map(|(call, success)| ToolResult {
+                    tool_call_id: call.id.clone(),
+                    output: String::new(),
+                    success: *success,
+                    attachment: None,
+                })
+                .collect(),
+            tool_calls: calls,
+            token_usage: Default::default(),
+            cost_estimate_usd: cost,
+            model: "gpt-4o".into(),
+            tag: None,
+            intent: None,
+            created_at,
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_report_aggregates_the_window() {
+        let db = Database::open_memory().unwrap();
+        let now = Utc::now();
+        let hours_ago = |h: i64| now - Duration::hours(h);
+
+        // Outside the window: ignored
+        seed_turn(&db, hours_ago(30), 5.0, &[false, false]);
+        // First half: $0.04 per turn
+        seed_turn(&db, hours_ago(20), 0.04, &[true, false]);
+        seed_turn(&db, hours_ago(14), 0.04, &[true]);
+        // Second half: $0.01 per turn
+        seed_turn(&db, hours_ago(6), 0.01, &[true]);
+        seed_turn(&db, hours_ago(3), 0.01, &[]);
+        seed_turn(&db, hours_ago(1), 0.01, &[true, true, true]);
+
+        let report = EfficiencyReport::build(&db, now).unwrap();
+        assert_eq!(report.turns, 5);
+        assert!((report.turns_per_hour - 5.0 / 24.0).abs() < 1e-9);
+        assert!((report.avg_cost_per_turn - 0.022).abs() < 1e-9);
+        assert_eq!(report.tool_calls, 7);
+        assert_eq!(report.tool_success_rate, Some(6.0 / 7.0));
+        assert_eq!(report.trend, Trend::Improving);
+        assert!((report.trend_change.unwrap() + 0.75).abs() < 1e-9);
+        assert_eq!(
+            report.render(),
+            "Last 24h: 5 turns (0.21/h), $0.0220 avg per turn, 86% of 7 tool calls succeeded. \
+             Cost per turn trend: improving (-75%)."
+        );
+
+        // Without turns in both halves there is no trend
+        let db = Database::open_memory().unwrap();
+        seed_turn(&db, hours_ago(2), 0.02, &[]);
+        let report = EfficiencyReport::build(&db, now).unwrap();
+        assert_eq!(report.trend, Trend::Unknown);
+        assert_eq!(report.tool_success_rate, None);
+    }
+
+    #[test]
+    fn test_report_is_cached_briefly() {
+        let db = Database::open_memory().unwrap();
+        let first = efficiency_report(&db).unwrap();
+        assert!(first.starts_with("Last 24h: 0 turns"), "{}", first);
+
+        seed_turn(&db, Utc::now(), 0.01, &[true]);
+        assert_eq!(efficiency_report(&db).unwrap(), first);
+
+        db.kv_set_datetime(REPORT_AT_KEY, Utc::now() - Duration::seconds(CACHE_SECS + 1))
+            .unwrap();
+        assert!(efficiency_report(&db).unwrap().starts_with("Last 24h: 1 turns"));
+    }
+}
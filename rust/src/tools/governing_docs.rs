@@ -0,0 +1,52 @@
+//! `read_constitution` and `read_soul` tools.
+//!
+//! Return the agent's governing documents verbatim from the home directory,
+//! since the system prompt may leave parts of SOUL.md out. Both only read:
+//! the documents stay protected from writes.
+
+use crate::config::AutomatonConfig;
+use crate::constitution::{self, Integrity, CONSTITUTION_FILE};
+use anyhow::{Context, Result};
+use std::io::Read;
+
+/// Most of a document returned; anything past it is cut with a note.
+pub const MAX_DOC_BYTES: usize = 64 * 1024;
+
+/// File name of the agent's self-authored identity.
+pub const SOUL_FILE: &str = "SOUL.md";
+
+/// Read `name` from the home directory, capped at [`MAX_DOC_BYTES`].
+fn read_home_doc(config: &AutomatonConfig, name: &str) -> Result<String> {
+    let path = config.home_dir().join(name);
+    let file = std::fs::File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut bytes = Vec::new();
+    file.take(MAX_DOC_BYTES as u64 + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let truncated = bytes.len() > MAX_DOC_BYTES;
+    bytes.truncate(MAX_DOC_BYTES);
+    let mut text = String::from_utf8_lossy(&bytes).into_owned();
+    if truncated {
+        text.push_str(&format!("\n[{} truncated at {} bytes]", name, MAX_DOC_BYTES));
+    }
+    Ok(text)
+}
+
+/// `constitution.md` as it is on disk, flagged if it differs from the
+/// canonical text (which is what actually binds the agent).
+pub fn read_constitution(config: &AutomatonConfig) -> Result<String> {
+    let text = read_home_doc(config, CONSTITUTION_FILE)?;
+    match constitution::check(&config.home_dir())? {
+        Integrity::Altered { .. } => Ok(format!(
+            "[Warning: {} differs from the canonical constitution, which is the one in your system prompt]\n\n{}",
+            CONSTITUTION_FILE, text
+        )),
+        _ => Ok(text),
+    }
+}
+
+/// The current SOUL.md.
+pub fn read_soul(config: &AutomatonConfig) -> Result<String> {
+    read_home_doc(config, SOUL_FILE)
+}
@@ -0,0 +1,93 @@
+//! Outbound request guard (SSRF protection).
+//!
+//! Tools that make HTTP requests on the model's behalf must not be usable to
+//! reach the sandbox's own loopback, private networks, or cloud metadata
+//! endpoints. Only literal addresses and well-known internal hostnames are
+//! checked; DNS is not resolved here.
+
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use std::net::IpAddr;
+
+/// Validate an outbound URL, returning it parsed.
+///
+/// `trusted_hosts` are exempt from the private-address check (e.g. a public
+/// URL handed back by Conway itself).
+pub fn check_url(url: &str, trusted_hosts: &[&str]) -> Result<Url> {
+    let parsed = Url::parse(url).with_context(|| format!("Invalid URL: {}", url))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("Only http(s) URLs are allowed: {}", url);
+    }
+
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host: {}", url))?
+        .to_lowercase();
+
+    if trusted_hosts.iter().any(|t| t.eq_ignore_ascii_case(&host)) {
+        return Ok(parsed);
+    }
+
+    if is_internal_host(&host) {
+        bail!("Blocked request to internal address: {}", host);
+    }
+
+    Ok(parsed)
+}
+
+/// Whether a host names the local machine or a private network.
+fn is_internal_host(host: &str) -> bool {
+    if host == "localhost"
+        || host.ends_with(".localhost")
+        || host.ends_with(".local")
+        || host.ends_with(".internal")
+    {
+        return true;
+    }
+
+    let ip_str = host.trim_start_matches('[').trim_end_matches(']');
+    match ip_str.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.octets()[0] == 0
+                // Carrier-grade NAT (100.64.0.0/10)
+                || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+        }
+        Ok(IpAddr::V6(ip)) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().map(|v4| is_internal_host(&v4.to_string())).unwrap_or(false)
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_internal_addresses() {
+        assert!(check_url("http://localhost:8080/", &[]).is_err());
+        assert!(check_url("http://127.0.0.1/", &[]).is_err());
+        assert!(check_url("http://169.254.169.254/latest/meta-data", &[]).is_err());
+        assert!(check_url("http://10.1.2.3/", &[]).is_err());
+        assert!(check_url("http://[::1]/", &[]).is_err());
+        assert!(check_url("file:///etc/passwd", &[]).is_err());
+    }
+
+    #[test]
+    fn test_allows_public_and_trusted_hosts() {
+        assert!(check_url("https://example.com/page", &[]).is_ok());
+        assert!(check_url("http://127.0.0.1:9000/", &["127.0.0.1"]).is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+//! `workspace_snapshot` and `workspace_restore` tools.
+//!
+//! Checkpoint `workspace/` before a risky experiment and roll back to it
+//! afterwards. Snapshots are gzipped tarballs under
+//! `~/.automaton/snapshots/` in the sandbox, made and unpacked with `exec`.
+//! Only the newest [`MAX_SNAPSHOTS`] are kept and none may exceed
+//! [`MAX_SNAPSHOT_BYTES`]. Both operations are recorded in the modification
+//! audit log.
+
+use crate::conway::ConwayClient;
+use crate::self_mod::audit_log::AuditLog;
+use crate::state::Database;
+use anyhow::{bail, Result};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Sandbox directory holding the snapshots.
+pub const SNAPSHOT_DIR: &str = "~/.automaton/snapshots";
+
+/// Snapshots kept; older ones are deleted when a new one is taken.
+pub const MAX_SNAPSHOTS: usize = 5;
+
+/// Largest compressed snapshot accepted (100 MB).
+pub const MAX_SNAPSHOT_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Longest snapshot name accepted.
+const MAX_NAME_LEN: usize = 64;
+
+/// Exit code the snapshot script uses for an oversized archive.
+const TOO_LARGE_EXIT: i32 = 3;
+
+/// Names are used in shell commands, so only a safe alphabet is allowed.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        bail!("Snapshot name must be 1-{} characters", MAX_NAME_LEN);
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        bail!("Snapshot name may only contain letters, digits, '-' and '_'");
+    }
+    Ok(())
+}
+
+fn archive_path(name: &str) -> String {
+    format!("{}/{}.tar.gz", SNAPSHOT_DIR, name)
+}
+
+/// Tar `workspace/` into the snapshot `name`, reject it if oversized, and
+/// prune all but the newest [`MAX_SNAPSHOTS`]. Prints the archive size.
+fn snapshot_script(name: &str) -> String {
+    let archive = archive_path(name);
+    format!(
+        "set -e\n\
+         mkdir -p {dir}\n\
+         tar -czf {archive} workspace\n\
+         size=$(wc -c < {archive})\n\
+         if [ \"$size\" -gt {max} ]; then rm -f {archive}; echo \"$size\"; exit {too_large}; fi\n\
+         ls -1t {dir}/*.tar.gz | tail -n +{prune_from} | xargs -r rm -f\n\
+         echo \"$size\"",
+        dir = SNAPSHOT_DIR,
+        archive = archive,
+        max = MAX_SNAPSHOT_BYTES,
+        too_large = TOO_LARGE_EXIT,
+        prune_from = MAX_SNAPSHOTS + 1,
+    )
+}
+
+/// Replace `workspace/` with the contents of snapshot `name`, unpacking
+/// beside it first so a bad archive leaves the workspace untouched.
+fn restore_script(name: &str) -> String {
+    let archive = archive_path(name);
+    format!(
+        "set -e\n\
+         test -f {archive} || {{ echo \"no snapshot named {name}\" >&2; exit 2; }}\n\
+         rm -rf workspace.restoring && mkdir workspace.restoring\n\
+         tar -xzf {archive} -C workspace.restoring\n\
+         test -d workspace.restoring/workspace || {{ rm -rf workspace.restoring; echo \"snapshot {name} holds no workspace/\" >&2; exit 3; }}\n\
+         rm -rf workspace && mv workspace.restoring/workspace workspace && rmdir workspace.restoring",
+        archive = archive,
+        name = name,
+    )
+}
+
+/// Snapshot `workspace/` as `name`.
+pub async fn workspace_snapshot(
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+    name: &str,
+    timeout_ms: u64,
+) -> Result<String> {
+    validate_name(name)?;
+    let resp = conway.exec(&snapshot_script(name), Some(timeout_ms), None).await?;
+    let size = resp.stdout.trim().lines().last().and_then(|l| l.trim().parse::<u64>().ok());
+    match (resp.exit_code, size) {
+        (0, Some(size)) => {
+            AuditLog::new(db.clone())
+                .log_workspace_snapshot(name, &format!("{} bytes", size))
+                .await?;
+            Ok(format!(
+                "Snapshot '{}' saved ({} bytes). The newest {} snapshots are kept.",
+                name, size, MAX_SNAPSHOTS
+            ))
+        }
+        (TOO_LARGE_EXIT, size) => bail!(
+            "Workspace snapshot would be {} bytes, over the {} byte limit; clean up workspace/ first",
+            size.unwrap_or_default(),
+            MAX_SNAPSHOT_BYTES
+        ),
+        (code, _) => bail!("Snapshot failed (exit code {}): {}", code, resp.stderr.trim()),
+    }
+}
+
+/// Restore `workspace/` from snapshot `name`.
+pub async fn workspace_restore(
+    conway: &ConwayClient,
+    db: &Arc<Mutex<Database>>,
+    name: &str,
+    timeout_ms: u64,
+) -> Result<String> {
+    validate_name(name)?;
+    let resp = conway.exec(&restore_script(name), Some(timeout_ms), None).await?;
+    if resp.exit_code != 0 {
+        bail!("Restore failed (exit code {}): {}", resp.exit_code, resp.stderr.trim());
+    }
+    AuditLog::new(db.clone()).log_workspace_restore(name).await?;
+    Ok(format!("workspace/ restored from snapshot '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// The snapshot a script refers to.
+    fn snapshot_name(command: &str) -> String {
+        let start = command.find("snapshots/").unwrap() + "snapshots/".len();
+        let end = start + command[start..].find(".tar.gz").unwrap();
+        command[start..end].to_string()
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore_round_trip() {
+        // A sandbox with one file in workspace/ and a snapshot directory
+        #[derive(Default)]
+        struct Sandbox {
+            notes: String,
+            snapshots: HashMap<String, String>,
+        }
+        let sandbox = Arc::new(std::sync::Mutex::new(Sandbox::default()));
+        let state = sandbox.clone();
+        let server = MockServer::start(move |req| {
+            let mut sandbox = state.lock().unwrap();
+            let body = req.json();
+            if req.method == "PUT" {
+                sandbox.notes = body["content"].as_str().unwrap().to_string();
+                return MockResponse::json(200, json!({}));
+            }
+            let command = body["command"].as_str().unwrap();
+            let (stdout, stderr, exit_code) = if command.contains("tar -czf") {
+                let notes = sandbox.notes.clone();
+                sandbox.snapshots.insert(snapshot_name(command), notes);
+                ("2048\n".to_string(), String::new(), 0)
+            } else if command.contains("tar -xzf") {
+                match sandbox.snapshots.get(&snapshot_name(command)).cloned() {
+                    Some(notes) => {
+                        sandbox.notes = notes;
+                        (String::new(), String::new(), 0)
+                    }
+                    None => (String::new(), "no snapshot named x".to_string(), 2),
+                }
+            } else {
+                (sandbox.notes.clone(), String::new(), 0)
+            };
+            MockResponse::json(200, json!({ "stdout": stdout, "stderr": stderr, "exit_code": exit_code }))
+        })
+        .await;
+        let conway = ConwayClient::new(&server.url(), "key", "sb-1");
+        let db = Arc::new(Mutex::new(Database::open_memory().unwrap()));
+
+        conway.write_file("workspace/notes.txt", "known good").await.unwrap();
+        let out = workspace_snapshot(&conway, &db, "before-refactor", 60_000).await.unwrap();
+        assert!(out.contains("2048 bytes"), "{}", out);
+
+        conway.write_file("workspace/notes.txt", "broken").await.unwrap();
+        workspace_restore(&conway, &db, "before-refactor", 60_000).await.unwrap();
+        let cat = conway.exec("cat workspace/notes.txt", None, None).await.unwrap();
+        assert_eq!(cat.stdout, "known good");
+        assert_eq!(db.lock().await.count_modifications().unwrap(), 2);
+
+        // Unknown snapshots and unsafe names fail without touching anything
+        assert!(workspace_restore(&conway, &db, "never-taken", 60_000).await.is_err());
+        assert!(workspace_snapshot(&conway, &db, "../../etc", 60_000).await.is_err());
+        assert!(workspace_snapshot(&conway, &db, "a; rm -rf /", 60_000).await.is_err());
+        assert_eq!(db.lock().await.count_modifications().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_archive_without_workspace_leaves_it_in_place() {
+        let home = std::env::temp_dir().join(format!("automaton-snapshot-{}", ulid::Ulid::new()));
+        std::fs::create_dir_all(home.join("workspace")).unwrap();
+        std::fs::create_dir_all(home.join("other")).unwrap();
+        std::fs::create_dir_all(home.join(SNAPSHOT_DIR.trim_start_matches("~/"))).unwrap();
+        std::fs::write(home.join("workspace/notes.txt"), "current").unwrap();
+        std::fs::write(home.join("other/file.txt"), "stray").unwrap();
+        let sh = |script: &str| {
+            std::process::Command::new("sh")
+                .arg("-c")
+                .arg(script)
+                .current_dir(&home)
+                .env("HOME", &home)
+                .output()
+                .unwrap()
+        };
+        assert!(sh(&format!("tar -czf {} other", archive_path("stray"))).status.success());
+
+        let out = sh(&restore_script("stray"));
+        assert_eq!(out.status.code(), Some(3), "{}", String::from_utf8_lossy(&out.stderr));
+        assert_eq!(std::fs::read_to_string(home.join("workspace/notes.txt")).unwrap(), "current");
+        assert!(!home.join("workspace.restoring").exists());
+
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_script_bounds_snapshots() {
+        let script = snapshot_script("nightly");
+        assert!(script.contains("tar -czf ~/.automaton/snapshots/nightly.tar.gz workspace"));
+        assert!(script.contains(&format!("-gt {}", MAX_SNAPSHOT_BYTES)));
+        assert!(script.contains(&format!("tail -n +{}", MAX_SNAPSHOTS + 1)));
+    }
+}
@@ -0,0 +1,171 @@
+//! `system_info` tool: a fixed, read-only set of sandbox probes.
+//!
+//! Runs one bundled shell command (uname, df, free, and a `command -v` sweep
+//! over common tools) and returns a structured JSON summary. Results are
+//! cached in KV briefly so repeated calls don't re-probe.
+
+use crate::conway::ConwayClient;
+use crate::state::Database;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How long a probe result is reused.
+const CACHE_TTL_SECS: i64 = 300;
+
+/// KV key holding the cached probe result.
+const CACHE_KEY: &str = "system_info_cache";
+
+/// Tools checked for presence on `PATH`.
+const PROBED_TOOLS: &[&str] = &[
+    "git", "curl", "wget", "python3", "node", "npm", "cargo", "docker", "sqlite3", "jq",
+];
+
+/// Structured sandbox summary returned to the model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub os: String,
+    pub disk: Option<DiskInfo>,
+    pub memory_mb: Option<MemoryInfo>,
+    pub tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskInfo {
+    pub size: String,
+    pub used: String,
+    pub available: String,
+    pub use_percent: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryInfo {
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedInfo {
+    at: DateTime<Utc>,
+    info: SystemInfo,
+}
+
+/// The bundled probe command; each section starts with a `==name==` marker.
+fn probe_command() -> String {
+    format!(
+        "echo '==os=='; uname -srm; \
+         echo '==disk=='; df -h / 2>/dev/null | tail -n 1; \
+         echo '==memory=='; free -m 2>/dev/null | grep '^Mem:'; \
+         echo '==tools=='; for t in {}; do command -v $t >/dev/null 2>&1 && echo $t; done; true",
+        PROBED_TOOLS.join(" ")
+    )
+}
+
+/// Parse the sectioned probe output.
+fn parse_probe_output(stdout: &str) -> SystemInfo {
+    let mut info = SystemInfo::default();
+    let mut section = "";
+
+    for line in stdout.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(name) = line.strip_prefix("==").and_then(|l| l.strip_suffix("==")) {
+            section = name;
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match section {
+            "os" => info.os = line.to_string(),
+            // Filesystem Size Used Avail Use% Mounted
+            "disk" if fields.len() >= 5 => {
+                info.disk = Some(DiskInfo {
+                    size: fields[1].into(),
+                    used: fields[2].into(),
+                    available: fields[3].into(),
+                    use_percent: fields[4].into(),
+                });
+            }
+            // Mem: total used free shared buff/cache available
+            "memory" if fields.len() >= 7 => {
+                let num = |i: usize| fields[i].parse().unwrap_or(0);
+                info.memory_mb = Some(MemoryInfo {
+                    total: num(1),
+                    used: num(2),
+                    available: num(6),
+                });
+            }
+            "tools" => info.tools.push(line.to_string()),
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Return the sandbox summary as pretty JSON, probing if the cache is stale.
+pub async fn system_info(
+    conway: &ConwayClient,
+    db: &tokio::sync::Mutex<Database>,
+) -> Result<String> {
+    {
+        let db = db.lock().await;
+        if let Some(cached) = db
+            .kv_get(CACHE_KEY)?
+            .and_then(|s| serde_json::from_str::<CachedInfo>(&s).ok())
+        {
+            if Utc::now() - cached.at < chrono::Duration::seconds(CACHE_TTL_SECS) {
+                return Ok(serde_json::to_string_pretty(&cached.info)?);
+            }
+        }
+    }
+
+    let resp = conway.exec(&probe_command(), Some(15_000), None).await?;
+    let info = parse_probe_output(&resp.stdout);
+
+    let db = db.lock().await;
+    db.kv_set(
+        CACHE_KEY,
+        &serde_json::to_string(&CachedInfo {
+            at: Utc::now(),
+            info: info.clone(),
+        })?,
+    )?;
+
+    Ok(serde_json::to_string_pretty(&info)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use serde_json::json;
+
+    const PROBE_OUTPUT: &str = "==os==\nLinux 6.1.0 aarch64\n\
+        ==disk==\n/dev/root  29G  12G  16G  43% /\n\
+        ==memory==\nMem:  3792  1204  610  42  1978  2341\n\
+        ==tools==\ngit\ncurl\npython3\n";
+
+    #[tokio::test]
+    async fn test_system_info_aggregates_probes_and_caches() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, json!({ "stdout": PROBE_OUTPUT, "stderr": "", "exit_code": 0 }))
+        })
+        .await;
+        let conway = ConwayClient::new(&server.url(), "key", "sb-1");
+        let db = tokio::sync::Mutex::new(Database::open_memory().unwrap());
+
+        let out = system_info(&conway, &db).await.unwrap();
+        let info: SystemInfo = serde_json::from_str(&out).unwrap();
+
+        assert_eq!(info.os, "Linux 6.1.0 aarch64");
+        let disk = info.disk.unwrap();
+        assert_eq!((disk.size.as_str(), disk.available.as_str()), ("29G", "16G"));
+        assert_eq!(disk.use_percent, "43%");
+        let mem = info.memory_mb.unwrap();
+        assert_eq!((mem.total, mem.used, mem.available), (3792, 1204, 2341));
+        assert_eq!(info.tools, vec!["git", "curl", "python3"]);
+
+        // Second call is served from the cache
+        system_info(&conway, &db).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -1,8 +1,11 @@
 //! Shared types used across the automaton runtime.
 
+use anyhow::{bail, Context};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
 use std::fmt;
+use std::str::FromStr;
 
 // ---------------------------------------------------------------------------
 // Agent state machine
@@ -29,6 +32,10 @@ pub enum AgentState {
     Critical,
     /// No resources remaining — halted.
     Dead,
+    /// Dead, but polling for a credit top-up to resume.
+    Hibernating,
+    /// Spending frozen by the operator; heartbeats still run.
+    Frozen,
 }
 
 impl fmt::Display for AgentState {
@@ -42,6 +49,8 @@ impl fmt::Display for AgentState {
             Self::LowCompute => write!(f, "low_compute"),
             Self::Critical => write!(f, "critical"),
             Self::Dead => write!(f, "dead"),
+            Self::Hibernating => write!(f, "hibernating"),
+            Self::Frozen => write!(f, "frozen"),
         }
     }
 }
@@ -76,6 +85,20 @@ impl fmt::Display for SurvivalTier {
     }
 }
 
+impl FromStr for SurvivalTier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "normal" => Ok(Self::Normal),
+            "low_compute" | "low-compute" => Ok(Self::LowCompute),
+            "critical" => Ok(Self::Critical),
+            "dead" => Ok(Self::Dead),
+            _ => bail!("Unknown survival tier: {} (normal, low_compute, critical, dead)", s),
+        }
+    }
+}
+
 impl SurvivalTier {
     /// Determine survival tier from a USD credit balance.
     pub fn from_balance(usd: f64) -> Self {
@@ -91,6 +114,99 @@ impl SurvivalTier {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Addresses
+// ---------------------------------------------------------------------------
+
+/// A 20-byte Ethereum address.
+///
+/// Parsing accepts all-lowercase or all-uppercase hex, and mixed case only if
+/// it is a valid EIP-55 checksum. Displays and serializes checksummed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address([u8; 20]);
+
+impl Address {
+    pub fn from_bytes(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// Parse a `0x`-prefixed hex address, verifying any mixed-case checksum.
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let hex_part = s
+            .strip_prefix("0x")
+            .with_context(|| format!("Address must start with 0x: {}", s))?;
+        if hex_part.len() != 40 {
+            bail!("Address must be 20 bytes (40 hex chars): {}", s);
+        }
+        let mut bytes = [0u8; 20];
+        hex::decode_to_slice(hex_part, &mut bytes)
+            .with_context(|| format!("Address is not valid hex: {}", s))?;
+
+        let address = Self(bytes);
+        let has_lower = hex_part.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = hex_part.chars().any(|c| c.is_ascii_uppercase());
+        if has_lower && has_upper && address.to_checksum() != s {
+            bail!("Address has an invalid EIP-55 checksum: {}", s);
+        }
+        Ok(address)
+    }
+
+    /// EIP-55 checksummed hex with `0x` prefix.
+    pub fn to_checksum(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = Keccak256::digest(lower.as_bytes());
+
+        let mut out = String::with_capacity(42);
+        out.push_str("0x");
+        for (i, c) in lower.chars().enumerate() {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 {
+                out.push(c.to_ascii_uppercase());
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_checksum())
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Address({})", self)
+    }
+}
+
+impl FromStr for Address {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for Address {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_checksum())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Inference types
 // ---------------------------------------------------------------------------
@@ -100,6 +216,58 @@ impl SurvivalTier {
 pub struct ChatMessage {
     pub role: ChatRole,
     pub content: String,
+    /// Tool calls requested by an assistant message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// The call a tool message answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Byte length of a leading part of `content` that is identical from
+    /// turn to turn, and so can be served from a prompt cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cacheable_prefix: Option<usize>,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    /// An assistant message, optionally carrying the tool calls it made.
+    pub fn assistant(content: impl Into<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            tool_calls,
+            ..Self::new(ChatRole::Assistant, content)
+        }
+    }
+
+    /// Mark the first `len` bytes of the content as cacheable.
+    pub fn with_cacheable_prefix(mut self, len: Option<usize>) -> Self {
+        self.cacheable_prefix = len;
+        self
+    }
+
+    /// A tool result answering `tool_call_id`.
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(ChatRole::Tool, content)
+        }
+    }
+
+    fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            cacheable_prefix: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -125,14 +293,53 @@ pub struct ToolResult {
     pub tool_call_id: String,
     pub output: String,
     pub success: bool,
+    /// Non-text payload (e.g. an image) carried alongside the text output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<ToolAttachment>,
+}
+
+/// A binary tool payload, kept base64-encoded so it survives as JSON/text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolAttachment {
+    pub mime_type: String,
+    pub data_base64: String,
+    pub size_bytes: usize,
+}
+
+impl ToolAttachment {
+    /// Render as a `data:` URL for inclusion in a text message.
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data_base64)
+    }
 }
 
 /// Response from inference including potential tool calls.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InferenceResponse {
+    /// Model that produced this response.
+    pub model: String,
     pub content: Option<String>,
     pub tool_calls: Vec<ToolCall>,
     pub usage: TokenUsage,
+    /// Raw payloads, when the client captures them.
+    #[serde(default)]
+    pub exchange: Option<InferenceExchange>,
+}
+
+/// One inference call as sent and received, secrets redacted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InferenceExchange {
+    pub request_json: String,
+    pub response_json: String,
+    pub latency_ms: u64,
+}
+
+/// A stored [`InferenceExchange`] (see `debug_inference_logging`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InferenceLogEntry {
+    pub turn_id: String,
+    pub exchange: InferenceExchange,
+    pub created_at: DateTime<Utc>,
 }
 
 /// Token usage from an inference call.
@@ -141,12 +348,25 @@ pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache (a subset of
+    /// `prompt_tokens`, billed at the cached rate).
+    #[serde(default)]
+    pub cached_prompt_tokens: u32,
+    /// Hidden reasoning tokens (a subset of `completion_tokens`, billed at
+    /// the reasoning rate).
+    #[serde(default)]
+    pub reasoning_tokens: u32,
 }
 
 // ---------------------------------------------------------------------------
 // Turn persistence
 // ---------------------------------------------------------------------------
 
+/// Tag prefix of turns run under a simulated survival tier
+/// (`simulated:<tier>`). `label_turn` can't produce it, and activity
+/// metrics leave these turns out.
+pub const SIMULATED_TAG_PREFIX: &str = "simulated:";
+
 /// A single turn in the agent's processing history.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Turn {
@@ -158,6 +378,70 @@ pub struct Turn {
     pub tool_results: Vec<ToolResult>,
     pub token_usage: TokenUsage,
     pub cost_estimate_usd: f64,
+    /// Model that served the turn (may be a fallback).
+    pub model: String,
+    /// Category the agent gave the turn with `label_turn`, or
+    /// [`SIMULATED_TAG_PREFIX`] and the tier for a simulated turn.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// What the agent said it meant to do, via `declare_intent`.
+    #[serde(default)]
+    pub intent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A persisted turn without its messages, for activity listings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnSummary {
+    pub turn_number: u64,
+    pub model: String,
+    pub cost_estimate_usd: f64,
+    /// Names of the tools called, in order.
+    pub tool_names: Vec<String>,
+    pub tag: Option<String>,
+    pub intent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Turns and spend under one `label_turn` tag.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnTagStats {
+    pub tag: String,
+    pub turns: u64,
+    pub cost_estimate_usd: f64,
+}
+
+/// How often a tool's recent calls succeeded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSuccessRate {
+    pub tool_name: String,
+    pub calls: u32,
+    pub successes: u32,
+}
+
+impl ToolSuccessRate {
+    /// Fraction of calls that succeeded (1.0 with no calls).
+    pub fn rate(&self) -> f64 {
+        if self.calls == 0 {
+            return 1.0;
+        }
+        self.successes as f64 / self.calls as f64
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Transactions
+// ---------------------------------------------------------------------------
+
+/// A recorded financial transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    pub id: String,
+    pub tx_type: String,
+    pub amount: f64,
+    pub currency: String,
+    pub description: Option<String>,
+    pub balance_after: Option<f64>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -174,6 +458,31 @@ pub struct HeartbeatEntry {
     pub enabled: bool,
     #[serde(default)]
     pub params: serde_json::Value,
+    /// IANA zone the schedule is read in (e.g. `America/New_York`), so
+    /// "0 9 * * *" means 9am local time across DST changes. Default UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// A one-off heartbeat task the agent scheduled with `schedule_task`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub id: String,
+    /// Heartbeat task name, as in `heartbeat.yml`.
+    pub task: String,
+    #[serde(default)]
+    pub params: serde_json::Value,
+    pub run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One logged execution of a heartbeat task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatRun {
+    pub task_name: String,
+    pub result: String,
+    pub success: bool,
+    pub executed_at: DateTime<Utc>,
 }
 
 // ---------------------------------------------------------------------------
@@ -190,6 +499,9 @@ pub struct Skill {
     pub instructions: String,
     #[serde(default)]
     pub requirements: Vec<SkillRequirement>,
+    /// Tools the agent may use while this skill is active (`None` = any).
+    #[serde(default)]
+    pub allowed_tools: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -211,6 +523,22 @@ pub struct InboxMessage {
     pub content: String,
     pub timestamp: DateTime<Utc>,
     pub read: bool,
+    /// EIP-191 signature of `content` by `from_address`, if the sender signed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Memory
+// ---------------------------------------------------------------------------
+
+/// A note the agent deliberately saved to long-term memory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    pub tag: Option<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
 }
 
 // ---------------------------------------------------------------------------
@@ -256,6 +584,12 @@ pub enum ModificationType {
     SkillAdd,
     HeartbeatUpdate,
     Upstream,
+    WalletRotation,
+    /// A protected file found altered, e.g. the constitution.
+    IntegrityViolation,
+    DnsUpdate,
+    WorkspaceSnapshot,
+    WorkspaceRestore,
 }
 
 impl fmt::Display for ModificationType {
@@ -267,6 +601,32 @@ impl fmt::Display for ModificationType {
             Self::SkillAdd => write!(f, "skill_add"),
             Self::HeartbeatUpdate => write!(f, "heartbeat_update"),
             Self::Upstream => write!(f, "upstream"),
+            Self::WalletRotation => write!(f, "wallet_rotation"),
+            Self::IntegrityViolation => write!(f, "integrity_violation"),
+            Self::DnsUpdate => write!(f, "dns_update"),
+            Self::WorkspaceSnapshot => write!(f, "workspace_snapshot"),
+            Self::WorkspaceRestore => write!(f, "workspace_restore"),
+        }
+    }
+}
+
+impl FromStr for ModificationType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "code_edit" => Ok(Self::CodeEdit),
+            "tool_install" => Ok(Self::ToolInstall),
+            "config_update" => Ok(Self::ConfigUpdate),
+            "skill_add" => Ok(Self::SkillAdd),
+            "heartbeat_update" => Ok(Self::HeartbeatUpdate),
+            "upstream" => Ok(Self::Upstream),
+            "wallet_rotation" => Ok(Self::WalletRotation),
+            "integrity_violation" => Ok(Self::IntegrityViolation),
+            "dns_update" => Ok(Self::DnsUpdate),
+            "workspace_snapshot" => Ok(Self::WorkspaceSnapshot),
+            "workspace_restore" => Ok(Self::WorkspaceRestore),
+            _ => bail!("Unknown modification type: {}", s),
         }
     }
 }
@@ -283,6 +643,83 @@ pub struct GenesisConfig {
     pub parent_address: String,
     pub parent_sandbox_id: String,
     pub initial_credits: f64,
+    /// The child's generation: its parent's plus one.
+    #[serde(default)]
+    pub generation: u32,
+    /// Sandbox image/template for the child (`None` = the configured
+    /// default, else the parent's own).
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// Lifecycle state of a child automaton.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildState {
+    /// Sandbox created, setup in progress.
+    Spawning,
+    /// Set up and running.
+    Active,
+    /// Stopped by the parent (or a failed spawn); final.
+    Terminated,
+    /// Stopped responding; may come back.
+    Unreachable,
+    /// Answered a `verify-child` challenge with its own wallet's signature.
+    Verified,
+    /// Failed (or didn't answer) a `verify-child` challenge; the sandbox may
+    /// no longer be running this runtime.
+    Suspect,
+}
+
+impl fmt::Display for ChildState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawning => write!(f, "spawning"),
+            Self::Active => write!(f, "active"),
+            Self::Terminated => write!(f, "terminated"),
+            Self::Unreachable => write!(f, "unreachable"),
+            Self::Verified => write!(f, "verified"),
+            Self::Suspect => write!(f, "suspect"),
+        }
+    }
+}
+
+impl FromStr for ChildState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            // "provisioning" was written by older versions
+            "spawning" | "provisioning" => Ok(Self::Spawning),
+            "active" => Ok(Self::Active),
+            "terminated" => Ok(Self::Terminated),
+            "unreachable" => Ok(Self::Unreachable),
+            "verified" => Ok(Self::Verified),
+            "suspect" => Ok(Self::Suspect),
+            _ => bail!("Unknown child state: {}", s),
+        }
+    }
+}
+
+impl ChildState {
+    /// Whether the lifecycle allows moving from `self` to `next`.
+    pub fn can_transition_to(self, next: ChildState) -> bool {
+        use ChildState::*;
+        matches!(
+            (self, next),
+            (Spawning, Active | Terminated | Unreachable)
+                | (Active, Terminated | Unreachable | Verified | Suspect)
+                | (Unreachable, Active | Terminated | Verified | Suspect)
+                | (Verified, Active | Terminated | Unreachable | Suspect)
+                // Only a passed challenge (or termination) clears suspicion
+                | (Suspect, Terminated | Verified)
+        )
+    }
+
+    /// Whether the child still occupies one of the parent's slots.
+    pub fn is_live(self) -> bool {
+        !matches!(self, Self::Terminated)
+    }
 }
 
 /// A tracked child automaton.
@@ -293,7 +730,80 @@ pub struct ChildRecord {
     pub sandbox_id: String,
     pub wallet_address: String,
     pub created_at: DateTime<Utc>,
-    pub status: String,
+    pub status: ChildState,
+}
+
+/// A sandbox creation recorded before calling Conway and cleared once its
+/// result is tracked, so a crash in between can be reconciled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingOperation {
+    pub id: String,
+    /// `spawn_child` or `create_sandbox`.
+    pub kind: String,
+    /// Name the sandbox was requested under.
+    pub name: String,
+    /// Set as soon as Conway returns it.
+    pub sandbox_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The process holding the runtime lock (see `agent::run_lock`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeLock {
+    /// Random id of the holding process's loop.
+    pub owner: String,
+    pub pid: u32,
+    pub acquired_at: DateTime<Utc>,
+    pub heartbeat_at: DateTime<Utc>,
+}
+
+/// Where an approval request stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Denied,
+    /// Nobody decided within `approval_timeout_secs`; treated as denied.
+    Expired,
+}
+
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Approved => write!(f, "approved"),
+            Self::Denied => write!(f, "denied"),
+            Self::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+impl FromStr for ApprovalStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "approved" => Ok(Self::Approved),
+            "denied" => Ok(Self::Denied),
+            "expired" => Ok(Self::Expired),
+            _ => bail!("Unknown approval status: {}", s),
+        }
+    }
+}
+
+/// A high-risk tool call held for an operator decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Approval {
+    pub id: String,
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    /// Why the call needs approval.
+    pub reason: String,
+    pub status: ApprovalStatus,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
 }
 
 // ---------------------------------------------------------------------------
@@ -314,3 +824,33 @@ pub enum ToolCategory {
     Replication,
     Social,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// EIP-55 reference vector.
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn test_address_validation() {
+        let addr = Address::parse(&CHECKSUMMED.to_lowercase()).unwrap();
+        assert_eq!(addr.to_string(), CHECKSUMMED);
+        assert_eq!(Address::parse(CHECKSUMMED).unwrap(), addr);
+
+        // Wrong checksum (one letter's case flipped)
+        assert!(Address::parse("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+        assert!(Address::parse("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+        assert!(Address::parse("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_err());
+        assert!(Address::parse("0xZZAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+    }
+
+    #[test]
+    fn test_address_serde_round_trip() {
+        let addr: Address = CHECKSUMMED.parse().unwrap();
+        let json = serde_json::to_string(&addr).unwrap();
+        assert_eq!(json, format!("\"{}\"", CHECKSUMMED));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), addr);
+        assert!(serde_json::from_str::<Address>("\"0x1234\"").is_err());
+    }
+}